@@ -1,7 +1,9 @@
 // benches/machine_bench.rs
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use lam::core::Machine;
 use lam::instruction::Instruction;
+use lam::machine::program::Program;
+use lam::prolog::compiler::compile_prolog;
 use lam::term::Term;
 
 /// Builds a sample program for the machine.
@@ -33,7 +35,7 @@ fn benchmark_machine_run(c: &mut Criterion) {
             // Run the machine; black_box prevents the compiler from optimizing away the call.
             let result = machine.run();
             // Verify that the run completed successfully.
-            black_box(result.expect("Machine run should succeed"));
+            black_box(result).expect("Machine run should succeed");
         })
     });
 }
@@ -56,6 +58,32 @@ fn benchmark_unification(c: &mut Criterion) {
   });
 }
 
-criterion_group!(benches, benchmark_machine_run, benchmark_unification);
+/// Builds a chain of `depth` predicates, `p0 :- p1.` up to a last one that
+/// backtracks twice over `pick/1`. The choice point left by `pick/1` sits under
+/// the whole chain, so the cost of creating and restoring it grows with the depth
+/// of the control and environment stacks unless they are shared rather than copied.
+fn deep_search_machine(depth: usize) -> Machine {
+    let mut source: String = (0..depth).map(|i| format!("p{} :- p{}.\n", i, i + 1)).collect();
+    source.push_str(&format!("p{} :- pick(X), X = c.\npick(a).\npick(b).\npick(c).\nmain :- p0.\n", depth));
+    let (code, predicate_table, num_registers) = compile_prolog(&source).expect("Benchmark program should compile");
+    Program { instructions: code, predicate_table, ..Program::default() }.into_machine(num_registers)
+}
+
+fn benchmark_deep_backtracking(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_backtracking");
+    for depth in [10, 100, 1000, 10000] {
+        let machine_for = || deep_search_machine(depth);
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, _| {
+            b.iter_batched(
+                machine_for,
+                |mut machine| black_box(machine.run()).expect("Deep search should succeed"),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_machine_run, benchmark_unification, benchmark_deep_backtracking);
 criterion_main!(benches);
 
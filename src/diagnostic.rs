@@ -0,0 +1,100 @@
+// src/diagnostic.rs
+//! Source positions and error reports for the Prolog reader and the `.lam`
+//! instruction parser.
+//!
+//! Parse errors carry a `Span` locating the offending text. `lamc` turns an
+//! error into a `Diagnostic` by adding the file name, and renders it with the
+//! source line and a caret underline:
+//!
+//! ```text
+//! error: Expected ')' but found end of clause
+//!  --> family.pl:12:18
+//!    |
+//! 12 | parent(john, mary.
+//!    |                  ^
+//! ```
+
+use std::fmt;
+
+/// A region of source text: a byte range together with the line and column
+/// (both starting at 1, columns counted in characters) of its first character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the start of the span.
+    pub start: usize,
+    /// Byte offset just past the end of the span.
+    pub end: usize,
+    /// Line of the start of the span.
+    pub line: usize,
+    /// Column of the start of the span.
+    pub column: usize,
+}
+
+impl Span {
+    /// Creates the span covering bytes `start..end` of `source`.
+    pub fn new(source: &str, start: usize, end: usize) -> Self {
+        let start = start.min(source.len());
+        let end = end.clamp(start, source.len());
+        let before = &source[..start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = source[line_start..start].chars().count() + 1;
+        Span { start, end, line, column }
+    }
+
+    /// Creates the span of `part`, which must be a subslice of `source`.
+    pub fn of_slice(source: &str, part: &str) -> Self {
+        let start = (part.as_ptr() as usize).saturating_sub(source.as_ptr() as usize);
+        Span::new(source, start, start + part.len())
+    }
+}
+
+/// An error located in a source file, ready to be reported to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Name of the file the error was found in.
+    pub file: String,
+    /// Description of the error.
+    pub message: String,
+    /// Location of the error in the file.
+    pub span: Span,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic for an error in `file`.
+    pub fn new(file: &str, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic { file: file.to_string(), message: message.into(), span }
+    }
+
+    /// Renders the diagnostic with the offending source line of `source` and a
+    /// caret underline below the span. Spans covering several lines are
+    /// underlined up to the end of their first line.
+    pub fn render(&self, source: &str) -> String {
+        let line_text = source.lines().nth(self.span.line - 1).unwrap_or("");
+        let gutter = " ".repeat(self.span.line.to_string().len());
+        // Keep tabs so that the carets line up with the source text.
+        let padding: String = line_text.chars()
+            .take(self.span.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let available = line_text.chars().count().saturating_sub(self.span.column - 1);
+        let width = source.get(self.span.start..self.span.end)
+            .map_or(0, |text| text.chars().take_while(|c| *c != '\n').count())
+            .min(available)
+            .max(1);
+        format!(
+            "error: {}\n{} --> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self.message,
+            gutter, self.file, self.span.line, self.span.column,
+            gutter,
+            self.span.line, line_text,
+            gutter, padding, "^".repeat(width),
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file, self.span.line, self.span.column, self.message)
+    }
+}
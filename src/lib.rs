@@ -6,8 +6,11 @@
 //!
 //! Modules:
 //! - machine: Contains the core machine implementation and supporting components.
+//! - prolog: The Prolog reader and the compiler from Prolog to LAM code.
+//! - diagnostic: Source positions and error reports for parse errors.
 
 // Re-export the machine modules for use in the library.
+pub mod diagnostic;
 pub mod machine;
 pub mod prolog;
 
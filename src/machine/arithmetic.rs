@@ -1,14 +1,22 @@
 // src/machine/arithmetic.rs
 //! Arithmetic evaluation and expression parsing for the LAM.
 
+use std::fmt;
+use num_bigint::BigInt;
+use num_integer::Integer as _;
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
 use crate::term::Term;
-use crate::error_handling::MachineError;
+use crate::error_handling::{IsoError, MachineError};
+use crate::machine::integer::Integer;
+use crate::machine::number::{format_float, Number};
 
 /// Represents an arithmetic expression.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
     /// Constant integer.
     Const(i32),
+    /// Constant float.
+    Float(f64),
     /// Variable (by register index).
     Var(usize),
     /// Addition.
@@ -17,44 +25,649 @@ pub enum Expression {
     Sub(Box<Expression>, Box<Expression>),
     /// Multiplication.
     Mul(Box<Expression>, Box<Expression>),
-    /// Division.
+    /// Division. The quotient of two integers is an integer if the division
+    /// is exact and a float otherwise.
     Div(Box<Expression>, Box<Expression>),
+    /// Integer division `//`, truncating toward zero.
+    IntDiv(Box<Expression>, Box<Expression>),
+    /// Integer division `div`, rounding toward negative infinity.
+    FloorDiv(Box<Expression>, Box<Expression>),
+    /// Modulo `mod`, with the sign of the divisor.
+    Mod(Box<Expression>, Box<Expression>),
+    /// Remainder `rem`, with the sign of the dividend.
+    Rem(Box<Expression>, Box<Expression>),
+    /// The smaller of two values.
+    Min(Box<Expression>, Box<Expression>),
+    /// The larger of two values.
+    Max(Box<Expression>, Box<Expression>),
+    /// Power `**`.
+    Pow(Box<Expression>, Box<Expression>),
+    /// Integer power `^`.
+    IntPow(Box<Expression>, Box<Expression>),
+    /// Bitwise and `/\`.
+    BitAnd(Box<Expression>, Box<Expression>),
+    /// Bitwise or `\/`.
+    BitOr(Box<Expression>, Box<Expression>),
+    /// Bitwise exclusive or `xor`.
+    Xor(Box<Expression>, Box<Expression>),
+    /// Left shift `<<`.
+    Shl(Box<Expression>, Box<Expression>),
+    /// Arithmetic right shift `>>`.
+    Shr(Box<Expression>, Box<Expression>),
+    /// Greatest common divisor.
+    Gcd(Box<Expression>, Box<Expression>),
+    /// The arc tangent of Y / X, `atan2(Y, X)`, in the quadrant of (X, Y).
+    Atan2(Box<Expression>, Box<Expression>),
+    /// Absolute value.
+    Abs(Box<Expression>),
+    /// Sign: -1, 0 or 1.
+    Sign(Box<Expression>),
+    /// Bitwise complement `\`.
+    BitNot(Box<Expression>),
+    /// The position of the most significant set bit of a positive integer.
+    Msb(Box<Expression>),
+    /// Conversion to a float, `float/1`.
+    ToFloat(Box<Expression>),
+    /// Rounding to the nearest integer, `integer/1`.
+    ToInteger(Box<Expression>),
+    /// Rounding toward zero.
+    Truncate(Box<Expression>),
+    /// Rounding to the nearest integer, halfway cases away from zero.
+    Round(Box<Expression>),
+    /// Rounding toward positive infinity.
+    Ceiling(Box<Expression>),
+    /// Rounding toward negative infinity.
+    Floor(Box<Expression>),
+    /// Square root.
+    Sqrt(Box<Expression>),
+    /// The exponential function.
+    Exp(Box<Expression>),
+    /// The natural logarithm.
+    Log(Box<Expression>),
+    /// Sine.
+    Sin(Box<Expression>),
+    /// Cosine.
+    Cos(Box<Expression>),
+    /// Tangent.
+    Tan(Box<Expression>),
+    /// Arc sine.
+    Asin(Box<Expression>),
+    /// Arc cosine.
+    Acos(Box<Expression>),
+    /// Arc tangent.
+    Atan(Box<Expression>),
 }
 
-/// Evaluates an arithmetic expression using the given registers.
-/// Returns the computed integer or a MachineError.
-pub fn evaluate(expr: &Expression, registers: &[Option<Term>]) -> Result<i32, MachineError> {
+impl Expression {
+    /// Returns the evaluable functor name and the operands of a binary operation.
+    pub fn binary(&self) -> Option<(&'static str, &Expression, &Expression)> {
+        let (name, left, right) = match self {
+            Expression::Add(left, right) => ("+", left, right),
+            Expression::Sub(left, right) => ("-", left, right),
+            Expression::Mul(left, right) => ("*", left, right),
+            Expression::Div(left, right) => ("/", left, right),
+            Expression::IntDiv(left, right) => ("//", left, right),
+            Expression::FloorDiv(left, right) => ("div", left, right),
+            Expression::Mod(left, right) => ("mod", left, right),
+            Expression::Rem(left, right) => ("rem", left, right),
+            Expression::Min(left, right) => ("min", left, right),
+            Expression::Max(left, right) => ("max", left, right),
+            Expression::Pow(left, right) => ("**", left, right),
+            Expression::IntPow(left, right) => ("^", left, right),
+            Expression::BitAnd(left, right) => ("/\\", left, right),
+            Expression::BitOr(left, right) => ("\\/", left, right),
+            Expression::Xor(left, right) => ("xor", left, right),
+            Expression::Shl(left, right) => ("<<", left, right),
+            Expression::Shr(left, right) => (">>", left, right),
+            Expression::Gcd(left, right) => ("gcd", left, right),
+            Expression::Atan2(left, right) => ("atan2", left, right),
+            _ => return None,
+        };
+        Some((name, left, right))
+    }
+
+    /// Returns the evaluable functor name and the operand of a unary operation.
+    pub fn unary(&self) -> Option<(&'static str, &Expression)> {
+        match self {
+            Expression::Abs(operand) => Some(("abs", operand)),
+            Expression::Sign(operand) => Some(("sign", operand)),
+            Expression::BitNot(operand) => Some(("\\", operand)),
+            Expression::Msb(operand) => Some(("msb", operand)),
+            Expression::ToFloat(operand) => Some(("float", operand)),
+            Expression::ToInteger(operand) => Some(("integer", operand)),
+            Expression::Truncate(operand) => Some(("truncate", operand)),
+            Expression::Round(operand) => Some(("round", operand)),
+            Expression::Ceiling(operand) => Some(("ceiling", operand)),
+            Expression::Floor(operand) => Some(("floor", operand)),
+            Expression::Sqrt(operand) => Some(("sqrt", operand)),
+            Expression::Exp(operand) => Some(("exp", operand)),
+            Expression::Log(operand) => Some(("log", operand)),
+            Expression::Sin(operand) => Some(("sin", operand)),
+            Expression::Cos(operand) => Some(("cos", operand)),
+            Expression::Tan(operand) => Some(("tan", operand)),
+            Expression::Asin(operand) => Some(("asin", operand)),
+            Expression::Acos(operand) => Some(("acos", operand)),
+            Expression::Atan(operand) => Some(("atan", operand)),
+            _ => None,
+        }
+    }
+
+    /// Returns the highest register the expression reads, if it reads any.
+    pub fn max_register(&self) -> Option<usize> {
+        match self {
+            Expression::Var(register) => Some(*register),
+            _ => match (self.binary(), self.unary()) {
+                (Some((_, left, right)), _) => left.max_register().max(right.max_register()),
+                (_, Some((_, operand))) => operand.max_register(),
+                _ => None,
+            },
+        }
+    }
+
+    /// Builds the binary operation with the given evaluable functor name.
+    pub fn from_binary(name: &str, left: Expression, right: Expression) -> Option<Expression> {
+        let make = match name {
+            "+" => Expression::Add,
+            "-" => Expression::Sub,
+            "*" => Expression::Mul,
+            "/" => Expression::Div,
+            "//" => Expression::IntDiv,
+            "div" => Expression::FloorDiv,
+            "mod" => Expression::Mod,
+            "rem" => Expression::Rem,
+            "min" => Expression::Min,
+            "max" => Expression::Max,
+            "**" => Expression::Pow,
+            "^" => Expression::IntPow,
+            "/\\" => Expression::BitAnd,
+            "\\/" => Expression::BitOr,
+            "xor" => Expression::Xor,
+            "<<" => Expression::Shl,
+            ">>" => Expression::Shr,
+            "gcd" => Expression::Gcd,
+            "atan2" => Expression::Atan2,
+            _ => return None,
+        };
+        Some(make(Box::new(left), Box::new(right)))
+    }
+
+    /// Builds the unary operation with the given evaluable functor name.
+    pub fn from_unary(name: &str, operand: Expression) -> Option<Expression> {
+        let make = match name {
+            "abs" => Expression::Abs,
+            "sign" => Expression::Sign,
+            "\\" => Expression::BitNot,
+            "msb" => Expression::Msb,
+            "float" => Expression::ToFloat,
+            "integer" => Expression::ToInteger,
+            "truncate" => Expression::Truncate,
+            "round" => Expression::Round,
+            "ceiling" => Expression::Ceiling,
+            "floor" => Expression::Floor,
+            "sqrt" => Expression::Sqrt,
+            "exp" => Expression::Exp,
+            "log" => Expression::Log,
+            "sin" => Expression::Sin,
+            "cos" => Expression::Cos,
+            "tan" => Expression::Tan,
+            "asin" => Expression::Asin,
+            "acos" => Expression::Acos,
+            "atan" => Expression::Atan,
+            _ => return None,
+        };
+        Some(make(Box::new(operand)))
+    }
+}
+
+/// Returns true if a binary operation is written as an infix operator rather
+/// than in functional notation.
+fn is_infix(name: &str) -> bool {
+    !matches!(name, "min" | "max" | "gcd" | "atan2")
+}
+
+/// Writes the expression in the syntax accepted by `parse_expression`, with
+/// registers as `Rn` and every compound operand parenthesized, so that parsing
+/// the output gives back the same expression.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Const(n) => write!(f, "{}", n),
+            Expression::Float(x) => write!(f, "{}", format_float(*x)),
+            Expression::Var(register) => write!(f, "R{}", register),
+            other => {
+                if let Some((name, left, right)) = other.binary() {
+                    if is_infix(name) {
+                        write!(f, "{} {} {}", Operand(left), name, Operand(right))
+                    } else {
+                        write!(f, "{}({}, {})", name, left, right)
+                    }
+                } else {
+                    let (name, operand) = other.unary().expect("unary operation");
+                    write!(f, "{}({})", name, operand)
+                }
+            },
+        }
+    }
+}
+
+/// An operand of a binary expression, parenthesized unless it is atomic.
+struct Operand<'a>(&'a Expression);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Expression::Const(_) | Expression::Float(_) | Expression::Var(_) => write!(f, "{}", self.0),
+            compound => write!(f, "({})", compound),
+        }
+    }
+}
+
+/// Evaluates an arithmetic expression using the given registers, whose terms
+/// are taken as they are: the machine reads registers through the current
+/// bindings with `evaluate_with` instead.
+/// Returns the computed number or a MachineError.
+pub fn evaluate(expr: &Expression, registers: &[Option<Term>]) -> Result<Number, MachineError> {
+    evaluate_with(expr, &mut |idx| match registers.get(idx) {
+        Some(Some(term)) => evaluate_term(term),
+        _ => Err(MachineError::UninitializedRegister(idx)),
+    })
+}
+
+/// Evaluates an arithmetic expression, reading the value of register `idx`
+/// with `register(idx)`.
+pub fn evaluate_with(expr: &Expression, register: &mut dyn FnMut(usize) -> Result<Number, MachineError>) -> Result<Number, MachineError> {
     match expr {
-        Expression::Const(n) => Ok(*n),
-        Expression::Var(idx) => {
-            if let Some(Term::Const(val)) = registers.get(*idx).and_then(|opt| opt.as_ref()) {
-                Ok(*val)
+        Expression::Const(n) => Ok(Number::Int(Integer::Small(*n))),
+        Expression::Float(x) => Ok(Number::Float(*x)),
+        Expression::Var(idx) => register(*idx),
+        other => {
+            if let Some((name, left, right)) = other.binary() {
+                apply_binary(name, &evaluate_with(left, register)?, &evaluate_with(right, register)?)
             } else {
-                Err(MachineError::UninitializedRegister(*idx))
+                let (name, operand) = other.unary().expect("unary operation");
+                apply_unary(name, &evaluate_with(operand, register)?)
             }
         },
-        Expression::Add(e1, e2) => Ok(evaluate(e1, registers)? + evaluate(e2, registers)?),
-        Expression::Sub(e1, e2) => Ok(evaluate(e1, registers)? - evaluate(e2, registers)?),
-        Expression::Mul(e1, e2) => Ok(evaluate(e1, registers)? * evaluate(e2, registers)?),
-        Expression::Div(e1, e2) => {
-            let denominator = evaluate(e2, registers)?;
-            if denominator == 0 {
-                return Err(MachineError::UnificationFailed("Division by zero".to_string()));
+    }
+}
+
+/// Evaluates a term as an arithmetic expression, as `is/2` does: numbers are
+/// their own value, the atoms `pi` and `e` are the constants and compound
+/// terms are evaluated if their functor is an evaluable functor.
+///
+/// Fails with `instantiation_error` if the term contains an unbound variable
+/// and with `type_error(evaluable, F/N)` if it contains a functor that is not
+/// evaluable.
+pub fn evaluate_term(term: &Term) -> Result<Number, MachineError> {
+    match term {
+        Term::Const(n) => Ok(Number::Int(Integer::Small(*n))),
+        Term::BigInt(n) => Ok(Number::Int(Integer::from(n.clone()))),
+        Term::Float(x) => Ok(Number::Float(x.0)),
+        Term::Var(_) => Err(IsoError::Instantiation.into()),
+        Term::Str(name) => match name.as_str() {
+            "pi" => Ok(Number::Float(std::f64::consts::PI)),
+            "e" => Ok(Number::Float(std::f64::consts::E)),
+            _ => Err(not_evaluable(name, 0)),
+        },
+        Term::Compound(name, args) => match args.as_slice() {
+            // The functor is checked before its arguments are evaluated.
+            [operand] if is_unary_evaluable(name) => apply_unary(name, &evaluate_term(operand)?),
+            [left, right] if is_binary_evaluable(name) => apply_binary(name, &evaluate_term(left)?, &evaluate_term(right)?),
+            _ => Err(not_evaluable(name, args.len())),
+        },
+        other => Err(IsoError::Type("evaluable".to_string(), other.clone()).into()),
+    }
+}
+
+/// The error for a functor that is not evaluable: `type_error(evaluable, Name/Arity)`.
+fn not_evaluable(name: &str, arity: usize) -> MachineError {
+    let indicator = Term::Compound("/".to_string(), vec![Term::Str(name.to_string()), Term::Const(arity as i32)]);
+    IsoError::Type("evaluable".to_string(), indicator).into()
+}
+
+fn is_unary_evaluable(name: &str) -> bool {
+    matches!(name, "-" | "+") || Expression::from_unary(name, Expression::Const(0)).is_some()
+}
+
+fn is_binary_evaluable(name: &str) -> bool {
+    Expression::from_binary(name, Expression::Const(0), Expression::Const(0)).is_some()
+}
+
+/// Returns true for the evaluable functors defined on integers only.
+fn is_integer_only(name: &str) -> bool {
+    matches!(name, "//" | "div" | "rem" | "mod" | "/\\" | "\\/" | "xor" | "<<" | ">>" | "gcd" | "\\" | "msb")
+}
+
+/// The largest result, in bits, that powers and shifts may produce. Integers
+/// are unbounded, but a result this large is almost certainly a mistake and
+/// would exhaust memory, so it is reported as `int_overflow` instead.
+pub const MAX_INTEGER_BITS: u64 = 1 << 24;
+
+fn int_overflow() -> MachineError {
+    IsoError::Evaluation("int_overflow".to_string()).into()
+}
+
+fn zero_divisor() -> MachineError {
+    IsoError::Evaluation("zero_divisor".to_string()).into()
+}
+
+fn undefined() -> MachineError {
+    IsoError::Evaluation("undefined".to_string()).into()
+}
+
+fn float_overflow() -> MachineError {
+    IsoError::Evaluation("float_overflow".to_string()).into()
+}
+
+/// The error for a float given to a functor defined on integers only.
+fn not_integer(x: f64) -> MachineError {
+    IsoError::Type("integer".to_string(), Number::Float(x).into_term()).into()
+}
+
+/// Converts a number to a float, failing with `float_overflow` if an integer
+/// is too large to be represented.
+fn to_float(a: &Number) -> Result<f64, MachineError> {
+    match a {
+        Number::Int(n) => {
+            let x = n.to_f64();
+            if x.is_finite() { Ok(x) } else { Err(float_overflow()) }
+        },
+        Number::Float(x) => Ok(*x),
+    }
+}
+
+/// Checks the result of a float operation: NaN is `undefined` and an infinite
+/// result is a `float_overflow`.
+fn float_result(x: f64) -> Result<Number, MachineError> {
+    if x.is_nan() {
+        Err(undefined())
+    } else if x.is_infinite() {
+        Err(float_overflow())
+    } else {
+        Ok(Number::Float(x))
+    }
+}
+
+/// Applies the binary evaluable functor `name` to two numbers. Two integers
+/// follow the integer rules, except for `/` with an inexact quotient, `**` with
+/// a negative exponent and `atan2`; otherwise both operands are converted to
+/// floats.
+fn apply_binary(name: &str, a: &Number, b: &Number) -> Result<Number, MachineError> {
+    match (a, b) {
+        (Number::Int(x), Number::Int(y)) => match name {
+            "/" if *y == 0 => Err(zero_divisor()),
+            "/" if integer_binary(x, y, "rem")? == 0 => integer_binary(x, y, "//").map(Number::Int),
+            "**" if y.signum() < 0 => float_binary(name, to_float(a)?, to_float(b)?),
+            "/" | "atan2" => float_binary(name, to_float(a)?, to_float(b)?),
+            _ => integer_binary(x, y, name).map(Number::Int),
+        },
+        (Number::Float(x), _) | (_, Number::Float(x)) if is_integer_only(name) => {
+            // The first float operand is the culprit.
+            let x = if let Number::Float(first) = a { *first } else { *x };
+            Err(not_integer(x))
+        },
+        _ => match name {
+            "min" => Ok(if a.compare(b).is_gt() { b.clone() } else { a.clone() }),
+            "max" => Ok(if a.compare(b).is_lt() { b.clone() } else { a.clone() }),
+            _ => float_binary(name, to_float(a)?, to_float(b)?),
+        },
+    }
+}
+
+/// Applies a binary functor to floats.
+fn float_binary(name: &str, x: f64, y: f64) -> Result<Number, MachineError> {
+    let result = match name {
+        "+" => x + y,
+        "-" => x - y,
+        "*" => x * y,
+        "/" if y == 0.0 => return Err(zero_divisor()),
+        "/" => x / y,
+        "**" | "^" if x == 0.0 && y < 0.0 => return Err(zero_divisor()),
+        "**" | "^" => x.powf(y),
+        "atan2" if x == 0.0 && y == 0.0 => return Err(undefined()),
+        "atan2" => x.atan2(y),
+        _ => return Err(not_evaluable(name, 2)),
+    };
+    float_result(result)
+}
+
+/// Applies a binary functor to two integers. Small operands are computed with
+/// `i32` arithmetic, falling back to big integers when the result does not fit.
+fn integer_binary(a: &Integer, b: &Integer, name: &str) -> Result<Integer, MachineError> {
+    if let (Integer::Small(x), Integer::Small(y)) = (a, b) {
+        if let Some(result) = small_binary(name, *x, *y)? {
+            return Ok(Integer::Small(result));
+        }
+    }
+    big_binary(name, a.to_bigint(), b.to_bigint())
+}
+
+/// Applies a binary functor to small integers. Returns `None` if the result
+/// does not fit in an `i32`.
+fn small_binary(name: &str, a: i32, b: i32) -> Result<Option<i32>, MachineError> {
+    let divisor = |b: i32| if b == 0 { Err(zero_divisor()) } else { Ok(b) };
+    let result = match name {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "//" => a.checked_div(divisor(b)?),
+        "div" => {
+            let b = divisor(b)?;
+            a.checked_div(b).map(|q| if a % b != 0 && (a < 0) != (b < 0) { q - 1 } else { q })
+        },
+        // The remainder is 0 when b is -1, including for i32::MIN.
+        "rem" => Some(a.checked_rem(divisor(b)?).unwrap_or(0)),
+        "mod" => {
+            let b = divisor(b)?;
+            let m = a.checked_rem(b).unwrap_or(0);
+            Some(if m != 0 && (m < 0) != (b < 0) { m + b } else { m })
+        },
+        "min" => Some(a.min(b)),
+        "max" => Some(a.max(b)),
+        "**" | "^" if b < 0 => Some(negative_power(&Integer::Small(a), b % 2 != 0)?),
+        "**" | "^" => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+        "/\\" => Some(a & b),
+        "\\/" => Some(a | b),
+        "xor" => Some(a ^ b),
+        "<<" => shift_left(a, b as i64),
+        ">>" => shift_left(a, -(b as i64)),
+        "gcd" => {
+            let (mut x, mut y) = (a.unsigned_abs(), b.unsigned_abs());
+            while y != 0 {
+                (x, y) = (y, x % y);
             }
-            Ok(evaluate(e1, registers)? / denominator)
+            i32::try_from(x).ok()
+        },
+        _ => return Err(not_evaluable(name, 2)),
+    };
+    Ok(result)
+}
+
+/// Applies a binary functor to big integers.
+fn big_binary(name: &str, a: BigInt, b: BigInt) -> Result<Integer, MachineError> {
+    let divisor = |b: BigInt| if b.is_zero() { Err(zero_divisor()) } else { Ok(b) };
+    let result = match name {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "//" => a / divisor(b)?,
+        "div" => a.div_floor(&divisor(b)?),
+        "rem" => a % divisor(b)?,
+        "mod" => a.mod_floor(&divisor(b)?),
+        "min" => a.min(b),
+        "max" => a.max(b),
+        "**" | "^" => return big_power(a, b),
+        "/\\" => a & b,
+        "\\/" => a | b,
+        "xor" => a ^ b,
+        "<<" => return big_shift_left(a, b),
+        ">>" => return big_shift_left(a, -b),
+        "gcd" => a.gcd(&b),
+        _ => return Err(not_evaluable(name, 2)),
+    };
+    Ok(Integer::from(result))
+}
+
+/// Applies the unary evaluable functor `name` to a number.
+fn apply_unary(name: &str, a: &Number) -> Result<Number, MachineError> {
+    match a {
+        Number::Int(n) => match name {
+            "float" => float_result(to_float(a)?),
+            "integer" | "truncate" | "round" | "ceiling" | "floor" => Ok(a.clone()),
+            "sqrt" | "exp" | "log" | "sin" | "cos" | "tan" | "asin" | "acos" | "atan" => float_unary(name, to_float(a)?),
+            _ => integer_unary(name, n).map(Number::Int),
         },
+        Number::Float(x) if is_integer_only(name) => Err(not_integer(*x)),
+        Number::Float(x) => match name {
+            "integer" | "round" => Ok(float_to_integer(x.round())),
+            "truncate" => Ok(float_to_integer(x.trunc())),
+            "ceiling" => Ok(float_to_integer(x.ceil())),
+            "floor" => Ok(float_to_integer(x.floor())),
+            _ => float_unary(name, *x),
+        },
+    }
+}
+
+/// Applies a unary functor to a float.
+fn float_unary(name: &str, x: f64) -> Result<Number, MachineError> {
+    let result = match name {
+        "-" => -x,
+        "+" | "float" => x,
+        "abs" => x.abs(),
+        // The sign of zero is zero, not 1.0 as with `f64::signum`.
+        "sign" if x == 0.0 => x,
+        "sign" => x.signum(),
+        "sqrt" if x < 0.0 => return Err(undefined()),
+        "sqrt" => x.sqrt(),
+        "exp" => x.exp(),
+        "log" if x <= 0.0 => return Err(undefined()),
+        "log" => x.ln(),
+        "sin" => x.sin(),
+        "cos" => x.cos(),
+        "tan" => x.tan(),
+        "asin" => x.asin(),
+        "acos" => x.acos(),
+        "atan" => x.atan(),
+        _ => return Err(not_evaluable(name, 1)),
+    };
+    float_result(result)
+}
+
+/// Converts a float with no fractional part to an integer.
+fn float_to_integer(x: f64) -> Number {
+    Number::Int(Integer::from(BigInt::from_f64(x).expect("finite float")))
+}
+
+/// Applies a unary functor to an integer.
+fn integer_unary(name: &str, a: &Integer) -> Result<Integer, MachineError> {
+    if let Integer::Small(x) = a {
+        let result = match name {
+            "-" => x.checked_neg(),
+            "+" => Some(*x),
+            "abs" => x.checked_abs(),
+            "sign" => Some(x.signum()),
+            "\\" => Some(!x),
+            "msb" if *x > 0 => Some(31 - x.leading_zeros() as i32),
+            _ => None,
+        };
+        if let Some(result) = result {
+            return Ok(Integer::Small(result));
+        }
+    }
+    let n = a.to_bigint();
+    let result = match name {
+        "-" => -n,
+        "+" => n,
+        "abs" => n.abs(),
+        "sign" => BigInt::from(a.signum()),
+        "\\" => !n,
+        "msb" if n.is_positive() => BigInt::from(n.bits() - 1),
+        "msb" => return Err(IsoError::Type("not_less_than_one".to_string(), a.clone().into_term()).into()),
+        _ => return Err(not_evaluable(name, 1)),
+    };
+    Ok(Integer::from(result))
+}
+
+/// Integer exponentiation with a negative exponent, which has an integer
+/// result only for the bases 1 and -1; 0 raised to a negative power is a
+/// division by zero.
+fn negative_power(base: &Integer, odd_exponent: bool) -> Result<i32, MachineError> {
+    match base {
+        Integer::Small(1) => Ok(1),
+        Integer::Small(-1) => Ok(if odd_exponent { -1 } else { 1 }),
+        Integer::Small(0) => Err(zero_divisor()),
+        _ => Err(IsoError::Type("float".to_string(), base.clone().into_term()).into()),
+    }
+}
+
+/// Integer exponentiation of big integers.
+fn big_power(base: BigInt, exponent: BigInt) -> Result<Integer, MachineError> {
+    if exponent.is_negative() {
+        return negative_power(&Integer::from(base), exponent.is_odd()).map(Integer::Small);
+    }
+    if base.is_zero() || base.is_one() {
+        return Ok(Integer::from(if exponent.is_zero() { BigInt::one() } else { base }));
+    }
+    if base == -BigInt::one() {
+        return Ok(Integer::Small(if exponent.is_even() { 1 } else { -1 }));
+    }
+    match exponent.to_u64() {
+        Some(e) if e.saturating_mul(base.bits()) <= MAX_INTEGER_BITS => Ok(Integer::from(base.pow(e as u32))),
+        _ => Err(int_overflow()),
+    }
+}
+
+/// Shifts left by `amount` bits, or right (arithmetically) by a negative amount.
+/// Returns `None` if the result does not fit in an `i32`.
+fn shift_left(a: i32, amount: i64) -> Option<i32> {
+    if amount < 0 {
+        return Some(a >> (-amount).min(31));
+    }
+    if a == 0 {
+        return Some(0);
+    }
+    if amount >= 32 {
+        return None;
+    }
+    i32::try_from((a as i64) << amount).ok()
+}
+
+/// Shifts a big integer left by `amount` bits, or right by a negative amount.
+fn big_shift_left(a: BigInt, amount: BigInt) -> Result<Integer, MachineError> {
+    if a.is_zero() {
+        return Ok(Integer::Small(0));
+    }
+    if amount.is_negative() {
+        // Shifting right by at least the width leaves only the sign.
+        let amount = (-amount).to_u64().unwrap_or(u64::MAX).min(a.bits());
+        return Ok(Integer::from(a >> amount));
+    }
+    match amount.to_u64() {
+        Some(amount) if amount.saturating_add(a.bits()) <= MAX_INTEGER_BITS => Ok(Integer::from(a << amount)),
+        _ => Err(int_overflow()),
     }
 }
 
 /// Parses a string expression into an Expression.
-/// The parser supports numbers, parentheses, and the operators +, -, *, and / with standard precedence.
-/// Grammar:
-///   Expr   → Term { ("+" | "-") Term }
-///   Term   → Factor { ("*" | "/") Factor }
-///   Factor → number | "(" Expr ")" | "-" Factor
+/// The parser supports numbers, registers (`R0`, `r1`), parentheses, the
+/// evaluable functors of `Expression` and the ISO operator priorities:
+///
+/// | Priority | Type | Operators |
+/// |----------|------|-----------|
+/// | 500 | yfx | `+ - /\ \/ xor` |
+/// | 400 | yfx | `* / // rem mod div << >>` |
+/// | 200 | xfx | `**` |
+/// | 200 | xfy | `^` |
+/// | 200 | fy  | `- \` |
+///
+/// `min`, `max`, `gcd` and `atan2` are written `min(X, Y)` and the other
+/// functions, such as `abs` and `sqrt`, are written `abs(X)`. Numbers are
+/// integers or floats with a fraction (`1.5`, `2.0e-3`); `pi` and `e` are the
+/// float constants. A minus sign applied to a number gives a negative
+/// constant; applied to anything else it is read as `0 - Operand`.
 pub fn parse_expression(input: &str) -> Result<Expression, String> {
     let tokens = tokenize(input)?;
-    let (expr, pos) = parse_expr(&tokens, 0)?;
+    let (expr, pos) = parse_expr(&tokens, 0, 1200)?;
     if pos != tokens.len() {
         return Err(format!("Unexpected token at position {}", pos));
     }
@@ -64,112 +677,193 @@ pub fn parse_expression(input: &str) -> Result<Expression, String> {
 /// Token types for arithmetic parsing.
 #[derive(Debug, PartialEq)]
 enum Token {
-    Number(i32),
-    Plus,
-    Minus,
-    Mul,
-    Div,
+    Number(i64),
+    Float(f64),
+    Register(usize),
+    /// An operator or function name, symbolic (`+`, `//`) or alphanumeric (`mod`, `max`).
+    Name(String),
     LParen,
     RParen,
+    Comma,
 }
 
+/// Symbolic operators, longest first so that `//` is not read as two `/`.
+const SYMBOLS: [&str; 12] = ["//", "/\\", "\\/", "**", "<<", ">>", "+", "-", "*", "/", "^", "\\"];
+
 /// Tokenizes the input arithmetic expression.
 fn tokenize(input: &str) -> Result<Vec<Token>, String> {
     let mut tokens = Vec::new();
-    let mut chars = input.chars().peekable();
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            ' ' | '\t' | '\n' => { chars.next(); },
-            '+' => { tokens.push(Token::Plus); chars.next(); },
-            '-' => { tokens.push(Token::Minus); chars.next(); },
-            '*' => { tokens.push(Token::Mul); chars.next(); },
-            '/' => { tokens.push(Token::Div); chars.next(); },
-            '(' => { tokens.push(Token::LParen); chars.next(); },
-            ')' => { tokens.push(Token::RParen); chars.next(); },
+    let mut rest = input;
+    while let Some(ch) = rest.chars().next() {
+        if ch.is_whitespace() {
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+        let len = match ch {
+            '(' => { tokens.push(Token::LParen); 1 },
+            ')' => { tokens.push(Token::RParen); 1 },
+            ',' => { tokens.push(Token::Comma); 1 },
             '0'..='9' => {
-                let mut num_str = String::new();
-                while let Some(&c) = chars.peek() {
-                    if c.is_digit(10) {
-                        num_str.push(c);
-                        chars.next();
-                    } else {
-                        break;
-                    }
+                let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                match float_length(rest, digits) {
+                    Some(len) => {
+                        let x = rest[..len].parse::<f64>().map_err(|e| format!("Invalid number: {}", e))?;
+                        if !x.is_finite() {
+                            return Err(format!("Float out of range: {}", &rest[..len]));
+                        }
+                        tokens.push(Token::Float(x));
+                        len
+                    },
+                    None => {
+                        let num = rest[..digits].parse::<i64>().map_err(|e| format!("Invalid number: {}", e))?;
+                        tokens.push(Token::Number(num));
+                        digits
+                    },
                 }
-                let num = num_str.parse::<i32>().map_err(|e| format!("Invalid number: {}", e))?;
-                tokens.push(Token::Number(num));
             },
-            _ => return Err(format!("Unexpected character: {}", ch)),
-        }
+            c if c.is_ascii_alphabetic() => {
+                let len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                let word = &rest[..len];
+                match word.strip_prefix(['R', 'r']).and_then(|n| n.parse::<usize>().ok()) {
+                    Some(register) => tokens.push(Token::Register(register)),
+                    None => tokens.push(Token::Name(word.to_string())),
+                }
+                len
+            },
+            _ => match SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+                Some(symbol) => {
+                    tokens.push(Token::Name(symbol.to_string()));
+                    symbol.len()
+                },
+                None => return Err(format!("Unexpected character: {}", ch)),
+            },
+        };
+        rest = &rest[len..];
     }
     Ok(tokens)
 }
 
-/// Parses an expression: Expr → Term { ("+" | "-") Term }
-fn parse_expr(tokens: &[Token], pos: usize) -> Result<(Expression, usize), String> {
-    let (mut expr, mut pos) = parse_term(tokens, pos)?;
-    while pos < tokens.len() {
-        match tokens[pos] {
-            Token::Plus => {
-                pos += 1;
-                let (right, new_pos) = parse_term(tokens, pos)?;
-                expr = Expression::Add(Box::new(expr), Box::new(right));
-                pos = new_pos;
-            },
-            Token::Minus => {
-                pos += 1;
-                let (right, new_pos) = parse_term(tokens, pos)?;
-                expr = Expression::Sub(Box::new(expr), Box::new(right));
-                pos = new_pos;
-            },
-            _ => break,
+/// Returns the length of the float literal at the start of `input`, whose
+/// integer part has `digits` digits: a fraction, as in `1.5`, optionally
+/// followed by an exponent, as in `1.5e10`. Returns `None` for an integer.
+fn float_length(input: &str, digits: usize) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let count_digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+    if bytes.get(digits) != Some(&b'.') {
+        return None;
+    }
+    let fraction = count_digits(digits + 1);
+    if fraction == 0 {
+        return None;
+    }
+    let mut len = digits + 1 + fraction;
+    if matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        let exponent = count_digits(len + 1 + sign);
+        if exponent > 0 {
+            len += 1 + sign + exponent;
         }
     }
-    Ok((expr, pos))
+    Some(len)
 }
 
-/// Parses a term: Term → Factor { ("*" | "/") Factor }
-fn parse_term(tokens: &[Token], pos: usize) -> Result<(Expression, usize), String> {
-    let (mut term, mut pos) = parse_factor(tokens, pos)?;
-    while pos < tokens.len() {
-        match tokens[pos] {
-            Token::Mul => {
-                pos += 1;
-                let (right, new_pos) = parse_factor(tokens, pos)?;
-                term = Expression::Mul(Box::new(term), Box::new(right));
-                pos = new_pos;
-            },
-            Token::Div => {
-                pos += 1;
-                let (right, new_pos) = parse_factor(tokens, pos)?;
-                term = Expression::Div(Box::new(term), Box::new(right));
-                pos = new_pos;
-            },
-            _ => break,
+/// Returns the priority and the maximum priorities of the left and right
+/// operands of an infix operator.
+fn infix(name: &str) -> Option<(u32, u32, u32)> {
+    match name {
+        "+" | "-" | "/\\" | "\\/" | "xor" => Some((500, 500, 499)),
+        "*" | "/" | "//" | "rem" | "mod" | "div" | "<<" | ">>" => Some((400, 400, 399)),
+        "**" => Some((200, 199, 199)),
+        "^" => Some((200, 199, 200)),
+        _ => None,
+    }
+}
+
+/// Parses an expression whose priority is at most `max`.
+fn parse_expr(tokens: &[Token], pos: usize, max: u32) -> Result<(Expression, usize), String> {
+    let (mut expr, mut pos) = parse_primary(tokens, pos)?;
+    let mut priority = 0;
+    while let Some(Token::Name(name)) = tokens.get(pos) {
+        let Some((op_priority, left_max, right_max)) = infix(name) else { break };
+        if op_priority > max || priority > left_max {
+            break;
         }
+        let (right, new_pos) = parse_expr(tokens, pos + 1, right_max)?;
+        expr = Expression::from_binary(name, expr, right).expect("infix operator");
+        priority = op_priority;
+        pos = new_pos;
     }
-    Ok((term, pos))
+    Ok((expr, pos))
 }
 
-/// Parses a factor: Factor → number | "(" Expr ")" | "-" Factor
-fn parse_factor(tokens: &[Token], pos: usize) -> Result<(Expression, usize), String> {
-    if pos >= tokens.len() {
+/// Parses a primary expression: a number, a register, a parenthesized
+/// expression, a prefix operator applied to an operand, or a function call.
+fn parse_primary(tokens: &[Token], pos: usize) -> Result<(Expression, usize), String> {
+    let Some(token) = tokens.get(pos) else {
         return Err("Unexpected end of input".to_string());
-    }
-    match tokens[pos] {
-        Token::Number(n) => Ok((Expression::Const(n), pos + 1)),
+    };
+    match token {
+        Token::Number(n) => Ok((constant(*n)?, pos + 1)),
+        Token::Float(x) => Ok((Expression::Float(*x), pos + 1)),
+        Token::Register(register) => Ok((Expression::Var(*register), pos + 1)),
         Token::LParen => {
-            let (expr, new_pos) = parse_expr(tokens, pos + 1)?;
-            if new_pos >= tokens.len() || tokens[new_pos] != Token::RParen {
+            let (expr, new_pos) = parse_expr(tokens, pos + 1, 1200)?;
+            if tokens.get(new_pos) != Some(&Token::RParen) {
                 return Err("Missing closing parenthesis".to_string());
             }
             Ok((expr, new_pos + 1))
         },
-        Token::Minus => {
-            // Handle unary minus as 0 - Factor.
-            let (factor, new_pos) = parse_factor(tokens, pos + 1)?;
-            Ok((Expression::Sub(Box::new(Expression::Const(0)), Box::new(factor)), new_pos))
+        Token::Name(name) if name == "-" => {
+            match tokens.get(pos + 1) {
+                Some(Token::Number(n)) => return Ok((constant(-n)?, pos + 2)),
+                Some(Token::Float(x)) => return Ok((Expression::Float(-x), pos + 2)),
+                _ => {},
+            }
+            // Handle unary minus as 0 - Operand.
+            let (operand, new_pos) = parse_expr(tokens, pos + 1, 200)?;
+            Ok((Expression::Sub(Box::new(Expression::Const(0)), Box::new(operand)), new_pos))
+        },
+        Token::Name(name) if name == "\\" => {
+            let (operand, new_pos) = parse_expr(tokens, pos + 1, 200)?;
+            Ok((Expression::BitNot(Box::new(operand)), new_pos))
+        },
+        Token::Name(name) if name == "pi" && tokens.get(pos + 1) != Some(&Token::LParen) => {
+            Ok((Expression::Float(std::f64::consts::PI), pos + 1))
+        },
+        Token::Name(name) if name == "e" && tokens.get(pos + 1) != Some(&Token::LParen) => {
+            Ok((Expression::Float(std::f64::consts::E), pos + 1))
+        },
+        Token::Name(name) if tokens.get(pos + 1) == Some(&Token::LParen) => {
+            let mut args = Vec::new();
+            let mut pos = pos + 2;
+            loop {
+                let (arg, new_pos) = parse_expr(tokens, pos, 999)?;
+                args.push(arg);
+                match tokens.get(new_pos) {
+                    Some(Token::Comma) => pos = new_pos + 1,
+                    Some(Token::RParen) => {
+                        pos = new_pos + 1;
+                        break;
+                    },
+                    _ => return Err("Missing closing parenthesis".to_string()),
+                }
+            }
+            let arity = args.len();
+            let mut args = args.into_iter();
+            let expr = match (args.next(), args.next()) {
+                (Some(operand), None) => Expression::from_unary(name, operand),
+                (Some(left), Some(right)) if arity == 2 => Expression::from_binary(name, left, right),
+                _ => None,
+            };
+            expr.map(|expr| (expr, pos)).ok_or_else(|| format!("Unknown function: {}/{}", name, arity))
         },
         _ => Err(format!("Unexpected token at position {}", pos)),
     }
 }
+
+/// Converts a number literal to a constant, rejecting values that do not fit.
+fn constant(n: i64) -> Result<Expression, String> {
+    i32::try_from(n)
+        .map(Expression::Const)
+        .map_err(|_| format!("Integer out of range: {}", n))
+}
@@ -0,0 +1,71 @@
+// src/machine/atom.rs
+//! The atom table: interned names.
+//!
+//! Every name the machine works with — atoms, functor names and predicate
+//! names — is interned once and referred to by an `Atom`, a compact id that is
+//! compared and hashed as an integer. The table belongs to a machine; ids from
+//! one machine mean nothing to another.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The id of an interned name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Atom(u32);
+
+impl Atom {
+    /// Returns the position of the atom in its table.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A table mapping names to atoms and back.
+#[derive(Debug, Clone, Default)]
+pub struct AtomTable {
+    names: Vec<Rc<str>>,
+    ids: HashMap<Rc<str>, Atom>,
+}
+
+impl AtomTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        AtomTable::default()
+    }
+
+    /// Returns the atom for `name`, adding it to the table if needed.
+    pub fn intern(&mut self, name: &str) -> Atom {
+        if let Some(&atom) = self.ids.get(name) {
+            return atom;
+        }
+        let atom = Atom(self.names.len() as u32);
+        let name: Rc<str> = Rc::from(name);
+        self.names.push(Rc::clone(&name));
+        self.ids.insert(name, atom);
+        atom
+    }
+
+    /// Returns the atom for `name` if it has been interned, without adding it.
+    pub fn lookup(&self, name: &str) -> Option<Atom> {
+        self.ids.get(name).copied()
+    }
+
+    /// Returns the name of an atom.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the atom does not belong to this table.
+    pub fn name(&self, atom: Atom) -> &str {
+        &self.names[atom.index()]
+    }
+
+    /// Returns the number of interned names.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if no name has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
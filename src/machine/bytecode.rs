@@ -0,0 +1,886 @@
+// src/machine/bytecode.rs
+//! A compact binary format for compiled LAM programs (`.lamb` files).
+//!
+//! Loading bytecode avoids parsing text, which matters for large rule bases.
+//! A file consists of:
+//! - the magic bytes `LAMB` and a format version (u16, little endian),
+//! - the string table: every predicate name, functor, variable name and string
+//!   constant of the program, stored once and referred to by index,
+//! - the instruction stream: one opcode byte per instruction followed by its
+//!   operands,
+//! - the predicate table: each predicate name with its clause addresses,
+//! - the index table: each predicate name with its keys and clause addresses.
+//!
+//! Unsigned numbers (counts, registers, addresses, string indices) are written
+//! as LEB128 varints and signed integers as zigzag-encoded varints. Tables are
+//! written in a sorted order, so a program always encodes to the same bytes.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use num_bigint::BigInt;
+use thiserror::Error;
+use crate::machine::arithmetic::Expression;
+use crate::machine::instruction::{CallTarget, Instruction};
+use crate::machine::lambda::Strategy;
+use crate::machine::number::Float;
+use crate::machine::program::Program;
+use crate::machine::term::Term;
+
+/// The bytes every bytecode file starts with.
+pub const MAGIC: &[u8; 4] = b"LAMB";
+/// The version of the format written by `Program::write_bytecode`.
+pub const VERSION: u16 = 1;
+
+/// Errors that can occur while reading bytecode.
+#[derive(Debug, Error)]
+pub enum BytecodeError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Not a LAM bytecode file (bad magic header).")]
+    BadMagic,
+    #[error("Unsupported bytecode version {0} (expected {VERSION}).")]
+    UnsupportedVersion(u16),
+    #[error("Malformed bytecode at byte {offset}: {message}")]
+    Malformed { offset: usize, message: String },
+}
+
+impl Program {
+    /// Writes the program in the binary bytecode format.
+    pub fn write_bytecode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut encoder = Encoder::default();
+        encoder.program(self);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        let mut table = Vec::new();
+        write_unsigned(&mut table, encoder.strings.len() as u64);
+        for s in &encoder.strings {
+            write_unsigned(&mut table, s.len() as u64);
+            table.extend_from_slice(s.as_bytes());
+        }
+        out.write_all(&table)?;
+        out.write_all(&encoder.body)
+    }
+
+    /// Reads a program written by `write_bytecode`.
+    pub fn read_bytecode<R: Read>(input: &mut R) -> Result<Program, BytecodeError> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        if bytes.len() < 6 || &bytes[..4] != MAGIC {
+            return Err(BytecodeError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(BytecodeError::UnsupportedVersion(version));
+        }
+        let mut decoder = Decoder { bytes: &bytes, pos: 6, strings: Vec::new() };
+        let program = decoder.program()?;
+        if decoder.pos != bytes.len() {
+            return Err(decoder.malformed("trailing bytes after the program"));
+        }
+        Ok(program)
+    }
+}
+
+/// Instruction opcodes. The numbering is part of the format: new instructions
+/// get new numbers and existing numbers never change.
+mod opcode {
+    pub const PUT_CONST: u8 = 0;
+    pub const PUT_VAR: u8 = 1;
+    pub const GET_CONST: u8 = 2;
+    pub const GET_VAR: u8 = 3;
+    pub const CALL: u8 = 4;
+    pub const PROCEED: u8 = 5;
+    pub const CHOICE: u8 = 6;
+    pub const ALLOCATE: u8 = 7;
+    pub const DEALLOCATE: u8 = 8;
+    pub const ARITHMETIC_IS: u8 = 9;
+    pub const SET_LOCAL: u8 = 10;
+    pub const GET_LOCAL: u8 = 11;
+    pub const FAIL: u8 = 12;
+    pub const GET_STRUCTURE: u8 = 13;
+    pub const INDEXED_CALL: u8 = 14;
+    pub const MULTI_INDEXED_CALL: u8 = 15;
+    pub const TAIL_CALL: u8 = 16;
+    pub const ASSERT_CLAUSE: u8 = 17;
+    pub const RETRACT_CLAUSE: u8 = 18;
+    pub const CUT: u8 = 19;
+    pub const BUILD_COMPOUND: u8 = 20;
+    pub const PUT_STR: u8 = 21;
+    pub const GET_STR: u8 = 22;
+    pub const MOVE: u8 = 23;
+    pub const GET_VALUE: u8 = 24;
+    pub const NEW_VAR: u8 = 25;
+    pub const STORE_LOCAL: u8 = 26;
+    pub const PUT_LOCAL: u8 = 27;
+    pub const HALT: u8 = 28;
+    pub const UNIFY_VAR: u8 = 29;
+    pub const UNIFY_VALUE: u8 = 30;
+    pub const UNIFY_CONST: u8 = 31;
+    pub const BUILD_LAMBDA: u8 = 32;
+    pub const APPLY: u8 = 33;
+    pub const NORMALIZE: u8 = 34;
+    pub const PUT_BIGINT: u8 = 35;
+    pub const GET_BIGINT: u8 = 36;
+    pub const PUT_FLOAT: u8 = 37;
+    pub const GET_FLOAT: u8 = 38;
+    pub const GET_LEVEL: u8 = 39;
+    pub const CUT_TO: u8 = 40;
+    pub const SOFT_CUT: u8 = 41;
+    pub const JUMP: u8 = 42;
+}
+
+/// Tags of the term and expression encodings.
+mod tag {
+    pub const CONST: u8 = 0;
+    pub const VAR: u8 = 1;
+    pub const COMPOUND: u8 = 2;
+    pub const LAMBDA: u8 = 3;
+    pub const APP: u8 = 4;
+    pub const PROB: u8 = 5;
+    pub const CONSTRAINT: u8 = 6;
+    pub const MODAL: u8 = 7;
+    pub const TEMPORAL: u8 = 8;
+    pub const HIGHER_ORDER: u8 = 9;
+    pub const STR: u8 = 10;
+    pub const BIG_INT: u8 = 11;
+    pub const FLOAT: u8 = 12;
+
+    pub const ADD: u8 = 2;
+    pub const SUB: u8 = 3;
+    pub const MUL: u8 = 4;
+    pub const DIV: u8 = 5;
+    pub const INT_DIV: u8 = 6;
+    pub const FLOOR_DIV: u8 = 7;
+    pub const MOD: u8 = 8;
+    pub const REM: u8 = 9;
+    pub const MIN: u8 = 10;
+    pub const MAX: u8 = 11;
+    pub const POW: u8 = 12;
+    pub const INT_POW: u8 = 13;
+    pub const BIT_AND: u8 = 14;
+    pub const BIT_OR: u8 = 15;
+    pub const XOR: u8 = 16;
+    pub const SHL: u8 = 17;
+    pub const SHR: u8 = 18;
+    pub const GCD: u8 = 19;
+    pub const ABS: u8 = 20;
+    pub const SIGN: u8 = 21;
+    pub const BIT_NOT: u8 = 22;
+    pub const MSB: u8 = 23;
+    pub const FLOAT_CONST: u8 = 24;
+    pub const TO_FLOAT: u8 = 25;
+    pub const TO_INTEGER: u8 = 26;
+    pub const TRUNCATE: u8 = 27;
+    pub const ROUND: u8 = 28;
+    pub const CEILING: u8 = 29;
+    pub const FLOOR: u8 = 30;
+    pub const SQRT: u8 = 31;
+    pub const EXP: u8 = 32;
+    pub const LOG: u8 = 33;
+    pub const SIN: u8 = 34;
+    pub const COS: u8 = 35;
+    pub const TAN: u8 = 36;
+    pub const ASIN: u8 = 37;
+    pub const ACOS: u8 = 38;
+    pub const ATAN: u8 = 39;
+    pub const ATAN2: u8 = 40;
+
+    pub const NORMAL_ORDER: u8 = 0;
+    pub const APPLICATIVE_ORDER: u8 = 1;
+    pub const HEAD_NORMAL_FORM: u8 = 2;
+}
+
+fn write_unsigned(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Collects the string table while encoding the body of a program.
+#[derive(Default)]
+struct Encoder {
+    strings: Vec<String>,
+    string_ids: HashMap<String, u64>,
+    body: Vec<u8>,
+}
+
+impl Encoder {
+    fn byte(&mut self, b: u8) {
+        self.body.push(b);
+    }
+
+    fn unsigned(&mut self, n: usize) {
+        write_unsigned(&mut self.body, n as u64);
+    }
+
+    fn signed(&mut self, n: i32) {
+        write_unsigned(&mut self.body, ((n << 1) ^ (n >> 31)) as u32 as u64);
+    }
+
+    /// Writes a big integer as the length of its two's complement bytes,
+    /// least significant first, followed by the bytes.
+    fn big_int(&mut self, n: &BigInt) {
+        let bytes = n.to_signed_bytes_le();
+        self.unsigned(bytes.len());
+        self.body.extend_from_slice(&bytes);
+    }
+
+    /// Writes a float as the eight bytes of its IEEE 754 representation,
+    /// least significant first.
+    fn float(&mut self, x: f64) {
+        self.body.extend_from_slice(&x.to_bits().to_le_bytes());
+    }
+
+    fn string(&mut self, s: &str) {
+        let id = match self.string_ids.get(s) {
+            Some(id) => *id,
+            None => {
+                let id = self.strings.len() as u64;
+                self.strings.push(s.to_string());
+                self.string_ids.insert(s.to_string(), id);
+                id
+            },
+        };
+        write_unsigned(&mut self.body, id);
+    }
+
+    fn registers(&mut self, registers: &[usize]) {
+        self.unsigned(registers.len());
+        for r in registers {
+            self.unsigned(*r);
+        }
+    }
+
+    fn program(&mut self, program: &Program) {
+        self.unsigned(program.instructions.len());
+        for instruction in &program.instructions {
+            self.instruction(instruction);
+        }
+
+        let mut predicates: Vec<_> = program.predicate_table.iter().collect();
+        predicates.sort();
+        self.unsigned(predicates.len());
+        for (name, addresses) in predicates {
+            self.string(name);
+            self.registers(addresses);
+        }
+
+        let mut indexes: Vec<_> = program.index_table.iter().collect();
+        indexes.sort_by_key(|(name, _)| *name);
+        self.unsigned(indexes.len());
+        for (name, keys) in indexes {
+            self.string(name);
+            // Terms are not ordered, so keys are sorted by their debug form.
+            let mut keys: Vec<_> = keys.iter().collect();
+            keys.sort_by_cached_key(|(key, _)| format!("{:?}", key));
+            self.unsigned(keys.len());
+            for (key, addresses) in keys {
+                self.terms(key);
+                self.registers(addresses);
+            }
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::PutConst { register, value } => {
+                self.byte(opcode::PUT_CONST);
+                self.unsigned(*register);
+                self.signed(*value);
+            },
+            Instruction::PutVar { register, var_id, name } => {
+                self.byte(opcode::PUT_VAR);
+                self.unsigned(*register);
+                self.unsigned(*var_id);
+                self.string(name);
+            },
+            Instruction::GetConst { register, value } => {
+                self.byte(opcode::GET_CONST);
+                self.unsigned(*register);
+                self.signed(*value);
+            },
+            Instruction::GetVar { register, var_id, name } => {
+                self.byte(opcode::GET_VAR);
+                self.unsigned(*register);
+                self.unsigned(*var_id);
+                self.string(name);
+            },
+            Instruction::Call { predicate, .. } => {
+                self.byte(opcode::CALL);
+                self.string(predicate);
+            },
+            Instruction::Proceed => self.byte(opcode::PROCEED),
+            Instruction::Choice { alternative } => {
+                self.byte(opcode::CHOICE);
+                self.unsigned(*alternative);
+            },
+            Instruction::Allocate { n } => {
+                self.byte(opcode::ALLOCATE);
+                self.unsigned(*n);
+            },
+            Instruction::Deallocate => self.byte(opcode::DEALLOCATE),
+            Instruction::ArithmeticIs { target, expression } => {
+                self.byte(opcode::ARITHMETIC_IS);
+                self.unsigned(*target);
+                self.expression(expression);
+            },
+            Instruction::SetLocal { index, value } => {
+                self.byte(opcode::SET_LOCAL);
+                self.unsigned(*index);
+                self.term(value);
+            },
+            Instruction::GetLocal { index, register } => {
+                self.byte(opcode::GET_LOCAL);
+                self.unsigned(*index);
+                self.unsigned(*register);
+            },
+            Instruction::Fail => self.byte(opcode::FAIL),
+            Instruction::GetStructure { register, functor, arity } => {
+                self.byte(opcode::GET_STRUCTURE);
+                self.unsigned(*register);
+                self.string(functor);
+                self.unsigned(*arity);
+            },
+            Instruction::IndexedCall { predicate, index_register } => {
+                self.byte(opcode::INDEXED_CALL);
+                self.string(predicate);
+                self.unsigned(*index_register);
+            },
+            Instruction::MultiIndexedCall { predicate, index_registers } => {
+                self.byte(opcode::MULTI_INDEXED_CALL);
+                self.string(predicate);
+                self.registers(index_registers);
+            },
+            Instruction::TailCall { predicate, .. } => {
+                self.byte(opcode::TAIL_CALL);
+                self.string(predicate);
+            },
+            Instruction::AssertClause { predicate, address } => {
+                self.byte(opcode::ASSERT_CLAUSE);
+                self.string(predicate);
+                self.unsigned(*address);
+            },
+            Instruction::RetractClause { predicate, address } => {
+                self.byte(opcode::RETRACT_CLAUSE);
+                self.string(predicate);
+                self.unsigned(*address);
+            },
+            Instruction::Cut => self.byte(opcode::CUT),
+            Instruction::BuildCompound { target, functor, arg_registers } => {
+                self.byte(opcode::BUILD_COMPOUND);
+                self.unsigned(*target);
+                self.string(functor);
+                self.registers(arg_registers);
+            },
+            Instruction::PutStr { register, value } => {
+                self.byte(opcode::PUT_STR);
+                self.unsigned(*register);
+                self.string(value);
+            },
+            Instruction::GetStr { register, value } => {
+                self.byte(opcode::GET_STR);
+                self.unsigned(*register);
+                self.string(value);
+            },
+            Instruction::Move { src, dst } => {
+                self.byte(opcode::MOVE);
+                self.unsigned(*src);
+                self.unsigned(*dst);
+            },
+            Instruction::GetValue { register, src } => {
+                self.byte(opcode::GET_VALUE);
+                self.unsigned(*register);
+                self.unsigned(*src);
+            },
+            Instruction::NewVar { register, name } => {
+                self.byte(opcode::NEW_VAR);
+                self.unsigned(*register);
+                self.string(name);
+            },
+            Instruction::StoreLocal { index, register } => {
+                self.byte(opcode::STORE_LOCAL);
+                self.unsigned(*index);
+                self.unsigned(*register);
+            },
+            Instruction::PutLocal { index, register } => {
+                self.byte(opcode::PUT_LOCAL);
+                self.unsigned(*index);
+                self.unsigned(*register);
+            },
+            Instruction::Halt => self.byte(opcode::HALT),
+            Instruction::UnifyVar { register } => {
+                self.byte(opcode::UNIFY_VAR);
+                self.unsigned(*register);
+            },
+            Instruction::UnifyValue { register } => {
+                self.byte(opcode::UNIFY_VALUE);
+                self.unsigned(*register);
+            },
+            Instruction::UnifyConst { value } => {
+                self.byte(opcode::UNIFY_CONST);
+                self.signed(*value);
+            },
+            Instruction::BuildLambda { target, param, body } => {
+                self.byte(opcode::BUILD_LAMBDA);
+                self.unsigned(*target);
+                self.unsigned(*param);
+                self.unsigned(*body);
+            },
+            Instruction::Apply { target, function, argument } => {
+                self.byte(opcode::APPLY);
+                self.unsigned(*target);
+                self.unsigned(*function);
+                self.unsigned(*argument);
+            },
+            Instruction::Normalize { register, strategy } => {
+                self.byte(opcode::NORMALIZE);
+                self.unsigned(*register);
+                self.byte(match strategy {
+                    Strategy::NormalOrder => tag::NORMAL_ORDER,
+                    Strategy::ApplicativeOrder => tag::APPLICATIVE_ORDER,
+                    Strategy::HeadNormalForm => tag::HEAD_NORMAL_FORM,
+                });
+            },
+            Instruction::PutBigInt { register, value } => {
+                self.byte(opcode::PUT_BIGINT);
+                self.unsigned(*register);
+                self.big_int(value);
+            },
+            Instruction::GetBigInt { register, value } => {
+                self.byte(opcode::GET_BIGINT);
+                self.unsigned(*register);
+                self.big_int(value);
+            },
+            Instruction::PutFloat { register, value } => {
+                self.byte(opcode::PUT_FLOAT);
+                self.unsigned(*register);
+                self.float(*value);
+            },
+            Instruction::GetFloat { register, value } => {
+                self.byte(opcode::GET_FLOAT);
+                self.unsigned(*register);
+                self.float(*value);
+            },
+            Instruction::GetLevel { register } => {
+                self.byte(opcode::GET_LEVEL);
+                self.unsigned(*register);
+            },
+            Instruction::CutTo { register } => {
+                self.byte(opcode::CUT_TO);
+                self.unsigned(*register);
+            },
+            Instruction::SoftCut { register } => {
+                self.byte(opcode::SOFT_CUT);
+                self.unsigned(*register);
+            },
+            Instruction::Jump { address } => {
+                self.byte(opcode::JUMP);
+                self.unsigned(*address);
+            },
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        let (op, left, right) = match expression {
+            Expression::Const(n) => {
+                self.byte(tag::CONST);
+                return self.signed(*n);
+            },
+            Expression::Float(x) => {
+                self.byte(tag::FLOAT_CONST);
+                return self.float(*x);
+            },
+            Expression::Var(register) => {
+                self.byte(tag::VAR);
+                return self.unsigned(*register);
+            },
+            Expression::Add(left, right) => (tag::ADD, left, right),
+            Expression::Sub(left, right) => (tag::SUB, left, right),
+            Expression::Mul(left, right) => (tag::MUL, left, right),
+            Expression::Div(left, right) => (tag::DIV, left, right),
+            Expression::IntDiv(left, right) => (tag::INT_DIV, left, right),
+            Expression::FloorDiv(left, right) => (tag::FLOOR_DIV, left, right),
+            Expression::Mod(left, right) => (tag::MOD, left, right),
+            Expression::Rem(left, right) => (tag::REM, left, right),
+            Expression::Min(left, right) => (tag::MIN, left, right),
+            Expression::Max(left, right) => (tag::MAX, left, right),
+            Expression::Pow(left, right) => (tag::POW, left, right),
+            Expression::IntPow(left, right) => (tag::INT_POW, left, right),
+            Expression::BitAnd(left, right) => (tag::BIT_AND, left, right),
+            Expression::BitOr(left, right) => (tag::BIT_OR, left, right),
+            Expression::Xor(left, right) => (tag::XOR, left, right),
+            Expression::Shl(left, right) => (tag::SHL, left, right),
+            Expression::Shr(left, right) => (tag::SHR, left, right),
+            Expression::Gcd(left, right) => (tag::GCD, left, right),
+            Expression::Atan2(left, right) => (tag::ATAN2, left, right),
+            Expression::Abs(operand) => return self.unary_expression(tag::ABS, operand),
+            Expression::Sign(operand) => return self.unary_expression(tag::SIGN, operand),
+            Expression::BitNot(operand) => return self.unary_expression(tag::BIT_NOT, operand),
+            Expression::Msb(operand) => return self.unary_expression(tag::MSB, operand),
+            Expression::ToFloat(operand) => return self.unary_expression(tag::TO_FLOAT, operand),
+            Expression::ToInteger(operand) => return self.unary_expression(tag::TO_INTEGER, operand),
+            Expression::Truncate(operand) => return self.unary_expression(tag::TRUNCATE, operand),
+            Expression::Round(operand) => return self.unary_expression(tag::ROUND, operand),
+            Expression::Ceiling(operand) => return self.unary_expression(tag::CEILING, operand),
+            Expression::Floor(operand) => return self.unary_expression(tag::FLOOR, operand),
+            Expression::Sqrt(operand) => return self.unary_expression(tag::SQRT, operand),
+            Expression::Exp(operand) => return self.unary_expression(tag::EXP, operand),
+            Expression::Log(operand) => return self.unary_expression(tag::LOG, operand),
+            Expression::Sin(operand) => return self.unary_expression(tag::SIN, operand),
+            Expression::Cos(operand) => return self.unary_expression(tag::COS, operand),
+            Expression::Tan(operand) => return self.unary_expression(tag::TAN, operand),
+            Expression::Asin(operand) => return self.unary_expression(tag::ASIN, operand),
+            Expression::Acos(operand) => return self.unary_expression(tag::ACOS, operand),
+            Expression::Atan(operand) => return self.unary_expression(tag::ATAN, operand),
+        };
+        self.byte(op);
+        self.expression(left);
+        self.expression(right);
+    }
+
+    fn unary_expression(&mut self, op: u8, operand: &Expression) {
+        self.byte(op);
+        self.expression(operand);
+    }
+
+    fn terms(&mut self, terms: &[Term]) {
+        self.unsigned(terms.len());
+        for term in terms {
+            self.term(term);
+        }
+    }
+
+    fn term(&mut self, term: &Term) {
+        match term {
+            Term::Const(n) => {
+                self.byte(tag::CONST);
+                self.signed(*n);
+            },
+            Term::BigInt(n) => {
+                self.byte(tag::BIG_INT);
+                self.big_int(n);
+            },
+            Term::Float(x) => {
+                self.byte(tag::FLOAT);
+                self.float(x.0);
+            },
+            Term::Var(id) => {
+                self.byte(tag::VAR);
+                self.unsigned(*id);
+            },
+            Term::Compound(functor, args) => {
+                self.byte(tag::COMPOUND);
+                self.string(functor);
+                self.terms(args);
+            },
+            Term::Lambda(param, body) => {
+                self.byte(tag::LAMBDA);
+                self.unsigned(*param);
+                self.term(body);
+            },
+            Term::App(function, argument) => {
+                self.byte(tag::APP);
+                self.term(function);
+                self.term(argument);
+            },
+            Term::Prob(inner) => {
+                self.byte(tag::PROB);
+                self.term(inner);
+            },
+            Term::Constraint(name, args) => {
+                self.byte(tag::CONSTRAINT);
+                self.string(name);
+                self.terms(args);
+            },
+            Term::Modal(name, inner) => {
+                self.byte(tag::MODAL);
+                self.string(name);
+                self.term(inner);
+            },
+            Term::Temporal(name, inner) => {
+                self.byte(tag::TEMPORAL);
+                self.string(name);
+                self.term(inner);
+            },
+            Term::HigherOrder(inner) => {
+                self.byte(tag::HIGHER_ORDER);
+                self.term(inner);
+            },
+            Term::Str(s) => {
+                self.byte(tag::STR);
+                self.string(s);
+            },
+        }
+    }
+}
+
+/// Reads a program from a byte buffer.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    strings: Vec<String>,
+}
+
+impl Decoder<'_> {
+    fn malformed(&self, message: &str) -> BytecodeError {
+        BytecodeError::Malformed { offset: self.pos, message: message.to_string() }
+    }
+
+    fn byte(&mut self) -> Result<u8, BytecodeError> {
+        let b = *self.bytes.get(self.pos).ok_or_else(|| self.malformed("unexpected end of input"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn varint(&mut self) -> Result<u64, BytecodeError> {
+        let mut n: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.malformed("varint is too long"))
+    }
+
+    fn unsigned(&mut self) -> Result<usize, BytecodeError> {
+        let n = self.varint()?;
+        usize::try_from(n).map_err(|_| self.malformed("number does not fit in usize"))
+    }
+
+    fn signed(&mut self) -> Result<i32, BytecodeError> {
+        let n = u32::try_from(self.varint()?).map_err(|_| self.malformed("integer out of range"))?;
+        Ok(((n >> 1) as i32) ^ -((n & 1) as i32))
+    }
+
+    fn big_int(&mut self) -> Result<BigInt, BytecodeError> {
+        let len = self.unsigned()?;
+        let end = self.pos.checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| self.malformed("big integer runs past the end"))?;
+        let n = BigInt::from_signed_bytes_le(&self.bytes[self.pos..end]);
+        self.pos = end;
+        Ok(n)
+    }
+
+    fn float(&mut self) -> Result<f64, BytecodeError> {
+        let bytes = self.bytes.get(self.pos..self.pos + 8).ok_or_else(|| self.malformed("float runs past the end"))?;
+        let x = f64::from_bits(u64::from_le_bytes(bytes.try_into().expect("eight bytes")));
+        self.pos += 8;
+        Ok(x)
+    }
+
+    fn string(&mut self) -> Result<String, BytecodeError> {
+        let id = self.unsigned()?;
+        self.strings.get(id).cloned().ok_or_else(|| self.malformed("string index out of range"))
+    }
+
+    fn registers(&mut self) -> Result<Vec<usize>, BytecodeError> {
+        let len = self.unsigned()?;
+        (0..len).map(|_| self.unsigned()).collect()
+    }
+
+    fn program(&mut self) -> Result<Program, BytecodeError> {
+        let count = self.unsigned()?;
+        for _ in 0..count {
+            let len = self.unsigned()?;
+            let end = self.pos.checked_add(len)
+                .filter(|end| *end <= self.bytes.len())
+                .ok_or_else(|| self.malformed("string extends past the end of input"))?;
+            let s = std::str::from_utf8(&self.bytes[self.pos..end])
+                .map_err(|_| self.malformed("string is not valid UTF-8"))?;
+            self.strings.push(s.to_string());
+            self.pos = end;
+        }
+
+        let mut program = Program::default();
+        let count = self.unsigned()?;
+        for _ in 0..count {
+            let instruction = self.instruction()?;
+            program.instructions.push(instruction);
+        }
+
+        let count = self.unsigned()?;
+        for _ in 0..count {
+            let name = self.string()?;
+            let addresses = self.registers()?;
+            program.predicate_table.insert(name, addresses);
+        }
+
+        let count = self.unsigned()?;
+        for _ in 0..count {
+            let name = self.string()?;
+            let num_keys = self.unsigned()?;
+            let mut keys = HashMap::new();
+            for _ in 0..num_keys {
+                let key = self.terms()?;
+                let addresses = self.registers()?;
+                keys.insert(key, addresses);
+            }
+            program.index_table.insert(name, keys);
+        }
+        Ok(program)
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, BytecodeError> {
+        let op = self.byte()?;
+        let instruction = match op {
+            opcode::PUT_CONST => Instruction::PutConst { register: self.unsigned()?, value: self.signed()? },
+            opcode::PUT_VAR => Instruction::PutVar { register: self.unsigned()?, var_id: self.unsigned()?, name: self.string()? },
+            opcode::GET_CONST => Instruction::GetConst { register: self.unsigned()?, value: self.signed()? },
+            opcode::GET_VAR => Instruction::GetVar { register: self.unsigned()?, var_id: self.unsigned()?, name: self.string()? },
+            opcode::CALL => Instruction::Call { predicate: self.string()?, target: CallTarget::Unresolved },
+            opcode::PROCEED => Instruction::Proceed,
+            opcode::CHOICE => Instruction::Choice { alternative: self.unsigned()? },
+            opcode::ALLOCATE => Instruction::Allocate { n: self.unsigned()? },
+            opcode::DEALLOCATE => Instruction::Deallocate,
+            opcode::ARITHMETIC_IS => Instruction::ArithmeticIs { target: self.unsigned()?, expression: self.expression()? },
+            opcode::SET_LOCAL => Instruction::SetLocal { index: self.unsigned()?, value: self.term()? },
+            opcode::GET_LOCAL => Instruction::GetLocal { index: self.unsigned()?, register: self.unsigned()? },
+            opcode::FAIL => Instruction::Fail,
+            opcode::GET_STRUCTURE => Instruction::GetStructure { register: self.unsigned()?, functor: self.string()?, arity: self.unsigned()? },
+            opcode::INDEXED_CALL => Instruction::IndexedCall { predicate: self.string()?, index_register: self.unsigned()? },
+            opcode::MULTI_INDEXED_CALL => Instruction::MultiIndexedCall { predicate: self.string()?, index_registers: self.registers()? },
+            opcode::TAIL_CALL => Instruction::TailCall { predicate: self.string()?, target: CallTarget::Unresolved },
+            opcode::ASSERT_CLAUSE => Instruction::AssertClause { predicate: self.string()?, address: self.unsigned()? },
+            opcode::RETRACT_CLAUSE => Instruction::RetractClause { predicate: self.string()?, address: self.unsigned()? },
+            opcode::CUT => Instruction::Cut,
+            opcode::BUILD_COMPOUND => Instruction::BuildCompound { target: self.unsigned()?, functor: self.string()?, arg_registers: self.registers()? },
+            opcode::PUT_STR => Instruction::PutStr { register: self.unsigned()?, value: self.string()? },
+            opcode::GET_STR => Instruction::GetStr { register: self.unsigned()?, value: self.string()? },
+            opcode::MOVE => Instruction::Move { src: self.unsigned()?, dst: self.unsigned()? },
+            opcode::GET_VALUE => Instruction::GetValue { register: self.unsigned()?, src: self.unsigned()? },
+            opcode::NEW_VAR => Instruction::NewVar { register: self.unsigned()?, name: self.string()? },
+            opcode::STORE_LOCAL => Instruction::StoreLocal { index: self.unsigned()?, register: self.unsigned()? },
+            opcode::PUT_LOCAL => Instruction::PutLocal { index: self.unsigned()?, register: self.unsigned()? },
+            opcode::HALT => Instruction::Halt,
+            opcode::UNIFY_VAR => Instruction::UnifyVar { register: self.unsigned()? },
+            opcode::UNIFY_VALUE => Instruction::UnifyValue { register: self.unsigned()? },
+            opcode::UNIFY_CONST => Instruction::UnifyConst { value: self.signed()? },
+            opcode::BUILD_LAMBDA => Instruction::BuildLambda { target: self.unsigned()?, param: self.unsigned()?, body: self.unsigned()? },
+            opcode::APPLY => Instruction::Apply { target: self.unsigned()?, function: self.unsigned()?, argument: self.unsigned()? },
+            opcode::NORMALIZE => Instruction::Normalize { register: self.unsigned()?, strategy: self.strategy()? },
+            opcode::PUT_BIGINT => Instruction::PutBigInt { register: self.unsigned()?, value: self.big_int()? },
+            opcode::GET_BIGINT => Instruction::GetBigInt { register: self.unsigned()?, value: self.big_int()? },
+            opcode::PUT_FLOAT => Instruction::PutFloat { register: self.unsigned()?, value: self.float()? },
+            opcode::GET_FLOAT => Instruction::GetFloat { register: self.unsigned()?, value: self.float()? },
+            opcode::GET_LEVEL => Instruction::GetLevel { register: self.unsigned()? },
+            opcode::CUT_TO => Instruction::CutTo { register: self.unsigned()? },
+            opcode::SOFT_CUT => Instruction::SoftCut { register: self.unsigned()? },
+            opcode::JUMP => Instruction::Jump { address: self.unsigned()? },
+            _ => {
+                self.pos -= 1;
+                return Err(self.malformed(&format!("unknown opcode {}", op)));
+            },
+        };
+        Ok(instruction)
+    }
+
+    fn expression(&mut self) -> Result<Expression, BytecodeError> {
+        let op = self.byte()?;
+        let make: fn(Box<Expression>, Box<Expression>) -> Expression = match op {
+            tag::CONST => return Ok(Expression::Const(self.signed()?)),
+            tag::FLOAT_CONST => return Ok(Expression::Float(self.float()?)),
+            tag::VAR => return Ok(Expression::Var(self.unsigned()?)),
+            tag::ADD => Expression::Add,
+            tag::SUB => Expression::Sub,
+            tag::MUL => Expression::Mul,
+            tag::DIV => Expression::Div,
+            tag::INT_DIV => Expression::IntDiv,
+            tag::FLOOR_DIV => Expression::FloorDiv,
+            tag::MOD => Expression::Mod,
+            tag::REM => Expression::Rem,
+            tag::MIN => Expression::Min,
+            tag::MAX => Expression::Max,
+            tag::POW => Expression::Pow,
+            tag::INT_POW => Expression::IntPow,
+            tag::BIT_AND => Expression::BitAnd,
+            tag::BIT_OR => Expression::BitOr,
+            tag::XOR => Expression::Xor,
+            tag::SHL => Expression::Shl,
+            tag::SHR => Expression::Shr,
+            tag::GCD => Expression::Gcd,
+            tag::ATAN2 => Expression::Atan2,
+            tag::ABS | tag::SIGN | tag::BIT_NOT | tag::MSB | tag::TO_FLOAT..=tag::ATAN => {
+                let operand = Box::new(self.expression()?);
+                return Ok(match op {
+                    tag::ABS => Expression::Abs(operand),
+                    tag::SIGN => Expression::Sign(operand),
+                    tag::BIT_NOT => Expression::BitNot(operand),
+                    tag::MSB => Expression::Msb(operand),
+                    tag::TO_FLOAT => Expression::ToFloat(operand),
+                    tag::TO_INTEGER => Expression::ToInteger(operand),
+                    tag::TRUNCATE => Expression::Truncate(operand),
+                    tag::ROUND => Expression::Round(operand),
+                    tag::CEILING => Expression::Ceiling(operand),
+                    tag::FLOOR => Expression::Floor(operand),
+                    tag::SQRT => Expression::Sqrt(operand),
+                    tag::EXP => Expression::Exp(operand),
+                    tag::LOG => Expression::Log(operand),
+                    tag::SIN => Expression::Sin(operand),
+                    tag::COS => Expression::Cos(operand),
+                    tag::TAN => Expression::Tan(operand),
+                    tag::ASIN => Expression::Asin(operand),
+                    tag::ACOS => Expression::Acos(operand),
+                    _ => Expression::Atan(operand),
+                });
+            },
+            _ => {
+                self.pos -= 1;
+                return Err(self.malformed(&format!("unknown expression tag {}", op)));
+            },
+        };
+        let left = self.expression()?;
+        let right = self.expression()?;
+        Ok(make(Box::new(left), Box::new(right)))
+    }
+
+    fn strategy(&mut self) -> Result<Strategy, BytecodeError> {
+        let t = self.byte()?;
+        match t {
+            tag::NORMAL_ORDER => Ok(Strategy::NormalOrder),
+            tag::APPLICATIVE_ORDER => Ok(Strategy::ApplicativeOrder),
+            tag::HEAD_NORMAL_FORM => Ok(Strategy::HeadNormalForm),
+            _ => {
+                self.pos -= 1;
+                Err(self.malformed(&format!("unknown reduction strategy {}", t)))
+            },
+        }
+    }
+
+    fn terms(&mut self) -> Result<Vec<Term>, BytecodeError> {
+        let len = self.unsigned()?;
+        (0..len).map(|_| self.term()).collect()
+    }
+
+    fn term(&mut self) -> Result<Term, BytecodeError> {
+        let t = self.byte()?;
+        let term = match t {
+            tag::CONST => Term::Const(self.signed()?),
+            tag::BIG_INT => Term::BigInt(self.big_int()?),
+            tag::FLOAT => Term::Float(Float(self.float()?)),
+            tag::VAR => Term::Var(self.unsigned()?),
+            tag::COMPOUND => Term::Compound(self.string()?, self.terms()?),
+            tag::LAMBDA => Term::Lambda(self.unsigned()?, Box::new(self.term()?)),
+            tag::APP => Term::App(Box::new(self.term()?), Box::new(self.term()?)),
+            tag::PROB => Term::Prob(Box::new(self.term()?)),
+            tag::CONSTRAINT => Term::Constraint(self.string()?, self.terms()?),
+            tag::MODAL => Term::Modal(self.string()?, Box::new(self.term()?)),
+            tag::TEMPORAL => Term::Temporal(self.string()?, Box::new(self.term()?)),
+            tag::HIGHER_ORDER => Term::HigherOrder(Box::new(self.term()?)),
+            tag::STR => Term::Str(self.string()?),
+            _ => {
+                self.pos -= 1;
+                return Err(self.malformed(&format!("unknown term tag {}", t)));
+            },
+        };
+        Ok(term)
+    }
+}
@@ -0,0 +1,327 @@
+// src/machine/call.rs
+//! The `call/N` built-in, which calls a goal only known at run time.
+//!
+//! A goal that names a predicate or a built-in is called directly, with its
+//! arguments in the argument registers. A goal made of control constructs
+//! (cut, conjunction, disjunction, if-then-else, negation or `call/N`) is lowered
+//! to a clause appended to the code, laid out as the Prolog compiler lays out
+//! the same constructs in a clause body. The arguments of the goals inside the
+//! construct become the arguments of that clause, so the clause only depends
+//! on the shape of the goal and is lowered once per shape.
+
+use crate::machine::core::{indicator, Machine};
+use crate::machine::error_handling::{IsoError, MachineError};
+use crate::machine::frame::Frame;
+use crate::machine::instruction::{CallTarget, Instruction};
+use crate::machine::term::Term;
+
+/// Returns how many leading arguments of the control construct `name/arity`
+/// are goals, or `None` if it is not a control construct.
+fn goal_arguments(name: &str, arity: usize) -> Option<usize> {
+    match (name, arity) {
+        (",", 2) | (";", 2) | ("->", 2) | ("*->", 2) => Some(2),
+        ("\\+", 1) => Some(1),
+        ("call", n) if n >= 1 => Some(1),
+        _ => None,
+    }
+}
+
+/// Returns true if `goal` is a control construct rather than a call.
+fn is_control(goal: &Term) -> bool {
+    match goal {
+        Term::Str(name) => matches!(name.as_str(), "!" | "true" | "fail" | "false"),
+        Term::Compound(name, args) => goal_arguments(name, args.len()).is_some(),
+        _ => false,
+    }
+}
+
+/// Adds the arguments `extra` to the goal, as `call/N` does.
+fn add_arguments(goal: Term, extra: Vec<Term>) -> Result<Term, MachineError> {
+    match goal {
+        Term::Var(_) => Err(IsoError::Instantiation.into()),
+        Term::Str(_) if extra.is_empty() => Ok(goal),
+        Term::Str(name) => Ok(Term::Compound(name, extra)),
+        Term::Compound(name, mut args) => {
+            args.extend(extra);
+            Ok(Term::Compound(name, args))
+        },
+        other => Err(IsoError::Type("callable".to_string(), other).into()),
+    }
+}
+
+/// Returns the elements of a list.
+fn list_items(list: &Term) -> Result<Vec<Term>, MachineError> {
+    let mut items = Vec::new();
+    let mut current = list;
+    loop {
+        match current {
+            Term::Str(name) if name == "[]" => return Ok(items),
+            Term::Compound(functor, cell) if functor == "." && cell.len() == 2 => {
+                items.push(cell[0].clone());
+                current = &cell[1];
+            },
+            Term::Var(_) => return Err(IsoError::Instantiation.into()),
+            _ => return Err(IsoError::Type("list".to_string(), list.clone()).into()),
+        }
+    }
+}
+
+/// Returns the list of the given terms.
+fn list_of(items: &[Term]) -> Term {
+    items.iter().rev().fold(Term::Str("[]".to_string()), |tail, item| {
+        Term::Compound(".".to_string(), vec![item.clone(), tail])
+    })
+}
+
+/// Replaces a term by the next argument of the clause lowered for a goal,
+/// written `Var(i)` in the shape for the i-th argument.
+fn argument(term: Term, arguments: &mut Vec<Term>) -> Term {
+    arguments.push(term);
+    Term::Var(arguments.len() - 1)
+}
+
+/// Returns the shape of a goal: the control constructs are kept and every
+/// argument of the other goals is replaced by an argument of the clause,
+/// appended to `arguments`. A variable goal `G` and a goal `call(G, A1, ..., An)`
+/// both become a call of the `call` built-in on the goal and the list of the
+/// extra arguments, which is left to the built-in when the goal is reached.
+/// `goal` is reported as not callable if it has a number in place of a goal.
+fn shape(term: &Term, goal: &Term, arguments: &mut Vec<Term>) -> Result<Term, MachineError> {
+    match term {
+        Term::Var(_) => shape(&Term::Compound("call".to_string(), vec![term.clone()]), goal, arguments),
+        Term::Str(name) => Ok(Term::Str(name.clone())),
+        Term::Compound(name, args) if name == "call" && !args.is_empty() => {
+            let called = argument(args[0].clone(), arguments);
+            let extra = argument(list_of(&args[1..]), arguments);
+            Ok(Term::Compound(name.clone(), vec![called, extra]))
+        },
+        Term::Compound(name, args) => {
+            let args = match goal_arguments(name, args.len()) {
+                Some(_) => args.iter().map(|arg| shape(arg, goal, arguments)).collect::<Result<_, _>>()?,
+                None => args.iter().map(|arg| argument(arg.clone(), arguments)).collect(),
+            };
+            Ok(Term::Compound(name.clone(), args))
+        },
+        _ => Err(IsoError::Type("callable".to_string(), goal.clone()).into()),
+    }
+}
+
+/// Returns true if a cut in the shape of a goal cuts the goal's own barrier,
+/// rather than being local to a condition, a negation or a call.
+fn has_cut(shape: &Term) -> bool {
+    match shape {
+        Term::Str(name) => name == "!",
+        Term::Compound(name, args) => match (name.as_str(), args.as_slice()) {
+            (",", [a, b]) | ("*->", [a, b]) => has_cut(a) || has_cut(b),
+            (";", [c, e]) => match c {
+                Term::Compound(arrow, branch) if (arrow == "->" || arrow == "*->") && branch.len() == 2 => {
+                    has_cut(&branch[1]) || has_cut(e)
+                },
+                _ => has_cut(c) || has_cut(e),
+            },
+            ("->", [_, t]) => has_cut(t),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Lowers the shape of a goal to the body of its clause. The clause's
+/// arguments are kept in the first slots of its environment and the cut
+/// barriers saved by GET_LEVEL in the slots after them.
+struct Lowering {
+    /// Address of the first instruction of `code`.
+    base: usize,
+    /// The lowered instructions.
+    code: Vec<Instruction>,
+    /// Number of environment slots used so far.
+    slots: usize,
+}
+
+impl Lowering {
+    /// Lowers a goal. `cut` is the slot of the barrier a cut in the goal cuts
+    /// to, or `None` for the clause's cut barrier, which CUT reads from the
+    /// machine.
+    fn lower(&mut self, goal: &Term, cut: Option<usize>) {
+        match goal {
+            Term::Str(name) if name == "true" => {},
+            Term::Str(name) if name == "fail" || name == "false" => self.code.push(Instruction::Fail),
+            Term::Str(name) if name == "!" => match cut {
+                Some(slot) => self.cut_to(slot),
+                None => self.code.push(Instruction::Cut),
+            },
+            Term::Compound(name, args) => match (name.as_str(), args.as_slice()) {
+                // Without an else branch, `*->` is a conjunction.
+                (",", [a, b]) | ("*->", [a, b]) => {
+                    self.lower(a, cut);
+                    self.lower(b, cut);
+                },
+                (";", [Term::Compound(arrow, branch), e]) if (arrow == "->" || arrow == "*->") && branch.len() == 2 => {
+                    let level = self.get_level();
+                    let choice = self.emit(Instruction::Choice { alternative: 0 });
+                    self.lower_opaque(&branch[0]);
+                    self.code.push(Instruction::PutLocal { index: level, register: 0 });
+                    if arrow == "*->" {
+                        self.code.push(Instruction::SoftCut { register: 0 });
+                    } else {
+                        self.code.push(Instruction::CutTo { register: 0 });
+                    }
+                    self.lower(&branch[1], cut);
+                    let jump = self.emit(Instruction::Jump { address: 0 });
+                    self.patch(choice);
+                    self.lower(e, cut);
+                    self.patch(jump);
+                },
+                (";", [a, b]) => {
+                    let choice = self.emit(Instruction::Choice { alternative: 0 });
+                    self.lower(a, cut);
+                    let jump = self.emit(Instruction::Jump { address: 0 });
+                    self.patch(choice);
+                    self.lower(b, cut);
+                    self.patch(jump);
+                },
+                ("->", [c, t]) => {
+                    let level = self.get_level();
+                    self.lower_opaque(c);
+                    self.cut_to(level);
+                    self.lower(t, cut);
+                },
+                ("\\+", [g]) => {
+                    let level = self.get_level();
+                    let choice = self.emit(Instruction::Choice { alternative: 0 });
+                    self.lower_opaque(g);
+                    self.cut_to(level);
+                    self.code.push(Instruction::Fail);
+                    self.patch(choice);
+                },
+                _ => {
+                    for (i, arg) in args.iter().enumerate() {
+                        if let Term::Var(slot) = arg {
+                            self.code.push(Instruction::PutLocal { index: *slot, register: i });
+                        }
+                    }
+                    self.code.push(Instruction::Call { predicate: indicator(name, args.len()), target: CallTarget::Unresolved });
+                },
+            },
+            Term::Str(name) => self.code.push(Instruction::Call { predicate: indicator(name, 0), target: CallTarget::Unresolved }),
+            _ => unreachable!("a shape only holds goals"),
+        }
+    }
+
+    /// Lowers a goal whose cuts are local to it: a condition or a negated goal.
+    fn lower_opaque(&mut self, goal: &Term) {
+        if has_cut(goal) {
+            let level = self.get_level();
+            self.lower(goal, Some(level));
+        } else {
+            self.lower(goal, None);
+        }
+    }
+
+    /// Appends an instruction and returns its index in the lowered code.
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    /// Points the CHOICE or JUMP at index `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let next = self.base + self.code.len();
+        match &mut self.code[at] {
+            Instruction::Choice { alternative } => *alternative = next,
+            Instruction::Jump { address } => *address = next,
+            other => unreachable!("cannot patch {}", other),
+        }
+    }
+
+    /// Saves the height of the choice stack in a new environment slot and
+    /// returns the slot.
+    fn get_level(&mut self) -> usize {
+        let slot = self.slots;
+        self.slots += 1;
+        self.code.push(Instruction::GetLevel { register: 0 });
+        self.code.push(Instruction::StoreLocal { index: slot, register: 0 });
+        slot
+    }
+
+    /// Cuts back to the barrier saved in `slot`.
+    fn cut_to(&mut self, slot: usize) {
+        self.code.push(Instruction::PutLocal { index: slot, register: 0 });
+        self.code.push(Instruction::CutTo { register: 0 });
+    }
+}
+
+/// Lowers the shape of a goal with `arity` arguments to a clause starting at
+/// `address`. The clause saves its arguments in its environment, runs the
+/// goal and returns.
+fn lower_clause(shape: &Term, arity: usize, address: usize) -> Vec<Instruction> {
+    let mut lowering = Lowering { base: address + 1 + arity, code: Vec::new(), slots: arity };
+    lowering.lower(shape, None);
+    let mut code = vec![Instruction::Allocate { n: lowering.slots }];
+    code.extend((0..arity).map(|i| Instruction::StoreLocal { index: i, register: i }));
+    code.extend(lowering.code);
+    code.push(Instruction::Deallocate);
+    code.push(Instruction::Proceed);
+    code
+}
+
+impl Machine {
+    /// Built–in predicate: call(Goal, Extra).
+    /// Calls the goal in register 0 with the elements of the list in register 1
+    /// added to its arguments. The Prolog compiler compiles `call(G, A1, ..., An)`
+    /// with a variable G to this call.
+    pub fn builtin_call(&mut self) -> Result<(), MachineError> {
+        let goal = self.argument(0)?;
+        let extra = list_items(&self.argument(1)?)?;
+        let goal = add_arguments(goal, extra)?;
+        if is_control(&goal) {
+            return self.call_construct(&goal);
+        }
+        let (name, args) = match goal {
+            Term::Compound(name, args) => (name, args),
+            Term::Str(name) => (name, Vec::new()),
+            _ => unreachable!("add_arguments only returns callable terms"),
+        };
+        let predicate = indicator(&name, args.len());
+        self.reserve_registers(args.len());
+        for (i, arg) in args.into_iter().enumerate() {
+            self.set_register(i, arg)?;
+        }
+        self.execute_call(predicate)
+    }
+
+    /// Grows the register file to at least `n` registers. The goal of a call
+    /// is only known at run time, so it may have more arguments than any goal
+    /// the machine was sized for.
+    fn reserve_registers(&mut self, n: usize) {
+        if self.registers.len() < n {
+            self.registers.resize(n, None);
+        }
+    }
+
+    /// Calls a goal made of control constructs through the clause lowered
+    /// for its shape, lowering the clause on its first use.
+    fn call_construct(&mut self, goal: &Term) -> Result<(), MachineError> {
+        let mut arguments = Vec::new();
+        let body = shape(goal, goal, &mut arguments)?;
+        let address = match self.compiled_goals.get(&body) {
+            Some(&address) => address,
+            None => {
+                let address = self.code.len();
+                self.code.extend(lower_clause(&body, arguments.len(), address));
+                self.resolve_calls(address);
+                self.compiled_goals.insert(body, address);
+                address
+            },
+        };
+        self.reserve_registers(arguments.len());
+        for (i, arg) in arguments.into_iter().enumerate() {
+            self.set_register(i, arg)?;
+        }
+        // The clause is entered like a predicate, so a cut inside the goal is local to it.
+        self.control_stack.push(Frame { return_pc: self.pc, cut_barrier: self.cut_barrier });
+        self.cut_barrier = self.choice_stack.len();
+        self.pc = address;
+        Ok(())
+    }
+}
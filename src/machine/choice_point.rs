@@ -1,27 +1,44 @@
 // src/machine/choice_point.rs
 //! Choice point structure for backtracking in the LAM.
 //!
-//! A choice point saves the state of the machine so that backtracking can restore it.
+//! A choice point records what backtracking needs to restore the machine: the
+//! argument registers, the tops of the trail, the heap and the variable
+//! numbering, the delayed unification problems, and the control and
+//! environment stacks. The stacks share their frames with the machine (see
+//! `Stack`), so creating a choice point does not copy them, and all bindings
+//! made after it are undone by rolling back the union–find trail.
 
-use std::collections::HashMap;
 use crate::term::Term;
+use crate::machine::heap::Cell;
 use crate::machine::frame::Frame;
+use crate::machine::stack::Stack;
 
 /// A saved machine state used for backtracking.
 #[derive(Debug, Clone)]
 pub struct ChoicePoint {
     /// Saved program counter.
     pub saved_pc: usize,
-    /// Saved registers.
-    pub saved_registers: Vec<Option<Term>>,
-    /// Saved substitution mapping.
-    pub saved_substitution: HashMap<usize, Term>,
-    /// Saved control stack.
-    pub saved_control_stack: Vec<Frame>,
+    /// Saved argument registers.
+    pub saved_registers: Vec<Option<Cell>>,
+    /// Saved control stack (the continuation).
+    pub saved_control_stack: Stack<Frame>,
+    /// Saved environment stack (the environment pointer).
+    pub saved_environment_stack: Stack<Vec<Option<Cell>>>,
     /// Alternative clause addresses for backtracking.
     pub alternative_clauses: Option<Vec<usize>>,
+    /// Remaining solutions of a nondeterministic built–in. Each solution is a list of
+    /// term pairs that are unified when execution resumes at `saved_pc`.
+    pub alternative_bindings: Option<Vec<Vec<(Term, Term)>>>,
     /// The union–find trail length at the time of choice point creation.
     pub uf_trail_len: usize,
-    /// The call level (e.g. the length of the control stack) when this choice point was created.
-    pub call_level: usize,
+    /// The heap length at the time of choice point creation. Cells allocated
+    /// later are discarded on backtracking.
+    pub heap_top: usize,
+    /// The next fresh variable id at the time of choice point creation. Variables
+    /// created later are discarded on backtracking and their ids reused.
+    pub var_top: usize,
+    /// The delayed unification problems at the time of choice point creation.
+    pub saved_delayed: Vec<(Term, Term)>,
+    /// The cut barrier register when this choice point was created, restored on backtracking.
+    pub cut_barrier: usize,
 }
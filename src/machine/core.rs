@@ -1,76 +1,203 @@
 // src/machine/core.rs
 //! Core implementation of the LAM abstract machine.
 
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use log::debug;
 
+use crate::machine::arithmetic;
+use crate::machine::atom::Atom;
 use crate::machine::choice_point::ChoicePoint;
 use crate::machine::error_handling::MachineError;
 use crate::machine::frame::Frame;
-use crate::machine::instruction::Instruction;
-use crate::machine::term::Term;
+use crate::machine::heap::{Cell, Heap};
+use crate::machine::instruction::{CallTarget, Instruction};
+use crate::machine::debruijn::{self, DbTerm};
+use crate::machine::lambda::{free_vars, substitute, Strategy};
+use crate::machine::stack::Stack;
+use crate::machine::term::{Term, COMPOUND_RANK};
 use crate::machine::unification::UnionFind;
+use crate::machine::ops::OpTable;
 
 /// The built–in predicate function type.
 pub type BuiltinPredicate = fn(&mut Machine) -> Result<(), MachineError>;
 
+/// Returns the predicate indicator `name/arity`, which identifies a predicate
+/// in the predicate table, the built-ins and CALL instructions, so that
+/// predicates with the same name and different arities are distinct.
+pub fn indicator(name: &str, arity: usize) -> String {
+    format!("{}/{}", name, arity)
+}
+
+/// Whether the argument instructions that follow GET_STRUCTURE match the
+/// arguments of an existing structure or fill in a newly built one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnifyMode {
+    /// The register held a structure; arguments are unified with its arguments.
+    Read,
+    /// The register held an unbound variable, now bound to a new structure
+    /// whose arguments are written by the argument instructions.
+    Write,
+}
+
+/// What unification does when it would bind a variable to a term containing
+/// that variable, creating a cyclic term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OccursCheck {
+    /// Bind the variable anyway (the usual Prolog behaviour).
+    #[default]
+    Off,
+    /// Fail the unification.
+    On,
+    /// Raise `MachineError::OccursCheck`.
+    Error,
+}
+
+/// How unification treats lambda abstractions and applications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unifier {
+    /// Structural unification: lambda terms are equal up to the renaming of
+    /// their parameters and applications only unify with applications.
+    #[default]
+    FirstOrder,
+    /// Higher-order pattern unification up to beta and eta conversion; problems
+    /// outside the pattern fragment are delayed (see `src/machine/pattern.rs`).
+    Pattern,
+}
+
 /// The LAM abstract machine.
 #[derive(Debug)]
 pub struct Machine {
-    /// Registers.
-    pub registers: Vec<Option<Term>>,
+    /// Registers, holding cells that refer to terms on the heap.
+    pub registers: Vec<Option<Cell>>,
     /// Program (list of instructions).
     pub code: Vec<Instruction>,
     /// Program counter.
     pub pc: usize,
-    /// Current substitution.
-    pub substitution: HashMap<usize, Term>,
     /// Control stack for call/return.
-    pub control_stack: Vec<Frame>,
-    /// Predicate table mapping names to clause addresses.
-    pub predicate_table: HashMap<String, Vec<usize>>,
+    pub control_stack: Stack<Frame>,
+    /// Predicate table mapping predicate indicators (`name/arity`) to clause addresses.
+    pub predicate_table: HashMap<Atom, Vec<usize>>,
     /// Choice stack for backtracking (each choice point is boxed).
     pub choice_stack: Vec<Box<ChoicePoint>>,
+    /// The cut barrier register: the height of the choice stack when the current
+    /// predicate was entered. CUT removes every choice point above it.
+    pub cut_barrier: usize,
     /// Environment stack.
-    pub environment_stack: Vec<Vec<Option<Term>>>,
+    pub environment_stack: Stack<Vec<Option<Cell>>>,
     /// Index table for clause indexing.
-    pub index_table: HashMap<String, HashMap<Vec<Term>, Vec<usize>>>,
+    pub index_table: HashMap<Atom, HashMap<Vec<Term>, Vec<usize>>>,
     /// Mapping from variable IDs to names.
     pub variable_names: HashMap<usize, String>,
-    /// Union–find structure for unification.
-    pub uf: UnionFind,
+    /// Union–find structure for unification, binding variables to heap cells.
+    pub uf: UnionFind<Cell>,
+    /// The heap holding the structures that registers, environments and bindings refer to.
+    pub heap: Heap,
+    /// The mode set by the last GET_STRUCTURE.
+    pub mode: UnifyMode,
+    /// Heap addresses of the structure arguments not yet visited by the argument
+    /// instructions; the start is the next argument (the S register of the WAM).
+    pub structure_arguments: Range<usize>,
+    /// The occurs check applied by unification (off by default).
+    pub occurs_check: OccursCheck,
+    /// The unification algorithm used for lambda terms (first-order by default).
+    pub unifier: Unifier,
+    /// Unification problems delayed by pattern unification because they are
+    /// outside the pattern fragment. They are retried after every unification.
+    pub delayed: Vec<(Term, Term)>,
+    /// The maximum number of reduction steps NORMALIZE and the `normalize` built-ins
+    /// take before giving up on a term.
+    pub reduction_limit: usize,
+    /// If true, normalization also performs eta reduction.
+    pub eta_reduction: bool,
     /// If true, the machine will trace execution.
     pub verbose: bool,
-    /// Built–in predicates.
-    pub builtins: HashMap<String, BuiltinPredicate>,
+    /// The ids of the built–in predicates, by predicate indicator.
+    pub builtins: HashMap<String, usize>,
+    /// The built–in predicates, indexed by id.
+    pub builtin_table: Vec<BuiltinPredicate>,
+    /// The identifier given to the next fresh variable.
+    pub next_var_id: usize,
+    /// Operator table used by `op/3`, `current_op/3` and for writing terms.
+    pub op_table: OpTable,
+    /// Addresses of the clauses lowered by `call/N` for goals made of control
+    /// constructs, keyed by the shape of the goal.
+    pub compiled_goals: HashMap<Term, usize>,
 }
 
 impl Machine {
     /// Creates a new machine with the specified number of registers and program code.
     pub fn new(num_registers: usize, code: Vec<Instruction>) -> Self {
+        // Fresh variables are numbered above every variable id mentioned in the code.
+        let next_var_id = code.iter()
+            .filter_map(|instr| match instr {
+                Instruction::PutVar { var_id, .. } | Instruction::GetVar { var_id, .. } => Some(var_id + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
         let mut machine = Self {
             registers: vec![None; num_registers],
             code,
             pc: 0,
-            substitution: HashMap::new(),
-            control_stack: Vec::new(),
+            control_stack: Stack::new(),
             predicate_table: HashMap::new(),
             choice_stack: Vec::new(),
-            environment_stack: Vec::new(),
+            cut_barrier: 0,
+            environment_stack: Stack::new(),
             index_table: HashMap::new(),
             variable_names: HashMap::new(),
-            uf: UnionFind::new(),
+            uf: UnionFind::default(),
+            heap: Heap::new(),
+            mode: UnifyMode::Read,
+            structure_arguments: 0..0,
+            occurs_check: OccursCheck::Off,
+            unifier: Unifier::FirstOrder,
+            delayed: Vec::new(),
+            reduction_limit: 10_000,
+            eta_reduction: false,
             verbose: false,
             builtins: HashMap::new(),
+            builtin_table: Vec::new(),
+            next_var_id,
+            op_table: OpTable::default(),
+            compiled_goals: HashMap::new(),
         };
         // Register example built–in predicates.
-        machine.builtins.insert("print".to_string(), Machine::builtin_print);
-        machine.builtins.insert("print_subst".to_string(), Machine::builtin_print_subst);
-        machine.builtins.insert("write".to_string(), Machine::builtin_write);
-        machine.builtins.insert("nl".to_string(), Machine::builtin_nl);
-        machine.builtins.insert("halt".to_string(), Machine::builtin_halt);
+        machine.register_builtin(indicator("print", 0), Machine::builtin_print);
+        machine.register_builtin(indicator("print_subst", 0), Machine::builtin_print_subst);
+        machine.register_builtin(indicator("write", 1), Machine::builtin_write);
+        machine.register_builtin(indicator("nl", 0), Machine::builtin_nl);
+        machine.register_builtin(indicator("halt", 0), Machine::builtin_halt);
         // Register the equality built-in for unification.
-        machine.builtins.insert("=".to_string(), Machine::builtin_eq);
+        machine.register_builtin(indicator("=", 2), Machine::builtin_eq);
+        machine.register_builtin(indicator("unify_with_occurs_check", 2), Machine::builtin_unify_with_occurs_check);
+        // Register the arithmetic built-ins.
+        machine.register_builtin(indicator("is", 2), Machine::builtin_is);
+        machine.register_builtin(indicator("<", 2), Machine::builtin_less_than);
+        machine.register_builtin(indicator(">", 2), Machine::builtin_greater_than);
+        machine.register_builtin(indicator("=<", 2), Machine::builtin_less_or_equal);
+        machine.register_builtin(indicator(">=", 2), Machine::builtin_greater_or_equal);
+        machine.register_builtin(indicator("=:=", 2), Machine::builtin_arithmetic_equal);
+        machine.register_builtin(indicator("=\\=", 2), Machine::builtin_arithmetic_not_equal);
+        // Register the standard order comparison built-ins.
+        machine.register_builtin(indicator("compare", 3), Machine::builtin_compare);
+        machine.register_builtin(indicator("==", 2), Machine::builtin_identical);
+        machine.register_builtin(indicator("\\==", 2), Machine::builtin_not_identical);
+        machine.register_builtin(indicator("@<", 2), Machine::builtin_term_less);
+        machine.register_builtin(indicator("@>", 2), Machine::builtin_term_greater);
+        machine.register_builtin(indicator("@=<", 2), Machine::builtin_term_less_or_equal);
+        machine.register_builtin(indicator("@>=", 2), Machine::builtin_term_greater_or_equal);
+        // Register the meta-call built-in.
+        machine.register_builtin(indicator("call", 2), Machine::builtin_call);
+        // Register the lambda calculus built-ins.
+        machine.register_builtin(indicator("normalize", 2), Machine::builtin_normalize);
+        machine.register_builtin(indicator("normalize_with", 3), Machine::builtin_normalize_with);
+        // Register the operator table built-ins.
+        machine.register_builtin(indicator("op", 3), Machine::builtin_op);
+        machine.register_builtin(indicator("current_op", 3), Machine::builtin_current_op);
+        machine.resolve_calls(0);
         machine
     }
 
@@ -79,13 +206,20 @@ impl Machine {
         if self.verbose {
             debug!("PC {}: Executing {:?}", self.pc - 1, instr);
             debug!("Registers: {:?}", self.registers);
-            debug!("Substitution: {:?}", self.substitution);
         }
     }
 
+    /// Returns the id of a new variable that has never been used by the machine.
+    pub fn fresh_var_id(&mut self) -> usize {
+        let id = self.next_var_id;
+        self.next_var_id += 1;
+        id
+    }
+
     /// Updates the index table upon clause retraction.
     pub fn update_index_table_on_retract(&mut self, predicate: &str, clause_address: usize) {
-        if let Some(index_map) = self.index_table.get_mut(predicate) {
+        let Some(predicate) = self.heap.lookup(predicate) else { return };
+        if let Some(index_map) = self.index_table.get_mut(&predicate) {
             for (_key, clauses) in index_map.iter_mut() {
                 if let Some(pos) = clauses.iter().position(|&a| a == clause_address) {
                     clauses.remove(pos);
@@ -109,57 +243,355 @@ impl Machine {
 
     /// Registers an indexed clause.
     pub fn register_indexed_clause(&mut self, predicate: String, key: Vec<Term>, address: usize) {
-        let entry = self.index_table.entry(predicate).or_insert_with(HashMap::new);
-        entry.entry(key).or_insert_with(Vec::new).push(address);
+        let predicate = self.intern(&predicate);
+        let entry = self.index_table.entry(predicate).or_default();
+        entry.entry(key).or_default().push(address);
+    }
+
+    /// Registers a built–in predicate under the predicate indicator `predicate`,
+    /// replacing any built-in registered there before.
+    pub fn register_builtin(&mut self, predicate: String, builtin: BuiltinPredicate) {
+        match self.builtins.get(&predicate) {
+            Some(&id) => self.builtin_table[id] = builtin,
+            None => {
+                self.builtins.insert(predicate, self.builtin_table.len());
+                self.builtin_table.push(builtin);
+            },
+        }
+    }
+
+    /// Resolves the target of a call to the predicate indicator `predicate`:
+    /// the built-in of that indicator if there is one, and otherwise the
+    /// predicate, whose clauses may be added after the call is resolved.
+    pub fn resolve_call(&mut self, predicate: &str) -> CallTarget {
+        match self.builtins.get(predicate) {
+            Some(&id) => CallTarget::Builtin(id),
+            None => CallTarget::Predicate(self.intern(predicate)),
+        }
+    }
+
+    /// Resolves the targets of the CALL and TAIL_CALL instructions of the code
+    /// from address `start` on.
+    pub fn resolve_calls(&mut self, start: usize) {
+        for address in start..self.code.len() {
+            let predicate = match &self.code[address] {
+                Instruction::Call { predicate, .. } | Instruction::TailCall { predicate, .. } => predicate.clone(),
+                _ => continue,
+            };
+            let resolved = self.resolve_call(&predicate);
+            if let Instruction::Call { target, .. } | Instruction::TailCall { target, .. } = &mut self.code[address] {
+                *target = resolved;
+            }
+        }
     }
 
     /// Registers a clause for a given predicate.
     pub fn register_predicate(&mut self, name: String, address: usize) {
-        self.predicate_table.entry(name).or_insert_with(Vec::new).push(address);
+        let name = self.intern(&name);
+        self.predicate_table.entry(name).or_default().push(address);
+    }
+
+    /// Returns the atom for a name, adding it to the machine's atom table if needed.
+    /// Predicate names, functors and atomic constants are all interned here.
+    pub fn intern(&mut self, name: &str) -> Atom {
+        self.heap.intern(name)
+    }
+
+    /// Returns the name of an atom interned by this machine.
+    pub fn atom_name(&self, atom: Atom) -> &str {
+        self.heap.atom_name(atom)
+    }
+
+    /// Returns the term held in a register, with variables read as they are.
+    pub fn register(&self, register: usize) -> Option<Term> {
+        self.registers.get(register)?.as_ref().map(|cell| self.heap.term(cell))
+    }
+
+    /// Stores a term on the heap and puts it in a register.
+    pub fn set_register(&mut self, register: usize, term: Term) -> Result<(), MachineError> {
+        if register >= self.registers.len() {
+            return Err(MachineError::RegisterOutOfBounds(register));
+        }
+        let cell = self.heap.put_term(&term);
+        self.registers[register] = Some(cell);
+        Ok(())
+    }
+
+    /// Resolves a term to its current binding (without resolving its subterms).
+    pub fn resolve(&mut self, term: &Term) -> Term {
+        let cell = self.heap.put_term(term);
+        let resolved = self.uf.resolve(&cell);
+        self.heap.term(&resolved)
+    }
+
+    /// Resolves a term and all of its subterms to their current bindings.
+    pub fn resolve_deep(&mut self, term: &Term) -> Term {
+        let cell = self.heap.put_term(term);
+        self.read_term(&cell)
+    }
+
+    /// Reads the term a cell refers to, with every bound variable replaced by its value.
+    ///
+    /// A cyclic term (one bound without the occurs check) is read up to the
+    /// point where it refers back to itself: a variable whose value is already
+    /// being read is left as the variable, so `X = f(X)` reads as `f(X)`.
+    pub fn read_term(&mut self, cell: &Cell) -> Term {
+        let mut reading = HashSet::new();
+        self.read_cell(cell, &mut reading)
+    }
+
+    /// Reads a cell; `reading` holds the variables whose values are being read.
+    ///
+    /// Structures are read with an explicit stack rather than by recursion, so
+    /// reading a long list does not exhaust the Rust stack. The terms read are
+    /// kept on `terms` until the structure that contains them is built.
+    fn read_cell(&mut self, cell: &Cell, reading: &mut HashSet<usize>) -> Term {
+        enum Task {
+            Read(Cell),
+            Build(String, usize),
+            Leave(usize),
+        }
+        let mut tasks = vec![Task::Read(cell.clone())];
+        let mut terms = Vec::new();
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Read(Cell::Var(v)) => {
+                    let binding = if reading.contains(&v) { None } else { self.uf.bindings.get(&v).cloned() };
+                    match binding {
+                        Some(binding) => {
+                            reading.insert(v);
+                            tasks.push(Task::Leave(v));
+                            tasks.push(Task::Read(binding));
+                        },
+                        None => terms.push(Term::Var(v)),
+                    }
+                },
+                Task::Read(Cell::Str(address)) => {
+                    let (functor, args) = self.heap.arguments(address);
+                    tasks.push(Task::Build(self.heap.functor_name(functor).to_string(), args.len()));
+                    // Pushed last first, so that the arguments are read left to right.
+                    tasks.extend(args.iter().rev().cloned().map(Task::Read));
+                },
+                Task::Read(Cell::Term(term)) => {
+                    let term = self.read_extended(&term, &mut Vec::new(), reading);
+                    terms.push(term);
+                },
+                Task::Read(other) => terms.push(self.heap.term(&other)),
+                Task::Build(name, arity) => {
+                    let args = terms.split_off(terms.len() - arity);
+                    terms.push(Term::Compound(name, args));
+                },
+                Task::Leave(v) => {
+                    reading.remove(&v);
+                },
+            }
+        }
+        terms.pop().expect("the term read")
+    }
+
+    /// Reads a term without a cell layout, replacing its free variables by their
+    /// values; `parameters` holds the parameters of the enclosing lambdas.
+    fn read_extended(&mut self, term: &Term, parameters: &mut Vec<usize>, reading: &mut HashSet<usize>) -> Term {
+        match term {
+            Term::Var(v) if !parameters.contains(v) => self.read_cell(&Cell::Var(*v), reading),
+            Term::Lambda(param, body) => {
+                parameters.push(*param);
+                let body = self.read_extended(body, parameters, reading);
+                parameters.pop();
+                Term::Lambda(*param, Box::new(body))
+            },
+            Term::Compound(name, args) => {
+                Term::Compound(name.clone(), args.iter().map(|arg| self.read_extended(arg, parameters, reading)).collect())
+            },
+            Term::Constraint(name, args) => {
+                Term::Constraint(name.clone(), args.iter().map(|arg| self.read_extended(arg, parameters, reading)).collect())
+            },
+            Term::App(function, argument) => Term::App(
+                Box::new(self.read_extended(function, parameters, reading)),
+                Box::new(self.read_extended(argument, parameters, reading)),
+            ),
+            Term::Prob(inner) => Term::Prob(Box::new(self.read_extended(inner, parameters, reading))),
+            Term::Modal(op, inner) => Term::Modal(op.clone(), Box::new(self.read_extended(inner, parameters, reading))),
+            Term::Temporal(op, inner) => Term::Temporal(op.clone(), Box::new(self.read_extended(inner, parameters, reading))),
+            Term::HigherOrder(inner) => Term::HigherOrder(Box::new(self.read_extended(inner, parameters, reading))),
+            other => other.clone(),
+        }
     }
 
     /// Unifies two terms.
     pub fn unify(&mut self, t1: &Term, t2: &Term) -> Result<(), MachineError> {
         debug!("Attempting to unify {:?} with {:?}", t1, t2);
-        let resolved1 = self.uf.resolve(t1);
-        let resolved2 = self.uf.resolve(t2);
-    
-        match (&resolved1, &resolved2) {
-            (&Term::Const(a), &Term::Const(b)) => {
-                if a == b {
-                    debug!("Constants matched: {} == {}", a, b);
-                    Ok(())
-                } else {
-                    Err(MachineError::UnificationFailed(format!("Constants do not match: {} vs {}", a, b)))
+        let c1 = self.heap.put_term(t1);
+        let c2 = self.heap.put_term(t2);
+        self.unify_cells(&c1, &c2)
+    }
+
+    /// Unifies the terms two cells refer to, applying the machine's occurs check mode.
+    pub fn unify_cells(&mut self, c1: &Cell, c2: &Cell) -> Result<(), MachineError> {
+        self.unify_cells_with(c1, c2, self.occurs_check)
+    }
+
+    /// Unifies the terms two cells refer to with the given occurs check mode.
+    ///
+    /// Arguments are unified with an explicit stack rather than by recursion, and
+    /// a pair of structures is unified at most once, so unifying cyclic terms
+    /// terminates.
+    pub fn unify_cells_with(&mut self, c1: &Cell, c2: &Cell, occurs_check: OccursCheck) -> Result<(), MachineError> {
+        let mut pending = vec![(c1.clone(), c2.clone())];
+        let mut unified = HashSet::new();
+        while let Some((c1, c2)) = pending.pop() {
+            let resolved1 = self.uf.resolve(&c1);
+            let resolved2 = self.uf.resolve(&c2);
+
+            if self.unifier == Unifier::Pattern {
+                match (&resolved1, &resolved2) {
+                    (Cell::Var(_), _) | (_, Cell::Var(_)) => {},
+                    (Cell::Term(_), _) | (_, Cell::Term(_)) => {
+                        let t1 = self.read_term(&resolved1);
+                        let t2 = self.read_term(&resolved2);
+                        self.unify_patterns(&t1, &t2)?;
+                        continue;
+                    },
+                    _ => {},
                 }
-            },
-            (&Term::Str(ref s1), &Term::Str(ref s2)) => {
-                if s1 == s2 {
-                    debug!("String constants matched: {} == {}", s1, s2);
-                    Ok(())
-                } else {
-                    Err(MachineError::UnificationFailed(format!("String constants do not match: {} vs {}", s1, s2)))
+            }
+
+            match (&resolved1, &resolved2) {
+                (&Cell::Int(a), &Cell::Int(b)) => {
+                    if a != b {
+                        return Err(MachineError::UnificationFailed(format!("Constants do not match: {} vs {}", a, b)));
+                    }
+                    debug!("Constants matched: {} == {}", a, b);
+                },
+                (&Cell::Atom(a1), &Cell::Atom(a2)) => {
+                    if a1 != a2 {
+                        return Err(MachineError::UnificationFailed(format!(
+                            "String constants do not match: {} vs {}",
+                            self.heap.atom_name(a1), self.heap.atom_name(a2)
+                        )));
+                    }
+                    debug!("String constants matched: {}", self.heap.atom_name(a1));
+                },
+                (&Cell::Var(v), other) | (other, &Cell::Var(v)) => {
+                    debug!("Binding variable {} to {:?}", v, other);
+                    self.bind(v, other, occurs_check)?;
+                },
+                (&Cell::Str(p1), &Cell::Str(p2)) => {
+                    if p1 == p2 || !unified.insert((p1, p2)) {
+                        continue;
+                    }
+                    let (f1, args1) = self.heap.arguments(p1);
+                    let (f2, _) = self.heap.arguments(p2);
+                    if f1 != f2 {
+                        return Err(MachineError::UnificationFailed(format!(
+                            "Compound term mismatch: {} vs {}",
+                            self.heap.functor_name(f1), self.heap.functor_name(f2)
+                        )));
+                    }
+                    // Arguments live right after the functor cells. They are
+                    // pushed last first, so that they are unified left to right.
+                    for i in (1..=args1.len()).rev() {
+                        let a = self.heap.get(p1 + i).cloned().expect("structure argument");
+                        let b = self.heap.get(p2 + i).cloned().expect("structure argument");
+                        pending.push((a, b));
+                    }
+                },
+                (Cell::Term(t1), Cell::Term(t2)) => self.unify_extended(t1, t2, &mut pending, occurs_check)?,
+                (c1, c2) => return Err(MachineError::UnificationFailed(format!(
+                    "Failed to unify {:?} with {:?}", self.heap.term(c1), self.heap.term(c2)
+                ))),
+            }
+        }
+        if self.unifier == Unifier::Pattern && !self.delayed.is_empty() {
+            self.wake_delayed()?;
+        }
+        Ok(())
+    }
+
+    /// Unifies two terms without a cell layout. The wrappers unify structurally:
+    /// the pairs of their parts are put on the heap and pushed onto `pending`.
+    /// Lambda abstractions unify up to the renaming of their parameters (alpha
+    /// equivalence): both parameters are renamed to one fresh variable and the
+    /// bodies are unified, which fails if that variable gets bound or ends up in
+    /// the binding of a variable outside the lambdas.
+    fn unify_extended(&mut self, t1: &Term, t2: &Term, pending: &mut Vec<(Cell, Cell)>, occurs_check: OccursCheck) -> Result<(), MachineError> {
+        let mut push = |machine: &mut Self, a: &Term, b: &Term| {
+            pending.push((machine.heap.put_term(a), machine.heap.put_term(b)));
+        };
+        match (t1, t2) {
+            (Term::Lambda(x, body1), Term::Lambda(y, body2)) => {
+                let param = self.fresh_var_id();
+                let body1 = self.heap.put_term(&substitute(body1, *x, &Term::Var(param)));
+                let body2 = self.heap.put_term(&substitute(body2, *y, &Term::Var(param)));
+                let trail_len = self.uf.trail.len();
+                self.unify_cells_with(&body1, &body2, occurs_check)?;
+                if self.escapes(param, trail_len) {
+                    return Err(MachineError::UnificationFailed(format!(
+                        "Failed to unify {:?} with {:?}: the parameter escapes its scope", t1, t2
+                    )));
                 }
             },
-            (&Term::Var(v), other) => {
-                debug!("Binding variable {} to {:?}", v, other);
-                self.uf.bind(v, other)
+            (Term::BigInt(a), Term::BigInt(b)) if a == b => {},
+            (Term::Float(a), Term::Float(b)) if a == b => {},
+            (Term::App(f1, a1), Term::App(f2, a2)) => {
+                push(self, a1, a2);
+                push(self, f1, f2);
             },
-            (other, &Term::Var(v)) => {
-                debug!("Binding variable {} to {:?}", v, other);
-                self.uf.bind(v, other)
+            (Term::Prob(a), Term::Prob(b)) | (Term::HigherOrder(a), Term::HigherOrder(b)) => push(self, a, b),
+            (Term::Modal(op1, a), Term::Modal(op2, b)) | (Term::Temporal(op1, a), Term::Temporal(op2, b)) if op1 == op2 => {
+                push(self, a, b);
             },
-            (&Term::Compound(ref f1, ref args1), &Term::Compound(ref f2, ref args2)) => {
-                if f1 != f2 || args1.len() != args2.len() {
-                    return Err(MachineError::UnificationFailed(format!("Compound term mismatch: {} vs {}", f1, f2)));
-                }
-                for (a, b) in args1.iter().zip(args2.iter()) {
-                    self.unify(a, b)?;
+            (Term::Constraint(name1, args1), Term::Constraint(name2, args2)) if name1 == name2 && args1.len() == args2.len() => {
+                for (a, b) in args1.iter().zip(args2).rev() {
+                    push(self, a, b);
                 }
-                Ok(())
             },
-            (t1, t2) => Err(MachineError::UnificationFailed(format!("Failed to unify {:?} with {:?}", t1, t2))),
+            _ => return Err(MachineError::UnificationFailed(format!("Failed to unify {:?} with {:?}", t1, t2))),
+        }
+        Ok(())
+    }
+
+    /// Returns true if the variable `param` standing for a lambda parameter has
+    /// been bound, or occurs in a binding recorded on the trail after `trail_len`.
+    fn escapes(&mut self, param: usize, trail_len: usize) -> bool {
+        if self.uf.bindings.contains_key(&param) {
+            return true;
+        }
+        let bound: Vec<usize> = self.uf.trail[trail_len..].iter().map(|entry| entry.var).collect();
+        bound.into_iter().any(|var| match self.uf.bindings.get(&var).cloned() {
+            Some(binding) => self.occurs(param, &binding),
+            None => false,
+        })
+    }
+
+    /// Binds a variable to a cell, first checking that the variable does not
+    /// occur in the term unless the occurs check is off.
+    fn bind(&mut self, var: usize, cell: &Cell, occurs_check: OccursCheck) -> Result<(), MachineError> {
+        if occurs_check != OccursCheck::Off && self.occurs(var, cell) {
+            let term = self.heap.term(cell);
+            return match occurs_check {
+                OccursCheck::Error => Err(MachineError::OccursCheck(var, term)),
+                _ => Err(MachineError::UnificationFailed(format!("Variable {} occurs in {:?}", var, term))),
+            };
+        }
+        self.uf.bind(var, cell)
+    }
+
+    /// Returns true if the variable `var` occurs in the term `cell` refers to.
+    pub fn occurs(&mut self, var: usize, cell: &Cell) -> bool {
+        let mut pending = vec![cell.clone()];
+        let mut visited = HashSet::new();
+        while let Some(cell) = pending.pop() {
+            match self.uf.resolve(&cell) {
+                Cell::Var(v) if v == var => return true,
+                Cell::Str(address) if visited.insert(address) => {
+                    pending.extend_from_slice(self.heap.arguments(address).1);
+                },
+                Cell::Term(term) => pending.extend(free_vars(&term).into_iter().map(Cell::Var)),
+                _ => {},
+            }
         }
+        false
     }
 
     /// Built–in predicate: halt.
@@ -174,13 +606,13 @@ impl Machine {
     pub fn builtin_print(&mut self) -> Result<(), MachineError> {
         println!("--- Machine Registers ---");
         for (i, reg) in self.registers.iter().enumerate() {
-            if let Some(term) = reg {
-                match term {
+            if let Some(cell) = reg {
+                match self.heap.term(cell) {
                     Term::Var(id) => {
-                        let name = self.variable_names.get(id).cloned().unwrap_or_default();
+                        let name = self.variable_names.get(&id).cloned().unwrap_or_default();
                         println!("Reg {:>3}: Var({}) \"{}\"", i, id, name);
                     }
-                    _ => println!("Reg {:>3}: {:?}", i, term),
+                    term => println!("Reg {:>3}: {:?}", i, term),
                 }
             }
         }
@@ -189,38 +621,36 @@ impl Machine {
     }
 
     /// Built–in predicate: prints the current substitution.
+    /// Every named variable that is bound is printed with its value, read
+    /// through the union–find bindings.
     pub fn builtin_print_subst(&mut self) -> Result<(), MachineError> {
         println!("--- Current Substitution ---");
-        if self.substitution.is_empty() {
-            println!("(no bindings)");
-        } else {
-            for (var_id, term) in &self.substitution {
-                let var_name = self.variable_names.get(var_id).cloned().unwrap_or_default();
-                println!("Variable {} (id {}) = {:?}", 
-                    if var_name.is_empty() { format!("_{}", var_id) } else { var_name },
-                    var_id,
-                    term);
+        let mut names: Vec<(usize, String)> = self.variable_names.iter()
+            .map(|(id, name)| (*id, name.clone()))
+            .collect();
+        names.sort();
+        let mut bound = false;
+        for (var_id, var_name) in names {
+            let term = self.resolve_deep(&Term::Var(var_id));
+            if term != Term::Var(var_id) {
+                println!("Variable {} (id {}) = {:?}", var_name, var_id, term);
+                bound = true;
             }
         }
+        if !bound {
+            println!("(no bindings)");
+        }
         println!("----------------------------");
         Ok(())
     }
 
     /// Built–in predicate: writes a term from register 0.
-    /// **Changed:** If the term is a compound with functor "-" and two arguments,
-    /// print it in the format "arg0-arg1".
+    /// Operator terms are written in operator notation using the machine's operator table.
     pub fn builtin_write(&mut self) -> Result<(), MachineError> {
-        if let Some(Some(term)) = self.registers.get(0) {
-            // Resolve the term to see if it has been bound to another term.
-            let resolved = self.uf.resolve(term);
-            match resolved {
-                Term::Compound(ref functor, ref args) if functor == "-" && args.len() == 2 => {
-                    print!("{}-{}", args[0], args[1]);
-                },
-                _ => {
-                    print!("{}", resolved);
-                }
-            }
+        if let Some(Some(cell)) = self.registers.first().cloned() {
+            // Resolve the term (and its arguments) to see if it has been bound to another term.
+            let resolved = self.read_term(&cell);
+            print!("{}", resolved.display_with(&self.op_table));
             use std::io::{self, Write};
             io::stdout().flush().unwrap();
         }
@@ -236,20 +666,294 @@ impl Machine {
     /// Built–in predicate for equality (unification).
     /// Assumes that the left argument is in register 0 and the right argument is in register 1.
     pub fn builtin_eq(&mut self) -> Result<(), MachineError> {
-        let term1 = self.registers.get(0)
+        let term1 = self.registers.first()
             .and_then(|opt| opt.clone())
             .ok_or(MachineError::UninitializedRegister(0))?;
         let term2 = self.registers.get(1)
             .and_then(|opt| opt.clone())
             .ok_or(MachineError::UninitializedRegister(1))?;
-        self.unify(&term1, &term2)
+        self.unify_cells(&term1, &term2)
+    }
+
+    /// Built–in predicate unify_with_occurs_check/2: unifies registers 0 and 1,
+    /// failing rather than creating a cyclic term whatever the occurs check mode.
+    pub fn builtin_unify_with_occurs_check(&mut self) -> Result<(), MachineError> {
+        let term1 = self.registers.first()
+            .and_then(|opt| opt.clone())
+            .ok_or(MachineError::UninitializedRegister(0))?;
+        let term2 = self.registers.get(1)
+            .and_then(|opt| opt.clone())
+            .ok_or(MachineError::UninitializedRegister(1))?;
+        self.unify_cells_with(&term1, &term2, OccursCheck::On)
+    }
+
+    /// Built–in predicate is(Result, Expression): evaluates the term in
+    /// register 1 under the current bindings and unifies its value with the
+    /// term in register 0. Evaluation errors are ISO error terms, such as
+    /// `instantiation_error` when the expression contains an unbound variable.
+    pub fn builtin_is(&mut self) -> Result<(), MachineError> {
+        let value = arithmetic::evaluate_term(&self.argument(1)?)?;
+        self.unify_argument_with(0, &value.into_term())
+    }
+
+    /// Evaluates the terms in registers 0 and 1 and succeeds if their values
+    /// satisfy `holds`; fails otherwise. An integer and a float are compared
+    /// exactly.
+    fn arithmetic_comparison(&mut self, name: &str, holds: fn(Ordering) -> bool) -> Result<(), MachineError> {
+        let left = arithmetic::evaluate_term(&self.argument(0)?)?;
+        let right = arithmetic::evaluate_term(&self.argument(1)?)?;
+        if holds(left.compare(&right)) {
+            Ok(())
+        } else {
+            Err(MachineError::UnificationFailed(format!("{} {} {} does not hold", left, name, right)))
+        }
+    }
+
+    /// Built–in predicate </2: the value of register 0 is less than that of register 1.
+    pub fn builtin_less_than(&mut self) -> Result<(), MachineError> {
+        self.arithmetic_comparison("<", Ordering::is_lt)
+    }
+
+    /// Built–in predicate >/2: the value of register 0 is greater than that of register 1.
+    pub fn builtin_greater_than(&mut self) -> Result<(), MachineError> {
+        self.arithmetic_comparison(">", Ordering::is_gt)
+    }
+
+    /// Built–in predicate =</2: the value of register 0 is at most that of register 1.
+    pub fn builtin_less_or_equal(&mut self) -> Result<(), MachineError> {
+        self.arithmetic_comparison("=<", Ordering::is_le)
+    }
+
+    /// Built–in predicate >=/2: the value of register 0 is at least that of register 1.
+    pub fn builtin_greater_or_equal(&mut self) -> Result<(), MachineError> {
+        self.arithmetic_comparison(">=", Ordering::is_ge)
+    }
+
+    /// Built–in predicate =:=/2: registers 0 and 1 have equal values.
+    pub fn builtin_arithmetic_equal(&mut self) -> Result<(), MachineError> {
+        self.arithmetic_comparison("=:=", Ordering::is_eq)
+    }
+
+    /// Built–in predicate =\=/2: registers 0 and 1 have different values.
+    pub fn builtin_arithmetic_not_equal(&mut self) -> Result<(), MachineError> {
+        self.arithmetic_comparison("=\\=", Ordering::is_ne)
+    }
+
+    /// Compares the terms two cells refer to in the standard order of terms,
+    /// the order of `Term::standard_order`.
+    ///
+    /// Arguments are compared with an explicit stack rather than by recursion,
+    /// so comparing long lists does not exhaust the Rust stack, and a pair of
+    /// structures is compared at most once, so comparing cyclic terms
+    /// terminates: two cyclic terms are identical if no difference is found by
+    /// unfolding them.
+    pub fn compare_cells(&mut self, c1: &Cell, c2: &Cell) -> Ordering {
+        let mut pending = vec![(c1.clone(), c2.clone())];
+        let mut compared = HashSet::new();
+        while let Some((c1, c2)) = pending.pop() {
+            let resolved1 = self.uf.resolve(&c1);
+            let resolved2 = self.uf.resolve(&c2);
+            let ordering = match (&resolved1, &resolved2) {
+                (&Cell::Str(p1), &Cell::Str(p2)) => {
+                    if p1 == p2 || !compared.insert((p1, p2)) {
+                        continue;
+                    }
+                    let (f1, args1) = self.heap.arguments(p1);
+                    let (f2, args2) = self.heap.arguments(p2);
+                    let arity = args1.len();
+                    let ordering = arity.cmp(&args2.len())
+                        .then_with(|| self.heap.functor_name(f1).cmp(self.heap.functor_name(f2)));
+                    if ordering.is_eq() {
+                        // Pushed last first, so that the arguments are compared left to right.
+                        for i in (1..=arity).rev() {
+                            let a = self.heap.get(p1 + i).cloned().expect("structure argument");
+                            let b = self.heap.get(p2 + i).cloned().expect("structure argument");
+                            pending.push((a, b));
+                        }
+                    }
+                    ordering
+                },
+                // The other cells hold no structure, so they are read as terms.
+                (&Cell::Str(_), other) => COMPOUND_RANK.cmp(&self.read_term(other).standard_rank()),
+                (other, &Cell::Str(_)) => self.read_term(other).standard_rank().cmp(&COMPOUND_RANK),
+                _ => self.read_term(&resolved1).standard_order(&self.read_term(&resolved2)),
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Built–in predicate compare(Order, Term1, Term2): unifies Order with the
+    /// atom `<`, `=` or `>` according to the standard order of the terms in
+    /// registers 1 and 2.
+    pub fn builtin_compare(&mut self) -> Result<(), MachineError> {
+        let (cell1, cell2) = (self.argument_cell(1)?, self.argument_cell(2)?);
+        let order = match self.compare_cells(&cell1, &cell2) {
+            Ordering::Less => "<",
+            Ordering::Equal => "=",
+            Ordering::Greater => ">",
+        };
+        self.unify_argument_with(0, &Term::Str(order.to_string()))
+    }
+
+    /// Succeeds if the standard order of the terms in registers 0 and 1
+    /// satisfies `holds`; fails otherwise.
+    fn standard_order_test(&mut self, name: &str, holds: fn(Ordering) -> bool) -> Result<(), MachineError> {
+        let (cell1, cell2) = (self.argument_cell(0)?, self.argument_cell(1)?);
+        if holds(self.compare_cells(&cell1, &cell2)) {
+            Ok(())
+        } else {
+            Err(MachineError::UnificationFailed(format!("{}/2 does not hold", name)))
+        }
+    }
+
+    /// Built–in predicate ==/2: succeeds if registers 0 and 1 hold identical terms.
+    pub fn builtin_identical(&mut self) -> Result<(), MachineError> {
+        self.standard_order_test("==", Ordering::is_eq)
+    }
+
+    /// Built–in predicate \==/2: succeeds if registers 0 and 1 hold terms that are not identical.
+    pub fn builtin_not_identical(&mut self) -> Result<(), MachineError> {
+        self.standard_order_test("\\==", Ordering::is_ne)
+    }
+
+    /// Built–in predicate @</2: the term in register 0 precedes the one in register 1.
+    pub fn builtin_term_less(&mut self) -> Result<(), MachineError> {
+        self.standard_order_test("@<", Ordering::is_lt)
+    }
+
+    /// Built–in predicate @>/2: the term in register 0 follows the one in register 1.
+    pub fn builtin_term_greater(&mut self) -> Result<(), MachineError> {
+        self.standard_order_test("@>", Ordering::is_gt)
+    }
+
+    /// Built–in predicate @=</2: the term in register 0 does not follow the one in register 1.
+    pub fn builtin_term_less_or_equal(&mut self) -> Result<(), MachineError> {
+        self.standard_order_test("@=<", Ordering::is_le)
+    }
+
+    /// Built–in predicate @>=/2: the term in register 0 does not precede the one in register 1.
+    pub fn builtin_term_greater_or_equal(&mut self) -> Result<(), MachineError> {
+        self.standard_order_test("@>=", Ordering::is_ge)
+    }
+
+    /// Reduces a term with the given strategy within the machine's reduction
+    /// limit. Parameters that must be renamed get fresh variable ids, so they
+    /// cannot clash with the machine's logic variables.
+    pub fn normalize_term(&mut self, term: &Term, strategy: Strategy, eta: bool) -> Result<Term, MachineError> {
+        let normal = debruijn::normalize(&DbTerm::from_term(term), strategy, self.reduction_limit, eta)?;
+        Ok(normal.to_term_with(&mut || self.fresh_var_id()))
+    }
+
+    /// Built–in predicate normalize(Term, Normal): unifies Normal with the normal
+    /// form of Term, reduced in normal order.
+    pub fn builtin_normalize(&mut self) -> Result<(), MachineError> {
+        let term = self.argument(0)?;
+        let normal = self.normalize_term(&term, Strategy::NormalOrder, self.eta_reduction)?;
+        self.unify_argument_with(1, &normal)
+    }
+
+    /// Built–in predicate normalize_with(Strategy, Term, Normal): like normalize/2,
+    /// reducing with the strategy named by the atom `normal`, `applicative` or `head`.
+    pub fn builtin_normalize_with(&mut self) -> Result<(), MachineError> {
+        let strategy = match self.argument(0)? {
+            Term::Str(name) => name.parse::<Strategy>().map_err(MachineError::InvalidStrategy)?,
+            other => return Err(MachineError::InvalidStrategy(format!("strategy must be an atom, got {}", other))),
+        };
+        let term = self.argument(1)?;
+        let normal = self.normalize_term(&term, strategy, self.eta_reduction)?;
+        self.unify_argument_with(2, &normal)
+    }
+
+    /// Unifies the term in an argument register with `term`.
+    fn unify_argument_with(&mut self, register: usize, term: &Term) -> Result<(), MachineError> {
+        let cell = self.argument_cell(register)?;
+        let value = self.heap.put_term(term);
+        self.unify_cells(&cell, &value)
+    }
+
+    /// Returns the fully resolved term held in an argument register.
+    pub(crate) fn argument(&mut self, register: usize) -> Result<Term, MachineError> {
+        let cell = self.argument_cell(register)?;
+        Ok(self.read_term(&cell))
+    }
+
+    /// Returns the cell held in an argument register.
+    fn argument_cell(&self, register: usize) -> Result<Cell, MachineError> {
+        self.registers.get(register)
+            .ok_or(MachineError::RegisterOutOfBounds(register))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(register))
+    }
+
+    /// Built–in predicate: op(Priority, Type, Names).
+    /// Defines the operators in `Names` (an atom or a list of atoms), found in
+    /// registers 0, 1 and 2. A priority of 0 removes a definition.
+    pub fn builtin_op(&mut self) -> Result<(), MachineError> {
+        let priority = match self.argument(0)? {
+            Term::Const(n) => n as i64,
+            other => return Err(MachineError::InvalidOperator(format!("priority must be an integer, got {}", other))),
+        };
+        let op_type = match self.argument(1)? {
+            Term::Str(name) => name,
+            other => return Err(MachineError::InvalidOperator(format!("type must be an atom, got {}", other))),
+        };
+        let mut names = Vec::new();
+        let mut current = self.argument(2)?;
+        loop {
+            match current {
+                Term::Str(name) if name == "[]" => break,
+                Term::Str(name) if names.is_empty() => {
+                    names.push(name);
+                    break;
+                },
+                Term::Compound(functor, mut cell) if functor == "." && cell.len() == 2 => {
+                    let tail = cell.pop().unwrap();
+                    match cell.pop().unwrap() {
+                        Term::Str(name) => names.push(name),
+                        other => return Err(MachineError::InvalidOperator(format!("operator name must be an atom, got {}", other))),
+                    }
+                    current = tail;
+                },
+                other => return Err(MachineError::InvalidOperator(format!("expected an atom or a list of atoms, got {}", other))),
+            }
+        }
+        for name in names {
+            self.op_table.define(priority, &op_type, &name).map_err(MachineError::InvalidOperator)?;
+        }
+        Ok(())
+    }
+
+    /// Built–in predicate: current_op(Priority, Type, Name).
+    /// Enumerates, on backtracking, the operator definitions matching the arguments
+    /// in registers 0, 1 and 2.
+    pub fn builtin_current_op(&mut self) -> Result<(), MachineError> {
+        let priority = self.argument(0)?;
+        let op_type = self.argument(1)?;
+        let name = self.argument(2)?;
+        // An unbound argument matches anything; a bound one must be equal.
+        let matches = |pattern: &Term, value: &Term| matches!(pattern, Term::Var(_)) || pattern == value;
+        let solutions = self.op_table.definitions().into_iter()
+            .map(|(p, t, n)| [Term::Const(p as i32), Term::Str(t.name().to_string()), Term::Str(n)])
+            .filter(|[p, t, n]| matches(&priority, p) && matches(&op_type, t) && matches(&name, n))
+            .map(|[p, t, n]| vec![(priority.clone(), p), (op_type.clone(), t), (name.clone(), n)])
+            .collect();
+        self.execute_solutions(solutions)
     }
 
     /// Executes one instruction.
     pub fn step(&mut self) -> Result<(), MachineError> {
         let instr = self.code.get(self.pc)
-            .ok_or(MachineError::NoMoreInstructions)?
-            .clone();
+            .ok_or(MachineError::NoMoreInstructions)?;
+        // A resolved call only needs its target, so it runs without cloning the instruction.
+        if let &Instruction::Call { target: target @ (CallTarget::Builtin(_) | CallTarget::Predicate(_)), .. } = instr {
+            self.pc += 1;
+            self.trace(&self.code[self.pc - 1]);
+            return self.call_resolved(target);
+        }
+        let instr = instr.clone();
         self.pc += 1;
         self.trace(&instr);
         instr.execute(self)
@@ -0,0 +1,382 @@
+// src/machine/debruijn.rs
+//! A locally nameless representation of lambda terms.
+//!
+//! In a `DbTerm`, a variable bound by an enclosing abstraction is a de Bruijn
+//! index (0 is the innermost abstraction) and every other variable keeps its
+//! id. Bound variables therefore have no names that could clash with the
+//! machine's logic variables: substitution needs no renaming, alpha-equivalent
+//! terms are equal, and fresh names are only chosen when a term is converted
+//! back to a `Term`.
+
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use num_bigint::BigInt;
+use crate::machine::error_handling::MachineError;
+use crate::machine::lambda::Strategy;
+use crate::machine::number::Float;
+use crate::machine::term::Term;
+
+/// The parameter name of an abstraction, kept so that converting back to a
+/// `Term` can reuse it. Names are ignored when terms are compared or hashed.
+#[derive(Debug, Clone, Copy, Eq)]
+pub struct Name(pub usize);
+
+impl PartialEq for Name {
+    fn eq(&self, _: &Name) -> bool {
+        true
+    }
+}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+/// A term with its bound variables written as de Bruijn indices.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DbTerm {
+    /// A variable bound by the abstraction `index` levels out.
+    Bound(usize),
+    /// A free variable (identified by its ID).
+    Free(usize),
+    /// A constant integer.
+    Const(i32),
+    /// An integer outside the range of `Const`.
+    BigInt(BigInt),
+    /// A floating-point number.
+    Float(Float),
+    /// A string constant.
+    Str(String),
+    /// A compound term with a functor and a list of arguments.
+    Compound(String, Vec<DbTerm>),
+    /// A lambda abstraction.
+    Lambda(Name, Box<DbTerm>),
+    /// Function application.
+    App(Box<DbTerm>, Box<DbTerm>),
+    /// A probabilistic term.
+    Prob(Box<DbTerm>),
+    /// A constraint with a name and arguments.
+    Constraint(String, Vec<DbTerm>),
+    /// A modal term.
+    Modal(String, Box<DbTerm>),
+    /// A temporal term.
+    Temporal(String, Box<DbTerm>),
+    /// A higher–order term.
+    HigherOrder(Box<DbTerm>),
+}
+
+impl DbTerm {
+    /// Converts a term to the locally nameless form.
+    pub fn from_term(term: &Term) -> DbTerm {
+        from_term_in(term, &mut Vec::new())
+    }
+
+    /// Converts back to a `Term`. Abstractions keep their original parameter
+    /// names unless that would capture a variable; other parameters are named
+    /// with ids above every variable of the term.
+    pub fn to_term(&self) -> Term {
+        let mut next = self.names().into_iter().max().map_or(0, |max| max + 1);
+        self.to_term_with(&mut || {
+            next += 1;
+            next - 1
+        })
+    }
+
+    /// Converts back to a `Term`, naming the parameters that cannot keep their
+    /// original name with ids taken from `fresh` (such as `Machine::fresh_var_id`).
+    pub fn to_term_with(&self, fresh: &mut dyn FnMut() -> usize) -> Term {
+        let free = self.free_vars();
+        to_term_in(self, &free, &mut Vec::new(), fresh)
+    }
+
+    /// Returns the ids of the free variables of the term.
+    pub fn free_vars(&self) -> HashSet<usize> {
+        let mut vars = HashSet::new();
+        self.visit(&mut |term| {
+            if let DbTerm::Free(v) = term {
+                vars.insert(*v);
+            }
+        });
+        vars
+    }
+
+    /// Returns the ids of the free variables and the parameter names.
+    fn names(&self) -> Vec<usize> {
+        let mut names = Vec::new();
+        self.visit(&mut |term| match term {
+            DbTerm::Free(v) | DbTerm::Lambda(Name(v), _) => names.push(*v),
+            _ => {},
+        });
+        names
+    }
+
+    /// Calls `f` on the term and each of its subterms.
+    fn visit(&self, f: &mut dyn FnMut(&DbTerm)) {
+        f(self);
+        for child in self.children() {
+            child.visit(f);
+        }
+    }
+
+    /// Returns the immediate subterms.
+    fn children(&self) -> Vec<&DbTerm> {
+        match self {
+            DbTerm::Compound(_, args) | DbTerm::Constraint(_, args) => args.iter().collect(),
+            DbTerm::App(function, argument) => vec![function, argument],
+            DbTerm::Lambda(_, inner) | DbTerm::Prob(inner) | DbTerm::Modal(_, inner)
+            | DbTerm::Temporal(_, inner) | DbTerm::HigherOrder(inner) => vec![inner],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Rebuilds the term with its immediate subterms replaced by `f(child)`.
+    /// The body of an abstraction is passed to `f` like any other subterm.
+    fn map_children(&self, mut f: impl FnMut(&DbTerm) -> DbTerm) -> DbTerm {
+        match self {
+            DbTerm::Compound(name, args) => DbTerm::Compound(name.clone(), args.iter().map(f).collect()),
+            DbTerm::Constraint(name, args) => DbTerm::Constraint(name.clone(), args.iter().map(f).collect()),
+            DbTerm::Lambda(name, body) => DbTerm::Lambda(*name, Box::new(f(body))),
+            DbTerm::App(function, argument) => DbTerm::App(Box::new(f(function)), Box::new(f(argument))),
+            DbTerm::Prob(inner) => DbTerm::Prob(Box::new(f(inner))),
+            DbTerm::Modal(op, inner) => DbTerm::Modal(op.clone(), Box::new(f(inner))),
+            DbTerm::Temporal(op, inner) => DbTerm::Temporal(op.clone(), Box::new(f(inner))),
+            DbTerm::HigherOrder(inner) => DbTerm::HigherOrder(Box::new(f(inner))),
+            leaf => leaf.clone(),
+        }
+    }
+}
+
+fn from_term_in(term: &Term, params: &mut Vec<usize>) -> DbTerm {
+    match term {
+        Term::Var(v) => match params.iter().rev().position(|param| param == v) {
+            Some(index) => DbTerm::Bound(index),
+            None => DbTerm::Free(*v),
+        },
+        Term::Const(n) => DbTerm::Const(*n),
+        Term::BigInt(n) => DbTerm::BigInt(n.clone()),
+        Term::Float(x) => DbTerm::Float(*x),
+        Term::Str(s) => DbTerm::Str(s.clone()),
+        Term::Compound(f, args) => DbTerm::Compound(f.clone(), args.iter().map(|arg| from_term_in(arg, params)).collect()),
+        Term::Lambda(param, body) => {
+            params.push(*param);
+            let body = from_term_in(body, params);
+            params.pop();
+            DbTerm::Lambda(Name(*param), Box::new(body))
+        },
+        Term::App(function, argument) => DbTerm::App(
+            Box::new(from_term_in(function, params)),
+            Box::new(from_term_in(argument, params)),
+        ),
+        Term::Prob(inner) => DbTerm::Prob(Box::new(from_term_in(inner, params))),
+        Term::Constraint(name, args) => DbTerm::Constraint(name.clone(), args.iter().map(|arg| from_term_in(arg, params)).collect()),
+        Term::Modal(op, inner) => DbTerm::Modal(op.clone(), Box::new(from_term_in(inner, params))),
+        Term::Temporal(op, inner) => DbTerm::Temporal(op.clone(), Box::new(from_term_in(inner, params))),
+        Term::HigherOrder(inner) => DbTerm::HigherOrder(Box::new(from_term_in(inner, params))),
+    }
+}
+
+/// Converts to a `Term`; `names` holds the names given to the enclosing
+/// abstractions, innermost last, and `free` the free variables of the whole term.
+fn to_term_in(term: &DbTerm, free: &HashSet<usize>, names: &mut Vec<usize>, fresh: &mut dyn FnMut() -> usize) -> Term {
+    match term {
+        DbTerm::Bound(index) => Term::Var(names[names.len() - 1 - index]),
+        DbTerm::Free(v) => Term::Var(*v),
+        DbTerm::Const(n) => Term::Const(*n),
+        DbTerm::BigInt(n) => Term::BigInt(n.clone()),
+        DbTerm::Float(x) => Term::Float(*x),
+        DbTerm::Str(s) => Term::Str(s.clone()),
+        DbTerm::Compound(f, args) => Term::Compound(f.clone(), args.iter().map(|arg| to_term_in(arg, free, names, fresh)).collect()),
+        DbTerm::Lambda(Name(name), body) => {
+            let param = if free.contains(name) || names.contains(name) { fresh() } else { *name };
+            names.push(param);
+            let body = to_term_in(body, free, names, fresh);
+            names.pop();
+            Term::Lambda(param, Box::new(body))
+        },
+        DbTerm::App(function, argument) => Term::App(
+            Box::new(to_term_in(function, free, names, fresh)),
+            Box::new(to_term_in(argument, free, names, fresh)),
+        ),
+        DbTerm::Prob(inner) => Term::Prob(Box::new(to_term_in(inner, free, names, fresh))),
+        DbTerm::Constraint(name, args) => Term::Constraint(name.clone(), args.iter().map(|arg| to_term_in(arg, free, names, fresh)).collect()),
+        DbTerm::Modal(op, inner) => Term::Modal(op.clone(), Box::new(to_term_in(inner, free, names, fresh))),
+        DbTerm::Temporal(op, inner) => Term::Temporal(op.clone(), Box::new(to_term_in(inner, free, names, fresh))),
+        DbTerm::HigherOrder(inner) => Term::HigherOrder(Box::new(to_term_in(inner, free, names, fresh))),
+    }
+}
+
+/// Returns true if two terms are equal up to the renaming of bound variables.
+pub fn alpha_equivalent(t1: &Term, t2: &Term) -> bool {
+    DbTerm::from_term(t1) == DbTerm::from_term(t2)
+}
+
+/// Replaces each bound variable by `f(index, depth)`, where depth is the
+/// number of abstractions entered below `term`.
+fn map_bound(term: &DbTerm, depth: usize, f: &dyn Fn(usize, usize) -> DbTerm) -> DbTerm {
+    match term {
+        DbTerm::Bound(index) => f(*index, depth),
+        DbTerm::Lambda(name, body) => DbTerm::Lambda(*name, Box::new(map_bound(body, depth + 1, f))),
+        other => other.map_children(|child| map_bound(child, depth, f)),
+    }
+}
+
+/// Adds `amount` to the indices of the variables bound outside `term`.
+fn shift(term: &DbTerm, amount: usize) -> DbTerm {
+    if amount == 0 {
+        return term.clone();
+    }
+    map_bound(term, 0, &|index, depth| DbTerm::Bound(if index >= depth { index + amount } else { index }))
+}
+
+/// Substitutes `argument` for the variable bound by the abstraction whose
+/// body is `body`: the result of reducing `(\. body) argument`.
+pub fn instantiate(body: &DbTerm, argument: &DbTerm) -> DbTerm {
+    map_bound(body, 0, &|index, depth| {
+        if index == depth {
+            shift(argument, depth)
+        } else if index > depth {
+            DbTerm::Bound(index - 1)
+        } else {
+            DbTerm::Bound(index)
+        }
+    })
+}
+
+/// Returns true if the variable bound `depth` levels out occurs in `term`.
+fn uses_bound(term: &DbTerm, depth: usize) -> bool {
+    match term {
+        DbTerm::Bound(index) => *index == depth,
+        DbTerm::Lambda(_, body) => uses_bound(body, depth + 1),
+        other => other.children().into_iter().any(|child| uses_bound(child, depth)),
+    }
+}
+
+/// Performs one step of eta reduction at the root of the term: `\. M 0`
+/// becomes `M` when the parameter does not occur in M.
+pub fn eta_reduce(term: &DbTerm) -> Option<DbTerm> {
+    if let DbTerm::Lambda(_, body) = term {
+        if let DbTerm::App(function, argument) = &**body {
+            if **argument == DbTerm::Bound(0) && !uses_bound(function, 0) {
+                // Dropping the abstraction lowers the outer indices of M by one.
+                return Some(instantiate(function, &DbTerm::Bound(0)));
+            }
+        }
+    }
+    None
+}
+
+/// Reduces the first of `terms` that has a redex, leaving the others as they are.
+fn step_first(terms: &[DbTerm], step: impl Fn(&DbTerm) -> Option<DbTerm>) -> Option<Vec<DbTerm>> {
+    terms.iter().enumerate().find_map(|(i, term)| {
+        step(term).map(|reduced| {
+            let mut terms = terms.to_vec();
+            terms[i] = reduced;
+            terms
+        })
+    })
+}
+
+/// Reduces inside the terms other than applications and abstractions, which
+/// have no redex of their own.
+fn step_inside(term: &DbTerm, step: impl Fn(&DbTerm) -> Option<DbTerm>) -> Option<DbTerm> {
+    match term {
+        DbTerm::Compound(f, args) => step_first(args, step).map(|args| DbTerm::Compound(f.clone(), args)),
+        DbTerm::Constraint(name, args) => step_first(args, step).map(|args| DbTerm::Constraint(name.clone(), args)),
+        DbTerm::Prob(inner) => step(inner).map(|inner| DbTerm::Prob(Box::new(inner))),
+        DbTerm::Modal(op, inner) => step(inner).map(|inner| DbTerm::Modal(op.clone(), Box::new(inner))),
+        DbTerm::Temporal(op, inner) => step(inner).map(|inner| DbTerm::Temporal(op.clone(), Box::new(inner))),
+        DbTerm::HigherOrder(inner) => step(inner).map(|inner| DbTerm::HigherOrder(Box::new(inner))),
+        _ => None,
+    }
+}
+
+fn normal_step(term: &DbTerm, eta: bool) -> Option<DbTerm> {
+    match term {
+        DbTerm::App(fun, arg) => {
+            if let DbTerm::Lambda(_, body) = &**fun {
+                return Some(instantiate(body, arg));
+            }
+            if let Some(fun) = normal_step(fun, eta) {
+                return Some(DbTerm::App(Box::new(fun), arg.clone()));
+            }
+            normal_step(arg, eta).map(|arg| DbTerm::App(fun.clone(), Box::new(arg)))
+        },
+        DbTerm::Lambda(name, body) => {
+            if eta {
+                if let Some(reduced) = eta_reduce(term) {
+                    return Some(reduced);
+                }
+            }
+            normal_step(body, eta).map(|body| DbTerm::Lambda(*name, Box::new(body)))
+        },
+        other => step_inside(other, |t| normal_step(t, eta)),
+    }
+}
+
+fn applicative_step(term: &DbTerm, eta: bool) -> Option<DbTerm> {
+    match term {
+        DbTerm::App(fun, arg) => {
+            if let Some(fun) = applicative_step(fun, eta) {
+                return Some(DbTerm::App(Box::new(fun), arg.clone()));
+            }
+            if let Some(arg) = applicative_step(arg, eta) {
+                return Some(DbTerm::App(fun.clone(), Box::new(arg)));
+            }
+            match &**fun {
+                DbTerm::Lambda(_, body) => Some(instantiate(body, arg)),
+                _ => None,
+            }
+        },
+        DbTerm::Lambda(name, body) => {
+            if let Some(body) = applicative_step(body, eta) {
+                return Some(DbTerm::Lambda(*name, Box::new(body)));
+            }
+            if eta { eta_reduce(term) } else { None }
+        },
+        other => step_inside(other, |t| applicative_step(t, eta)),
+    }
+}
+
+fn head_step(term: &DbTerm, eta: bool) -> Option<DbTerm> {
+    match term {
+        DbTerm::App(fun, arg) => {
+            if let DbTerm::Lambda(_, body) = &**fun {
+                return Some(instantiate(body, arg));
+            }
+            head_step(fun, eta).map(|fun| DbTerm::App(Box::new(fun), arg.clone()))
+        },
+        DbTerm::Lambda(name, body) => {
+            if let Some(body) = head_step(body, eta) {
+                return Some(DbTerm::Lambda(*name, Box::new(body)));
+            }
+            if eta { eta_reduce(term) } else { None }
+        },
+        _ => None,
+    }
+}
+
+/// Performs one reduction step with the given strategy, also trying eta steps
+/// if `eta` is set. Returns None if the term has no redex the strategy reduces.
+pub fn reduce_step(term: &DbTerm, strategy: Strategy, eta: bool) -> Option<DbTerm> {
+    match strategy {
+        Strategy::NormalOrder => normal_step(term, eta),
+        Strategy::ApplicativeOrder => applicative_step(term, eta),
+        Strategy::HeadNormalForm => head_step(term, eta),
+    }
+}
+
+/// Reduces a term until the strategy finds no more redexes, taking at most
+/// `max_steps` steps. Fails with `MachineError::ReductionLimit` if the term is
+/// not normalized by then, which stops divergent terms such as `(\x. x x) (\x. x x)`.
+pub fn normalize(term: &DbTerm, strategy: Strategy, max_steps: usize, eta: bool) -> Result<DbTerm, MachineError> {
+    let mut current = term.clone();
+    for _ in 0..max_steps {
+        match reduce_step(&current, strategy, eta) {
+            Some(next) => current = next,
+            None => return Ok(current),
+        }
+    }
+    match reduce_step(&current, strategy, eta) {
+        Some(_) => Err(MachineError::ReductionLimit(max_steps)),
+        None => Ok(current),
+    }
+}
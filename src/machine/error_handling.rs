@@ -1,11 +1,50 @@
 // src/machine/error_handling.rs
 //! Error handling for the LAM machine.
 //!
-//! This module defines the `MachineError` enum for reporting various errors.
+//! This module defines the `MachineError` enum for reporting various errors,
+//! and `IsoError` for the errors that ISO Prolog reports as error terms.
 
+use std::fmt;
 use thiserror::Error;
 use crate::term::Term;
 
+/// An error that ISO Prolog reports with an error term. `formal_term` gives the
+/// formal part of the term, the `Formal` in `error(Formal, Context)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IsoError {
+    /// An argument is an unbound variable: `instantiation_error`.
+    Instantiation,
+    /// An argument has the wrong type: `type_error(Type, Culprit)`, for example
+    /// `type_error(evaluable, foo/2)`.
+    Type(String, Term),
+    /// An arithmetic operation has no value: `evaluation_error(Error)`, where the
+    /// error is `zero_divisor`, `undefined` or `int_overflow`.
+    Evaluation(String),
+}
+
+impl IsoError {
+    /// Returns the formal error term.
+    pub fn formal_term(&self) -> Term {
+        match self {
+            IsoError::Instantiation => Term::Str("instantiation_error".to_string()),
+            IsoError::Type(kind, culprit) => {
+                Term::Compound("type_error".to_string(), vec![Term::Str(kind.clone()), culprit.clone()])
+            },
+            IsoError::Evaluation(error) => {
+                Term::Compound("evaluation_error".to_string(), vec![Term::Str(error.clone())])
+            },
+        }
+    }
+}
+
+impl fmt::Display for IsoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.formal_term())
+    }
+}
+
+impl std::error::Error for IsoError {}
+
 #[derive(Debug, Error)]
 pub enum MachineError {
     #[error("Register {0} is out of bounds.")]
@@ -22,15 +61,16 @@ pub enum MachineError {
     PredicateClauseNotFound(String),
     #[error("No choice point available.")]
     NoChoicePoint,
-    #[error("Structure mismatch: expected {expected_functor}/{expected_arity} but found {found_functor}/{found_arity}.")]
-    StructureMismatch {
-        expected_functor: String,
-        expected_arity: usize,
-        found_functor: String,
-        found_arity: usize,
-    },
-    #[error("Term in register {0} is not a compound term.")]
-    NotACompoundTerm(usize),
+    #[error("No structure argument left to unify.")]
+    NoStructureArgument,
+    #[error("Occurs check: variable {0} occurs in {1:?}.")]
+    OccursCheck(usize, Term),
+    #[error("Reduction did not reach a normal form within {0} steps.")]
+    ReductionLimit(usize),
+    #[error("Term in register {0} is not an unbound variable.")]
+    NotAVariable(usize),
+    #[error("Invalid reduction strategy: {0}")]
+    InvalidStrategy(String),
     #[error("No indexed clause for predicate {0} with key {1:?}.")]
     NoIndexedClause(String, Term),
     #[error("No index entry for predicate {0} with key {1:?}.")]
@@ -39,4 +79,8 @@ pub enum MachineError {
     PredicateNotInIndex(String),
     #[error("No more instructions.")]
     NoMoreInstructions,
+    #[error("Invalid operator definition: {0}")]
+    InvalidOperator(String),
+    #[error("{0}")]
+    Iso(#[from] IsoError),
 }
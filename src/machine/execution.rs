@@ -3,17 +3,24 @@
 //!
 //! This module implements the methods that execute each instruction on a Machine.
 
-use crate::machine::core::Machine;
+use num_bigint::BigInt;
+use crate::machine::atom::Atom;
+use crate::machine::core::{Machine, UnifyMode};
 use crate::machine::frame::Frame;
+use crate::machine::heap::Cell;
+use crate::machine::instruction::CallTarget;
 use crate::machine::choice_point::ChoicePoint;
 use crate::machine::term::Term;
-use crate::machine::error_handling::MachineError;
+use crate::machine::error_handling::{IsoError, MachineError};
 use crate::machine::arithmetic;
+use crate::machine::integer::Integer;
+use crate::machine::lambda::Strategy;
+use crate::machine::number::{format_float, Float};
 
 impl Machine {
     pub fn execute_put_const(&mut self, register: usize, value: i32) -> Result<(), MachineError> {
         if let Some(slot) = self.registers.get_mut(register) {
-            *slot = Some(Term::Const(value));
+            *slot = Some(Cell::Int(value));
             Ok(())
         } else {
             Err(MachineError::RegisterOutOfBounds(register))
@@ -22,7 +29,7 @@ impl Machine {
 
     pub fn execute_put_var(&mut self, register: usize, var_id: usize, name: String) -> Result<(), MachineError> {
         if let Some(slot) = self.registers.get_mut(register) {
-            *slot = Some(Term::Var(var_id));
+            *slot = Some(Cell::Var(var_id));
             self.variable_names.insert(var_id, name);
             Ok(())
         } else {
@@ -30,12 +37,22 @@ impl Machine {
         }
     }
 
+    pub fn execute_new_var(&mut self, register: usize, name: String) -> Result<(), MachineError> {
+        if register >= self.registers.len() {
+            return Err(MachineError::RegisterOutOfBounds(register));
+        }
+        let var_id = self.fresh_var_id();
+        self.registers[register] = Some(Cell::Var(var_id));
+        self.variable_names.insert(var_id, name);
+        Ok(())
+    }
+
     pub fn execute_get_const(&mut self, register: usize, value: i32) -> Result<(), MachineError> {
         match self.registers.get(register) {
-            Some(Some(term)) => {
-                let term_clone = term.clone();
-                self.unify(&term_clone, &Term::Const(value))
-                    .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with Const({})", term_clone, value)))
+            Some(Some(cell)) => {
+                let cell = cell.clone();
+                self.unify_cells(&cell, &Cell::Int(value))
+                    .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with Const({})", self.heap.term(&cell), value)))
             },
             Some(None) => Err(MachineError::UninitializedRegister(register)),
             None => Err(MachineError::RegisterOutOfBounds(register)),
@@ -47,65 +64,85 @@ impl Machine {
             return Err(MachineError::RegisterOutOfBounds(register));
         }
         self.variable_names.entry(var_id).or_insert(name);
-        if let Some(term) = self.registers[register].clone() {
-            let goal = Term::Var(var_id);
-            self.unify(&goal, &term)
-                .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {:?}", goal, term)))?;
-            let resolved = self.uf.resolve(&term);
+        if let Some(cell) = self.registers[register].clone() {
+            self.unify_cells(&Cell::Var(var_id), &cell)
+                .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {:?}", Term::Var(var_id), self.heap.term(&cell))))?;
+            let resolved = self.uf.resolve(&cell);
             self.registers[register] = Some(resolved);
             Ok(())
         } else {
-            self.registers[register] = Some(Term::Var(var_id));
+            self.registers[register] = Some(Cell::Var(var_id));
             Ok(())
         }
     }
 
+    pub fn execute_get_value(&mut self, register: usize, src: usize) -> Result<(), MachineError> {
+        let term = self.registers.get(register)
+            .ok_or(MachineError::RegisterOutOfBounds(register))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(register))?;
+        let value = self.registers.get(src)
+            .ok_or(MachineError::RegisterOutOfBounds(src))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(src))?;
+        self.unify_cells(&term, &value)
+            .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {:?}", self.heap.term(&term), self.heap.term(&value))))
+    }
+
     pub fn execute_call(&mut self, predicate: String) -> Result<(), MachineError> {
-        if let Some(builtin) = self.builtins.get(&predicate) {
-            builtin(self)
-        } else if let Some(clauses) = self.predicate_table.get(&predicate) {
-            if clauses.is_empty() {
-                return Err(MachineError::PredicateClauseNotFound(predicate));
-            }
-            self.control_stack.push(Frame { return_pc: self.pc });
-            let mut alternatives = clauses.clone();
-            let jump_to = alternatives.remove(0);
-            let alternative_clauses = if alternatives.is_empty() { None } else { Some(alternatives) };
-            let cp = ChoicePoint {
-                saved_pc: self.pc,
-                saved_registers: self.registers.clone(),
-                saved_substitution: self.substitution.clone(),
-                saved_control_stack: self.control_stack.clone(),
-                alternative_clauses,
-                uf_trail_len: self.uf.trail.len(),
-                call_level: self.control_stack.len(),
-            };
-            self.choice_stack.push(Box::new(cp));
-            self.pc = jump_to;
-            Ok(())
-        } else {
-            Err(MachineError::PredicateNotFound(predicate))
+        self.execute_call_to(CallTarget::Unresolved, &predicate)
+    }
+
+    /// Calls the predicate `predicate` through `target`, resolving it first if
+    /// the instruction was not resolved when its code was loaded.
+    pub fn execute_call_to(&mut self, target: CallTarget, predicate: &str) -> Result<(), MachineError> {
+        let target = match target {
+            CallTarget::Unresolved => self.resolve_call(predicate),
+            target => target,
+        };
+        self.call_resolved(target)
+    }
+
+    /// Calls a resolved target: runs the built-in, or enters the first clause
+    /// of the predicate with a control frame to return to.
+    pub(crate) fn call_resolved(&mut self, target: CallTarget) -> Result<(), MachineError> {
+        match target {
+            CallTarget::Builtin(id) => (self.builtin_table[id])(self),
+            CallTarget::Predicate(atom) => {
+                let mut alternatives = self.clauses_of(atom)?;
+                self.control_stack.push(Frame { return_pc: self.pc, cut_barrier: self.cut_barrier });
+                self.cut_barrier = self.choice_stack.len();
+                let jump_to = alternatives.remove(0);
+                // A choice point is only needed if there are alternative clauses.
+                if !alternatives.is_empty() {
+                    self.push_choice_point(self.pc, Some(alternatives), None);
+                }
+                self.pc = jump_to;
+                Ok(())
+            },
+            CallTarget::Unresolved => unreachable!("call targets are resolved before the call"),
+        }
+    }
+
+    /// Returns the clause addresses of the predicate named by `atom`.
+    fn clauses_of(&self, atom: Atom) -> Result<Vec<usize>, MachineError> {
+        match self.predicate_table.get(&atom) {
+            Some(clauses) if !clauses.is_empty() => Ok(clauses.clone()),
+            Some(_) => Err(MachineError::PredicateClauseNotFound(self.heap.atom_name(atom).to_string())),
+            None => Err(MachineError::PredicateNotFound(self.heap.atom_name(atom).to_string())),
         }
     }
 
     pub fn execute_proceed(&mut self) -> Result<(), MachineError> {
         if let Some(frame) = self.control_stack.pop() {
             self.pc = frame.return_pc;
+            self.cut_barrier = frame.cut_barrier;
         }
         Ok(())
     }
 
     pub fn execute_choice(&mut self, alternative: usize) -> Result<(), MachineError> {
-        let cp = ChoicePoint {
-            saved_pc: self.pc,
-            saved_registers: self.registers.clone(),
-            saved_substitution: self.substitution.clone(),
-            saved_control_stack: self.control_stack.clone(),
-            alternative_clauses: Some(vec![alternative]),
-            uf_trail_len: self.uf.trail.len(),
-            call_level: self.control_stack.len(),
-        };
-        self.choice_stack.push(Box::new(cp));
+        self.push_choice_point(self.pc, Some(vec![alternative]), None);
         Ok(())
     }
 
@@ -123,19 +160,27 @@ impl Machine {
     }
 
     pub fn execute_arithmetic_is(&mut self, target: usize, expression: arithmetic::Expression) -> Result<(), MachineError> {
-        let result = arithmetic::evaluate(&expression, &self.registers)?;
-        if let Some(slot) = self.registers.get_mut(target) {
-            *slot = Some(Term::Const(result));
-            Ok(())
-        } else {
-            Err(MachineError::RegisterOutOfBounds(target))
+        let result = arithmetic::evaluate_with(&expression, &mut |idx| {
+            let cell = self.registers.get(idx).cloned().flatten().ok_or(MachineError::UninitializedRegister(idx))?;
+            arithmetic::evaluate_term(&self.read_term(&cell))
+        })?;
+        if target >= self.registers.len() {
+            return Err(MachineError::RegisterOutOfBounds(target));
         }
+        self.registers[target] = Some(self.heap.put_term(&result.into_term()));
+        Ok(())
     }
 
     pub fn execute_set_local(&mut self, index: usize, value: Term) -> Result<(), MachineError> {
+        let cell = self.heap.put_term(&value);
+        self.store_local(index, cell)
+    }
+
+    /// Stores a cell in a slot of the top environment frame.
+    fn store_local(&mut self, index: usize, cell: Cell) -> Result<(), MachineError> {
         if let Some(env) = self.environment_stack.last_mut() {
             if let Some(slot) = env.get_mut(index) {
-                *slot = Some(value.clone());
+                *slot = Some(cell);
                 Ok(())
             } else {
                 Err(MachineError::RegisterOutOfBounds(index))
@@ -149,10 +194,10 @@ impl Machine {
         if let Some(env) = self.environment_stack.last() {
             let term = env.get(index).and_then(|t| t.clone()).ok_or(MachineError::UninitializedRegister(index))?;
             if let Some(reg_slot) = self.registers.get_mut(register) {
-                if let Some(existing_term) = reg_slot {
-                    let cloned = existing_term.clone();
-                    self.unify(&cloned, &term)
-                        .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {:?}", cloned, term)))
+                if let Some(existing) = reg_slot {
+                    let cloned = existing.clone();
+                    self.unify_cells(&cloned, &term)
+                        .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {:?}", self.heap.term(&cloned), self.heap.term(&term))))
                 } else {
                     *reg_slot = Some(term);
                     Ok(())
@@ -165,84 +210,231 @@ impl Machine {
         }
     }
 
+    pub fn execute_store_local(&mut self, index: usize, register: usize) -> Result<(), MachineError> {
+        let term = self.registers.get(register)
+            .ok_or(MachineError::RegisterOutOfBounds(register))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(register))?;
+        self.store_local(index, term)
+    }
+
+    pub fn execute_put_local(&mut self, index: usize, register: usize) -> Result<(), MachineError> {
+        if let Some(env) = self.environment_stack.last() {
+            let term = env.get(index).and_then(|t| t.clone()).ok_or(MachineError::UninitializedRegister(index))?;
+            if let Some(slot) = self.registers.get_mut(register) {
+                *slot = Some(term);
+                Ok(())
+            } else {
+                Err(MachineError::RegisterOutOfBounds(register))
+            }
+        } else {
+            Err(MachineError::EnvironmentMissing)
+        }
+    }
+
+    /// Backtracks to the most recent choice point with an alternative left.
+    ///
+    /// The machine's stacks are reset to the ones the choice point shares, and
+    /// every binding made since it was created is undone through the trail, so
+    /// backtracking costs time in the work to be undone rather than in the size
+    /// of the machine state. A choice point with further alternatives stays on
+    /// the choice stack.
     pub fn execute_fail(&mut self) -> Result<(), MachineError> {
-        while let Some(cp_box) = self.choice_stack.pop() {
-            let cp = *cp_box;
-            self.registers = cp.saved_registers;
-            self.substitution = cp.saved_substitution;
-            self.control_stack = cp.saved_control_stack;
+        while let Some(mut cp) = self.choice_stack.pop() {
             // Roll back union–find bindings to the saved trail length.
             self.uf.undo_trail(cp.uf_trail_len);
-            if let Some(mut alternatives) = cp.alternative_clauses {
-                if let Some(next_addr) = alternatives.pop() {
+            self.heap.truncate(cp.heap_top);
+            self.next_var_id = cp.var_top;
+            self.delayed.clone_from(&cp.saved_delayed);
+            self.control_stack = cp.saved_control_stack.clone();
+            self.environment_stack = cp.saved_environment_stack.clone();
+            self.registers.clone_from(&cp.saved_registers);
+            self.pc = cp.saved_pc;
+            self.cut_barrier = cp.cut_barrier;
+            if let Some(alternatives) = cp.alternative_clauses.as_mut() {
+                // Alternatives are tried in clause order.
+                if !alternatives.is_empty() {
+                    self.pc = alternatives.remove(0);
                     if !alternatives.is_empty() {
-                        let new_cp = ChoicePoint {
-                            saved_pc: cp.saved_pc,
-                            saved_registers: self.registers.clone(),
-                            saved_substitution: self.substitution.clone(),
-                            saved_control_stack: self.control_stack.clone(),
-                            alternative_clauses: Some(alternatives),
-                            uf_trail_len: self.uf.trail.len(),
-                            call_level: cp.call_level,
-                        };
-                        self.choice_stack.push(Box::new(new_cp));
+                        self.choice_stack.push(cp);
                     }
-                    self.pc = next_addr;
                     return Ok(());
                 }
             }
+            if let Some(solutions) = cp.alternative_bindings.as_mut() {
+                if !solutions.is_empty() {
+                    let next_solution = solutions.remove(0);
+                    if !solutions.is_empty() {
+                        self.choice_stack.push(cp);
+                    }
+                    if self.unify_pairs(&next_solution).is_ok() {
+                        return Ok(());
+                    }
+                }
+            }
         }
         Err(MachineError::NoChoicePoint)
     }
 
+    /// Succeeds with the first of the given solutions of a nondeterministic built–in
+    /// and leaves a choice point holding the remaining ones. Each solution is a list
+    /// of term pairs to unify.
+    pub fn execute_solutions(&mut self, mut solutions: Vec<Vec<(Term, Term)>>) -> Result<(), MachineError> {
+        if solutions.is_empty() {
+            return Err(MachineError::UnificationFailed("No solutions".to_string()));
+        }
+        let first = solutions.remove(0);
+        if !solutions.is_empty() {
+            self.push_choice_point(self.pc, None, Some(solutions));
+        }
+        self.unify_pairs(&first)
+    }
+
+    /// Pushes a choice point that resumes at `saved_pc` with the given alternatives.
+    /// The choice point shares the control and environment stacks with the machine
+    /// and records the trail top instead of the bindings.
+    fn push_choice_point(
+        &mut self,
+        saved_pc: usize,
+        alternative_clauses: Option<Vec<usize>>,
+        alternative_bindings: Option<Vec<Vec<(Term, Term)>>>,
+    ) {
+        let cp = ChoicePoint {
+            saved_pc,
+            saved_registers: self.registers.clone(),
+            saved_control_stack: self.control_stack.clone(),
+            saved_environment_stack: self.environment_stack.clone(),
+            alternative_clauses,
+            alternative_bindings,
+            uf_trail_len: self.uf.trail.len(),
+            heap_top: self.heap.len(),
+            var_top: self.next_var_id,
+            saved_delayed: self.delayed.clone(),
+            cut_barrier: self.cut_barrier,
+        };
+        self.choice_stack.push(Box::new(cp));
+    }
+
+    fn unify_pairs(&mut self, pairs: &[(Term, Term)]) -> Result<(), MachineError> {
+        for (left, right) in pairs {
+            self.unify(left, right)?;
+        }
+        Ok(())
+    }
+
     pub fn execute_get_structure(&mut self, register: usize, functor: String, arity: usize) -> Result<(), MachineError> {
-        match self.registers.get(register).and_then(|t| t.clone()) {
-            Some(Term::Compound(ref f, ref args)) => {
-                if f == &functor && args.len() == arity {
+        let cell = self.registers.get(register)
+            .ok_or(MachineError::RegisterOutOfBounds(register))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(register))?;
+        match self.uf.resolve(&cell) {
+            Cell::Var(var_id) => {
+                // Write mode: build the structure with fresh variables as its
+                // arguments, for the argument instructions to fill in.
+                let functor = self.heap.functor(&functor, arity);
+                let arguments: Vec<Cell> = (0..arity).map(|_| Cell::Var(self.fresh_var_id())).collect();
+                let structure = self.heap.structure(functor, arguments);
+                self.uf.bind(var_id, &structure)?;
+                let Cell::Str(address) = structure else { unreachable!("heap structures are Str cells") };
+                self.registers[register] = Some(structure);
+                self.mode = UnifyMode::Write;
+                self.structure_arguments = address + 1..address + 1 + arity;
+                Ok(())
+            },
+            Cell::Str(address) => {
+                let (found, _) = self.heap.arguments(address);
+                let (found_functor, found_arity) = (self.heap.functor_name(found), self.heap.functor_arity(found));
+                if found_functor == functor && found_arity == arity {
+                    self.mode = UnifyMode::Read;
+                    self.structure_arguments = address + 1..address + 1 + arity;
                     Ok(())
                 } else {
-                    Err(MachineError::StructureMismatch {
-                        expected_functor: functor,
-                        expected_arity: arity,
-                        found_functor: f.clone(),
-                        found_arity: args.len(),
-                    })
+                    Err(MachineError::UnificationFailed(format!(
+                        "Structure mismatch: expected {}/{} but found {}/{}", functor, arity, found_functor, found_arity
+                    )))
                 }
             },
-            Some(_) => Err(MachineError::NotACompoundTerm(register)),
-            None => Err(MachineError::UninitializedRegister(register)),
+            other => Err(MachineError::UnificationFailed(format!(
+                "Cannot unify {:?} with a structure {}/{}", self.heap.term(&other), functor, arity
+            ))),
+        }
+    }
+
+    /// Returns the heap address of the next structure argument and advances past it.
+    fn next_structure_argument(&mut self) -> Result<usize, MachineError> {
+        self.structure_arguments.next().ok_or(MachineError::NoStructureArgument)
+    }
+
+    pub fn execute_unify_var(&mut self, register: usize) -> Result<(), MachineError> {
+        if register >= self.registers.len() {
+            return Err(MachineError::RegisterOutOfBounds(register));
+        }
+        // In write mode the argument is still the fresh variable GET_STRUCTURE
+        // put there, so both modes load the argument cell.
+        let address = self.next_structure_argument()?;
+        self.registers[register] = self.heap.get(address).cloned();
+        Ok(())
+    }
+
+    pub fn execute_unify_value(&mut self, register: usize) -> Result<(), MachineError> {
+        let value = self.registers.get(register)
+            .ok_or(MachineError::RegisterOutOfBounds(register))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(register))?;
+        let address = self.next_structure_argument()?;
+        self.unify_argument(address, value)
+    }
+
+    pub fn execute_unify_const(&mut self, value: i32) -> Result<(), MachineError> {
+        let address = self.next_structure_argument()?;
+        self.unify_argument(address, Cell::Int(value))
+    }
+
+    /// Writes `value` into the structure argument at `address` in write mode, or
+    /// unifies it with the argument in read mode.
+    fn unify_argument(&mut self, address: usize, value: Cell) -> Result<(), MachineError> {
+        match self.mode {
+            // A structure is not written into the argument directly, since it
+            // may contain the structure being built; binding the argument goes
+            // through the occurs check instead.
+            UnifyMode::Write if !matches!(self.uf.resolve(&value), Cell::Str(_)) => {
+                self.heap.set(address, value);
+                Ok(())
+            },
+            UnifyMode::Write => {
+                let argument = self.heap.get(address).cloned().expect("structure argument");
+                self.unify_cells(&argument, &value)
+            },
+            UnifyMode::Read => {
+                let argument = self.heap.get(address).cloned().expect("structure argument");
+                self.unify_cells(&argument, &value)
+                    .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {:?}", self.heap.term(&argument), self.heap.term(&value))))
+            },
         }
     }
 
     pub fn execute_indexed_call(&mut self, predicate: String, index_register: usize) -> Result<(), MachineError> {
-        let key_term = self.registers.get(index_register)
+        let key_cell = self.registers.get(index_register)
             .ok_or(MachineError::RegisterOutOfBounds(index_register))?
             .clone()
             .ok_or(MachineError::UninitializedRegister(index_register))?;
-        let key_vec = vec![key_term];
-        if let Some(index_map) = self.index_table.get(&predicate) {
+        let key_vec = vec![self.heap.term(&key_cell)];
+        if let Some(index_map) = self.heap.lookup(&predicate).and_then(|atom| self.index_table.get(&atom)) {
             if let Some(clauses) = index_map.get(&key_vec) {
                 if !clauses.is_empty() {
                     let mut alternatives = clauses.clone();
                     let jump_to = alternatives.remove(0);
-                    let alternative_clauses = if alternatives.is_empty() { None } else { Some(alternatives) };
-                    let cp = ChoicePoint {
-                        saved_pc: self.pc,
-                        saved_registers: self.registers.clone(),
-                        saved_substitution: self.substitution.clone(),
-                        saved_control_stack: self.control_stack.clone(),
-                        alternative_clauses,
-                        uf_trail_len: self.uf.trail.len(),
-                        call_level: self.control_stack.len(),
-                    };
-                    self.choice_stack.push(Box::new(cp));
+                    self.cut_barrier = self.choice_stack.len();
+                    if !alternatives.is_empty() {
+                        self.push_choice_point(self.pc, Some(alternatives), None);
+                    }
                     self.pc = jump_to;
-                    return Ok(());
+                    Ok(())
                 } else {
-                    return Err(MachineError::NoIndexedClause(predicate, key_vec[0].clone()));
+                    Err(MachineError::NoIndexedClause(predicate, key_vec[0].clone()))
                 }
             } else {
-                return Err(MachineError::NoIndexEntry(predicate, key_vec[0].clone()));
+                Err(MachineError::NoIndexEntry(predicate, key_vec[0].clone()))
             }
         } else {
             Err(MachineError::PredicateNotInIndex(predicate))
@@ -250,8 +442,8 @@ impl Machine {
     }
 
     pub fn execute_put_str(&mut self, register: usize, value: String) -> Result<(), MachineError> {
-        if let Some(slot) = self.registers.get_mut(register) {
-            *slot = Some(Term::Str(value));
+        if register < self.registers.len() {
+            self.registers[register] = Some(self.heap.atom(&value));
             Ok(())
         } else {
             Err(MachineError::RegisterOutOfBounds(register))
@@ -262,11 +454,11 @@ impl Machine {
         let term_option = self.registers.get(register).cloned()
             .ok_or(MachineError::RegisterOutOfBounds(register))?;
         match term_option {
-            Some(term) => {
-                let term_clone = term.clone();
-                self.unify(&term_clone, &Term::Str(value.clone()))
-                    .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with Str({})", term_clone, value)))?;
-                let resolved = self.uf.resolve(&term);
+            Some(cell) => {
+                let atom = self.heap.atom(&value);
+                self.unify_cells(&cell, &atom)
+                    .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with Str({})", self.heap.term(&cell), value)))?;
+                let resolved = self.uf.resolve(&cell);
                 self.registers[register] = Some(resolved);
                 Ok(())
             },
@@ -274,38 +466,67 @@ impl Machine {
         }
     }
 
+    pub fn execute_put_big_int(&mut self, register: usize, value: BigInt) -> Result<(), MachineError> {
+        if register >= self.registers.len() {
+            return Err(MachineError::RegisterOutOfBounds(register));
+        }
+        self.registers[register] = Some(self.heap.put_term(&Integer::from(value).into_term()));
+        Ok(())
+    }
+
+    pub fn execute_get_big_int(&mut self, register: usize, value: BigInt) -> Result<(), MachineError> {
+        let cell = self.registers.get(register)
+            .ok_or(MachineError::RegisterOutOfBounds(register))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(register))?;
+        let integer = self.heap.put_term(&Integer::from(value.clone()).into_term());
+        self.unify_cells(&cell, &integer)
+            .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {}", self.heap.term(&cell), value)))
+    }
+
+    pub fn execute_put_float(&mut self, register: usize, value: f64) -> Result<(), MachineError> {
+        if register >= self.registers.len() {
+            return Err(MachineError::RegisterOutOfBounds(register));
+        }
+        self.registers[register] = Some(self.heap.put_term(&Term::Float(Float(value))));
+        Ok(())
+    }
+
+    pub fn execute_get_float(&mut self, register: usize, value: f64) -> Result<(), MachineError> {
+        let cell = self.registers.get(register)
+            .ok_or(MachineError::RegisterOutOfBounds(register))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(register))?;
+        let float = self.heap.put_term(&Term::Float(Float(value)));
+        self.unify_cells(&cell, &float)
+            .map_err(|_| MachineError::UnificationFailed(format!("Cannot unify {:?} with {}", self.heap.term(&cell), format_float(value))))
+    }
+
     pub fn execute_multi_indexed_call(&mut self, predicate: String, index_registers: Vec<usize>) -> Result<(), MachineError> {
         let mut key_vec = Vec::new();
         for reg in index_registers {
-            let term = self.registers.get(reg)
+            let cell = self.registers.get(reg)
                 .ok_or(MachineError::RegisterOutOfBounds(reg))?
                 .clone()
                 .ok_or(MachineError::UninitializedRegister(reg))?;
-            key_vec.push(term);
+            key_vec.push(self.heap.term(&cell));
         }
-        if let Some(index_map) = self.index_table.get(&predicate) {
+        if let Some(index_map) = self.heap.lookup(&predicate).and_then(|atom| self.index_table.get(&atom)) {
             if let Some(clauses) = index_map.get(&key_vec) {
                 if !clauses.is_empty() {
                     let mut alternatives = clauses.clone();
                     let jump_to = alternatives.remove(0);
-                    let alternative_clauses = if alternatives.is_empty() { None } else { Some(alternatives) };
-                    let cp = ChoicePoint {
-                        saved_pc: self.pc,
-                        saved_registers: self.registers.clone(),
-                        saved_substitution: self.substitution.clone(),
-                        saved_control_stack: self.control_stack.clone(),
-                        alternative_clauses,
-                        uf_trail_len: self.uf.trail.len(),
-                        call_level: self.control_stack.len(),
-                    };
-                    self.choice_stack.push(Box::new(cp));
+                    self.cut_barrier = self.choice_stack.len();
+                    if !alternatives.is_empty() {
+                        self.push_choice_point(self.pc, Some(alternatives), None);
+                    }
                     self.pc = jump_to;
-                    return Ok(());
+                    Ok(())
                 } else {
-                    return Err(MachineError::NoIndexedClause(predicate, key_vec[0].clone()));
+                    Err(MachineError::NoIndexedClause(predicate, key_vec[0].clone()))
                 }
             } else {
-                return Err(MachineError::NoIndexEntry(predicate, key_vec[0].clone()));
+                Err(MachineError::NoIndexEntry(predicate, key_vec[0].clone()))
             }
         } else {
             Err(MachineError::PredicateNotInIndex(predicate))
@@ -313,6 +534,12 @@ impl Machine {
     }
 
     pub fn execute_tail_call(&mut self, predicate: String) -> Result<(), MachineError> {
+        self.execute_tail_call_to(CallTarget::Unresolved, &predicate)
+    }
+
+    /// Tail-calls the predicate `predicate` through `target`, resolving it first
+    /// if the instruction was not resolved when its code was loaded.
+    pub fn execute_tail_call_to(&mut self, target: CallTarget, predicate: &str) -> Result<(), MachineError> {
         // For a tail call, the current environment frame is no longer needed.
         // Ensure there is an environment frame to pop.
         if self.environment_stack.is_empty() {
@@ -322,39 +549,33 @@ impl Machine {
 
         // Tail call does not push a new control frame.
         // Instead, we simply update the program counter.
-        if let Some(builtin) = self.builtins.get(&predicate) {
-            builtin(self)
-        } else if let Some(clauses) = self.predicate_table.get(&predicate) {
-            if clauses.is_empty() {
-                return Err(MachineError::PredicateClauseNotFound(predicate));
-            }
-            let mut alternatives = clauses.clone();
-            let jump_to = alternatives.remove(0);
-            // Only if there are alternative clauses, we push a choice point.
-            if !alternatives.is_empty() {
-                let cp = crate::machine::choice_point::ChoicePoint {
-                    saved_pc: self.pc,
-                    saved_registers: self.registers.clone(),
-                    saved_substitution: self.substitution.clone(),
-                    saved_control_stack: self.control_stack.clone(),
-                    alternative_clauses: Some(alternatives),
-                    uf_trail_len: self.uf.trail.len(),
-                    call_level: self.control_stack.len(),
-                };
-                self.choice_stack.push(Box::new(cp));
-            }
-            self.pc = jump_to;
-            Ok(())
-        } else {
-            Err(MachineError::PredicateNotFound(predicate))
+        let target = match target {
+            CallTarget::Unresolved => self.resolve_call(predicate),
+            target => target,
+        };
+        match target {
+            CallTarget::Builtin(id) => (self.builtin_table[id])(self),
+            CallTarget::Predicate(atom) => {
+                let mut alternatives = self.clauses_of(atom)?;
+                let jump_to = alternatives.remove(0);
+                self.cut_barrier = self.choice_stack.len();
+                // Only if there are alternative clauses, we push a choice point.
+                if !alternatives.is_empty() {
+                    self.push_choice_point(self.pc, Some(alternatives), None);
+                }
+                self.pc = jump_to;
+                Ok(())
+            },
+            CallTarget::Unresolved => unreachable!("call targets are resolved before the call"),
         }
-    }    
+    }
 
     pub fn execute_assert_clause(&mut self, predicate: String, address: usize) -> Result<(), MachineError> {
         // Add the clause address to the predicate table.
-        self.predicate_table.entry(predicate.clone()).or_insert_with(Vec::new).push(address);
+        let atom = self.intern(&predicate);
+        self.predicate_table.entry(atom).or_default().push(address);
         // If the predicate is already indexed, update all keys in the index table.
-        if let Some(index_map) = self.index_table.get_mut(&predicate) {
+        if let Some(index_map) = self.index_table.get_mut(&atom) {
              for (_key, clause_list) in index_map.iter_mut() {
                   clause_list.push(address);
              }
@@ -363,7 +584,7 @@ impl Machine {
     }    
 
     pub fn execute_retract_clause(&mut self, predicate: String, address: usize) -> Result<(), MachineError> {
-        if let Some(clauses) = self.predicate_table.get_mut(&predicate) {
+        if let Some(clauses) = self.heap.lookup(&predicate).and_then(|atom| self.predicate_table.get_mut(&atom)) {
             if let Some(pos) = clauses.iter().position(|&a| a == address) {
                 clauses.remove(pos);
                 self.update_index_table_on_retract(&predicate, address);
@@ -377,25 +598,84 @@ impl Machine {
     }
 
     pub fn execute_cut(&mut self) -> Result<(), MachineError> {
-        let current_call_level = self.control_stack.len();
-        self.choice_stack.retain(|cp| cp.call_level < current_call_level);
+        self.choice_stack.truncate(self.cut_barrier);
+        Ok(())
+    }
+
+    pub fn execute_get_level(&mut self, register: usize) -> Result<(), MachineError> {
+        let level = Term::Const(self.choice_stack.len() as i32);
+        self.set_register(register, level)
+    }
+
+    /// Reads a choice stack height saved by GET_LEVEL.
+    fn saved_level(&mut self, register: usize) -> Result<usize, MachineError> {
+        match self.argument(register)? {
+            Term::Const(level) if level >= 0 => Ok(level as usize),
+            other => Err(IsoError::Type("integer".to_string(), other).into()),
+        }
+    }
+
+    pub fn execute_cut_to(&mut self, register: usize) -> Result<(), MachineError> {
+        let level = self.saved_level(register)?;
+        self.choice_stack.truncate(level);
+        Ok(())
+    }
+
+    pub fn execute_soft_cut(&mut self, register: usize) -> Result<(), MachineError> {
+        // The choice point stays on the stack, so the ones above it keep their
+        // places, but backtracking into it finds no alternative left.
+        let level = self.saved_level(register)?;
+        if let Some(cp) = self.choice_stack.get_mut(level) {
+            cp.alternative_clauses = None;
+            cp.alternative_bindings = None;
+        }
+        Ok(())
+    }
+
+    pub fn execute_jump(&mut self, address: usize) -> Result<(), MachineError> {
+        self.pc = address;
         Ok(())
     }
 
     pub fn execute_build_compound(&mut self, target: usize, functor: String, arg_registers: Vec<usize>) -> Result<(), MachineError> {
-        let mut args = Vec::new();
         for &reg in &arg_registers {
-            let term = self.registers.get(reg)
+            self.registers.get(reg)
                 .ok_or(MachineError::RegisterOutOfBounds(reg))?
-                .clone()
+                .as_ref()
                 .ok_or(MachineError::UninitializedRegister(reg))?;
-            args.push(term);
         }
-        if let Some(slot) = self.registers.get_mut(target) {
-            *slot = Some(Term::Compound(functor, args));
-            Ok(())
-        } else {
-            Err(MachineError::RegisterOutOfBounds(target))
+        if target >= self.registers.len() {
+            return Err(MachineError::RegisterOutOfBounds(target));
         }
+        // The arguments are copied from the registers straight onto the heap.
+        let functor = self.heap.functor(&functor, arg_registers.len());
+        let registers = &self.registers;
+        let structure = self.heap.structure(functor, arg_registers.iter().map(|&reg| registers[reg].clone().unwrap()));
+        self.registers[target] = Some(structure);
+        Ok(())
+    }
+
+    pub fn execute_build_lambda(&mut self, target: usize, param: usize, body: usize) -> Result<(), MachineError> {
+        let param_cell = self.registers.get(param)
+            .ok_or(MachineError::RegisterOutOfBounds(param))?
+            .clone()
+            .ok_or(MachineError::UninitializedRegister(param))?;
+        let Cell::Var(param_id) = self.uf.resolve(&param_cell) else {
+            return Err(MachineError::NotAVariable(param));
+        };
+        let body = self.argument(body)?;
+        self.set_register(target, Term::Lambda(param_id, Box::new(body)))
+    }
+
+    pub fn execute_apply(&mut self, target: usize, function: usize, argument: usize) -> Result<(), MachineError> {
+        let function = self.argument(function)?;
+        let argument = self.argument(argument)?;
+        self.set_register(target, Term::App(Box::new(function), Box::new(argument)))
+    }
+
+    pub fn execute_normalize(&mut self, register: usize, strategy: Strategy) -> Result<(), MachineError> {
+        let term = self.argument(register)?;
+        let normal = self.normalize_term(&term, strategy, self.eta_reduction)?;
+        self.set_register(register, normal)
     }
 }
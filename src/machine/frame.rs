@@ -5,4 +5,6 @@
 pub struct Frame {
     /// The program counter to return to.
     pub return_pc: usize,
+    /// The caller's cut barrier, restored on return.
+    pub cut_barrier: usize,
 }
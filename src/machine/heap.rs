@@ -0,0 +1,203 @@
+// src/machine/heap.rs
+//! The heap: an arena of tagged cells holding the terms the machine works on.
+//!
+//! Registers, environment slots and variable bindings hold `Cell`s rather than
+//! `Term` trees. A cell is a small value: a variable, an integer, an atom or a
+//! pointer to a structure. A structure is stored on the heap as a functor cell
+//! followed by its argument cells, so copying a term is O(1) and building one
+//! only appends cells to the heap. Names are interned in the heap's atom table
+//! and functors as atom/arity pairs, so comparing them compares integers.
+//!
+//! `Term` remains the type of the public API: `Heap::put_term` stores a term on
+//! the heap and `Heap::term` reads one back. Terms that have no cell layout
+//! (lambda terms and the other extensions) are kept whole behind a shared
+//! pointer.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::machine::atom::{Atom, AtomTable};
+use crate::machine::term::Term;
+use crate::machine::unification::Bindable;
+
+/// The id of an interned functor (a name together with an arity).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FunctorId(u32);
+
+/// A tagged heap cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// A reference to the variable with the given id.
+    Var(usize),
+    /// An integer constant.
+    Int(i32),
+    /// An atom.
+    Atom(Atom),
+    /// A pointer to the functor cell of a structure.
+    Str(usize),
+    /// The header of a structure, followed on the heap by its arguments.
+    Functor(FunctorId),
+    /// A term without a cell layout, shared rather than copied.
+    Term(Rc<Term>),
+}
+
+impl Bindable for Cell {
+    fn var_id(&self) -> Option<usize> {
+        match self {
+            Cell::Var(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// The cell arena together with its atom and functor tables.
+#[derive(Debug, Clone, Default)]
+pub struct Heap {
+    cells: Vec<Cell>,
+    atoms: AtomTable,
+    functors: Vec<(Atom, usize)>,
+    functor_ids: HashMap<(Atom, usize), FunctorId>,
+}
+
+impl Heap {
+    /// Creates an empty heap.
+    pub fn new() -> Self {
+        Heap::default()
+    }
+
+    /// Returns the number of cells on the heap (the heap top).
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns true if the heap has no cells.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Discards the cells above `len`. Used on backtracking, when nothing refers
+    /// to cells created after the choice point any more.
+    pub fn truncate(&mut self, len: usize) {
+        self.cells.truncate(len);
+    }
+
+    /// Returns the cell at the given address.
+    pub fn get(&self, address: usize) -> Option<&Cell> {
+        self.cells.get(address)
+    }
+
+    /// Returns the atom for `name`, interning it if needed.
+    pub fn intern(&mut self, name: &str) -> Atom {
+        self.atoms.intern(name)
+    }
+
+    /// Returns the atom for `name` if it has been interned.
+    pub fn lookup(&self, name: &str) -> Option<Atom> {
+        self.atoms.lookup(name)
+    }
+
+    /// Returns the name of an atom.
+    pub fn atom_name(&self, atom: Atom) -> &str {
+        self.atoms.name(atom)
+    }
+
+    /// Overwrites the cell at the given address. Used to fill in the arguments of
+    /// a structure built in write mode.
+    pub fn set(&mut self, address: usize, cell: Cell) {
+        self.cells[address] = cell;
+    }
+
+    /// Returns the id of the functor `name/arity`, interning it if needed.
+    pub fn functor(&mut self, name: &str, arity: usize) -> FunctorId {
+        let name = self.atoms.intern(name);
+        if let Some(&id) = self.functor_ids.get(&(name, arity)) {
+            return id;
+        }
+        let id = FunctorId(self.functors.len() as u32);
+        self.functors.push((name, arity));
+        self.functor_ids.insert((name, arity), id);
+        id
+    }
+
+    /// Returns the name of an interned functor as an atom.
+    pub fn functor_atom(&self, id: FunctorId) -> Atom {
+        self.functors[id.0 as usize].0
+    }
+
+    /// Returns the name of an interned functor.
+    pub fn functor_name(&self, id: FunctorId) -> &str {
+        self.atoms.name(self.functor_atom(id))
+    }
+
+    /// Returns the arity of an interned functor.
+    pub fn functor_arity(&self, id: FunctorId) -> usize {
+        self.functors[id.0 as usize].1
+    }
+
+    /// Returns the cell of the atom with the given name.
+    pub fn atom(&mut self, name: &str) -> Cell {
+        Cell::Atom(self.atoms.intern(name))
+    }
+
+    /// Allocates a structure with the given functor and arguments and returns a
+    /// pointer to it.
+    pub fn structure(&mut self, functor: FunctorId, args: impl IntoIterator<Item = Cell>) -> Cell {
+        let address = self.cells.len();
+        self.cells.push(Cell::Functor(functor));
+        self.cells.extend(args);
+        Cell::Str(address)
+    }
+
+    /// Returns the functor and the arguments of the structure at `address`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `address` does not hold a functor cell.
+    pub fn arguments(&self, address: usize) -> (FunctorId, &[Cell]) {
+        match &self.cells[address] {
+            Cell::Functor(functor) => {
+                let arity = self.functor_arity(*functor);
+                (*functor, &self.cells[address + 1..address + 1 + arity])
+            },
+            other => panic!("Heap address {} holds {:?}, not a functor", address, other),
+        }
+    }
+
+    /// Stores a term on the heap and returns the cell that refers to it.
+    pub fn put_term(&mut self, term: &Term) -> Cell {
+        match term {
+            Term::Const(n) => Cell::Int(*n),
+            Term::Var(id) => Cell::Var(*id),
+            Term::Str(name) => self.atom(name),
+            Term::Compound(name, args) => {
+                // Reserve the argument cells first, so that the arguments of the
+                // structure are contiguous even if they contain structures.
+                let functor = self.functor(name, args.len());
+                let address = self.cells.len();
+                self.cells.push(Cell::Functor(functor));
+                self.cells.resize(address + 1 + args.len(), Cell::Int(0));
+                for (i, arg) in args.iter().enumerate() {
+                    let cell = self.put_term(arg);
+                    self.cells[address + 1 + i] = cell;
+                }
+                Cell::Str(address)
+            },
+            other => Cell::Term(Rc::new(other.clone())),
+        }
+    }
+
+    /// Reads the term a cell refers to. Variables are read as they are, bound or
+    /// not; `Machine::resolve_deep` reads a term with its bindings applied.
+    pub fn term(&self, cell: &Cell) -> Term {
+        match cell {
+            Cell::Var(id) => Term::Var(*id),
+            Cell::Int(n) => Term::Const(*n),
+            Cell::Atom(atom) => Term::Str(self.atom_name(*atom).to_string()),
+            Cell::Str(address) => {
+                let (functor, args) = self.arguments(*address);
+                Term::Compound(self.functor_name(functor).to_string(), args.iter().map(|arg| self.term(arg)).collect())
+            },
+            Cell::Functor(functor) => Term::Str(self.functor_name(*functor).to_string()),
+            Cell::Term(term) => (**term).clone(),
+        }
+    }
+}
@@ -2,10 +2,28 @@
 //! Definitions of LAM instructions.
 use std::fmt;
 
+use num_bigint::BigInt;
+use crate::machine::atom::Atom;
 use crate::machine::term::Term;
 use crate::machine::arithmetic::Expression;
 use crate::machine::error_handling::MachineError;
 use crate::machine::core::Machine;
+use crate::machine::lambda::Strategy;
+use crate::machine::number::format_float;
+
+/// Where a CALL or TAIL_CALL goes. The target is resolved from the predicate
+/// indicator when the code is loaded into a machine, so that running the call
+/// does not look the indicator up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CallTarget {
+    /// Not resolved yet: the indicator is looked up when the call runs.
+    #[default]
+    Unresolved,
+    /// The built-in with this id in the machine's built-in table.
+    Builtin(usize),
+    /// The predicate whose clauses the predicate table lists under this atom.
+    Predicate(Atom),
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Instruction {
@@ -13,7 +31,7 @@ pub enum Instruction {
     PutVar { register: usize, var_id: usize, name: String },
     GetConst { register: usize, value: i32 },
     GetVar { register: usize, var_id: usize, name: String },
-    Call { predicate: String },
+    Call { predicate: String, target: CallTarget },
     Proceed,
     Choice { alternative: usize },
     Allocate { n: usize },
@@ -25,7 +43,7 @@ pub enum Instruction {
     GetStructure { register: usize, functor: String, arity: usize },
     IndexedCall { predicate: String, index_register: usize },
     MultiIndexedCall { predicate: String, index_registers: Vec<usize> },
-    TailCall { predicate: String },
+    TailCall { predicate: String, target: CallTarget },
     AssertClause { predicate: String, address: usize },
     RetractClause { predicate: String, address: usize },
     Cut,
@@ -34,7 +52,43 @@ pub enum Instruction {
     GetStr { register: usize, value: String },
     /// New: Move instruction copies the content from register `src` into register `dst`.
     Move { src: usize, dst: usize },
+    /// Unifies the term in register `register` with the term in register `src`.
+    GetValue { register: usize, src: usize },
+    /// Places a fresh variable, distinct from every existing one, in register `register`.
+    NewVar { register: usize, name: String },
+    /// Stores the term in register `register` in slot `index` of the current environment.
+    StoreLocal { index: usize, register: usize },
+    /// Loads slot `index` of the current environment into register `register`, overwriting it.
+    PutLocal { index: usize, register: usize },
     Halt,
+    /// Loads the next argument of the structure matched or built by GetStructure into register `register`.
+    UnifyVar { register: usize },
+    /// Unifies the next structure argument with the term in register `register`, or writes the term there in write mode.
+    UnifyValue { register: usize },
+    /// Unifies the next structure argument with Const(value), or writes the constant there in write mode.
+    UnifyConst { value: i32 },
+    /// Builds the lambda abstraction over the variable in register `param` with the term in register `body`.
+    BuildLambda { target: usize, param: usize, body: usize },
+    /// Builds the application of the term in register `function` to the term in register `argument`.
+    Apply { target: usize, function: usize, argument: usize },
+    /// Reduces the term in register `register` to normal form with the given strategy.
+    Normalize { register: usize, strategy: Strategy },
+    /// Places the integer `value`, which may lie outside the range of PutConst, in register `register`.
+    PutBigInt { register: usize, value: BigInt },
+    /// Unifies the term in register `register` with the integer `value`.
+    GetBigInt { register: usize, value: BigInt },
+    /// Places the float `value` in register `register`.
+    PutFloat { register: usize, value: f64 },
+    /// Unifies the term in register `register` with the float `value`.
+    GetFloat { register: usize, value: f64 },
+    /// Places the height of the choice stack in register `register`, as a cut barrier for CUT_TO and SOFT_CUT.
+    GetLevel { register: usize },
+    /// Removes every choice point above the height saved by GET_LEVEL in register `register`.
+    CutTo { register: usize },
+    /// Disables the choice point at the height saved by GET_LEVEL in register `register`, keeping those above it.
+    SoftCut { register: usize },
+    /// Continues execution at `address`.
+    Jump { address: usize },
 }
 
 impl Instruction {
@@ -45,7 +99,7 @@ impl Instruction {
             Instruction::PutVar { register, var_id, name } => machine.execute_put_var(*register, *var_id, name.clone()),
             Instruction::GetConst { register, value } => machine.execute_get_const(*register, *value),
             Instruction::GetVar { register, var_id, name } => machine.execute_get_var(*register, *var_id, name.clone()),
-            Instruction::Call { predicate } => machine.execute_call(predicate.clone()),
+            Instruction::Call { predicate, target } => machine.execute_call_to(*target, predicate),
             Instruction::Proceed => machine.execute_proceed(),
             Instruction::Choice { alternative } => machine.execute_choice(*alternative),
             Instruction::Allocate { n } => machine.execute_allocate(*n),
@@ -57,7 +111,7 @@ impl Instruction {
             Instruction::GetStructure { register, functor, arity } => machine.execute_get_structure(*register, functor.clone(), *arity),
             Instruction::IndexedCall { predicate, index_register } => machine.execute_indexed_call(predicate.clone(), *index_register),
             Instruction::MultiIndexedCall { predicate, index_registers } => machine.execute_multi_indexed_call(predicate.clone(), index_registers.clone()),
-            Instruction::TailCall { predicate } => machine.execute_tail_call(predicate.clone()),
+            Instruction::TailCall { predicate, target } => machine.execute_tail_call_to(*target, predicate),
             Instruction::AssertClause { predicate, address } => machine.execute_assert_clause(predicate.clone(), *address),
             Instruction::RetractClause { predicate, address } => machine.execute_retract_clause(predicate.clone(), *address),
             Instruction::Cut => machine.execute_cut(),
@@ -65,24 +119,118 @@ impl Instruction {
             Instruction::PutStr { register, value } => machine.execute_put_str(*register, value.clone()),
             Instruction::GetStr { register, value } => machine.execute_get_str(*register, value.clone()),
             Instruction::Move { src, dst } => machine.execute_move(*src, *dst),
+            Instruction::GetValue { register, src } => machine.execute_get_value(*register, *src),
+            Instruction::NewVar { register, name } => machine.execute_new_var(*register, name.clone()),
+            Instruction::StoreLocal { index, register } => machine.execute_store_local(*index, *register),
+            Instruction::PutLocal { index, register } => machine.execute_put_local(*index, *register),
             Instruction::Halt => Ok(()),
+            Instruction::UnifyVar { register } => machine.execute_unify_var(*register),
+            Instruction::UnifyValue { register } => machine.execute_unify_value(*register),
+            Instruction::UnifyConst { value } => machine.execute_unify_const(*value),
+            Instruction::BuildLambda { target, param, body } => machine.execute_build_lambda(*target, *param, *body),
+            Instruction::Apply { target, function, argument } => machine.execute_apply(*target, *function, *argument),
+            Instruction::Normalize { register, strategy } => machine.execute_normalize(*register, *strategy),
+            Instruction::PutBigInt { register, value } => machine.execute_put_big_int(*register, value.clone()),
+            Instruction::GetBigInt { register, value } => machine.execute_get_big_int(*register, value.clone()),
+            Instruction::PutFloat { register, value } => machine.execute_put_float(*register, *value),
+            Instruction::GetFloat { register, value } => machine.execute_get_float(*register, *value),
+            Instruction::GetLevel { register } => machine.execute_get_level(*register),
+            Instruction::CutTo { register } => machine.execute_cut_to(*register),
+            Instruction::SoftCut { register } => machine.execute_soft_cut(*register),
+            Instruction::Jump { address } => machine.execute_jump(*address),
+        }
+    }
+
+    /// Returns the highest register this instruction reads or writes, if it uses any.
+    pub fn max_register(&self) -> Option<usize> {
+        match self {
+            Instruction::PutConst { register, .. }
+            | Instruction::PutVar { register, .. }
+            | Instruction::GetConst { register, .. }
+            | Instruction::GetVar { register, .. }
+            | Instruction::GetLocal { register, .. }
+            | Instruction::GetStructure { register, .. }
+            | Instruction::PutStr { register, .. }
+            | Instruction::GetStr { register, .. }
+            | Instruction::NewVar { register, .. }
+            | Instruction::StoreLocal { register, .. }
+            | Instruction::PutLocal { register, .. }
+            | Instruction::UnifyVar { register }
+            | Instruction::UnifyValue { register }
+            | Instruction::Normalize { register, .. }
+            | Instruction::PutBigInt { register, .. }
+            | Instruction::GetBigInt { register, .. }
+            | Instruction::PutFloat { register, .. }
+            | Instruction::GetFloat { register, .. }
+            | Instruction::GetLevel { register }
+            | Instruction::CutTo { register }
+            | Instruction::SoftCut { register }
+            | Instruction::IndexedCall { index_register: register, .. } => Some(*register),
+            Instruction::MultiIndexedCall { index_registers, .. } => index_registers.iter().copied().max(),
+            Instruction::ArithmeticIs { target, expression } => expression.max_register().max(Some(*target)),
+            Instruction::BuildCompound { target, arg_registers, .. } => arg_registers.iter().copied().max().max(Some(*target)),
+            Instruction::Move { src, dst } => Some(*src.max(dst)),
+            Instruction::GetValue { register, src } => Some(*register.max(src)),
+            Instruction::BuildLambda { target, param, body } => Some(*target.max(param).max(body)),
+            Instruction::Apply { target, function, argument } => Some(*target.max(function).max(argument)),
+            _ => None,
         }
     }
 }
 
+/// Writes a string as a double-quoted `.lam` literal, escaping backslashes,
+/// quotes and line breaks.
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes a term as a `.lam` literal: integers as they are, variables as the
+/// register-like `Rn` and strings quoted. Other terms have no literal syntax and
+/// are written with their `Display` form.
+pub(crate) fn format_literal(term: &Term) -> String {
+    match term {
+        Term::Const(n) => n.to_string(),
+        Term::BigInt(n) => n.to_string(),
+        Term::Float(x) => x.to_string(),
+        Term::Var(id) => format!("R{}", id),
+        Term::Str(s) => quote(s),
+        other => other.to_string(),
+    }
+}
+
+/// Writes a list of registers as comma-separated `Rn` parameters.
+fn format_registers(registers: &[usize]) -> String {
+    registers.iter().map(|r| format!("R{}", r)).collect::<Vec<_>>().join(", ")
+}
+
+/// Writes the instruction in the `.lam` syntax read by `parse_instructions`,
+/// so that parsing the output gives back the same instruction.
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Instruction::PutConst { register, value } =>
                 write!(f, "PUT_CONST R{}, {}", register, value),
             Instruction::PutVar { register, var_id, name } =>
-                write!(f, "PUT_VAR   R{}, {}, \"{}\"", register, var_id, name),
+                write!(f, "PUT_VAR   R{}, {}, {}", register, var_id, quote(name)),
             Instruction::GetConst { register, value } =>
                 write!(f, "GET_CONST R{}, {}", register, value),
             Instruction::GetVar { register, var_id, name } =>
-                write!(f, "GET_VAR   R{}, {}, \"{}\"", register, var_id, name),
-            Instruction::Call { predicate } =>
-                write!(f, "CALL      \"{}\"", predicate),
+                write!(f, "GET_VAR   R{}, {}, {}", register, var_id, quote(name)),
+            Instruction::Call { predicate, .. } =>
+                write!(f, "CALL      {}", quote(predicate)),
             Instruction::Proceed =>
                 write!(f, "PROCEED"),
             Instruction::Choice { alternative } =>
@@ -92,37 +240,73 @@ impl fmt::Display for Instruction {
             Instruction::Deallocate =>
                 write!(f, "DEALLOCATE"),
             Instruction::ArithmeticIs { target, expression } =>
-                write!(f, "ARITHMETIC_IS R{}, {:?}", target, expression),
+                write!(f, "ARITHMETIC_IS R{}, {}", target, expression),
             Instruction::SetLocal { index, value } =>
-                write!(f, "SET_LOCAL {} <- {:?}", index, value),
+                write!(f, "SET_LOCAL {}, {}", index, format_literal(value)),
             Instruction::GetLocal { index, register } =>
-                write!(f, "GET_LOCAL {} -> R{}", index, register),
+                write!(f, "GET_LOCAL {}, R{}", index, register),
             Instruction::Fail =>
                 write!(f, "FAIL"),
             Instruction::GetStructure { register, functor, arity } =>
-                write!(f, "GET_STRUCTURE R{}, {} / {}", register, functor, arity),
+                write!(f, "GET_STRUCTURE R{}, {}, {}", register, quote(functor), arity),
             Instruction::IndexedCall { predicate, index_register } =>
-                write!(f, "INDEXED_CALL \"{}\", R{}", predicate, index_register),
+                write!(f, "INDEXED_CALL {}, R{}", quote(predicate), index_register),
             Instruction::MultiIndexedCall { predicate, index_registers } =>
-                write!(f, "MULTI_INDEXED_CALL \"{}\", {:?}", predicate, index_registers),
-            Instruction::TailCall { predicate } =>
-                write!(f, "TAIL_CALL \"{}\"", predicate),
+                write!(f, "MULTI_INDEXED_CALL {}, {}", quote(predicate), format_registers(index_registers)),
+            Instruction::TailCall { predicate, .. } =>
+                write!(f, "TAIL_CALL {}", quote(predicate)),
             Instruction::AssertClause { predicate, address } =>
-                write!(f, "ASSERT_CLAUSE \"{}\", {}", predicate, address),
+                write!(f, "ASSERT_CLAUSE {}, {}", quote(predicate), address),
             Instruction::RetractClause { predicate, address } =>
-                write!(f, "RETRACT_CLAUSE \"{}\", {}", predicate, address),
+                write!(f, "RETRACT_CLAUSE {}, {}", quote(predicate), address),
             Instruction::Cut =>
                 write!(f, "CUT"),
             Instruction::BuildCompound { target, functor, arg_registers } =>
-                write!(f, "BUILD_COMPOUND R{}, {} {:?}", target, functor, arg_registers),
+                write!(f, "BUILD_COMPOUND R{}, {}, {}", target, quote(functor), format_registers(arg_registers)),
             Instruction::PutStr { register, value } =>
-                write!(f, "PUT_STR   R{}, \"{}\"", register, value),
+                write!(f, "PUT_STR   R{}, {}", register, quote(value)),
             Instruction::GetStr { register, value } =>
-                write!(f, "GET_STR   R{}, \"{}\"", register, value),
+                write!(f, "GET_STR   R{}, {}", register, quote(value)),
             Instruction::Move { src, dst } =>
-                write!(f, "MOVE      R{} -> R{}", src, dst),
+                write!(f, "MOVE      R{}, R{}", src, dst),
+            Instruction::GetValue { register, src } =>
+                write!(f, "GET_VALUE R{}, R{}", register, src),
+            Instruction::NewVar { register, name } =>
+                write!(f, "NEW_VAR   R{}, {}", register, quote(name)),
+            Instruction::StoreLocal { index, register } =>
+                write!(f, "STORE_LOCAL {}, R{}", index, register),
+            Instruction::PutLocal { index, register } =>
+                write!(f, "PUT_LOCAL {}, R{}", index, register),
             Instruction::Halt =>
                 write!(f, "HALT"),
+            Instruction::UnifyVar { register } =>
+                write!(f, "UNIFY_VAR R{}", register),
+            Instruction::UnifyValue { register } =>
+                write!(f, "UNIFY_VALUE R{}", register),
+            Instruction::UnifyConst { value } =>
+                write!(f, "UNIFY_CONST {}", value),
+            Instruction::BuildLambda { target, param, body } =>
+                write!(f, "BUILD_LAMBDA R{}, R{}, R{}", target, param, body),
+            Instruction::Apply { target, function, argument } =>
+                write!(f, "APPLY     R{}, R{}, R{}", target, function, argument),
+            Instruction::Normalize { register, strategy } =>
+                write!(f, "NORMALIZE R{}, {}", register, strategy),
+            Instruction::PutBigInt { register, value } =>
+                write!(f, "PUT_BIGINT R{}, {}", register, value),
+            Instruction::GetBigInt { register, value } =>
+                write!(f, "GET_BIGINT R{}, {}", register, value),
+            Instruction::PutFloat { register, value } =>
+                write!(f, "PUT_FLOAT R{}, {}", register, format_float(*value)),
+            Instruction::GetFloat { register, value } =>
+                write!(f, "GET_FLOAT R{}, {}", register, format_float(*value)),
+            Instruction::GetLevel { register } =>
+                write!(f, "GET_LEVEL R{}", register),
+            Instruction::CutTo { register } =>
+                write!(f, "CUT_TO    R{}", register),
+            Instruction::SoftCut { register } =>
+                write!(f, "SOFT_CUT  R{}", register),
+            Instruction::Jump { address } =>
+                write!(f, "JUMP      {}", address),
         }
     }
 }
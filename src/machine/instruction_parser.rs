@@ -1,437 +1,695 @@
-use crate::machine::instruction::Instruction;
-use crate::machine::arithmetic::parse_expression;
+// src/machine/instruction_parser.rs
+//! Parser for the textual `.lam` format: one instruction per line, a mnemonic
+//! followed by comma-separated parameters.
+//!
+//! Code can be addressed symbolically. A line may start with a label (`loop:`),
+//! which names the address of the next instruction, and CHOICE, JUMP, ASSERT_CLAUSE
+//! and RETRACT_CLAUSE accept a label wherever they take an address. Labels may be used
+//! before they are defined.
+//!
+//! Directives fill the tables of the program. Each one applies to the instruction
+//! that follows it:
+//! - `.pred name/arity` registers the instruction as the start of a clause of
+//!   `name/arity` in the predicate table,
+//! - `.index name/arity key, ...` registers it in the index table under the given
+//!   keys (integers, quoted strings or bare atoms).
+//!
+//! The machine looks predicates up by their indicator `name/arity`, as written
+//! in CALL instructions. A predicate written without an arity (`.pred name`) is
+//! looked up by its name alone.
+//!
+//! Strings, predicate names and functors may be written in double quotes, with
+//! the escapes `\\`, `\"`, `\n`, `\r` and `\t`. Quoted text may contain commas,
+//! spaces and colons. The `Display` implementations of `Instruction` and
+//! `Program` write this format, so printed programs can be read back.
+//!
+//! Errors are reported as `InstructionParseError` values, which say what went
+//! wrong (unknown mnemonic, wrong number of parameters, bad register, literal or
+//! expression) and carry the span of the offending text.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use num_bigint::BigInt;
+use crate::diagnostic::Span;
+use crate::machine::instruction::{CallTarget, Instruction};
+use crate::machine::integer::Integer;
+use crate::machine::number::Float;
+use crate::machine::arithmetic::{parse_expression, Expression};
+use crate::machine::program::Program;
 use crate::machine::term::Term;
 
+/// An error found while parsing LAM instructions. Every variant carries the
+/// span of the offending text: the mnemonic, the parameter that could not be
+/// parsed, or the whole line when the number of parameters is wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstructionParseError {
+    /// The line does not start with a known instruction mnemonic.
+    UnknownMnemonic { mnemonic: String, span: Span },
+    /// The instruction was given the wrong number of parameters. When
+    /// `at_least` is set, `expected` is the minimum number of parameters.
+    WrongArity { mnemonic: String, expected: usize, at_least: bool, found: usize, span: Span },
+    /// A parameter is empty, for example because of a trailing comma.
+    EmptyParameter { span: Span },
+    /// A register parameter is not of the form `Rn`.
+    BadRegister { token: String, span: Span },
+    /// A number or string parameter could not be parsed. `expected` names the
+    /// parameter, e.g. "integer", "index" or "quoted string".
+    BadLiteral { mnemonic: String, expected: &'static str, token: String, span: Span },
+    /// The arithmetic expression of an instruction could not be parsed.
+    BadExpression { mnemonic: String, reason: String, span: Span },
+    /// An address refers to a label that is never defined.
+    UnknownLabel { label: String, span: Span },
+    /// A label is defined more than once.
+    DuplicateLabel { label: String, span: Span },
+    /// A directive is unknown, malformed or not followed by an instruction.
+    BadDirective { reason: String, span: Span },
+}
+
+impl InstructionParseError {
+    /// Returns the location of the error in the input.
+    pub fn span(&self) -> Span {
+        match self {
+            InstructionParseError::UnknownMnemonic { span, .. }
+            | InstructionParseError::WrongArity { span, .. }
+            | InstructionParseError::EmptyParameter { span }
+            | InstructionParseError::BadRegister { span, .. }
+            | InstructionParseError::BadLiteral { span, .. }
+            | InstructionParseError::BadExpression { span, .. }
+            | InstructionParseError::UnknownLabel { span, .. }
+            | InstructionParseError::DuplicateLabel { span, .. }
+            | InstructionParseError::BadDirective { span, .. } => *span,
+        }
+    }
+
+    /// Returns the line of the error (starting at 1).
+    pub fn line(&self) -> usize {
+        self.span().line
+    }
+
+    /// Returns the column of the error (starting at 1).
+    pub fn column(&self) -> usize {
+        self.span().column
+    }
+
+    /// Returns the description of the error, without its position.
+    pub fn message(&self) -> String {
+        match self {
+            InstructionParseError::UnknownMnemonic { mnemonic, .. } => {
+                format!("unknown instruction mnemonic '{}'", mnemonic)
+            },
+            InstructionParseError::WrongArity { mnemonic, expected, at_least, found, .. } => {
+                let count = match expected {
+                    0 => "no parameters".to_string(),
+                    1 => "1 parameter".to_string(),
+                    n => format!("{} parameters", n),
+                };
+                let bound = if *at_least { "at least " } else { "" };
+                format!("{} expects {}{}, got {}", mnemonic, bound, count, found)
+            },
+            InstructionParseError::EmptyParameter { .. } => {
+                "Empty token detected: possible trailing comma or missing parameter".to_string()
+            },
+            InstructionParseError::BadRegister { token, .. } => {
+                format!("Register token '{}' must start with 'R' or 'r' followed by a register number", token)
+            },
+            InstructionParseError::BadLiteral { mnemonic, expected, token, .. } => {
+                format!("failed to parse {} in {}: '{}'", expected, mnemonic, token)
+            },
+            InstructionParseError::BadExpression { mnemonic, reason, .. } => {
+                format!("failed to parse expression in {}: {}", mnemonic, reason)
+            },
+            InstructionParseError::UnknownLabel { label, .. } => format!("undefined label '{}'", label),
+            InstructionParseError::DuplicateLabel { label, .. } => format!("label '{}' is already defined", label),
+            InstructionParseError::BadDirective { reason, .. } => format!("invalid directive: {}", reason),
+        }
+    }
+}
+
+impl fmt::Display for InstructionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line(), self.column(), self.message())
+    }
+}
+
+impl std::error::Error for InstructionParseError {}
+
 /// Parse an input string (from a file) containing one LAM instruction per line.
 /// Lines starting with ';' or '#' are treated as comments and ignored.
-/// Returns a Vec of Instructions or an error string.
-pub fn parse_instructions(input: &str) -> Result<Vec<Instruction>, String> {
-    let mut instructions = Vec::new();
-    for (line_num, line) in input.lines().enumerate() {
-        let line = line.trim();
-        // Skip empty lines or comment lines.
-        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+/// Labels are resolved; directives are checked but their tables are dropped
+/// (use `parse_program` to keep them).
+/// Returns a Vec of Instructions or the first error found.
+pub fn parse_instructions(input: &str) -> Result<Vec<Instruction>, InstructionParseError> {
+    parse_program(input).map(|program| program.instructions)
+}
+
+/// Parse a `.lam` program: its instructions together with the predicate and index
+/// tables described by its directives.
+pub fn parse_program(input: &str) -> Result<Program, InstructionParseError> {
+    let lines = source_lines(input);
+    let labels = collect_labels(input, &lines)?;
+    let mut program = Program::default();
+    // The last directive seen since the previous instruction.
+    let mut pending_directive = None;
+    for (_, line) in lines {
+        if line.is_empty() {
             continue;
         }
-        // Split the line into tokens by commas.
-        let tokens = split_tokens(line).map_err(|e| format!("Line {}: {}", line_num + 1, e))?;
-        if tokens.is_empty() {
+        let address = program.instructions.len();
+        if line.starts_with('.') {
+            parse_directive(input, line, address, &mut program)?;
+            pending_directive = Some(line);
             continue;
         }
-        // The first token may contain the mnemonic and some parameters.
-        let mut parts = tokens[0].split_whitespace();
-        let mnemonic = parts
-            .next()
-            .ok_or_else(|| format!("Line {}: missing mnemonic", line_num + 1))?
-            .to_uppercase();
-        // Collect any extra parts from the first token along with remaining tokens.
-        let mut params: Vec<&str> = parts.collect();
-        params.extend_from_slice(&tokens[1..]);
-
-        // Match on the mnemonic and parse parameters accordingly.
-        let instr = match mnemonic.as_str() {
-            "PUT_CONST" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: PUT_CONST expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let register = parse_register(params[0])?;
-                let value = params[1]
-                    .parse::<i32>()
-                    .map_err(|e| format!("Line {}: failed to parse integer in PUT_CONST: {}", line_num + 1, e))?;
-                Instruction::PutConst { register, value }
-            }
-            "PUT_VAR" => {
-                if params.len() != 3 {
-                    return Err(format!(
-                        "Line {}: PUT_VAR expects 3 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let register = parse_register(params[0])?;
-                let var_id = params[1]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse variable id in PUT_VAR: {}", line_num + 1, e))?;
-                let name = parse_string(params[2])?;
-                Instruction::PutVar { register, var_id, name }
-            }
-            "GET_CONST" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: GET_CONST expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let register = parse_register(params[0])?;
-                let value = params[1]
-                    .parse::<i32>()
-                    .map_err(|e| format!("Line {}: failed to parse integer in GET_CONST: {}", line_num + 1, e))?;
-                Instruction::GetConst { register, value }
-            }
-            "GET_VAR" => {
-                if params.len() != 3 {
-                    return Err(format!(
-                        "Line {}: GET_VAR expects 3 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let register = parse_register(params[0])?;
-                let var_id = params[1]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse variable id in GET_VAR: {}", line_num + 1, e))?;
-                let name = parse_string(params[2])?;
-                Instruction::GetVar { register, var_id, name }
-            }
-            "CALL" => {
-                if params.len() != 1 {
-                    return Err(format!(
-                        "Line {}: CALL expects 1 parameter, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let predicate = parse_string_or_ident(params[0]);
-                Instruction::Call { predicate }
-            }
-            "PROCEED" => {
-                if !params.is_empty() {
-                    return Err(format!(
-                        "Line {}: PROCEED expects no parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                Instruction::Proceed
-            }
-            "CHOICE" => {
-                if params.len() != 1 {
-                    return Err(format!(
-                        "Line {}: CHOICE expects 1 parameter, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let alternative = params[0]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse alternative in CHOICE: {}", line_num + 1, e))?;
-                Instruction::Choice { alternative }
-            }
-            "ALLOCATE" => {
-                if params.len() != 1 {
-                    return Err(format!(
-                        "Line {}: ALLOCATE expects 1 parameter, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let n = params[0]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse integer in ALLOCATE: {}", line_num + 1, e))?;
-                Instruction::Allocate { n }
-            }
-            "DEALLOCATE" => {
-                if !params.is_empty() {
-                    return Err(format!(
-                        "Line {}: DEALLOCATE expects no parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                Instruction::Deallocate
-            }
-            "ARITHMETIC_IS" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: ARITHMETIC_IS expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let target = parse_register(params[0])?;
-                let expr = parse_expression(params[1])
-                    .map_err(|e| format!("Line {}: failed to parse expression in ARITHMETIC_IS: {}", line_num + 1, e))?;
-                Instruction::ArithmeticIs { target, expression: expr }
-            }
-            "SET_LOCAL" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: SET_LOCAL expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let index = params[0]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse index in SET_LOCAL: {}", line_num + 1, e))?;
-                let value = parse_term_literal(params[1])?;
-                Instruction::SetLocal { index, value }
-            }
-            "GET_LOCAL" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: GET_LOCAL expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let index = params[0]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse index in GET_LOCAL: {}", line_num + 1, e))?;
-                let register = parse_register(params[1])?;
-                Instruction::GetLocal { index, register }
-            }
-            "FAIL" => {
-                if !params.is_empty() {
-                    return Err(format!(
-                        "Line {}: FAIL expects no parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                Instruction::Fail
-            }
-            "GET_STRUCTURE" => {
-                if params.len() != 3 {
-                    return Err(format!(
-                        "Line {}: GET_STRUCTURE expects 3 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let register = parse_register(params[0])?;
-                let functor = parse_string_or_ident(params[1]);
-                let arity = params[2]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse arity in GET_STRUCTURE: {}", line_num + 1, e))?;
-                Instruction::GetStructure { register, functor, arity }
-            }
-            "INDEXED_CALL" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: INDEXED_CALL expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let predicate = parse_string_or_ident(params[0]);
-                let index_register = parse_register(params[1])?;
-                Instruction::IndexedCall { predicate, index_register }
-            }
-            "MULTI_INDEXED_CALL" => {
-                if params.len() < 2 {
-                    return Err(format!(
-                        "Line {}: MULTI_INDEXED_CALL expects at least 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let predicate = parse_string_or_ident(params[0]);
-                let mut index_registers = Vec::new();
-                for token in &params[1..] {
-                    let reg = parse_register(token)?;
-                    index_registers.push(reg);
-                }
-                Instruction::MultiIndexedCall { predicate, index_registers }
-            }
-            "TAIL_CALL" => {
-                if params.len() != 1 {
-                    return Err(format!(
-                        "Line {}: TAIL_CALL expects 1 parameter, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let predicate = parse_string_or_ident(params[0]);
-                Instruction::TailCall { predicate }
-            }
-            "ASSERT_CLAUSE" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: ASSERT_CLAUSE expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let predicate = parse_string_or_ident(params[0]);
-                let address = params[1]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse address in ASSERT_CLAUSE: {}", line_num + 1, e))?;
-                Instruction::AssertClause { predicate, address }
-            }
-            "RETRACT_CLAUSE" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: RETRACT_CLAUSE expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let predicate = parse_string_or_ident(params[0]);
-                let address = params[1]
-                    .parse::<usize>()
-                    .map_err(|e| format!("Line {}: failed to parse address in RETRACT_CLAUSE: {}", line_num + 1, e))?;
-                Instruction::RetractClause { predicate, address }
+        program.instructions.push(parse_instruction(input, line, &labels)?);
+        pending_directive = None;
+    }
+    if let Some(directive) = pending_directive {
+        return Err(InstructionParseError::BadDirective {
+            reason: "a directive must be followed by an instruction".to_string(),
+            span: Span::of_slice(input, directive),
+        });
+    }
+    Ok(program)
+}
+
+/// Returns the lines of the input that are not blank or comments, each split
+/// into its label (if any) and the rest of the line.
+fn source_lines(input: &str) -> Vec<(Option<&str>, &str)> {
+    input.lines()
+        .map(str::trim)
+        .filter(|line| !(line.is_empty() || line.starts_with(';') || line.starts_with('#')))
+        .map(|line| match line.split_once(':') {
+            Some((label, rest)) if is_identifier(label) => (Some(label), rest.trim()),
+            _ => (None, line),
+        })
+        .collect()
+}
+
+/// Returns true for names usable as labels: a letter or underscore followed by
+/// letters, digits and underscores.
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Computes the address of every label: the index of the next instruction.
+fn collect_labels(input: &str, lines: &[(Option<&str>, &str)]) -> Result<HashMap<String, usize>, InstructionParseError> {
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    for (label, line) in lines {
+        if let Some(label) = label {
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(InstructionParseError::DuplicateLabel {
+                    label: label.to_string(),
+                    span: Span::of_slice(input, label),
+                });
             }
-            "CUT" => {
-                if !params.is_empty() {
-                    return Err(format!(
-                        "Line {}: CUT expects no parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                Instruction::Cut
+        }
+        if !line.is_empty() && !line.starts_with('.') {
+            address += 1;
+        }
+    }
+    Ok(labels)
+}
+
+/// Applies a `.pred` or `.index` directive to the tables of `program`, for the
+/// clause starting at `address`.
+fn parse_directive(input: &str, line: &str, address: usize, program: &mut Program) -> Result<(), InstructionParseError> {
+    let bad = |reason: String, part: &str| InstructionParseError::BadDirective { reason, span: Span::of_slice(input, part) };
+    let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let indicator = split_outside_quotes(rest, char::is_whitespace).into_iter().find(|part| !part.is_empty()).unwrap_or(rest);
+    let keys = &rest[indicator.len()..];
+    let name = match unquote(indicator) {
+        Some(name) => name,
+        None => match indicator.rsplit_once('/') {
+            Some((name, arity)) if !name.is_empty() && arity.parse::<usize>().is_ok() => {
+                crate::machine::core::indicator(&parse_string_or_ident(name), arity.parse().unwrap())
+            },
+            None if !indicator.is_empty() => indicator.to_string(),
+            _ => return Err(bad(format!("expected a predicate name or name/arity, got '{}'", indicator), line)),
+        },
+    };
+    match directive {
+        ".pred" => {
+            if !keys.trim().is_empty() {
+                return Err(bad(format!("unexpected text after {}", indicator), keys));
             }
-            "BUILD_COMPOUND" => {
-                if params.len() < 3 {
-                    return Err(format!(
-                        "Line {}: BUILD_COMPOUND expects at least 3 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let target = parse_register(params[0])?;
-                let functor = parse_string_or_ident(params[1]);
-                let mut arg_registers = Vec::new();
-                for token in &params[2..] {
-                    let reg = parse_register(token)?;
-                    arg_registers.push(reg);
-                }
-                Instruction::BuildCompound { target, functor, arg_registers }
+            program.predicate_table.entry(name).or_default().push(address);
+        },
+        ".index" => {
+            if keys.trim().is_empty() {
+                return Err(bad(format!("expected index keys after {}", indicator), line));
             }
-            "PUT_STR" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: PUT_STR expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let register = parse_register(params[0])?;
-                let value = parse_string(params[1])?;
-                Instruction::PutStr { register, value }
+            let tokens = split_tokens(input, keys.trim())?;
+            let key = tokens.iter()
+                .map(|token| parse_key(token).ok_or_else(|| bad(format!("invalid index key '{}'", token), token)))
+                .collect::<Result<Vec<Term>, _>>()?;
+            program.index_table.entry(name).or_default().entry(key).or_default().push(address);
+        },
+        _ => return Err(bad(format!("unknown directive '{}'", directive), directive)),
+    }
+    Ok(())
+}
+
+/// Parse an index key: an integer, a quoted string or a bare atom.
+fn parse_key(token: &str) -> Option<Term> {
+    if let Ok(n) = token.parse::<BigInt>() {
+        Some(Integer::from(n).into_term())
+    } else if let Some(x) = float_literal(token) {
+        Some(Term::Float(Float(x)))
+    } else if let Some(s) = unquote(token) {
+        Some(Term::Str(s))
+    } else if is_identifier(token) {
+        Some(Term::Str(token.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Reads a float literal: a finite number with a decimal point, such as `1.5`.
+fn float_literal(token: &str) -> Option<f64> {
+    token.contains('.').then(|| token.parse::<f64>().ok()).flatten().filter(|x| x.is_finite())
+}
+
+/// Parse a single instruction (without its label).
+fn parse_instruction(input: &str, line: &str, labels: &HashMap<String, usize>) -> Result<Instruction, InstructionParseError> {
+    // Split the line into tokens by commas.
+    let tokens = split_tokens(input, line)?;
+    // The first token contains the mnemonic and possibly the first parameter.
+    let mut parts = split_outside_quotes(tokens[0], char::is_whitespace).into_iter().filter(|part| !part.is_empty());
+    let mnemonic_token = parts.next().unwrap_or(tokens[0]);
+    let ctx = LineContext { input, line, labels, mnemonic: mnemonic_token.to_uppercase() };
+    // Collect any extra parts from the first token along with remaining tokens.
+    let mut params: Vec<&str> = parts.collect();
+    params.extend_from_slice(&tokens[1..]);
+    let p = params.as_slice();
+
+    // Match on the mnemonic and parse parameters accordingly.
+    let instr = match ctx.mnemonic.as_str() {
+        "PUT_CONST" => {
+            ctx.arity(p, 2)?;
+            Instruction::PutConst { register: ctx.register(p[0])?, value: ctx.number(p[1], "integer")? }
+        }
+        "PUT_VAR" => {
+            ctx.arity(p, 3)?;
+            Instruction::PutVar {
+                register: ctx.register(p[0])?,
+                var_id: ctx.number(p[1], "variable id")?,
+                name: ctx.string(p[2])?,
             }
-            "GET_STR" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: GET_STR expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let register = parse_register(params[0])?;
-                let value = parse_string(params[1])?;
-                Instruction::GetStr { register, value }
+        }
+        "GET_CONST" => {
+            ctx.arity(p, 2)?;
+            Instruction::GetConst { register: ctx.register(p[0])?, value: ctx.number(p[1], "integer")? }
+        }
+        "GET_VAR" => {
+            ctx.arity(p, 3)?;
+            Instruction::GetVar {
+                register: ctx.register(p[0])?,
+                var_id: ctx.number(p[1], "variable id")?,
+                name: ctx.string(p[2])?,
             }
-            "MOVE" => {
-                if params.len() != 2 {
-                    return Err(format!(
-                        "Line {}: MOVE expects 2 parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                let src = parse_register(params[0])?;
-                let dst = parse_register(params[1])?;
-                Instruction::Move { src, dst }
+        }
+        "CALL" => {
+            ctx.arity(p, 1)?;
+            Instruction::Call { predicate: parse_string_or_ident(p[0]), target: CallTarget::Unresolved }
+        }
+        "PROCEED" => {
+            ctx.arity(p, 0)?;
+            Instruction::Proceed
+        }
+        "CHOICE" => {
+            ctx.arity(p, 1)?;
+            Instruction::Choice { alternative: ctx.address(p[0], "alternative")? }
+        }
+        "ALLOCATE" => {
+            ctx.arity(p, 1)?;
+            Instruction::Allocate { n: ctx.number(p[0], "integer")? }
+        }
+        "DEALLOCATE" => {
+            ctx.arity(p, 0)?;
+            Instruction::Deallocate
+        }
+        "ARITHMETIC_IS" => {
+            ctx.arity(p, 2)?;
+            Instruction::ArithmeticIs { target: ctx.register(p[0])?, expression: ctx.expression(p[1])? }
+        }
+        "SET_LOCAL" => {
+            ctx.arity(p, 2)?;
+            Instruction::SetLocal { index: ctx.number(p[0], "index")?, value: ctx.term_literal(p[1])? }
+        }
+        "GET_LOCAL" => {
+            ctx.arity(p, 2)?;
+            Instruction::GetLocal { index: ctx.number(p[0], "index")?, register: ctx.register(p[1])? }
+        }
+        "FAIL" => {
+            ctx.arity(p, 0)?;
+            Instruction::Fail
+        }
+        "GET_STRUCTURE" => {
+            ctx.arity(p, 3)?;
+            Instruction::GetStructure {
+                register: ctx.register(p[0])?,
+                functor: parse_string_or_ident(p[1]),
+                arity: ctx.number(p[2], "arity")?,
             }
-            "HALT" => {
-                if !params.is_empty() {
-                    return Err(format!(
-                        "Line {}: HALT expects no parameters, got {}",
-                        line_num + 1,
-                        params.len()
-                    ));
-                }
-                Instruction::Halt
+        }
+        "INDEXED_CALL" => {
+            ctx.arity(p, 2)?;
+            Instruction::IndexedCall { predicate: parse_string_or_ident(p[0]), index_register: ctx.register(p[1])? }
+        }
+        "MULTI_INDEXED_CALL" => {
+            ctx.arity_at_least(p, 2)?;
+            Instruction::MultiIndexedCall {
+                predicate: parse_string_or_ident(p[0]),
+                index_registers: ctx.registers(&p[1..])?,
             }
-            _ => {
-                return Err(format!(
-                    "Line {}: unknown instruction mnemonic '{}'",
-                    line_num + 1,
-                    mnemonic
-                ))
+        }
+        "TAIL_CALL" => {
+            ctx.arity(p, 1)?;
+            Instruction::TailCall { predicate: parse_string_or_ident(p[0]), target: CallTarget::Unresolved }
+        }
+        "ASSERT_CLAUSE" => {
+            ctx.arity(p, 2)?;
+            Instruction::AssertClause { predicate: parse_string_or_ident(p[0]), address: ctx.address(p[1], "address")? }
+        }
+        "RETRACT_CLAUSE" => {
+            ctx.arity(p, 2)?;
+            Instruction::RetractClause { predicate: parse_string_or_ident(p[0]), address: ctx.address(p[1], "address")? }
+        }
+        "CUT" => {
+            ctx.arity(p, 0)?;
+            Instruction::Cut
+        }
+        "BUILD_COMPOUND" => {
+            ctx.arity_at_least(p, 3)?;
+            Instruction::BuildCompound {
+                target: ctx.register(p[0])?,
+                functor: parse_string_or_ident(p[1]),
+                arg_registers: ctx.registers(&p[2..])?,
             }
-        };
-        instructions.push(instr);
-    }
-    Ok(instructions)
+        }
+        "PUT_STR" => {
+            ctx.arity(p, 2)?;
+            Instruction::PutStr { register: ctx.register(p[0])?, value: ctx.string(p[1])? }
+        }
+        "GET_STR" => {
+            ctx.arity(p, 2)?;
+            Instruction::GetStr { register: ctx.register(p[0])?, value: ctx.string(p[1])? }
+        }
+        "MOVE" => {
+            ctx.arity(p, 2)?;
+            Instruction::Move { src: ctx.register(p[0])?, dst: ctx.register(p[1])? }
+        }
+        "GET_VALUE" => {
+            ctx.arity(p, 2)?;
+            Instruction::GetValue { register: ctx.register(p[0])?, src: ctx.register(p[1])? }
+        }
+        "NEW_VAR" => {
+            ctx.arity(p, 2)?;
+            Instruction::NewVar { register: ctx.register(p[0])?, name: ctx.string(p[1])? }
+        }
+        "STORE_LOCAL" => {
+            ctx.arity(p, 2)?;
+            Instruction::StoreLocal { index: ctx.number(p[0], "index")?, register: ctx.register(p[1])? }
+        }
+        "PUT_LOCAL" => {
+            ctx.arity(p, 2)?;
+            Instruction::PutLocal { index: ctx.number(p[0], "index")?, register: ctx.register(p[1])? }
+        }
+        "HALT" => {
+            ctx.arity(p, 0)?;
+            Instruction::Halt
+        }
+        "UNIFY_VAR" => {
+            ctx.arity(p, 1)?;
+            Instruction::UnifyVar { register: ctx.register(p[0])? }
+        }
+        "UNIFY_VALUE" => {
+            ctx.arity(p, 1)?;
+            Instruction::UnifyValue { register: ctx.register(p[0])? }
+        }
+        "UNIFY_CONST" => {
+            ctx.arity(p, 1)?;
+            Instruction::UnifyConst { value: ctx.number(p[0], "integer")? }
+        }
+        "BUILD_LAMBDA" => {
+            ctx.arity(p, 3)?;
+            Instruction::BuildLambda { target: ctx.register(p[0])?, param: ctx.register(p[1])?, body: ctx.register(p[2])? }
+        }
+        "APPLY" => {
+            ctx.arity(p, 3)?;
+            Instruction::Apply { target: ctx.register(p[0])?, function: ctx.register(p[1])?, argument: ctx.register(p[2])? }
+        }
+        "NORMALIZE" => {
+            ctx.arity(p, 2)?;
+            Instruction::Normalize { register: ctx.register(p[0])?, strategy: ctx.number(p[1], "reduction strategy")? }
+        }
+        "PUT_BIGINT" => {
+            ctx.arity(p, 2)?;
+            Instruction::PutBigInt { register: ctx.register(p[0])?, value: ctx.number(p[1], "integer")? }
+        }
+        "GET_BIGINT" => {
+            ctx.arity(p, 2)?;
+            Instruction::GetBigInt { register: ctx.register(p[0])?, value: ctx.number(p[1], "integer")? }
+        }
+        "PUT_FLOAT" => {
+            ctx.arity(p, 2)?;
+            Instruction::PutFloat { register: ctx.register(p[0])?, value: ctx.float(p[1])? }
+        }
+        "GET_FLOAT" => {
+            ctx.arity(p, 2)?;
+            Instruction::GetFloat { register: ctx.register(p[0])?, value: ctx.float(p[1])? }
+        }
+        "GET_LEVEL" => {
+            ctx.arity(p, 1)?;
+            Instruction::GetLevel { register: ctx.register(p[0])? }
+        }
+        "CUT_TO" => {
+            ctx.arity(p, 1)?;
+            Instruction::CutTo { register: ctx.register(p[0])? }
+        }
+        "SOFT_CUT" => {
+            ctx.arity(p, 1)?;
+            Instruction::SoftCut { register: ctx.register(p[0])? }
+        }
+        "JUMP" => {
+            ctx.arity(p, 1)?;
+            Instruction::Jump { address: ctx.address(p[0], "address")? }
+        }
+        _ => {
+            return Err(InstructionParseError::UnknownMnemonic {
+                mnemonic: ctx.mnemonic.clone(),
+                span: Span::of_slice(input, mnemonic_token),
+            })
+        }
+    };
+    Ok(instr)
 }
 
 /// Splits a line by commas and trims whitespace.
 /// If any token (after splitting) is empty (e.g. due to a trailing comma), returns an error.
-fn split_tokens(line: &str) -> Result<Vec<&str>, String> {
-    let tokens: Vec<&str> = line.split(',').map(|token| token.trim()).collect();
-    // If the trimmed line is not empty and any token is empty, report an error.
-    if !line.trim().is_empty() && tokens.iter().any(|token| token.is_empty()) {
-        return Err("Empty token detected: possible trailing comma or missing parameter".to_string());
+fn split_tokens<'a>(input: &str, line: &'a str) -> Result<Vec<&'a str>, InstructionParseError> {
+    let tokens: Vec<&str> = split_outside_quotes(line, |c| c == ',').into_iter().map(|token| token.trim()).collect();
+    if let Some(empty) = tokens.iter().find(|token| token.is_empty()) {
+        return Err(InstructionParseError::EmptyParameter { span: Span::of_slice(input, empty) });
     }
     Ok(tokens)
 }
 
-/// Parse a register token. For example, "R0" or "r1" returns 0 or 1.
-/// Now, if the token is too short or does not start with 'R' or 'r', it produces an error message containing
-/// the expected phrase.
-fn parse_register(token: &str) -> Result<usize, String> {
-    let token = token.trim();
-    if token.len() < 2 || !(token.starts_with('R') || token.starts_with('r')) {
-        return Err(format!("Register token '{}' must start with 'R' or 'r'", token));
-    }
-    let num_str: String = token.chars().skip(1).collect();
-    num_str
-        .parse::<usize>()
-        .map_err(|e| format!("Failed to parse register number in '{}': {}", token, e))
+/// The line being parsed. Parameters are slices of `input`, so errors can
+/// point at them.
+struct LineContext<'a> {
+    input: &'a str,
+    line: &'a str,
+    labels: &'a HashMap<String, usize>,
+    mnemonic: String,
 }
 
-/// Parse a string literal. If the token is enclosed in double quotes, remove them.
-fn parse_string(token: &str) -> Result<String, String> {
-    let token = token.trim();
-    if token.starts_with('"') && token.ends_with('"') && token.len() >= 2 {
-        Ok(token[1..token.len() - 1].to_string())
-    } else {
-        // Allow bare strings if they contain no spaces.
-        if token.contains(' ') {
-            Err(format!("Expected a quoted string if spaces are present: {}", token))
+impl LineContext<'_> {
+    /// Checks that exactly `expected` parameters were given.
+    fn arity(&self, params: &[&str], expected: usize) -> Result<(), InstructionParseError> {
+        if params.len() == expected {
+            Ok(())
+        } else {
+            Err(self.wrong_arity(params, expected, false))
+        }
+    }
+
+    /// Checks that at least `expected` parameters were given.
+    fn arity_at_least(&self, params: &[&str], expected: usize) -> Result<(), InstructionParseError> {
+        if params.len() >= expected {
+            Ok(())
+        } else {
+            Err(self.wrong_arity(params, expected, true))
+        }
+    }
+
+    fn wrong_arity(&self, params: &[&str], expected: usize, at_least: bool) -> InstructionParseError {
+        InstructionParseError::WrongArity {
+            mnemonic: self.mnemonic.clone(),
+            expected,
+            at_least,
+            found: params.len(),
+            span: Span::of_slice(self.input, self.line),
+        }
+    }
+
+    fn bad_literal(&self, token: &str, expected: &'static str) -> InstructionParseError {
+        InstructionParseError::BadLiteral {
+            mnemonic: self.mnemonic.clone(),
+            expected,
+            token: token.to_string(),
+            span: Span::of_slice(self.input, token),
+        }
+    }
+
+    /// Parse a register token. For example, "R0" or "r1" returns 0 or 1.
+    fn register(&self, token: &str) -> Result<usize, InstructionParseError> {
+        let token = token.trim();
+        token.strip_prefix(['R', 'r'])
+            .and_then(|number| number.parse::<usize>().ok())
+            .ok_or_else(|| InstructionParseError::BadRegister {
+                token: token.to_string(),
+                span: Span::of_slice(self.input, token),
+            })
+    }
+
+    /// Parse a list of register tokens.
+    fn registers(&self, tokens: &[&str]) -> Result<Vec<usize>, InstructionParseError> {
+        tokens.iter().map(|token| self.register(token)).collect()
+    }
+
+    /// Parse a numeric parameter; `expected` names it in the error.
+    fn number<T: FromStr>(&self, token: &str, expected: &'static str) -> Result<T, InstructionParseError> {
+        token.parse::<T>().map_err(|_| self.bad_literal(token, expected))
+    }
+
+    /// Parse a finite float.
+    fn float(&self, token: &str) -> Result<f64, InstructionParseError> {
+        token.parse::<f64>()
+            .ok()
+            .filter(|x| x.is_finite())
+            .ok_or_else(|| self.bad_literal(token, "float"))
+    }
+
+    /// Parse a code address: a number or a label.
+    fn address(&self, token: &str, expected: &'static str) -> Result<usize, InstructionParseError> {
+        if !is_identifier(token) {
+            return self.number(token, expected);
+        }
+        self.labels.get(token).copied().ok_or_else(|| InstructionParseError::UnknownLabel {
+            label: token.to_string(),
+            span: Span::of_slice(self.input, token),
+        })
+    }
+
+    /// Parse a string literal. If the token is enclosed in double quotes, remove them.
+    fn string(&self, token: &str) -> Result<String, InstructionParseError> {
+        let token = token.trim();
+        if let Some(s) = unquote(token) {
+            Ok(s)
+        } else if token.contains(' ') {
+            // Bare strings are only allowed if they contain no spaces.
+            Err(self.bad_literal(token, "quoted string"))
         } else {
             Ok(token.to_string())
         }
     }
+
+    /// Parse a term literal for instructions such as SET_LOCAL.
+    /// Supports integer constants, quoted string literals, or register references (which become variables).
+    fn term_literal(&self, token: &str) -> Result<Term, InstructionParseError> {
+        let token = token.trim();
+        if token.starts_with('R') || token.starts_with('r') {
+            Ok(Term::Var(self.register(token)?))
+        } else if let Some(s) = unquote(token) {
+            Ok(Term::Str(s))
+        } else if let Ok(n) = token.parse::<BigInt>() {
+            Ok(Integer::from(n).into_term())
+        } else if let Some(x) = float_literal(token) {
+            Ok(Term::Float(Float(x)))
+        } else if token.contains(' ') {
+            // If the token is unquoted and contains spaces, produce an error.
+            Err(self.bad_literal(token, "quoted string"))
+        } else {
+            // Otherwise, treat as a bare identifier string.
+            Ok(Term::Str(token.to_string()))
+        }
+    }
+
+    /// Parse an arithmetic expression parameter.
+    fn expression(&self, token: &str) -> Result<Expression, InstructionParseError> {
+        parse_expression(token).map_err(|reason| InstructionParseError::BadExpression {
+            mnemonic: self.mnemonic.clone(),
+            reason,
+            span: Span::of_slice(self.input, token),
+        })
+    }
 }
 
 /// Parse a token that can be either a quoted string literal or an identifier.
 fn parse_string_or_ident(token: &str) -> String {
     let token = token.trim();
-    if token.starts_with('"') && token.ends_with('"') && token.len() >= 2 {
-        token[1..token.len() - 1].to_string()
-    } else {
-        token.to_string()
+    unquote(token).unwrap_or_else(|| token.to_string())
+}
+
+/// Splits `text` at every character matching `is_separator` that is not inside
+/// a double-quoted string or parentheses, so `max(R1, 2)` stays one token.
+fn split_outside_quotes(text: &str, is_separator: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        if in_quotes {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_quotes = false,
+                _ => {},
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == '(' {
+            depth += 1;
+        } else if c == ')' {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && is_separator(c) {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
     }
+    parts.push(&text[start..]);
+    parts
 }
 
-/// Parse a term literal for instructions such as SET_LOCAL.
-/// Supports integer constants, quoted string literals, or register references (which become variables).
-fn parse_term_literal(token: &str) -> Result<Term, String> {
-    let token = token.trim();
-    if token.starts_with('R') || token.starts_with('r') {
-        let reg = parse_register(token)?;
-        Ok(Term::Var(reg))
-    } else if token.starts_with('"') && token.ends_with('"') && token.len() >= 2 {
-        Ok(Term::Str(token[1..token.len() - 1].to_string()))
-    } else if let Ok(n) = token.parse::<i32>() {
-        Ok(Term::Const(n))
-    } else {
-        // If the token is unquoted and contains spaces, produce an error.
-        if token.contains(' ') {
-            Err(format!("Expected a quoted string if spaces are present: {}", token))
-        } else {
-            // Otherwise, treat as a bare identifier string.
-            Ok(Term::Str(token.to_string()))
+/// Returns the contents of a double-quoted token with its escapes processed, or
+/// `None` if the token is not quoted. Unknown escapes are kept as written.
+fn unquote(token: &str) -> Option<String> {
+    let body = token.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some('t') => text.push('\t'),
+            Some(c @ ('"' | '\\')) => text.push(c),
+            Some(c) => {
+                text.push('\\');
+                text.push(c);
+            },
+            None => text.push('\\'),
         }
     }
+    Some(text)
 }
@@ -0,0 +1,134 @@
+// src/machine/integer.rs
+//! Integers of any size.
+//!
+//! Values that fit in an `i32` are kept inline, as in `Term::Const`; larger
+//! values are kept as a `BigInt`, as in `Term::BigInt`. Every constructor
+//! normalizes, so a value has exactly one representation and two integers are
+//! equal exactly when their representations are.
+
+use std::cmp::Ordering;
+use std::fmt;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use crate::machine::term::Term;
+
+/// An integer of any size.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Integer {
+    /// A value in the range of `i32`.
+    Small(i32),
+    /// A value outside the range of `i32`.
+    Big(BigInt),
+}
+
+impl Integer {
+    /// Returns the integer a term denotes, if it is an integer.
+    pub fn from_term(term: &Term) -> Option<Integer> {
+        match term {
+            Term::Const(n) => Some(Integer::Small(*n)),
+            Term::BigInt(n) => Some(Integer::from(n.clone())),
+            _ => None,
+        }
+    }
+
+    /// Returns the term denoting the integer: `Const` or `BigInt`.
+    pub fn into_term(self) -> Term {
+        match self {
+            Integer::Small(n) => Term::Const(n),
+            Integer::Big(n) => Term::BigInt(n),
+        }
+    }
+
+    /// Returns the value as a `BigInt`.
+    pub fn to_bigint(&self) -> BigInt {
+        match self {
+            Integer::Small(n) => BigInt::from(*n),
+            Integer::Big(n) => n.clone(),
+        }
+    }
+
+    /// Returns the value as an `i64`, if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Integer::Small(n) => Some(*n as i64),
+            Integer::Big(n) => n.to_i64(),
+        }
+    }
+
+    /// Returns the nearest float, which is infinite if the value is too large.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Integer::Small(n) => *n as f64,
+            Integer::Big(n) => n.to_f64().unwrap_or(if n.sign() == Sign::Minus { f64::NEG_INFINITY } else { f64::INFINITY }),
+        }
+    }
+
+    /// Returns -1, 0 or 1 according to the sign of the value.
+    pub fn signum(&self) -> i32 {
+        match self {
+            Integer::Small(n) => n.signum(),
+            Integer::Big(n) => match n.sign() {
+                Sign::Minus => -1,
+                Sign::NoSign => 0,
+                Sign::Plus => 1,
+            },
+        }
+    }
+}
+
+impl From<i32> for Integer {
+    fn from(n: i32) -> Self {
+        Integer::Small(n)
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(n: i64) -> Self {
+        match i32::try_from(n) {
+            Ok(n) => Integer::Small(n),
+            Err(_) => Integer::Big(BigInt::from(n)),
+        }
+    }
+}
+
+impl From<BigInt> for Integer {
+    fn from(n: BigInt) -> Self {
+        match n.to_i32() {
+            Some(n) => Integer::Small(n),
+            None => Integer::Big(n),
+        }
+    }
+}
+
+impl PartialEq<i32> for Integer {
+    fn eq(&self, other: &i32) -> bool {
+        *self == Integer::Small(*other)
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => a.cmp(b),
+            (Integer::Big(a), Integer::Big(b)) => a.cmp(b),
+            // A big value lies beyond every small one, on the side of its sign.
+            (Integer::Big(a), Integer::Small(_)) => if a.sign() == Sign::Minus { Ordering::Less } else { Ordering::Greater },
+            (Integer::Small(_), Integer::Big(b)) => if b.sign() == Sign::Minus { Ordering::Greater } else { Ordering::Less },
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integer::Small(n) => write!(f, "{}", n),
+            Integer::Big(n) => write!(f, "{}", n),
+        }
+    }
+}
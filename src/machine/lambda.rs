@@ -1,18 +1,23 @@
 // src/machine/lambda.rs
-//! Lambda calculus utilities: free variable collection, substitution, and beta reduction.
+//! Lambda calculus utilities: free variable collection, substitution, beta and
+//! eta reduction, and normalization with a choice of reduction strategy.
 
 use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use crate::machine::debruijn::{self, DbTerm};
+use crate::machine::error_handling::MachineError;
 use crate::machine::term::Term;
 
 /// Returns the set of free variable IDs in the term.
-fn free_vars(term: &Term) -> HashSet<usize> {
+pub fn free_vars(term: &Term) -> HashSet<usize> {
     match term {
         Term::Var(v) => {
             let mut set = HashSet::new();
             set.insert(*v);
             set
         },
-        Term::Const(_) | Term::Str(_) => HashSet::new(),
+        Term::Const(_) | Term::BigInt(_) | Term::Float(_) | Term::Str(_) => HashSet::new(),
         Term::Compound(_, args) => {
             args.iter().fold(HashSet::new(), |mut acc, t| {
                 acc.extend(free_vars(t));
@@ -42,14 +47,9 @@ fn free_vars(term: &Term) -> HashSet<usize> {
     }
 }
 
-/// Generates a fresh variable ID not present in either term.
+/// Generates a variable ID above every free variable of either term.
 fn generate_fresh_var(term: &Term, replacement: &Term) -> usize {
-    let union: HashSet<usize> = free_vars(term).union(&free_vars(replacement)).cloned().collect();
-    let mut fresh = 0;
-    while union.contains(&fresh) {
-        fresh += 1;
-    }
-    fresh
+    free_vars(term).into_iter().chain(free_vars(replacement)).max().map_or(0, |max| max + 1)
 }
 
 /// Performs capture–avoiding substitution of variable `var` with `replacement` in `term`.
@@ -58,7 +58,7 @@ pub fn substitute(term: &Term, var: usize, replacement: &Term) -> Term {
         Term::Var(v) => {
             if *v == var { replacement.clone() } else { term.clone() }
         },
-        Term::Const(_) | Term::Str(_) => term.clone(),
+        Term::Const(_) | Term::BigInt(_) | Term::Float(_) | Term::Str(_) => term.clone(),
         Term::Compound(f, args) => {
             Term::Compound(f.clone(), args.iter().map(|t| substitute(t, var, replacement)).collect())
         },
@@ -110,3 +110,68 @@ pub fn beta_reduce(term: &Term) -> Term {
         _ => term.clone(),
     }
 }
+
+/// The order in which `normalize` contracts redexes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Leftmost-outermost redex first. Reaches the normal form whenever one exists.
+    NormalOrder,
+    /// Leftmost-innermost redex first: arguments are normalized before they are
+    /// substituted. May diverge on terms that have a normal form.
+    ApplicativeOrder,
+    /// Only redexes in head position, giving a head normal form
+    /// `\x1. ... \xn. h M1 ... Mm` whose arguments are left unreduced.
+    HeadNormalForm,
+}
+
+impl Strategy {
+    /// Returns the name of the strategy, as written in `.lam` files and passed to
+    /// the `normalize_with/3` built-in.
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::NormalOrder => "normal",
+            Strategy::ApplicativeOrder => "applicative",
+            Strategy::HeadNormalForm => "head",
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Strategy::NormalOrder),
+            "applicative" => Ok(Strategy::ApplicativeOrder),
+            "head" => Ok(Strategy::HeadNormalForm),
+            _ => Err(format!("Unknown reduction strategy: {}", s)),
+        }
+    }
+}
+
+/// Performs one step of eta reduction at the root of the term: `\x. M x`
+/// becomes `M` when x does not occur free in M.
+pub fn eta_reduce(term: &Term) -> Option<Term> {
+    if let Term::Lambda(param, body) = term {
+        if let Term::App(function, argument) = &**body {
+            if **argument == Term::Var(*param) && !free_vars(function).contains(param) {
+                return Some((**function).clone());
+            }
+        }
+    }
+    None
+}
+
+/// Normalizes a term with `debruijn::normalize`. No parameter is renamed
+/// during reduction; parameters keep their names in the result unless that
+/// would capture a variable.
+pub fn normalize(term: &Term, strategy: Strategy, max_steps: usize, eta: bool) -> Result<Term, MachineError> {
+    let normal = debruijn::normalize(&DbTerm::from_term(term), strategy, max_steps, eta)?;
+    Ok(normal.to_term())
+}
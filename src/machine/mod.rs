@@ -4,13 +4,24 @@
 //! This module re-exports all the submodules that make up the LAM machine.
 
 pub mod arithmetic;
+pub mod atom;
+pub mod bytecode;
+pub mod call;
 pub mod choice_point;
 pub mod core;
+pub mod debruijn;
 pub mod error_handling;
 pub mod execution;
 pub mod frame;
+pub mod heap;
+pub mod integer;
 pub mod instruction_parser;
 pub mod instruction;
 pub mod lambda;
+pub mod number;
+pub mod ops;
+pub mod pattern;
+pub mod program;
+pub mod stack;
 pub mod term;
 pub mod unification;
\ No newline at end of file
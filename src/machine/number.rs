@@ -0,0 +1,132 @@
+// src/machine/number.rs
+//! Numbers: integers of any size and floating-point numbers.
+//!
+//! `Number` is the value of an arithmetic expression. `Float` is the form a
+//! floating-point number takes inside a `Term`: it compares and hashes by its
+//! bits, so terms can stay `Eq` and `Hash`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+use crate::machine::integer::Integer;
+use crate::machine::term::Term;
+
+/// A floating-point number as a term. Two floats are equal when they have the
+/// same bits, so `0.0` and `-0.0` are different terms.
+#[derive(Debug, Clone, Copy)]
+pub struct Float(pub f64);
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Float {}
+
+impl Hash for Float {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl fmt::Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_float(self.0))
+    }
+}
+
+/// Writes a float so that it reads back as a float, in Prolog and in `.lam`
+/// files: always with a fractional part, as in `2.0` and `1.0e20`.
+pub fn format_float(x: f64) -> String {
+    let text = format!("{:?}", x);
+    match text.find('e') {
+        Some(e) if !text[..e].contains('.') => format!("{}.0{}", &text[..e], &text[e..]),
+        _ => text,
+    }
+}
+
+/// The value of an arithmetic expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    /// An integer.
+    Int(Integer),
+    /// A floating-point number.
+    Float(f64),
+}
+
+impl Number {
+    /// Returns the number a term denotes, if it is a number.
+    pub fn from_term(term: &Term) -> Option<Number> {
+        match term {
+            Term::Float(x) => Some(Number::Float(x.0)),
+            other => Integer::from_term(other).map(Number::Int),
+        }
+    }
+
+    /// Returns the term denoting the number.
+    pub fn into_term(self) -> Term {
+        match self {
+            Number::Int(n) => n.into_term(),
+            Number::Float(x) => Term::Float(Float(x)),
+        }
+    }
+
+    /// Compares two numbers by value. An integer and a float are compared
+    /// exactly, without rounding the integer. The infinities order past every
+    /// integer, and NaN, which only a `.lamb` file can hold, after every other
+    /// number, so that the order stays total.
+    pub fn compare(&self, other: &Number) -> Ordering {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a.cmp(b),
+            (Number::Float(x), Number::Float(y)) => x.partial_cmp(y).unwrap_or_else(|| x.is_nan().cmp(&y.is_nan())),
+            (Number::Int(a), Number::Float(y)) => compare_integer_float(a, *y),
+            (Number::Float(x), Number::Int(b)) => compare_integer_float(b, *x).reverse(),
+        }
+    }
+}
+
+/// Compares an integer with a float.
+fn compare_integer_float(a: &Integer, x: f64) -> Ordering {
+    if x.is_nan() || x == f64::INFINITY {
+        return Ordering::Less;
+    }
+    if x == f64::NEG_INFINITY {
+        return Ordering::Greater;
+    }
+    let floor = x.floor();
+    let whole = BigInt::from_f64(floor).map(Integer::from).expect("finite float");
+    match a.cmp(&whole) {
+        Ordering::Equal if x > floor => Ordering::Less,
+        ordering => ordering,
+    }
+}
+
+impl From<Integer> for Number {
+    fn from(n: Integer) -> Self {
+        Number::Int(n)
+    }
+}
+
+impl PartialEq<i32> for Number {
+    fn eq(&self, other: &i32) -> bool {
+        *self == Number::Int(Integer::Small(*other))
+    }
+}
+
+impl PartialEq<f64> for Number {
+    fn eq(&self, other: &f64) -> bool {
+        *self == Number::Float(*other)
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{}", n),
+            Number::Float(x) => write!(f, "{}", format_float(*x)),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// src/machine/ops.rs
+//! The operator table used by the Prolog reader and for writing terms.
+//!
+//! Every operator has a priority (1..=1200) and a type. The type describes the
+//! position of the operator (prefix, infix or postfix) and whether its arguments
+//! may have the same priority as the operator itself (`y`) or must bind tighter
+//! (`x`). The default table is the standard ISO operator table.
+
+use std::collections::HashMap;
+
+/// The associativity and position of an operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpType {
+    /// Non-associative infix operator.
+    Xfx,
+    /// Right-associative infix operator.
+    Xfy,
+    /// Left-associative infix operator.
+    Yfx,
+    /// Non-associative prefix operator.
+    Fx,
+    /// Associative prefix operator.
+    Fy,
+    /// Non-associative postfix operator.
+    Xf,
+    /// Associative postfix operator.
+    Yf,
+}
+
+impl OpType {
+    /// Returns true for the infix operator types.
+    pub fn is_infix(self) -> bool {
+        matches!(self, OpType::Xfx | OpType::Xfy | OpType::Yfx)
+    }
+
+    /// Returns true for the prefix operator types.
+    pub fn is_prefix(self) -> bool {
+        matches!(self, OpType::Fx | OpType::Fy)
+    }
+
+    /// Returns true for the postfix operator types.
+    pub fn is_postfix(self) -> bool {
+        matches!(self, OpType::Xf | OpType::Yf)
+    }
+
+    /// Parses an operator type name such as `xfx` or `fy`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "xfx" => Some(OpType::Xfx),
+            "xfy" => Some(OpType::Xfy),
+            "yfx" => Some(OpType::Yfx),
+            "fx" => Some(OpType::Fx),
+            "fy" => Some(OpType::Fy),
+            "xf" => Some(OpType::Xf),
+            "yf" => Some(OpType::Yf),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the operator type, as used by `op/3`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OpType::Xfx => "xfx",
+            OpType::Xfy => "xfy",
+            OpType::Yfx => "yfx",
+            OpType::Fx => "fx",
+            OpType::Fy => "fy",
+            OpType::Xf => "xf",
+            OpType::Yf => "yf",
+        }
+    }
+
+    /// Returns the maximum priorities allowed for the left and right arguments
+    /// of an operator with this type and the given priority. Prefix operators have
+    /// no left argument and postfix operators no right argument; the missing side
+    /// is reported as 0.
+    pub fn arg_priorities(self, priority: u32) -> (u32, u32) {
+        let below = priority.saturating_sub(1);
+        match self {
+            OpType::Xfx => (below, below),
+            OpType::Xfy => (below, priority),
+            OpType::Yfx => (priority, below),
+            OpType::Fx => (0, below),
+            OpType::Fy => (0, priority),
+            OpType::Xf => (below, 0),
+            OpType::Yf => (priority, 0),
+        }
+    }
+}
+
+/// A table of prefix, infix and postfix operator definitions.
+#[derive(Debug, Clone)]
+pub struct OpTable {
+    prefix: HashMap<String, (u32, OpType)>,
+    infix: HashMap<String, (u32, OpType)>,
+    postfix: HashMap<String, (u32, OpType)>,
+}
+
+impl OpTable {
+    /// Creates a table without any operators.
+    pub fn empty() -> Self {
+        OpTable { prefix: HashMap::new(), infix: HashMap::new(), postfix: HashMap::new() }
+    }
+
+    /// Defines `name` as an operator of the given priority and type, replacing
+    /// any previous definition in the same class (prefix, infix or postfix).
+    /// A priority of 0 removes the definition.
+    pub fn add(&mut self, priority: u32, op_type: OpType, name: &str) {
+        let class = if op_type.is_prefix() {
+            &mut self.prefix
+        } else if op_type.is_infix() {
+            &mut self.infix
+        } else {
+            &mut self.postfix
+        };
+        if priority == 0 {
+            class.remove(name);
+        } else {
+            class.insert(name.to_string(), (priority, op_type));
+        }
+    }
+
+    /// Defines an operator as requested by `op(Priority, Type, Name)`, checking the
+    /// restrictions of the ISO standard. Returns a description of the problem if the
+    /// definition is not allowed.
+    pub fn define(&mut self, priority: i64, type_name: &str, name: &str) -> Result<(), String> {
+        if !(0..=1200).contains(&priority) {
+            return Err(format!("Operator priority must be between 0 and 1200, got {}", priority));
+        }
+        let priority = priority as u32;
+        let op_type = OpType::from_name(type_name)
+            .ok_or_else(|| format!("Invalid operator type: {}", type_name))?;
+        if name == "," {
+            return Err("The operator ',' cannot be modified".to_string());
+        }
+        if name == "|" && priority > 0 && (!op_type.is_infix() || priority < 1001) {
+            return Err("The operator '|' must be an infix operator with priority of at least 1001".to_string());
+        }
+        if priority > 0 {
+            let clash = if op_type.is_infix() {
+                self.postfix.contains_key(name)
+            } else if op_type.is_postfix() {
+                self.infix.contains_key(name)
+            } else {
+                false
+            };
+            if clash {
+                return Err(format!("{} cannot be both an infix and a postfix operator", name));
+            }
+        }
+        self.add(priority, op_type, name);
+        Ok(())
+    }
+
+    /// Returns every operator definition as `(priority, type, name)`, ordered by name.
+    pub fn definitions(&self) -> Vec<(u32, OpType, String)> {
+        let mut definitions: Vec<(u32, OpType, String)> = self.prefix.iter()
+            .chain(self.infix.iter())
+            .chain(self.postfix.iter())
+            .map(|(name, (priority, op_type))| (*priority, *op_type, name.clone()))
+            .collect();
+        definitions.sort_by(|a, b| a.2.cmp(&b.2).then(a.0.cmp(&b.0)));
+        definitions
+    }
+
+    /// Looks up the prefix definition of `name`.
+    pub fn prefix(&self, name: &str) -> Option<(u32, OpType)> {
+        self.prefix.get(name).copied()
+    }
+
+    /// Looks up the infix definition of `name`.
+    pub fn infix(&self, name: &str) -> Option<(u32, OpType)> {
+        self.infix.get(name).copied()
+    }
+
+    /// Looks up the postfix definition of `name`.
+    pub fn postfix(&self, name: &str) -> Option<(u32, OpType)> {
+        self.postfix.get(name).copied()
+    }
+
+    /// Returns true if `name` is defined as an operator of any class.
+    pub fn is_op(&self, name: &str) -> bool {
+        self.prefix.contains_key(name) || self.infix.contains_key(name) || self.postfix.contains_key(name)
+    }
+}
+
+impl Default for OpTable {
+    /// The standard ISO operator table.
+    fn default() -> Self {
+        let mut table = OpTable::empty();
+        let definitions: &[(u32, OpType, &[&str])] = &[
+            (1200, OpType::Xfx, &[":-", "-->"]),
+            (1200, OpType::Fx, &[":-", "?-"]),
+            (1100, OpType::Xfy, &[";", "|"]),
+            (1050, OpType::Xfy, &["->", "*->"]),
+            (1000, OpType::Xfy, &[","]),
+            (900, OpType::Fy, &["\\+"]),
+            (700, OpType::Xfx, &[
+                "=", "\\=", "==", "\\==", "@<", "@>", "@=<", "@>=",
+                "=..", "is", "=:=", "=\\=", "<", ">", "=<", ">=",
+            ]),
+            (600, OpType::Xfy, &[":"]),
+            (500, OpType::Yfx, &["+", "-", "/\\", "\\/", "xor"]),
+            (400, OpType::Yfx, &["*", "/", "//", "rem", "mod", "div", "<<", ">>"]),
+            (200, OpType::Xfx, &["**"]),
+            (200, OpType::Xfy, &["^"]),
+            (200, OpType::Fy, &["-", "+", "\\"]),
+        ];
+        for (priority, op_type, names) in definitions {
+            for name in *names {
+                table.add(*priority, *op_type, name);
+            }
+        }
+        table
+    }
+}
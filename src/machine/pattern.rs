@@ -0,0 +1,280 @@
+// src/machine/pattern.rs
+//! Higher-order pattern unification.
+//!
+//! With `Unifier::Pattern` selected, terms containing lambda abstractions and
+//! applications are unified up to beta and eta conversion with Miller's
+//! algorithm for the pattern fragment: a logic variable applied to distinct
+//! variables bound by enclosing lambdas (a flexible term `F x1 ... xn`) is
+//! solved by abstracting over those variables. Problems outside the fragment
+//! are not failed but delayed, and retried whenever a later unification
+//! binds more variables.
+//!
+//! Terms are unified as `Term`s in beta-eta normal form. The variables bound by
+//! the enclosing lambdas are renamed to fresh variables that are never bound;
+//! every other unbound variable is a logic variable.
+
+use crate::machine::core::Machine;
+use crate::machine::error_handling::MachineError;
+use crate::machine::lambda::{free_vars, substitute, Strategy};
+use crate::machine::term::Term;
+
+/// Splits an application into its head and its arguments.
+fn spine(term: &Term) -> (&Term, Vec<&Term>) {
+    let mut head = term;
+    let mut args = Vec::new();
+    while let Term::App(function, argument) = head {
+        args.push(&**argument);
+        head = function;
+    }
+    args.reverse();
+    (head, args)
+}
+
+/// Applies a term to arguments.
+fn apply(head: Term, args: impl IntoIterator<Item = Term>) -> Term {
+    args.into_iter().fold(head, |function, arg| Term::App(Box::new(function), Box::new(arg)))
+}
+
+/// Abstracts a term over the given variables, the first one outermost.
+fn abstract_over(params: &[usize], body: Term) -> Term {
+    params.iter().rev().fold(body, |body, param| Term::Lambda(*param, Box::new(body)))
+}
+
+/// Returns the variables of a flexible term's arguments if they are distinct
+/// variables bound by the enclosing lambdas, that is, if the term is a pattern.
+fn pattern_args(args: &[&Term], bound: &[usize]) -> Option<Vec<usize>> {
+    let mut vars = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg {
+            Term::Var(v) if bound.contains(v) && !vars.contains(v) => vars.push(*v),
+            _ => return None,
+        }
+    }
+    Some(vars)
+}
+
+/// Returns the immediate subterms of a term that is not a variable, an
+/// application or an abstraction.
+fn parts(term: &Term) -> Vec<&Term> {
+    match term {
+        Term::Compound(_, args) | Term::Constraint(_, args) => args.iter().collect(),
+        Term::Prob(inner) | Term::HigherOrder(inner) | Term::Modal(_, inner) | Term::Temporal(_, inner) => vec![&**inner],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns true if two rigid heads have the same constructor, name and number of parts.
+fn same_shape(t1: &Term, t2: &Term) -> bool {
+    match (t1, t2) {
+        (Term::Const(a), Term::Const(b)) => a == b,
+        (Term::BigInt(a), Term::BigInt(b)) => a == b,
+        (Term::Float(a), Term::Float(b)) => a == b,
+        (Term::Str(a), Term::Str(b)) => a == b,
+        (Term::Compound(f, args1), Term::Compound(g, args2)) | (Term::Constraint(f, args1), Term::Constraint(g, args2)) => {
+            f == g && args1.len() == args2.len()
+        },
+        (Term::Prob(_), Term::Prob(_)) | (Term::HigherOrder(_), Term::HigherOrder(_)) => true,
+        (Term::Modal(op1, _), Term::Modal(op2, _)) | (Term::Temporal(op1, _), Term::Temporal(op2, _)) => op1 == op2,
+        _ => false,
+    }
+}
+
+fn mismatch(t1: &Term, t2: &Term) -> MachineError {
+    MachineError::UnificationFailed(format!("Failed to unify {:?} with {:?}", t1, t2))
+}
+
+impl Machine {
+    /// Unifies two terms with higher-order pattern unification, delaying the
+    /// problems outside the pattern fragment. Delayed problems are retried by
+    /// `wake_delayed`.
+    pub(crate) fn unify_patterns(&mut self, t1: &Term, t2: &Term) -> Result<(), MachineError> {
+        self.unify_under(t1, t2, &mut Vec::new())
+    }
+
+    /// Retries the delayed problems until none of them can be solved any more.
+    /// Fails if one of them has no solution under the current bindings.
+    pub(crate) fn wake_delayed(&mut self) -> Result<(), MachineError> {
+        loop {
+            let delayed = std::mem::take(&mut self.delayed);
+            let count = delayed.len();
+            for (t1, t2) in delayed {
+                self.unify_under(&t1, &t2, &mut Vec::new())?;
+            }
+            if self.delayed.len() >= count {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Applies the current bindings to a term and brings it to beta-eta normal form.
+    fn normal_form(&mut self, term: &Term) -> Result<Term, MachineError> {
+        let cell = self.heap.put_term(term);
+        let term = self.read_term(&cell);
+        self.normalize_term(&term, Strategy::NormalOrder, true)
+    }
+
+    /// Binds a logic variable to a term.
+    fn bind_term(&mut self, var: usize, term: &Term) -> Result<(), MachineError> {
+        let cell = self.heap.put_term(term);
+        self.uf.bind(var, &cell)
+    }
+
+    /// Unifies two terms under the lambda-bound variables `bound`.
+    fn unify_under(&mut self, t1: &Term, t2: &Term, bound: &mut Vec<usize>) -> Result<(), MachineError> {
+        let t1 = self.normal_form(t1)?;
+        let t2 = self.normal_form(t2)?;
+        match (&t1, &t2) {
+            (Term::Lambda(x, body1), Term::Lambda(y, body2)) => {
+                let z = self.fresh_var_id();
+                bound.push(z);
+                let result = self.unify_under(&substitute(body1, *x, &Term::Var(z)), &substitute(body2, *y, &Term::Var(z)), bound);
+                bound.pop();
+                return result;
+            },
+            (Term::Lambda(x, body), other) | (other, Term::Lambda(x, body)) => {
+                // Eta expansion: `\x. M` and `N` are equal if `M` equals `N x`.
+                let z = self.fresh_var_id();
+                bound.push(z);
+                let expanded = Term::App(Box::new(other.clone()), Box::new(Term::Var(z)));
+                let result = self.unify_under(&substitute(body, *x, &Term::Var(z)), &expanded, bound);
+                bound.pop();
+                return result;
+            },
+            _ => {},
+        }
+
+        let (head1, args1) = spine(&t1);
+        let (head2, args2) = spine(&t2);
+        let flex1 = matches!(head1, Term::Var(v) if !bound.contains(v));
+        let flex2 = matches!(head2, Term::Var(v) if !bound.contains(v));
+        match (head1, head2) {
+            (&Term::Var(f), &Term::Var(g)) if flex1 && flex2 => {
+                match (pattern_args(&args1, bound), pattern_args(&args2, bound)) {
+                    (Some(xs), Some(ys)) => self.flex_flex(f, &xs, g, &ys),
+                    _ => {
+                        self.delay(&t1, &t2, bound);
+                        Ok(())
+                    },
+                }
+            },
+            (&Term::Var(f), _) if flex1 => self.flex_rigid(f, &args1, &t1, &t2, bound),
+            (_, &Term::Var(g)) if flex2 => self.flex_rigid(g, &args2, &t2, &t1, bound),
+            _ => {
+                if args1.len() != args2.len() {
+                    return Err(mismatch(&t1, &t2));
+                }
+                let mut pairs: Vec<(&Term, &Term)> = match (head1, head2) {
+                    (Term::Var(a), Term::Var(b)) if a == b => Vec::new(),
+                    _ if same_shape(head1, head2) => parts(head1).into_iter().zip(parts(head2)).collect(),
+                    _ => return Err(mismatch(&t1, &t2)),
+                };
+                pairs.extend(args1.into_iter().zip(args2));
+                for (a, b) in pairs {
+                    self.unify_under(a, b, bound)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// Records a problem outside the pattern fragment, closed over the
+    /// variables bound by the enclosing lambdas.
+    fn delay(&mut self, t1: &Term, t2: &Term, bound: &[usize]) {
+        self.delayed.push((abstract_over(bound, t1.clone()), abstract_over(bound, t2.clone())));
+    }
+
+    /// Solves `F xs = G ys` for two patterns. With the same head, F keeps the
+    /// arguments on which both sides agree; otherwise both become a fresh H
+    /// applied to the arguments they have in common.
+    fn flex_flex(&mut self, f: usize, xs: &[usize], g: usize, ys: &[usize]) -> Result<(), MachineError> {
+        let h = self.fresh_var_id();
+        if f == g {
+            if xs.len() != ys.len() {
+                return Err(MachineError::UnificationFailed(format!(
+                    "Variable {} is applied to {} and {} arguments", f, xs.len(), ys.len()
+                )));
+            }
+            if xs == ys {
+                return Ok(());
+            }
+            let kept = xs.iter().zip(ys).filter(|(x, y)| x == y).map(|(x, _)| Term::Var(*x));
+            return self.bind_term(f, &abstract_over(xs, apply(Term::Var(h), kept)));
+        }
+        let common: Vec<usize> = xs.iter().copied().filter(|x| ys.contains(x)).collect();
+        let shared = || common.iter().map(|v| Term::Var(*v));
+        self.bind_term(f, &abstract_over(xs, apply(Term::Var(h), shared())))?;
+        self.bind_term(g, &abstract_over(ys, apply(Term::Var(h), shared())))
+    }
+
+    /// Solves `F args = rigid`, where `flex` is the term `F args`. If the
+    /// arguments are a pattern xs, F becomes `\xs. rigid`, provided F does not
+    /// occur in `rigid` and every bound variable of `rigid` is among xs; flexible
+    /// subterms applied to other bound variables are pruned first.
+    fn flex_rigid(&mut self, f: usize, args: &[&Term], flex: &Term, rigid: &Term, bound: &[usize]) -> Result<(), MachineError> {
+        let Some(xs) = pattern_args(args, bound) else {
+            self.delay(flex, rigid, bound);
+            return Ok(());
+        };
+        if !self.prune(rigid, f, &xs, bound, &mut Vec::new())? {
+            self.delay(flex, rigid, bound);
+            return Ok(());
+        }
+        let rigid = self.normal_form(rigid)?;
+        self.bind_term(f, &abstract_over(&xs, rigid))
+    }
+
+    /// Checks that `term` can become the body of the solution of the flexible
+    /// variable `f` over the arguments `allowed`, pruning the flexible subterms
+    /// that depend on other bound variables. `local` holds the variables bound
+    /// inside `term`. Returns false if the check depends on a delayed problem.
+    fn prune(&mut self, term: &Term, f: usize, allowed: &[usize], bound: &[usize], local: &mut Vec<usize>) -> Result<bool, MachineError> {
+        if let Term::Lambda(param, body) = term {
+            local.push(*param);
+            let result = self.prune(body, f, allowed, bound, local);
+            local.pop();
+            return result;
+        }
+        let (head, args) = spine(term);
+        match head {
+            Term::Var(v) if local.contains(v) || allowed.contains(v) => {},
+            Term::Var(v) if bound.contains(v) => {
+                return Err(MachineError::UnificationFailed(format!(
+                    "Bound variable {} escapes the scope of variable {}", v, f
+                )));
+            },
+            &Term::Var(v) if v == f => {
+                return Err(MachineError::UnificationFailed(format!("Variable {} occurs in {:?}", f, term)));
+            },
+            &Term::Var(g) => {
+                let in_scope: Vec<usize> = local.iter().chain(bound).copied().collect();
+                return match pattern_args(&args, &in_scope) {
+                    Some(ys) => {
+                        let kept: Vec<usize> = ys.iter().copied().filter(|y| local.contains(y) || allowed.contains(y)).collect();
+                        if kept.len() < ys.len() {
+                            let h = self.fresh_var_id();
+                            self.bind_term(g, &abstract_over(&ys, apply(Term::Var(h), kept.into_iter().map(Term::Var))))?;
+                        }
+                        Ok(true)
+                    },
+                    None => {
+                        let vars = args.iter().flat_map(|arg| free_vars(arg)).collect::<Vec<usize>>();
+                        Ok(!vars.iter().any(|v| *v == f || (bound.contains(v) && !allowed.contains(v) && !local.contains(v))))
+                    },
+                };
+            },
+            rigid => {
+                for part in parts(rigid) {
+                    if !self.prune(part, f, allowed, bound, local)? {
+                        return Ok(false);
+                    }
+                }
+            },
+        }
+        for arg in args {
+            if !self.prune(arg, f, allowed, bound, local)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
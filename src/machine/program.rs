@@ -0,0 +1,107 @@
+// src/machine/program.rs
+//! A complete LAM program: the instructions together with the predicate and
+//! index tables the machine needs to run them.
+//!
+//! A `.lam` file with `.pred` and `.index` directives is read into a `Program`
+//! (see `instruction_parser::parse_program`), so it can be run without the
+//! Prolog compiler. `Display` writes a program back in the same format.
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::machine::core::Machine;
+use crate::machine::instruction::{format_literal, quote, Instruction};
+use crate::machine::term::Term;
+
+/// Instructions and the tables that locate the clauses of each predicate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    /// The code of the program.
+    pub instructions: Vec<Instruction>,
+    /// Predicate table mapping predicate indicators (`name/arity`) to clause addresses.
+    pub predicate_table: HashMap<String, Vec<usize>>,
+    /// Index table mapping predicate names and keys to clause addresses.
+    pub index_table: HashMap<String, HashMap<Vec<Term>, Vec<usize>>>,
+}
+
+impl Program {
+    /// Creates a program with the given code and empty tables.
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Program { instructions, ..Program::default() }
+    }
+
+    /// Returns the number of registers the program needs: one more than the
+    /// highest register any of its instructions uses.
+    pub fn num_registers(&self) -> usize {
+        self.instructions.iter()
+            .filter_map(Instruction::max_register)
+            .max()
+            .map_or(0, |register| register + 1)
+    }
+
+    /// Creates a machine with the specified number of registers that runs this program.
+    pub fn into_machine(self, num_registers: usize) -> Machine {
+        let mut machine = Machine::new(num_registers, self.instructions);
+        for (name, addresses) in self.predicate_table {
+            let name = machine.intern(&name);
+            machine.predicate_table.insert(name, addresses);
+        }
+        for (name, keys) in self.index_table {
+            let name = machine.intern(&name);
+            machine.index_table.insert(name, keys);
+        }
+        machine
+    }
+}
+
+/// Returns true if `name` is a plain identifier, which needs no quotes.
+fn is_plain(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Writes a predicate in a directive: an indicator `name/arity` with a plain
+/// name as it is, and any other predicate quoted.
+fn format_name(name: &str) -> String {
+    let plain = match name.rsplit_once('/') {
+        Some((name, arity)) => is_plain(name) && !arity.is_empty() && arity.chars().all(|c| c.is_ascii_digit()),
+        None => is_plain(name),
+    };
+    if plain { name.to_string() } else { quote(name) }
+}
+
+/// Writes the program in the `.lam` format, one instruction per line. Before each
+/// instruction come the `.pred` and `.index` directives of the clauses starting
+/// there, ordered by predicate name, so that parsing the output rebuilds the
+/// same tables. This requires every address in the tables to lie inside the
+/// program and the addresses of each entry to be in increasing order, as the
+/// compiler produces them; entries pointing past the end are not written.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut predicates: Vec<(&String, &Vec<usize>)> = self.predicate_table.iter().collect();
+        predicates.sort();
+        let mut indexes: Vec<(&String, String, &Vec<usize>)> = Vec::new();
+        for (name, keys) in &self.index_table {
+            for (key, addresses) in keys {
+                let key: Vec<String> = key.iter().map(format_literal).collect();
+                indexes.push((name, key.join(", "), addresses));
+            }
+        }
+        indexes.sort();
+
+        for (address, instruction) in self.instructions.iter().enumerate() {
+            for (name, addresses) in &predicates {
+                for _ in addresses.iter().filter(|a| **a == address) {
+                    writeln!(f, ".pred {}", format_name(name))?;
+                }
+            }
+            for (name, key, addresses) in &indexes {
+                for _ in addresses.iter().filter(|a| **a == address) {
+                    writeln!(f, ".index {} {}", format_name(name), key)?;
+                }
+            }
+            writeln!(f, "{}", instruction)?;
+        }
+        Ok(())
+    }
+}
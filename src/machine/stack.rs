@@ -0,0 +1,134 @@
+// src/machine/stack.rs
+//! A stack whose frames are shared between copies.
+//!
+//! The machine keeps its call frames and environments on `Stack`s. Cloning a
+//! stack is O(1): the copy shares every frame with the original, so a choice
+//! point can save the control and environment stacks as pointers to their tops,
+//! like the continuation and environment registers of the WAM. Pushing and
+//! popping never affect other copies, and `last_mut` copies the top frame first
+//! if another stack still refers to it.
+
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Clone)]
+struct Node<T> {
+    value: T,
+    below: Option<Rc<Node<T>>>,
+}
+
+/// A persistent stack of frames.
+pub struct Stack<T> {
+    top: Option<Rc<Node<T>>>,
+    len: usize,
+}
+
+impl<T> Stack<T> {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        Stack { top: None, len: 0 }
+    }
+
+    /// Returns the number of frames on the stack.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the stack has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes a frame.
+    pub fn push(&mut self, value: T) {
+        let below = self.top.take();
+        self.top = Some(Rc::new(Node { value, below }));
+        self.len += 1;
+    }
+
+    /// Returns the top frame.
+    pub fn last(&self) -> Option<&T> {
+        self.top.as_ref().map(|node| &node.value)
+    }
+
+    /// Iterates over the frames from the top down.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        std::iter::successors(self.top.as_deref(), |node| node.below.as_deref()).map(|node| &node.value)
+    }
+}
+
+impl<T: Clone> Stack<T> {
+    /// Removes and returns the top frame. The frame is copied if another stack
+    /// shares it.
+    pub fn pop(&mut self) -> Option<T> {
+        let node = self.top.take()?;
+        self.len -= 1;
+        match Rc::try_unwrap(node) {
+            Ok(node) => {
+                self.top = node.below;
+                Some(node.value)
+            },
+            Err(shared) => {
+                self.top = shared.below.clone();
+                Some(shared.value.clone())
+            },
+        }
+    }
+
+    /// Returns the top frame for modification, copying it first if another
+    /// stack shares it.
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.top.as_mut().map(|node| &mut Rc::make_mut(node).value)
+    }
+}
+
+impl<T> Clone for Stack<T> {
+    fn clone(&self) -> Self {
+        Stack { top: self.top.clone(), len: self.len }
+    }
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Stack::new()
+    }
+}
+
+impl<T> Drop for Stack<T> {
+    /// Drops the frames one by one, so that a deep stack does not overflow the
+    /// native stack through recursive drops.
+    fn drop(&mut self) {
+        let mut next = self.top.take();
+        while let Some(node) = next {
+            match Rc::try_unwrap(node) {
+                Ok(mut node) => next = node.below.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Stack<T> {
+    /// Builds a stack from frames listed bottom first.
+    fn from(frames: Vec<T>) -> Self {
+        let mut stack = Stack::new();
+        for frame in frames {
+            stack.push(frame);
+        }
+        stack
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Stack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut frames: Vec<&T> = self.iter().collect();
+        frames.reverse();
+        f.debug_list().entries(frames).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for Stack<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
@@ -1,13 +1,26 @@
 // src/machine/term.rs
 //! Definition of terms in the LAM system.
+//!
+//! Terms are displayed in Prolog syntax: operator terms are written in prefix,
+//! infix or postfix form according to an operator table, and lists in list notation.
 
+use std::cmp::Ordering;
 use std::fmt;
+use std::sync::OnceLock;
+use num_bigint::BigInt;
+
+use crate::machine::number::{Float, Number};
+use crate::machine::ops::OpTable;
 
 /// The various types of terms.
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Term {
     /// A constant integer.
     Const(i32),
+    /// An integer outside the range of `Const`.
+    BigInt(BigInt),
+    /// A floating-point number.
+    Float(Float),
     /// A variable (identified by an ID).
     Var(usize),
     /// A compound term with a functor and a list of arguments.
@@ -30,21 +43,171 @@ pub enum Term {
     Str(String),
 }
 
+impl Term {
+    /// Returns a displayable view of the term that writes operator terms using the
+    /// given operator table. `Display` for `Term` uses the standard ISO table.
+    pub fn display_with<'a>(&'a self, ops: &'a OpTable) -> TermDisplay<'a> {
+        TermDisplay { term: self, ops }
+    }
+
+    /// Compares two terms in the standard order of terms: variables come
+    /// first, by id, then numbers, then atoms, then compound terms, then the
+    /// other kinds of terms.
+    ///
+    /// Numbers are ordered by value; a float comes before an integer of the
+    /// same value. Atoms are ordered alphabetically. Compound terms are
+    /// ordered by arity, then by name, then by their arguments from left to
+    /// right. The other kinds of terms are ordered by their debug form.
+    pub fn standard_order(&self, other: &Term) -> Ordering {
+        // Arguments are compared with an explicit stack rather than by
+        // recursion, so comparing long lists does not exhaust the Rust stack.
+        let mut pending = vec![(self, other)];
+        while let Some((a, b)) = pending.pop() {
+            let ordering = match (a, b) {
+                (Term::Var(a), Term::Var(b)) => a.cmp(b),
+                (Term::Str(a), Term::Str(b)) => a.cmp(b),
+                (Term::Compound(f, args1), Term::Compound(g, args2)) => {
+                    let ordering = args1.len().cmp(&args2.len()).then_with(|| f.cmp(g));
+                    if ordering.is_eq() {
+                        // Pushed last first, so that the arguments are compared left to right.
+                        pending.extend(args1.iter().zip(args2).rev());
+                    }
+                    ordering
+                },
+                (a, b) if a.standard_rank() == 1 && b.standard_rank() == 1 => {
+                    let (x, y) = (Number::from_term(a).expect("number"), Number::from_term(b).expect("number"));
+                    x.compare(&y).then_with(|| match (x, y) {
+                        (Number::Float(x), Number::Float(y)) => x.total_cmp(&y),
+                        (Number::Float(_), Number::Int(_)) => Ordering::Less,
+                        (Number::Int(_), Number::Float(_)) => Ordering::Greater,
+                        (Number::Int(_), Number::Int(_)) => Ordering::Equal,
+                    })
+                },
+                (a, b) => a.standard_rank().cmp(&b.standard_rank()).then_with(|| format!("{:?}", a).cmp(&format!("{:?}", b))),
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// The class of the term in the standard order, from 0 for variables to 4
+    /// for the kinds of terms that come after compound terms.
+    pub(crate) fn standard_rank(&self) -> u8 {
+        match self {
+            Term::Var(_) => 0,
+            Term::Const(_) | Term::BigInt(_) | Term::Float(_) => 1,
+            Term::Str(_) => 2,
+            Term::Compound(_, _) => COMPOUND_RANK,
+            _ => 4,
+        }
+    }
+}
+
+/// The rank of compound terms in the standard order.
+pub(crate) const COMPOUND_RANK: u8 = 3;
+
+/// A term paired with the operator table used to display it.
+pub struct TermDisplay<'a> {
+    term: &'a Term,
+    ops: &'a OpTable,
+}
+
+impl fmt::Display for TermDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_term(self.term, self.ops, 1200))
+    }
+}
+
 impl fmt::Display for Term {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Term::Const(n) => write!(f, "{}", n),
-            Term::Str(s) => write!(f, "{}", s),
-            Term::Var(id) => write!(f, "Var({})", id),
-            Term::Compound(functor, args) => {
-                write!(f, "{}(", functor)?;
-                for (i, arg) in args.iter().enumerate() {
-                    if i > 0 { write!(f, ", ")?; }
-                    write!(f, "{}", arg)?;
-                }
-                write!(f, ")")
+        static DEFAULT_OPS: OnceLock<OpTable> = OnceLock::new();
+        self.display_with(DEFAULT_OPS.get_or_init(OpTable::default)).fmt(f)
+    }
+}
+
+fn is_graphic_char(c: char) -> bool {
+    "#$&*+-./:<=>?@^~\\".contains(c)
+}
+
+/// Formats a term whose context allows at most priority `max`. Operator terms
+/// that bind more loosely than the context are parenthesized.
+fn format_term(term: &Term, ops: &OpTable, max: u32) -> String {
+    match term {
+        Term::Const(n) => n.to_string(),
+        Term::BigInt(n) => n.to_string(),
+        Term::Float(x) => x.to_string(),
+        Term::Str(s) => s.clone(),
+        Term::Var(id) => format!("Var({})", id),
+        Term::Compound(functor, args) => format_compound(functor, args, ops, max),
+        other => format!("{:?}", other),
+    }
+}
+
+fn format_compound(functor: &str, args: &[Term], ops: &OpTable, max: u32) -> String {
+    if functor == "." && args.len() == 2 {
+        return format_list(args, ops);
+    }
+    if functor == "{}" && args.len() == 1 {
+        return format!("{{{}}}", format_term(&args[0], ops, 1200));
+    }
+    // Symbolic operators are written without surrounding spaces (`X=Y`), except
+    // where two graphic tokens would run together; alphanumeric ones (`X is Y`)
+    // and the comma are always spaced.
+    let alphanumeric = functor.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let op_and_priority = match args.len() {
+        2 => ops.infix(functor).map(|(priority, op_type)| {
+            let (left_max, right_max) = op_type.arg_priorities(priority);
+            let left = format_term(&args[0], ops, left_max);
+            let right = format_term(&args[1], ops, right_max);
+            let text = if alphanumeric {
+                format!("{} {} {}", left, functor, right)
+            } else if functor == "," {
+                format!("{}, {}", left, right)
+            } else {
+                let space_before = if left.ends_with(is_graphic_char) { " " } else { "" };
+                let space_after = if right.starts_with(is_graphic_char) { " " } else { "" };
+                format!("{}{}{}{}{}", left, space_before, functor, space_after, right)
+            };
+            (text, priority)
+        }),
+        1 => ops.prefix(functor).map(|(priority, op_type)| {
+            let (_, arg_max) = op_type.arg_priorities(priority);
+            let arg = format_term(&args[0], ops, arg_max);
+            // `- 1` must not read back as a negative number, nor `- (a, b)` as `-/2`.
+            let needs_space = alphanumeric || arg.starts_with(|c: char| is_graphic_char(c) || c.is_ascii_digit() || c == '(');
+            (format!("{}{}{}", functor, if needs_space { " " } else { "" }, arg), priority)
+        }).or_else(|| ops.postfix(functor).map(|(priority, op_type)| {
+            let (arg_max, _) = op_type.arg_priorities(priority);
+            let arg = format_term(&args[0], ops, arg_max);
+            let needs_space = alphanumeric || arg.ends_with(is_graphic_char);
+            (format!("{}{}{}", arg, if needs_space { " " } else { "" }, functor), priority)
+        })),
+        _ => None,
+    };
+    match op_and_priority {
+        Some((text, priority)) if priority > max => format!("({})", text),
+        Some((text, _)) => text,
+        None => {
+            let args: Vec<String> = args.iter().map(|arg| format_term(arg, ops, 999)).collect();
+            format!("{}({})", functor, args.join(", "))
+        },
+    }
+}
+
+/// Formats a list cell and its tail in list notation: `[a, b|T]`.
+fn format_list(args: &[Term], ops: &OpTable) -> String {
+    let mut items = vec![format_term(&args[0], ops, 999)];
+    let mut tail = &args[1];
+    loop {
+        match tail {
+            Term::Compound(functor, cell) if functor == "." && cell.len() == 2 => {
+                items.push(format_term(&cell[0], ops, 999));
+                tail = &cell[1];
             },
-            other => write!(f, "{:?}", other),
+            Term::Str(name) if name == "[]" => return format!("[{}]", items.join(", ")),
+            other => return format!("[{}|{}]", items.join(", "), format_term(other, ops, 999)),
         }
     }
 }
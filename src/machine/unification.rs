@@ -4,33 +4,88 @@
 //! This version implements path compression to reduce the cost of repeated resolution.
 //! When a variable’s binding is resolved recursively, we update it (path compression)
 //! and record the previous binding on the trail so that backtracking can restore the state.
+//!
+//! Variables are bound to values of any `Bindable` type: the machine binds them to heap
+//! cells, and `Term` is the default for standalone use.
 
 use std::collections::HashMap;
 use crate::machine::term::Term;
 use crate::machine::error_handling::MachineError;
 
+/// A value a variable can be bound to, which may itself be a variable.
+pub trait Bindable: Clone + PartialEq {
+    /// Returns the id of the variable this value is, if it is one.
+    fn var_id(&self) -> Option<usize>;
+}
+
+impl Bindable for Term {
+    fn var_id(&self) -> Option<usize> {
+        match self {
+            Term::Var(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
 /// Represents a trail entry recording a variable’s old binding.
 #[derive(Debug, Clone)]
-pub struct TrailEntry {
+pub struct TrailEntry<T = Term> {
     pub var: usize,
-    pub old_binding: Option<Term>,
+    pub old_binding: Option<T>,
 }
 
 /// Union–find structure with a trailing mechanism.
 #[derive(Debug, Clone)]
-pub struct UnionFind {
-    pub bindings: HashMap<usize, Term>,
-    pub trail: Vec<TrailEntry>,
+pub struct UnionFind<T = Term> {
+    pub bindings: HashMap<usize, T>,
+    pub trail: Vec<TrailEntry<T>>,
 }
 
-impl UnionFind {
-    /// Creates a new UnionFind structure.
-    pub fn new() -> Self {
+impl<T> Default for UnionFind<T> {
+    fn default() -> Self {
         Self {
             bindings: HashMap::new(),
             trail: Vec::new(),
         }
     }
+}
+
+impl UnionFind {
+    /// Creates a new UnionFind structure over terms.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves a term and, recursively, all of its subterms, so that no bound
+    /// variable remains anywhere inside the result. A variable bound to a term
+    /// containing itself is left in place where it recurs, so cyclic bindings
+    /// resolve to a finite term.
+    pub fn resolve_deep(&mut self, term: &Term) -> Term {
+        self.resolve_deep_within(term, &mut Vec::new())
+    }
+
+    /// Resolves a term deeply; `resolving` holds the variables whose bindings are being resolved.
+    fn resolve_deep_within(&mut self, term: &Term, resolving: &mut Vec<usize>) -> Term {
+        match term {
+            Term::Var(v) if resolving.contains(v) => term.clone(),
+            Term::Var(v) => match self.bindings.get(v).cloned() {
+                Some(binding) => {
+                    resolving.push(*v);
+                    let resolved = self.resolve_deep_within(&binding, resolving);
+                    resolving.pop();
+                    resolved
+                },
+                None => term.clone(),
+            },
+            Term::Compound(functor, args) => {
+                Term::Compound(functor.clone(), args.iter().map(|arg| self.resolve_deep_within(arg, resolving)).collect())
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+impl<T: Bindable> UnionFind<T> {
 
     /// Recursively resolves a term to its current binding using path compression.
     ///
@@ -39,35 +94,33 @@ impl UnionFind {
     /// and the previous binding is recorded on the trail so that backtracking can restore it.
     ///
     /// **Note:** This method now requires a mutable reference to self.
-    pub fn resolve(&mut self, term: &Term) -> Term {
-        match term {
-            Term::Var(v) => {
+    pub fn resolve(&mut self, term: &T) -> T {
+        match term.var_id() {
+            Some(v) => {
                 // Clone the binding out of the HashMap to release the immutable borrow.
-                if let Some(binding) = self.bindings.get(v).cloned() {
+                if let Some(binding) = self.bindings.get(&v).cloned() {
                     // Recursively resolve the binding.
                     let resolved = self.resolve(&binding);
                     // If the binding is not yet compressed, update it and record the old value.
                     if binding != resolved {
-                        self.trail.push(TrailEntry { var: *v, old_binding: Some(binding) });
-                        self.bindings.insert(*v, resolved.clone());
+                        self.trail.push(TrailEntry { var: v, old_binding: Some(binding) });
+                        self.bindings.insert(v, resolved.clone());
                     }
                     resolved
                 } else {
                     term.clone()
                 }
             },
-            _ => term.clone(),
+            None => term.clone(),
         }
     }
 
     /// Binds the variable `var` to `term` (after resolution), recording the previous binding on the trail.
-    pub fn bind(&mut self, var: usize, term: &Term) -> Result<(), MachineError> {
+    pub fn bind(&mut self, var: usize, term: &T) -> Result<(), MachineError> {
         let resolved_term = self.resolve(term);
         // Avoid binding a variable to itself.
-        if let Term::Var(v) = &resolved_term {
-            if *v == var {
-                return Ok(());
-            }
+        if resolved_term.var_id() == Some(var) {
+            return Ok(());
         }
         // Record the current binding (if any) on the trail.
         let old_binding = self.bindings.get(&var).cloned();
@@ -7,21 +7,52 @@ use std::error::Error;
 
 // Import the Prolog compiler.
 use lam::prolog::compiler::compile_prolog;
+use lam::prolog::parser::ParseError;
+// Import the error reporting used for parse errors.
+use lam::diagnostic::{Diagnostic, Span};
 // Import the LAM instruction parser.
-use lam::machine::instruction_parser::parse_instructions;
-// Import the LAM machine.
-use lam::machine::core::Machine;
+use lam::machine::instruction_parser::parse_program;
+use lam::machine::program::Program;
+
+/// Prints an error located in `source` with the offending line and a caret
+/// underline, then exits with a failure status.
+fn report(filename: &str, source: &str, message: String, span: Span) -> ! {
+    eprint!("{}", Diagnostic::new(filename, message, span).render(source));
+    std::process::exit(1);
+}
+
+/// Runs a program on a new machine with `num_registers` registers, exiting with
+/// a failure status on a machine error.
+fn run(program: Program, num_registers: usize) {
+    let mut machine = program.into_machine(num_registers);
+    machine.run().unwrap_or_else(|e| {
+        eprintln!("Machine execution error: {:?}", e);
+        std::process::exit(1);
+    });
+}
+
+/// Writes a program as bytecode next to `path`, with the .lamb extension.
+fn write_bytecode(program: &Program, path: &Path) {
+    let output_path = path.with_extension("lamb");
+    let mut file = fs::File::create(&output_path)
+        .unwrap_or_else(|e| panic!("Failed to create output file '{:?}': {}", output_path, e));
+    program.write_bytecode(&mut file)
+        .unwrap_or_else(|e| panic!("Failed to write output file '{:?}': {}", output_path, e));
+    println!("Bytecode written to {:?}", output_path);
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Retrieve command-line arguments.
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: lamc <file> [--execute | -x]");
+        eprintln!("Usage: lamc <file> [--execute | -x] [--bytecode | -b]");
         std::process::exit(1);
     }
     let filename = &args[1];
     // Check for the optional execute flag.
     let execute_flag = args.iter().any(|arg| arg == "--execute" || arg == "-x");
+    // Check for the optional flag selecting the binary output format.
+    let bytecode_flag = args.iter().any(|arg| arg == "--bytecode" || arg == "-b");
 
     let path = Path::new(filename);
     match path.extension().and_then(|ext| ext.to_str()) {
@@ -29,37 +60,54 @@ fn main() -> Result<(), Box<dyn Error>> {
             // If a .lam file is provided, parse its instructions and run it.
             let content = fs::read_to_string(filename)
                 .unwrap_or_else(|e| panic!("Failed to read file '{}': {}", filename, e));
-            let instructions = parse_instructions(&content)
-                .unwrap_or_else(|e| panic!("Failed to parse LAM instructions: {}", e));
-            println!("Executing LAM program from file '{}':", filename);
-            let mut machine = Machine::new(10, instructions);
-            machine.run().unwrap_or_else(|e| {
-                eprintln!("Machine execution error: {:?}", e);
+            let program = parse_program(&content)
+                .unwrap_or_else(|e| report(filename, &content, e.message(), e.span()));
+            if bytecode_flag {
+                // Assemble the program into a .lamb file instead of running it.
+                write_bytecode(&program, path);
+            } else {
+                println!("Executing LAM program from file '{}':", filename);
+                let num_registers = program.num_registers();
+                run(program, num_registers);
+            }
+        },
+        Some("lamb") => {
+            // A bytecode file is loaded and run directly.
+            let mut file = fs::File::open(filename)
+                .unwrap_or_else(|e| panic!("Failed to read file '{}': {}", filename, e));
+            let program = Program::read_bytecode(&mut file).unwrap_or_else(|e| {
+                eprintln!("Failed to load bytecode file '{}': {}", filename, e);
                 std::process::exit(1);
             });
+            println!("Executing LAM bytecode from file '{}':", filename);
+            let num_registers = program.num_registers();
+            run(program, num_registers);
         },
         Some("pl") => {
             // For Prolog (.pl) files, compile them to LAM instructions.
             let program = fs::read_to_string(filename)
                 .unwrap_or_else(|e| panic!("Failed to read file '{}': {}", filename, e));
-            let (instructions, pred_table) = compile_prolog(&program)
-                .unwrap_or_else(|e| panic!("Failed to compile Prolog program: {}", e));
+            let (instructions, pred_table, num_registers) = compile_prolog(&program).unwrap_or_else(|e| {
+                match e.downcast_ref::<ParseError>() {
+                    Some(parse_error) => report(filename, &program, parse_error.message(), parse_error.span()),
+                    None => {
+                        eprintln!("Failed to compile Prolog program: {}", e);
+                        std::process::exit(1);
+                    },
+                }
+            });
 
+            let program = Program { instructions, predicate_table: pred_table, ..Program::default() };
             if execute_flag {
                 // If the execute flag is provided, execute the compiled LAM program.
-                let mut machine = Machine::new(10, instructions);
-                // *** FIX: Inject the compiled predicate table into the machine ***
-                machine.predicate_table = pred_table;
-                machine.run().unwrap_or_else(|e| {
-                    eprintln!("Machine execution error: {:?}", e);
-                    std::process::exit(1);
-                });
+                run(program, num_registers);
+            } else if bytecode_flag {
+                // Write the compiled program to a new file with .lamb extension.
+                write_bytecode(&program, path);
             } else {
-                // Otherwise, write the compiled instructions to a new file with .lam extension.
-                let mut output = String::new();
-                for instr in instructions.iter() {
-                    output.push_str(&format!("{}\n", instr));
-                }
+                // Otherwise, write the compiled program, with its predicate table, to a new
+                // file with .lam extension.
+                let output = program.to_string();
                 let output_path = path.with_extension("lam");
                 let mut file = fs::File::create(&output_path)
                     .unwrap_or_else(|e| panic!("Failed to create output file '{:?}': {}", output_path, e));
@@ -69,7 +117,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             }
         },
         _ => {
-            eprintln!("Unsupported file extension. Please provide a .lam or .pl file.");
+            eprintln!("Unsupported file extension. Please provide a .lam, .lamb or .pl file.");
             std::process::exit(1);
         }
     }
@@ -1,5 +1,7 @@
 // src/languages/prolog/ast.rs
 
+use num_bigint::BigInt;
+
 /// Represents a Prolog term.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Term {
@@ -7,8 +9,12 @@ pub enum Term {
     Var(String),
     /// An atom (a constant, typically starting with a lowercase letter).
     Atom(String),
-    /// A number (we support integers for now).
+    /// An integer in the range of `i32`.
     Number(i32),
+    /// An integer too large for `Number`.
+    BigInt(BigInt),
+    /// A floating-point number.
+    Float(f64),
     /// A compound term: functor with arguments.
     Compound(String, Vec<Term>),
 }
@@ -25,6 +31,10 @@ pub enum Clause {
         head: Term,
         body: Vec<Term>,
     },
+    /// A directive (`:- Goal.`), run when the program is loaded.
+    Directive {
+        goal: Term,
+    },
 }
 
 /// Represents a Prolog query, which is simply a sequence of goals.
@@ -3,172 +3,922 @@
 //! into a LAM program. It parses all clauses (ignoring query lines) and
 //! compiles each clause into a code block. It also builds a predicate table.
 //!
-//! Facts are compiled into a code block that uses PUT instructions to record
-//! each argument (if any) so that the fact’s head is fully represented.
+//! Before a clause is compiled, its variables are classified:
+//! - a variable that occurs only once is *void*: it needs no storage and becomes
+//!   a fresh variable wherever it appears in the body;
+//! - a *temporary* variable occurs in a single chunk of the clause (the head and
+//!   the goals up to the first call form one chunk, and every call ends a chunk)
+//!   and lives in a register above the argument registers;
+//! - a *permanent* variable occurs in several chunks and must survive calls, so it
+//!   lives in a slot of an environment created with ALLOCATE.
 //!
-//! Rules are compiled by processing each goal (the body of the clause).
+//! Variables are never given fixed ids: their first occurrence either takes the
+//! caller's argument or creates a new variable with NEW_VAR, so every activation
+//! of a clause works on freshly renamed variables and recursion is safe.
 //!
-//! For goals that are equality predicates (i.e. with functor "=" and two arguments)
-//! are handled specially. For goals with functor "write", we now check whether the argument
-//! is an atom or a variable. If it is a variable, we emit a MOVE instruction to move the variable's
-//! value from register 1 into register 0 before CALLing "write".
-//! For all other compound goals, the compiler generates PUT instructions for each argument
-//! (placing atoms, numbers, or variables into registers) before emitting a CALL instruction.
+//! Every clause starts with its head: each argument of the head is matched
+//! against the corresponding argument register:
+//! - atoms compile to `GET_STR Ri, "atom"`,
+//! - numbers compile to `GET_CONST Ri, n`,
+//! - the first occurrence of a variable records the argument (MOVE into its
+//!   register or STORE_LOCAL into its environment slot), later occurrences unify
+//!   with it (GET_VALUE or GET_LOCAL),
+//! - compound terms compile to `GET_STRUCTURE Ri, f/n` followed by one UNIFY
+//!   instruction per argument; arguments that are themselves atoms or compound
+//!   terms are loaded into scratch registers with UNIFY_VAR and matched next.
 //!
-//! Finally, a PROCEED is appended at the end of each clause’s code block.
+//! A failing GET instruction raises a unification failure, which makes the
+//! machine backtrack into the next clause of the predicate.
+//!
+//! The body of a rule is compiled goal by goal: the arguments of each goal are
+//! placed in registers R0..Rn with PUT instructions (compound arguments are
+//! assembled with BUILD_COMPOUND, permanent variables are loaded with PUT_LOCAL)
+//! before a CALL to the goal's predicate. Predicates are named by their
+//! indicator `name/arity`, in CALL instructions as in the predicate table, so
+//! `p/1` and `p/2` are different predicates.
+//!
+//! Arithmetic goals are compiled inline when their expressions are made of
+//! numbers, variables and evaluable functors:
+//! - `X is E` evaluates E with ARITHMETIC_IS into a scratch register and
+//!   unifies X with the result;
+//! - a comparison `E1 < E2` (or `>`, `=<`, `>=`, `=:=`, `=\=`) evaluates E1 into
+//!   R0 and E2 into R1 and calls the comparison built-in on the two numbers.
+//!
+//! Other arithmetic goals, such as `X is foo + 1`, are compiled as calls, so the
+//! built-ins report the ISO errors when the goal runs.
+//!
+//! The control constructs `(A ; B)`, `(C -> T ; E)`, `(C *-> T ; E)`, `(C -> T)`
+//! and `\+ G` are compiled inline with CHOICE, JUMP and FAIL. A condition is
+//! committed to by cutting back to the height of the choice stack saved with
+//! GET_LEVEL before it ran: CUT_TO removes every choice point above it, while
+//! SOFT_CUT (for `*->`) only disables the CHOICE of the construct. The saved
+//! height is a hidden variable of the clause, so it is kept in the environment
+//! when the condition makes calls.
+//!
+//! A cut `!` compiles to CUT, which removes the choice points created since the
+//! predicate was entered, as recorded by the machine's cut barrier register.
+//! The cuts in a condition, a negated goal or the goal of `call/N` are local
+//! to that goal: they compile to CUT_TO the height saved by GET_LEVEL just
+//! before it.
+//!
+//! `call(G, A1, ..., An)` is compiled as the goal G with the extra arguments
+//! when G is known, and otherwise as a call to the `call` built-in with G in R0
+//! and the list of extra arguments in R1.
+//!
+//! Finally, a PROCEED (preceded by DEALLOCATE when the clause has an environment)
+//! is appended at the end of each clause’s code block. If the program has
+//! directives (`:- Goal.`) or defines `main`, the code is prefixed with an entry
+//! point that runs the directive goals in order, calls `main` and halts. This is
+//! how `op/3` directives reach the machine's operator table.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use crate::machine::instruction::Instruction;
+use crate::machine::arithmetic::Expression;
+use crate::machine::core::indicator;
+use crate::machine::instruction::{CallTarget, Instruction};
 use crate::prolog::ast::{Clause, Term};
-use crate::prolog::parser::{parse_program};
+use crate::prolog::parser::parse_program;
+
+/// The result of compiling a Prolog program: the instructions, the predicate
+/// table and the number of registers the instructions use.
+pub type CompiledProgram = (Vec<Instruction>, HashMap<String, Vec<usize>>, usize);
 
 /// Compiles a Prolog program (facts and rules) into a LAM program.
-/// Returns a tuple of (compiled instructions, predicate table).
+/// Returns a tuple of (compiled instructions, predicate table, number of registers).
 ///
-/// The predicate table maps each predicate name (from the clause head) to a list of
-/// starting addresses for the corresponding code blocks.
-pub fn compile_prolog(program: &str) -> Result<(Vec<Instruction>, HashMap<String, Vec<usize>>), Box<dyn Error>> {
-    let clauses = parse_program(program)
-        .map_err(|e| Box::<dyn Error>::from(format!("Parse error: {:?}", e)))?;
-    
+/// The predicate table maps each predicate indicator `name/arity` (from the clause
+/// head) to a list of starting addresses for the corresponding code blocks. The number of registers is
+/// one more than the highest register the code uses, so a machine of that size can
+/// run it.
+pub fn compile_prolog(program: &str) -> Result<CompiledProgram, Box<dyn Error>> {
+    // The parse error is passed on as is, so callers can downcast it and report its position.
+    let clauses = parse_program(program)?;
+
     let mut instructions = Vec::new();
     let mut predicate_table: HashMap<String, Vec<usize>> = HashMap::new();
-    
+
+    // Emit the entry point before any clause: directives run first, in source order,
+    // then execution continues with `main`.
+    let has_main = clauses.iter().any(|clause| match clause {
+        Clause::Fact { head } | Clause::Rule { head, .. } => matches!(head, Term::Atom(name) if name == "main"),
+        Clause::Directive { .. } => false,
+    });
+    let directives: Vec<&Term> = clauses.iter()
+        .filter_map(|clause| match clause {
+            Clause::Directive { goal } => Some(goal),
+            _ => None,
+        })
+        .collect();
+    if has_main || !directives.is_empty() {
+        for goal in directives {
+            let head = Term::Atom("directive".to_string());
+            let mut compiler = ClauseCompiler::new(&head, std::slice::from_ref(goal), instructions.len());
+            compiler.allocate();
+            compiler.compile_body(goal, None)?;
+            compiler.deallocate();
+            instructions.extend(compiler.code);
+        }
+        if has_main {
+            instructions.push(Instruction::Call { predicate: indicator("main", 0), target: CallTarget::Unresolved });
+        }
+        instructions.push(Instruction::Halt);
+    }
+
     for clause in clauses {
         let addr = instructions.len();
-        let (pred_name, code_block) = match clause {
-            Clause::Fact { head } => {
-                // For a fact, if the head is a compound term, generate PUT instructions for each argument.
-                match head {
-                    Term::Compound(functor, args) => {
-                        let mut block = Vec::new();
-                        for (i, arg) in args.iter().enumerate() {
-                            match arg {
-                                Term::Atom(s) => {
-                                    block.push(Instruction::PutStr { register: i, value: s.clone() });
-                                },
-                                Term::Number(n) => {
-                                    block.push(Instruction::PutConst { register: i, value: *n });
-                                },
-                                Term::Var(s) => {
-                                    block.push(Instruction::PutVar { register: i, var_id: i, name: s.clone() });
-                                },
-                                _ => return Err(Box::from("Unsupported argument type in fact head")),
-                            }
-                        }
-                        block.push(Instruction::Proceed);
-                        (functor, block)
-                    },
-                    Term::Atom(s) => {
-                        // Fact with no arguments.
-                        (s, vec![Instruction::Proceed])
-                    },
-                    _ => return Err(Box::from("Unsupported fact head type")),
+        let (head, body) = match clause {
+            Clause::Fact { head } => (head, Vec::new()),
+            Clause::Rule { head, body } => (head, body),
+            Clause::Directive { .. } => continue,
+        };
+        let (pred_name, code) = compile_clause(&head, &body, addr)?;
+        instructions.extend(code);
+        predicate_table.entry(pred_name).or_default().push(addr);
+    }
+
+    let num_registers = instructions.iter()
+        .filter_map(Instruction::max_register)
+        .max()
+        .map_or(0, |register| register + 1);
+    Ok((instructions, predicate_table, num_registers))
+}
+
+/// Compiles the clause `head :- body` into code that starts at address `base`
+/// and returns the predicate indicator with the code.
+fn compile_clause(head: &Term, body: &[Term], base: usize) -> Result<(String, Vec<Instruction>), Box<dyn Error>> {
+    let mut compiler = ClauseCompiler::new(head, body, base);
+    compiler.allocate();
+    let pred_name = compiler.compile_head(head)?;
+    for goal in body {
+        compiler.compile_body(goal, None)?;
+    }
+    compiler.deallocate();
+    compiler.code.push(Instruction::Proceed);
+    Ok((pred_name, compiler.code))
+}
+
+/// Where a clause variable is kept while the clause runs.
+#[derive(Debug, Clone, Copy)]
+enum VarHome {
+    /// The variable occurs once, so it is never stored.
+    Void,
+    /// A temporary variable held in a register.
+    Register(usize),
+    /// A permanent variable held in an environment slot.
+    Local(usize),
+}
+
+/// Returns the arity of a head or goal.
+fn arity(term: &Term) -> usize {
+    match term {
+        Term::Compound(_, args) => args.len(),
+        _ => 0,
+    }
+}
+
+/// Returns true if `functor` names an arithmetic comparison.
+fn is_comparison(functor: &str) -> bool {
+    matches!(functor, "<" | ">" | "=<" | ">=" | "=:=" | "=\\=")
+}
+
+/// Returns true if `term` can be compiled into an `Expression`: a number, a
+/// variable, `pi`, `e` or an evaluable functor applied to such terms. Other
+/// terms are left to the `is/2` and comparison built-ins, which report the
+/// ISO errors when the goal runs.
+fn is_arithmetic(term: &Term) -> bool {
+    match term {
+        Term::Var(_) | Term::Number(_) | Term::BigInt(_) | Term::Float(_) => true,
+        Term::Atom(name) => name == "pi" || name == "e",
+        Term::Compound(functor, args) => {
+            let evaluable = match args.len() {
+                1 => matches!(functor.as_str(), "+" | "-") || Expression::from_unary(functor, Expression::Const(0)).is_some(),
+                2 => Expression::from_binary(functor, Expression::Const(0), Expression::Const(0)).is_some(),
+                _ => false,
+            };
+            evaluable && args.iter().all(is_arithmetic)
+        },
+    }
+}
+
+/// Appends the named variables of `term` to `names`, in order of occurrence.
+fn variables(term: &Term, names: &mut Vec<String>) {
+    match term {
+        Term::Var(name) if name != "_" => names.push(name.clone()),
+        Term::Compound(_, args) => {
+            for arg in args {
+                variables(arg, names);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Adds the arguments `extra` to a callable term, as `call/N` does. Returns
+/// `None` if the term is not an atom or a compound term.
+fn add_arguments(goal: &Term, extra: &[Term]) -> Option<Term> {
+    match goal {
+        Term::Atom(_) if extra.is_empty() => Some(goal.clone()),
+        Term::Atom(name) => Some(Term::Compound(name.clone(), extra.to_vec())),
+        Term::Compound(name, args) => Some(Term::Compound(name.clone(), args.iter().chain(extra).cloned().collect())),
+        _ => None,
+    }
+}
+
+/// A body goal, seen as a control construct or a simple goal.
+enum Goal<'a> {
+    /// `true`, which does nothing.
+    True,
+    /// `fail` or `false`.
+    Fail,
+    /// `!`.
+    Cut,
+    /// `(A, B)`.
+    Conjunction(&'a Term, &'a Term),
+    /// `(A ; B)`.
+    Disjunction(&'a Term, &'a Term),
+    /// `(C -> T ; E)`.
+    IfThenElse(&'a Term, &'a Term, &'a Term),
+    /// `(C *-> T ; E)`.
+    SoftIfThenElse(&'a Term, &'a Term, &'a Term),
+    /// `(C -> T)`, which fails when C fails.
+    IfThen(&'a Term, &'a Term),
+    /// `\+ G`.
+    Not(&'a Term),
+    /// `call(G, A1, ..., An)` where G is an atom or a compound term: the goal
+    /// G with the extra arguments added.
+    Call(Term),
+    /// A variable goal, or `call(G, A1, ..., An)` where G is only known when
+    /// the clause runs: the goal and the extra arguments.
+    DynamicCall(&'a Term, &'a [Term]),
+    /// A call to a predicate or a built-in.
+    Simple(&'a Term),
+}
+
+impl<'a> Goal<'a> {
+    /// Classifies a body goal.
+    fn of(goal: &'a Term) -> Self {
+        match goal {
+            Term::Atom(name) if name == "true" => Goal::True,
+            Term::Atom(name) if name == "fail" || name == "false" => Goal::Fail,
+            Term::Atom(name) if name == "!" => Goal::Cut,
+            Term::Var(_) => Goal::DynamicCall(goal, &[]),
+            Term::Compound(functor, args) => match (functor.as_str(), args.as_slice()) {
+                (",", [a, b]) => Goal::Conjunction(a, b),
+                (";", [Term::Compound(arrow, branch), e]) if arrow == "->" && branch.len() == 2 => {
+                    Goal::IfThenElse(&branch[0], &branch[1], e)
+                },
+                (";", [Term::Compound(arrow, branch), e]) if arrow == "*->" && branch.len() == 2 => {
+                    Goal::SoftIfThenElse(&branch[0], &branch[1], e)
+                },
+                (";", [a, b]) => Goal::Disjunction(a, b),
+                ("->", [c, t]) => Goal::IfThen(c, t),
+                // Without an else branch, the soft-cut is a plain conjunction.
+                ("*->", [c, t]) => Goal::Conjunction(c, t),
+                ("\\+", [g]) => Goal::Not(g),
+                ("call", [g, extra @ ..]) => match add_arguments(g, extra) {
+                    Some(goal) => Goal::Call(goal),
+                    None => Goal::DynamicCall(g, extra),
+                },
+                _ => Goal::Simple(goal),
+            },
+            _ => Goal::Simple(goal),
+        }
+    }
+}
+
+/// Returns true if `goal` contains a cut that cuts through it, outside the
+/// conditions, negations and calls that keep their cuts local.
+fn has_cut(goal: &Term) -> bool {
+    match Goal::of(goal) {
+        Goal::Cut => true,
+        Goal::Conjunction(a, b) | Goal::Disjunction(a, b) => has_cut(a) || has_cut(b),
+        Goal::IfThenElse(_, t, e) | Goal::SoftIfThenElse(_, t, e) => has_cut(t) || has_cut(e),
+        Goal::IfThen(_, t) => has_cut(t),
+        _ => false,
+    }
+}
+
+/// The variable occurrences of a clause, in execution order.
+///
+/// Every occurrence belongs to a chunk, and the chunk changes after each call.
+/// The branches of a construct are walked one after the other, so a variable
+/// used in two branches separated by a call is made permanent.
+struct Occurrences {
+    /// The variable of each occurrence, its chunk, and whether it occurs in the
+    /// clause (rather than being initialized before a construct with several
+    /// branches).
+    list: Vec<(String, usize, bool)>,
+    /// The chunk of the goals being walked.
+    chunk: usize,
+    /// Number of cut barriers created so far.
+    barriers: usize,
+    /// The largest arity of the head and the called goals.
+    max_arity: usize,
+}
+
+impl Occurrences {
+    /// Records the occurrences of the variables of a head or a simple goal.
+    fn term(&mut self, term: &Term) {
+        let mut names = Vec::new();
+        variables(term, &mut names);
+        self.list.extend(names.into_iter().map(|name| (name, self.chunk, true)));
+    }
+
+    /// Records the variables of a construct with several branches, which are
+    /// initialized before its first branch.
+    fn initialize(&mut self, construct: &Term) {
+        let mut names = Vec::new();
+        variables(construct, &mut names);
+        self.list.extend(names.into_iter().map(|name| (name, self.chunk, false)));
+    }
+
+    /// Creates a cut barrier, a hidden variable holding the height of the
+    /// choice stack, and records its first occurrence.
+    fn barrier(&mut self) -> String {
+        let name = format!("$level{}", self.barriers);
+        self.barriers += 1;
+        self.list.push((name.clone(), self.chunk, true));
+        name
+    }
+
+    /// Records a use of a cut barrier.
+    fn cut(&mut self, barrier: String) {
+        self.list.push((barrier, self.chunk, true));
+    }
+
+    /// Records the occurrences of a body goal. `cut` is the barrier a cut in
+    /// the goal cuts to, or `None` for the clause's cut barrier.
+    fn goal(&mut self, goal: &Term, cut: Option<&str>) {
+        match Goal::of(goal) {
+            Goal::True | Goal::Fail => {},
+            Goal::Cut => {
+                if let Some(barrier) = cut {
+                    self.cut(barrier.to_string());
                 }
             },
-            Clause::Rule { head, body } => {
-                // The head’s predicate name (we ignore its arguments during call compilation).
-                let name = match head {
-                    Term::Atom(s) => s,
-                    Term::Compound(ref functor, _) => functor.clone(),
-                    _ => return Err(Box::from("Unsupported rule head type")),
-                };
-                let mut block = Vec::new();
-                for goal in body {
-                    match goal {
-                        // Special handling for unification goal: X = Y
-                        Term::Compound(ref functor, ref args) if functor == "=" && args.len() == 2 => {
-                            // Move left argument into register 0
-                            match args[0] {
-                                Term::Atom(ref s) => {
-                                    block.push(Instruction::PutStr { register: 0, value: s.clone() });
-                                },
-                                Term::Number(n) => {
-                                    block.push(Instruction::PutConst { register: 0, value: n });
-                                },
-                                Term::Var(ref s) => {
-                                    block.push(Instruction::PutVar { register: 0, var_id: 0, name: s.clone() });
-                                },
-                                _ => return Err(Box::from("Unsupported argument type for equality (left)")),
-                            }
-                            // Move right argument into register 1
-                            match args[1] {
-                                Term::Atom(ref s) => {
-                                    block.push(Instruction::PutStr { register: 1, value: s.clone() });
-                                },
-                                Term::Number(n) => {
-                                    block.push(Instruction::PutConst { register: 1, value: n });
-                                },
-                                Term::Var(ref s) => {
-                                    block.push(Instruction::PutVar { register: 1, var_id: 1, name: s.clone() });
-                                },
-                                _ => return Err(Box::from("Unsupported argument type for equality (right)")),
-                            }
-                            // Emit call to built–in "="
-                            block.push(Instruction::Call { predicate: "=".to_string() });
-                        },
-                        // Special handling for write goal.
-                        Term::Compound(ref functor, ref args) if functor == "write" && args.len() == 1 => {
-                            match &args[0] {
-                                Term::Atom(ref literal) => {
-                                    // If the argument is a literal, simply put it in register 0.
-                                    block.push(Instruction::PutStr { register: 0, value: literal.clone() });
-                                    block.push(Instruction::Call { predicate: functor.clone() });
-                                },
-                                Term::Var(_) => {
-                                    // For a variable, we assume that the variable was already bound
-                                    // from a previous goal (e.g. in a call to parent) and is in register 1.
-                                    // So we emit a MOVE instruction to move the value from register 1 to register 0.
-                                    block.push(Instruction::Move { src: 1, dst: 0 });
-                                    block.push(Instruction::Call { predicate: functor.clone() });
-                                },
-                                _ => {
-                                    // Fallback: compile generically.
-                                    for (i, arg) in args.iter().enumerate() {
-                                        match arg {
-                                            Term::Atom(ref s) => { block.push(Instruction::PutStr { register: i, value: s.clone() }); },
-                                            Term::Number(n) => { block.push(Instruction::PutConst { register: i, value: *n }); },
-                                            Term::Var(ref s) => { block.push(Instruction::PutVar { register: i, var_id: i, name: s.clone() }); },
-                                            _ => return Err(Box::from("Unsupported argument type in goal")),
-                                        }
-                                    }
-                                    block.push(Instruction::Call { predicate: functor.clone() });
-                                },
-                            }
+            Goal::Conjunction(a, b) => {
+                self.goal(a, cut);
+                self.goal(b, cut);
+            },
+            Goal::Disjunction(a, b) => {
+                self.initialize(goal);
+                self.goal(a, cut);
+                self.goal(b, cut);
+            },
+            Goal::IfThenElse(c, t, e) | Goal::SoftIfThenElse(c, t, e) => {
+                self.initialize(goal);
+                let barrier = self.barrier();
+                self.opaque(c);
+                self.cut(barrier);
+                self.goal(t, cut);
+                self.goal(e, cut);
+            },
+            Goal::IfThen(c, t) => {
+                let barrier = self.barrier();
+                self.opaque(c);
+                self.cut(barrier);
+                self.goal(t, cut);
+            },
+            Goal::Not(g) => {
+                self.initialize(goal);
+                let barrier = self.barrier();
+                self.opaque(g);
+                self.cut(barrier);
+            },
+            Goal::Call(goal) => self.opaque(&goal),
+            Goal::DynamicCall(g, extra) => {
+                self.term(g);
+                for arg in extra {
+                    self.term(arg);
+                }
+                self.max_arity = self.max_arity.max(2);
+                self.chunk += 1;
+            },
+            Goal::Simple(goal) => {
+                self.term(goal);
+                self.max_arity = self.max_arity.max(arity(goal));
+                self.chunk += 1;
+            },
+        }
+    }
+
+    /// Records the occurrences of a goal whose cuts are local to it, with the
+    /// barrier they cut to.
+    fn opaque(&mut self, goal: &Term) {
+        if has_cut(goal) {
+            let barrier = self.barrier();
+            self.goal(goal, Some(&barrier));
+        } else {
+            self.goal(goal, None);
+        }
+    }
+}
+
+/// Compiles a single clause, keeping track of where its variables live.
+struct ClauseCompiler {
+    /// The home of each named variable of the clause.
+    homes: HashMap<String, VarHome>,
+    /// Variables whose first occurrence has already been compiled.
+    seen: HashSet<String>,
+    /// Number of environment slots needed by the permanent variables.
+    num_permanent: usize,
+    /// First register free for building compound terms.
+    scratch: usize,
+    /// The address of the first instruction of the clause.
+    base: usize,
+    /// Number of cut barriers created so far, in the order of `Occurrences`.
+    barriers: usize,
+    /// The instructions generated so far.
+    code: Vec<Instruction>,
+}
+
+impl ClauseCompiler {
+    /// Classifies the variables of the clause `head :- body`, whose code starts
+    /// at address `base`.
+    fn new(head: &Term, body: &[Term], base: usize) -> Self {
+        let mut occurrences = Occurrences { list: Vec::new(), chunk: 0, barriers: 0, max_arity: arity(head) };
+        occurrences.term(head);
+        for goal in body {
+            occurrences.goal(goal, None);
+        }
+
+        // Temporary registers start above every argument register used by the clause.
+        let max_arity = occurrences.max_arity;
+        let mut homes = HashMap::new();
+        let mut num_temporary = 0;
+        let mut num_permanent = 0;
+        for (name, first_chunk, _) in &occurrences.list {
+            if homes.contains_key(name) {
+                continue;
+            }
+            let uses: Vec<(usize, bool)> = occurrences.list.iter()
+                .filter(|(other, _, _)| other == name)
+                .map(|(_, chunk, occurs)| (*chunk, *occurs))
+                .collect();
+            let home = if uses.iter().filter(|(_, occurs)| *occurs).count() == 1 {
+                VarHome::Void
+            } else if uses.iter().all(|(chunk, _)| chunk == first_chunk) {
+                num_temporary += 1;
+                VarHome::Register(max_arity + num_temporary - 1)
+            } else {
+                num_permanent += 1;
+                VarHome::Local(num_permanent - 1)
+            };
+            homes.insert(name.clone(), home);
+        }
+
+        Self {
+            homes,
+            seen: HashSet::new(),
+            num_permanent,
+            scratch: max_arity + num_temporary,
+            base,
+            barriers: 0,
+            code: Vec::new(),
+        }
+    }
+
+    /// Creates the environment of the clause if it has permanent variables.
+    fn allocate(&mut self) {
+        if self.num_permanent > 0 {
+            self.code.push(Instruction::Allocate { n: self.num_permanent });
+        }
+    }
+
+    /// Discards the environment created by `allocate`.
+    fn deallocate(&mut self) {
+        if self.num_permanent > 0 {
+            self.code.push(Instruction::Deallocate);
+        }
+    }
+
+    /// Returns the home of a variable and whether this is its first occurrence.
+    fn occurrence(&mut self, name: &str) -> (VarHome, bool) {
+        let home = self.homes.get(name).copied().unwrap_or(VarHome::Void);
+        let first = self.seen.insert(name.to_string());
+        (home, first)
+    }
+
+    /// Compiles the head of a clause into GET instructions against the argument
+    /// registers and returns the predicate indicator.
+    fn compile_head(&mut self, head: &Term) -> Result<String, Box<dyn Error>> {
+        match head {
+            Term::Atom(name) => Ok(indicator(name, 0)),
+            Term::Compound(functor, args) => {
+                for (i, arg) in args.iter().enumerate() {
+                    self.get_term(arg, i, self.scratch)?;
+                }
+                Ok(indicator(functor, args.len()))
+            },
+            _ => Err(Box::from("Unsupported clause head type")),
+        }
+    }
+
+    /// Emits instructions that unify the term in `register` with `term`.
+    /// The arguments of compound terms are loaded into registers starting at `scratch`.
+    fn get_term(&mut self, term: &Term, register: usize, scratch: usize) -> Result<(), Box<dyn Error>> {
+        match term {
+            Term::Atom(s) => {
+                self.code.push(Instruction::GetStr { register, value: s.clone() });
+            },
+            Term::Number(n) => {
+                self.code.push(Instruction::GetConst { register, value: *n });
+            },
+            Term::BigInt(n) => {
+                self.code.push(Instruction::GetBigInt { register, value: n.clone() });
+            },
+            Term::Float(x) => {
+                self.code.push(Instruction::GetFloat { register, value: *x });
+            },
+            Term::Var(name) => match self.occurrence(name) {
+                (VarHome::Void, _) => {},
+                (VarHome::Register(t), true) => {
+                    self.code.push(Instruction::Move { src: register, dst: t });
+                },
+                (VarHome::Register(t), false) => {
+                    self.code.push(Instruction::GetValue { register, src: t });
+                },
+                (VarHome::Local(k), true) => {
+                    self.code.push(Instruction::StoreLocal { index: k, register });
+                },
+                (VarHome::Local(k), false) => {
+                    self.code.push(Instruction::GetLocal { index: k, register });
+                },
+            },
+            Term::Compound(functor, args) => {
+                self.code.push(Instruction::GetStructure { register, functor: functor.clone(), arity: args.len() });
+                // Arguments that are neither numbers nor variables are loaded into
+                // registers and matched once every argument of the structure has
+                // been read. The last one is loaded into `scratch`, so a list
+                // compiles with the same few registers whatever its length.
+                let nested: Vec<&Term> = args.iter()
+                    .filter(|arg| !matches!(arg, Term::Number(_) | Term::Var(_)))
+                    .collect();
+                let transient = scratch + nested.len();
+                let mut next = nested.len();
+                for arg in args {
+                    match arg {
+                        Term::Number(n) => {
+                            self.code.push(Instruction::UnifyConst { value: *n });
                         },
-                        // For any other compound goal, generate PUT instructions for each argument.
-                        Term::Compound(ref functor, ref args) => {
-                            for (i, arg) in args.iter().enumerate() {
-                                match arg {
-                                    Term::Atom(ref s) => {
-                                        block.push(Instruction::PutStr { register: i, value: s.clone() });
-                                    },
-                                    Term::Number(n) => {
-                                        block.push(Instruction::PutConst { register: i, value: *n });
-                                    },
-                                    Term::Var(ref s) => {
-                                        block.push(Instruction::PutVar { register: i, var_id: i, name: s.clone() });
-                                    },
-                                    _ => return Err(Box::from("Unsupported argument type in goal")),
-                                }
-                            }
-                            block.push(Instruction::Call { predicate: functor.clone() });
+                        Term::Var(name) => match self.occurrence(name) {
+                            (VarHome::Void, _) => {
+                                self.code.push(Instruction::UnifyVar { register: transient });
+                            },
+                            (VarHome::Register(t), true) => {
+                                self.code.push(Instruction::UnifyVar { register: t });
+                            },
+                            (VarHome::Register(t), false) => {
+                                self.code.push(Instruction::UnifyValue { register: t });
+                            },
+                            (VarHome::Local(k), true) => {
+                                self.code.push(Instruction::UnifyVar { register: transient });
+                                self.code.push(Instruction::StoreLocal { index: k, register: transient });
+                            },
+                            (VarHome::Local(k), false) => {
+                                self.code.push(Instruction::PutLocal { index: k, register: transient });
+                                self.code.push(Instruction::UnifyValue { register: transient });
+                            },
                         },
-                        // For an atomic goal (with no arguments), simply emit a CALL.
-                        Term::Atom(s) => {
-                            block.push(Instruction::Call { predicate: s });
+                        _ => {
+                            next -= 1;
+                            self.code.push(Instruction::UnifyVar { register: scratch + next });
                         },
-                        _ => return Err(Box::from("Unsupported goal type in rule")),
                     }
                 }
-                block.push(Instruction::Proceed);
-                (name, block)
+                // The registers of the arguments not matched yet lie below
+                // the one being matched, which is free again once it is read.
+                for (i, arg) in nested.into_iter().enumerate() {
+                    let register = transient - 1 - i;
+                    self.get_term(arg, register, register)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Compiles a body goal, which may be a control construct. `cut` is the
+    /// barrier a cut in the goal cuts to, or `None` for the clause's cut
+    /// barrier, which CUT reads from the machine.
+    ///
+    /// The branches of a construct are laid out one after the other: CHOICE
+    /// points to the next branch and JUMP skips the branches that follow.
+    /// A condition is cut away with CUT_TO (or SOFT_CUT for `*->`) to the
+    /// height of the choice stack saved by GET_LEVEL before its CHOICE.
+    fn compile_body(&mut self, goal: &Term, cut: Option<&str>) -> Result<(), Box<dyn Error>> {
+        match Goal::of(goal) {
+            Goal::True => {},
+            Goal::Fail => self.code.push(Instruction::Fail),
+            Goal::Cut => match cut {
+                Some(barrier) => {
+                    let register = self.barrier_register(barrier);
+                    self.code.push(Instruction::CutTo { register });
+                },
+                None => self.code.push(Instruction::Cut),
+            },
+            Goal::Conjunction(a, b) => {
+                self.compile_body(a, cut)?;
+                self.compile_body(b, cut)?;
+            },
+            Goal::Disjunction(a, b) => {
+                self.initialize(goal);
+                let choice = self.emit(Instruction::Choice { alternative: 0 });
+                self.compile_body(a, cut)?;
+                let jump = self.emit(Instruction::Jump { address: 0 });
+                self.patch(choice);
+                self.compile_body(b, cut)?;
+                self.patch(jump);
+            },
+            Goal::IfThenElse(c, t, e) | Goal::SoftIfThenElse(c, t, e) => {
+                let soft = matches!(Goal::of(goal), Goal::SoftIfThenElse(..));
+                self.initialize(goal);
+                let barrier = self.get_level();
+                let choice = self.emit(Instruction::Choice { alternative: 0 });
+                self.compile_opaque(c)?;
+                let register = self.barrier_register(&barrier);
+                if soft {
+                    self.code.push(Instruction::SoftCut { register });
+                } else {
+                    self.code.push(Instruction::CutTo { register });
+                }
+                self.compile_body(t, cut)?;
+                let jump = self.emit(Instruction::Jump { address: 0 });
+                self.patch(choice);
+                self.compile_body(e, cut)?;
+                self.patch(jump);
+            },
+            Goal::IfThen(c, t) => {
+                let barrier = self.get_level();
+                self.compile_opaque(c)?;
+                let register = self.barrier_register(&barrier);
+                self.code.push(Instruction::CutTo { register });
+                self.compile_body(t, cut)?;
+            },
+            Goal::Not(g) => {
+                // The goal's first solution removes the CHOICE and fails; if
+                // the goal fails, the CHOICE continues after the negation.
+                self.initialize(goal);
+                let barrier = self.get_level();
+                let choice = self.emit(Instruction::Choice { alternative: 0 });
+                self.compile_opaque(g)?;
+                let register = self.barrier_register(&barrier);
+                self.code.push(Instruction::CutTo { register });
+                self.code.push(Instruction::Fail);
+                self.patch(choice);
+            },
+            Goal::Call(goal) => self.compile_opaque(&goal)?,
+            Goal::DynamicCall(g, extra) => {
+                // The `call` built-in takes the goal and the list of extra arguments.
+                let list = extra.iter().rev().fold(Term::Atom("[]".to_string()), |tail, arg| {
+                    Term::Compound(".".to_string(), vec![arg.clone(), tail])
+                });
+                self.put_term(g, 0, self.scratch)?;
+                self.put_term(&list, 1, self.scratch)?;
+                self.code.push(Instruction::Call { predicate: indicator("call", 2), target: CallTarget::Unresolved });
+            },
+            Goal::Simple(goal) => self.compile_goal(goal)?,
+        }
+        Ok(())
+    }
+
+    /// Compiles a goal whose cuts are local to it: a condition, a negated goal
+    /// or the goal of `call/N`. Its cuts go back to the height of the choice
+    /// stack saved by GET_LEVEL just before it.
+    fn compile_opaque(&mut self, goal: &Term) -> Result<(), Box<dyn Error>> {
+        if has_cut(goal) {
+            let barrier = self.get_level();
+            self.compile_body(goal, Some(&barrier))
+        } else {
+            self.compile_body(goal, None)
+        }
+    }
+
+    /// Appends an instruction and returns its index in the clause code.
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    /// Points the CHOICE or JUMP at index `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let next = self.base + self.code.len();
+        match &mut self.code[at] {
+            Instruction::Choice { alternative } => *alternative = next,
+            Instruction::Jump { address } => *address = next,
+            other => unreachable!("cannot patch {}", other),
+        }
+    }
+
+    /// Creates the variables of a construct with several branches that do
+    /// not exist yet, so that every branch finds them in their homes.
+    fn initialize(&mut self, construct: &Term) {
+        let mut names = Vec::new();
+        variables(construct, &mut names);
+        for name in names {
+            let home = self.homes.get(&name).copied().unwrap_or(VarHome::Void);
+            if matches!(home, VarHome::Void) || !self.seen.insert(name.clone()) {
+                continue;
+            }
+            match home {
+                VarHome::Register(t) => {
+                    self.code.push(Instruction::NewVar { register: t, name });
+                },
+                VarHome::Local(k) => {
+                    self.code.push(Instruction::NewVar { register: self.scratch, name });
+                    self.code.push(Instruction::StoreLocal { index: k, register: self.scratch });
+                },
+                VarHome::Void => {},
+            }
+        }
+    }
+
+    /// Creates the next cut barrier and saves the height of the choice stack
+    /// in its home with GET_LEVEL. Returns the name of the barrier.
+    fn get_level(&mut self) -> String {
+        let name = format!("$level{}", self.barriers);
+        self.barriers += 1;
+        match self.homes.get(&name).copied() {
+            Some(VarHome::Local(k)) => {
+                self.code.push(Instruction::GetLevel { register: self.scratch });
+                self.code.push(Instruction::StoreLocal { index: k, register: self.scratch });
+            },
+            Some(VarHome::Register(t)) => {
+                self.code.push(Instruction::GetLevel { register: t });
+            },
+            _ => unreachable!("a cut barrier is used twice"),
+        }
+        name
+    }
+
+    /// Returns the register holding a cut barrier, loading it from the
+    /// environment if needed.
+    fn barrier_register(&mut self, name: &str) -> usize {
+        match self.homes.get(name).copied() {
+            Some(VarHome::Local(k)) => {
+                self.code.push(Instruction::PutLocal { index: k, register: self.scratch });
+                self.scratch
+            },
+            Some(VarHome::Register(t)) => t,
+            _ => unreachable!("a cut barrier is used twice"),
+        }
+    }
+
+    /// Compiles a body goal: its arguments are placed in R0..Rn and the predicate is called.
+    fn compile_goal(&mut self, goal: &Term) -> Result<(), Box<dyn Error>> {
+        match goal {
+            Term::Atom(name) => {
+                self.code.push(Instruction::Call { predicate: indicator(name, 0), target: CallTarget::Unresolved });
+                Ok(())
+            },
+            Term::Compound(functor, args) if functor == "is" && args.len() == 2 && is_arithmetic(&args[1]) => {
+                // The value is computed into the first scratch register and
+                // then unified with the left-hand side.
+                let result = self.scratch;
+                let mut scratch = result + 1;
+                let expression = self.compile_expression(&args[1], &mut scratch)?;
+                self.code.push(Instruction::ArithmeticIs { target: result, expression });
+                match &args[0] {
+                    // A new variable is created with its name, as for a call,
+                    // so that its value can be reported.
+                    Term::Var(name) if !self.seen.contains(name) => {
+                        self.put_term(&args[0], scratch, scratch + 1)?;
+                        self.code.push(Instruction::GetValue { register: scratch, src: result });
+                        Ok(())
+                    },
+                    other => self.get_term(other, result, scratch),
+                }
+            },
+            Term::Compound(functor, args) if is_comparison(functor) && args.len() == 2 && args.iter().all(is_arithmetic) => {
+                // Both sides are evaluated into R0 and R1, which the comparison
+                // built-in reads as numbers.
+                for (i, arg) in args.iter().enumerate() {
+                    let mut scratch = self.scratch;
+                    let expression = self.compile_expression(arg, &mut scratch)?;
+                    self.code.push(Instruction::ArithmeticIs { target: i, expression });
+                }
+                self.code.push(Instruction::Call { predicate: indicator(functor, 2), target: CallTarget::Unresolved });
+                Ok(())
             },
+            Term::Compound(functor, args) => {
+                for (i, arg) in args.iter().enumerate() {
+                    self.put_term(arg, i, self.scratch)?;
+                }
+                self.code.push(Instruction::Call { predicate: indicator(functor, args.len()), target: CallTarget::Unresolved });
+                Ok(())
+            },
+            _ => Err(Box::from("Unsupported goal type in rule")),
+        }
+    }
+
+    /// Places an operand of an expression in the register `scratch`, which is
+    /// advanced past it, and returns the expression reading that register.
+    fn load_operand(&mut self, term: &Term, scratch: &mut usize) -> Result<Expression, Box<dyn Error>> {
+        let register = *scratch;
+        *scratch += 1;
+        self.put_term(term, register, *scratch)?;
+        Ok(Expression::Var(register))
+    }
+
+    /// Compiles an arithmetic term (see `is_arithmetic`) into an expression.
+    /// Variables held in registers are read in place; other variables and big
+    /// integers are first loaded into registers from `scratch` on, and
+    /// `scratch` is advanced past them.
+    fn compile_expression(&mut self, term: &Term, scratch: &mut usize) -> Result<Expression, Box<dyn Error>> {
+        let expression = match term {
+            Term::Number(n) => Expression::Const(*n),
+            Term::Float(x) => Expression::Float(*x),
+            Term::Atom(name) if name == "pi" => Expression::Float(std::f64::consts::PI),
+            Term::Atom(name) if name == "e" => Expression::Float(std::f64::consts::E),
+            Term::Var(name) => match self.homes.get(name) {
+                Some(VarHome::Register(t)) if self.seen.contains(name) => Expression::Var(*t),
+                _ => self.load_operand(term, scratch)?,
+            },
+            Term::BigInt(_) => self.load_operand(term, scratch)?,
+            Term::Compound(functor, args) => match args.as_slice() {
+                [operand] if functor == "+" => self.compile_expression(operand, scratch)?,
+                [operand] if functor == "-" => {
+                    let operand = self.compile_expression(operand, scratch)?;
+                    Expression::Sub(Box::new(Expression::Const(0)), Box::new(operand))
+                },
+                [operand] => {
+                    let operand = self.compile_expression(operand, scratch)?;
+                    Expression::from_unary(functor, operand).ok_or("Not an evaluable functor")?
+                },
+                [left, right] => {
+                    let left = self.compile_expression(left, scratch)?;
+                    let right = self.compile_expression(right, scratch)?;
+                    Expression::from_binary(functor, left, right).ok_or("Not an evaluable functor")?
+                },
+                _ => return Err(Box::from("Not an evaluable functor")),
+            },
+            Term::Atom(_) => return Err(Box::from("Not an evaluable atom")),
         };
-        instructions.extend(code_block);
-        predicate_table.entry(pred_name).or_insert_with(Vec::new).push(addr);
+        Ok(expression)
+    }
+
+    /// Emits instructions that place `term` in `register`.
+    /// Compound terms are assembled from their arguments, which are first placed in
+    /// consecutive registers starting at `scratch`, and the registers above them
+    /// are used to build nested terms.
+    fn put_term(&mut self, term: &Term, register: usize, scratch: usize) -> Result<(), Box<dyn Error>> {
+        match term {
+            Term::Atom(s) => {
+                self.code.push(Instruction::PutStr { register, value: s.clone() });
+            },
+            Term::Number(n) => {
+                self.code.push(Instruction::PutConst { register, value: *n });
+            },
+            Term::BigInt(n) => {
+                self.code.push(Instruction::PutBigInt { register, value: n.clone() });
+            },
+            Term::Float(x) => {
+                self.code.push(Instruction::PutFloat { register, value: *x });
+            },
+            Term::Var(name) => match self.occurrence(name) {
+                (VarHome::Void, _) => {
+                    self.code.push(Instruction::NewVar { register, name: name.clone() });
+                },
+                (VarHome::Register(t), first) => {
+                    if first {
+                        self.code.push(Instruction::NewVar { register: t, name: name.clone() });
+                    }
+                    if t != register {
+                        self.code.push(Instruction::Move { src: t, dst: register });
+                    }
+                },
+                (VarHome::Local(k), true) => {
+                    self.code.push(Instruction::NewVar { register, name: name.clone() });
+                    self.code.push(Instruction::StoreLocal { index: k, register });
+                },
+                (VarHome::Local(k), false) => {
+                    self.code.push(Instruction::PutLocal { index: k, register });
+                },
+            },
+            Term::Compound(functor, args) => {
+                // Compound arguments are built first, each from the registers
+                // above the ones already holding an argument, so the registers
+                // used by an argument are free again once it is built and a
+                // list takes the same few registers whatever its length. The
+                // other arguments are placed afterwards.
+                let mut free = scratch;
+                for (j, arg) in args.iter().enumerate() {
+                    if matches!(arg, Term::Compound(_, _)) {
+                        self.put_term(arg, scratch + j, free)?;
+                        free = scratch + j + 1;
+                    }
+                }
+                for (j, arg) in args.iter().enumerate() {
+                    if !matches!(arg, Term::Compound(_, _)) {
+                        self.put_term(arg, scratch + j, free)?;
+                    }
+                }
+                self.code.push(Instruction::BuildCompound {
+                    target: register,
+                    functor: functor.clone(),
+                    arg_registers: (scratch..scratch + args.len()).collect(),
+                });
+            },
+        }
+        Ok(())
     }
-    
-    Ok((instructions, predicate_table))
 }
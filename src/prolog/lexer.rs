@@ -0,0 +1,368 @@
+// src/prolog/lexer.rs
+//! Tokenizer for Prolog source text.
+//!
+//! The lexer turns source text into a flat list of tokens following the ISO
+//! token syntax:
+//! - names: letter-digit atoms (`foo_1`), graphic atoms (`=..`, `:-`), quoted
+//!   atoms (`'hello world'`) and the solo atoms `!` and `;`,
+//! - variables, starting with an uppercase letter or an underscore,
+//! - integers (including `0'c` character codes and `0x`, `0o`, `0b` literals)
+//!   and floating point numbers,
+//! - double-quoted strings,
+//! - punctuation: `( ) [ ] { } , |`,
+//! - the end token: a `.` followed by layout, a `%` comment or the end of input.
+//!
+//! Layout (whitespace, `%` line comments and `/* */` block comments) is skipped,
+//! but every token records whether layout preceded it, which the parser needs to
+//! tell `foo(` (functional notation) from `foo (` (an operator applied to a
+//! parenthesized term).
+
+use num_bigint::BigInt;
+use num_traits::Num;
+use crate::diagnostic::Span;
+use crate::prolog::parser::ParseError;
+
+/// The kinds of tokens produced by the lexer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// An atom name (unquoted, graphic, quoted or solo).
+    Name(String),
+    /// A variable name.
+    Var(String),
+    /// An integer literal, of any size.
+    Int(BigInt),
+    /// A floating point literal.
+    Float(f64),
+    /// A double-quoted string, with escapes already processed.
+    Str(String),
+    /// A punctuation character: one of `( ) [ ] { } , |`.
+    Punct(char),
+    /// The end of a clause.
+    End,
+}
+
+/// A token together with its position in the source text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    /// What the token is.
+    pub kind: TokenKind,
+    /// Byte offset of the first character of the token.
+    pub start: usize,
+    /// Byte offset just past the last character of the token.
+    pub end: usize,
+    /// Whether layout text (whitespace or comments) precedes the token.
+    pub layout_before: bool,
+}
+
+/// Characters that may appear in graphic atoms such as `:-` or `=..`.
+fn is_graphic(c: char) -> bool {
+    "#$&*+-./:<=>?@^~\\".contains(c)
+}
+
+/// Characters that may appear after the first character of an unquoted name or variable.
+fn is_alphanumeric(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A single pass tokenizer over a source string.
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    /// Start of the token (or comment) being read, where errors are reported.
+    token_start: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Reports an error spanning the text read since the start of the current token.
+    fn error(&self, msg: String) -> ParseError {
+        // Cover at least the first character, even if nothing has been consumed yet.
+        let first_len = self.src[self.token_start..].chars().next().map_or(0, char::len_utf8);
+        let end = self.pos.max(self.token_start + first_len);
+        ParseError::UnexpectedToken(msg, Span::new(self.src, self.token_start, end))
+    }
+
+    /// Skips whitespace and comments. Returns whether anything was skipped.
+    fn skip_layout(&mut self) -> Result<bool, ParseError> {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                },
+                Some('%') => {
+                    while let Some(c) = self.bump() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                },
+                Some('/') if self.peek_at(1) == Some('*') => {
+                    self.token_start = self.pos;
+                    self.pos += 2;
+                    match self.src[self.pos..].find("*/") {
+                        Some(end) => self.pos += end + 2,
+                        None => {
+                            self.pos = self.token_start + 2;
+                            return Err(self.error("Unterminated block comment".to_string()));
+                        },
+                    }
+                },
+                _ => return Ok(self.pos > start),
+            }
+        }
+    }
+
+    /// Reads the next token, or returns `None` at the end of the input.
+    fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        let layout_before = self.skip_layout()?;
+        let start = self.pos;
+        self.token_start = start;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let kind = if c.is_ascii_digit() {
+            self.number()?
+        } else if c == '_' || c.is_uppercase() {
+            TokenKind::Var(self.take_while(is_alphanumeric))
+        } else if c.is_alphabetic() {
+            TokenKind::Name(self.take_while(is_alphanumeric))
+        } else if c == '\'' {
+            self.bump();
+            TokenKind::Name(self.quoted('\'')?)
+        } else if c == '"' {
+            self.bump();
+            TokenKind::Str(self.quoted('"')?)
+        } else if c == '.' && self.peek_at(1).is_none_or(|n| n.is_whitespace() || n == '%') {
+            self.bump();
+            TokenKind::End
+        } else if is_graphic(c) {
+            TokenKind::Name(self.graphic())
+        } else if c == '!' || c == ';' {
+            self.bump();
+            TokenKind::Name(c.to_string())
+        } else if "()[]{},|".contains(c) {
+            self.bump();
+            TokenKind::Punct(c)
+        } else {
+            return Err(self.error(format!("Unexpected character '{}'", c)));
+        };
+        Ok(Some(Token { kind, start, end: self.pos, layout_before }))
+    }
+
+    fn take_while(&mut self, pred: fn(char) -> bool) -> String {
+        let start = self.pos;
+        // The first character has already been checked by the caller.
+        self.bump();
+        while let Some(c) = self.peek() {
+            if !pred(c) {
+                break;
+            }
+            self.bump();
+        }
+        self.src[start..self.pos].to_string()
+    }
+
+    /// Reads a graphic atom. It never swallows the start of a block comment.
+    fn graphic(&mut self) -> String {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_graphic(c) || (c == '/' && self.peek_at(1) == Some('*')) {
+                break;
+            }
+            self.bump();
+        }
+        self.src[start..self.pos].to_string()
+    }
+
+    /// Reads an integer or float literal, including `0'c` character codes and
+    /// `0x`, `0o` and `0b` prefixed integers.
+    fn number(&mut self) -> Result<TokenKind, ParseError> {
+        let start = self.pos;
+        if self.peek() == Some('0') {
+            match self.peek_at(1) {
+                Some('\'') => {
+                    self.pos += 2;
+                    return self.char_code();
+                },
+                Some(prefix @ ('x' | 'o' | 'b')) => {
+                    let radix = match prefix {
+                        'x' => 16,
+                        'o' => 8,
+                        _ => 2,
+                    };
+                    if self.peek_at(2).is_some_and(|c| c.is_digit(radix)) {
+                        self.pos += 2;
+                        let digits_start = self.pos;
+                        while self.peek().is_some_and(|c| c.is_digit(radix)) {
+                            self.bump();
+                        }
+                        let n = BigInt::from_str_radix(&self.src[digits_start..self.pos], radix).expect("digits of the radix");
+                        return Ok(TokenKind::Int(n));
+                    }
+                },
+                _ => {},
+            }
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        // A fraction needs a digit after the dot; otherwise the dot ends the clause.
+        let is_float = self.peek() == Some('.') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit());
+        if !is_float {
+            let n = self.src[start..self.pos].parse::<BigInt>().expect("decimal digits");
+            return Ok(TokenKind::Int(n));
+        }
+        self.bump();
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let exponent_digit = match self.peek_at(1) {
+                Some('+' | '-') => self.peek_at(2),
+                other => other,
+            };
+            if exponent_digit.is_some_and(|c| c.is_ascii_digit()) {
+                self.bump();
+                if matches!(self.peek(), Some('+' | '-')) {
+                    self.bump();
+                }
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.bump();
+                }
+            }
+        }
+        match self.src[start..self.pos].parse::<f64>() {
+            Ok(x) if x.is_finite() => Ok(TokenKind::Float(x)),
+            _ => Err(self.error(format!("Invalid number: {}", &self.src[start..self.pos]))),
+        }
+    }
+
+    /// Reads the character after `0'` and returns its code.
+    fn char_code(&mut self) -> Result<TokenKind, ParseError> {
+        let c = match self.bump() {
+            Some('\\') => match self.escape('\'')? {
+                Some(c) => c,
+                None => return Err(self.error("Invalid character code".to_string())),
+            },
+            // Both 0'' and 0''' denote the code of the quote character.
+            Some('\'') => {
+                if self.peek() == Some('\'') {
+                    self.bump();
+                }
+                '\''
+            },
+            Some(c) => c,
+            None => return Err(self.error("Unexpected end of input in character code".to_string())),
+        };
+        Ok(TokenKind::Int(BigInt::from(c as u32)))
+    }
+
+    /// Reads the body of a quoted item up to the closing `quote`, processing
+    /// escape sequences and doubled quotes.
+    fn quoted(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                None => {
+                    return Err(self.error(format!("Unterminated quoted {}", if quote == '"' { "string" } else { "atom" })));
+                },
+                Some(c) if c == quote => {
+                    if self.peek() == Some(quote) {
+                        self.bump();
+                        text.push(quote);
+                    } else {
+                        return Ok(text);
+                    }
+                },
+                Some('\\') => {
+                    if let Some(c) = self.escape(quote)? {
+                        text.push(c);
+                    }
+                },
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    /// Reads an escape sequence (after the backslash). Returns `None` for a
+    /// line continuation, which contributes no character.
+    fn escape(&mut self, quote: char) -> Result<Option<char>, ParseError> {
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Err(self.error("Unexpected end of input in escape sequence".to_string())),
+        };
+        let escaped = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'a' => '\u{7}',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            'e' => '\u{1b}',
+            's' => ' ',
+            '0'..='7' => self.numeric_escape(c, 8)?,
+            'x' => self.numeric_escape_hex()?,
+            '\n' => return Ok(None),
+            '\\' | '\'' | '"' | '`' => c,
+            other if other == quote => other,
+            other => return Err(self.error(format!("Unknown escape sequence '\\{}'", other))),
+        };
+        Ok(Some(escaped))
+    }
+
+    /// Reads an octal escape `\NNN\` whose first digit has already been consumed.
+    fn numeric_escape(&mut self, first: char, radix: u32) -> Result<char, ParseError> {
+        let mut digits = first.to_string();
+        while let Some(c) = self.peek().filter(|c| c.is_digit(radix)) {
+            digits.push(c);
+            self.bump();
+        }
+        self.close_numeric_escape(&digits, radix)
+    }
+
+    /// Reads a hexadecimal escape `\xHH\` after the `x`.
+    fn numeric_escape_hex(&mut self) -> Result<char, ParseError> {
+        let mut digits = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_hexdigit()) {
+            digits.push(c);
+            self.bump();
+        }
+        self.close_numeric_escape(&digits, 16)
+    }
+
+    fn close_numeric_escape(&mut self, digits: &str, radix: u32) -> Result<char, ParseError> {
+        if self.peek() == Some('\\') {
+            self.bump();
+        }
+        u32::from_str_radix(digits, radix)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(format!("Invalid character code in escape sequence: {}", digits)))
+    }
+}
+
+/// Splits Prolog source text into tokens.
+pub fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    let mut lexer = Lexer { src, pos: 0, token_start: 0 };
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
@@ -1,245 +1,442 @@
 // src/prolog/parser.rs
-//! A minimal Prolog parser.
+//! An operator-precedence reader for Prolog text.
 //!
-//! This parser now supports multi–line clauses while enforcing that full
-//! statements always end with a period. It does so by:
-//! 1. Removing comment lines (lines starting with `%`, `//`, or `?-`).
-//! 2. Joining the remaining lines into one long string.
-//! 3. Verifying that the joined string ends with a period (if not, an error is raised).
-//! 4. Splitting the string into clauses using a custom function that splits on
-//!    periods ('.') that are not part of a decimal number.
+//! The source is first split into tokens by the lexer (see `prolog::lexer`) and
+//! then read term by term with a standard operator-precedence parser driven by an
+//! operator table (see `machine::ops`). The reader supports:
+//! - the ISO operator table (`:-`, `,`, `;`, `->`, `\+`, `is`, comparison and
+//!   arithmetic operators), including prefix operators and negative numbers,
+//! - functional notation `f(a, b)` and parenthesized terms,
+//! - lists `[a, b | T]`, represented as `'.'(H, T)` cells ending in `[]`,
+//! - curly terms `{T}`, represented as `'{}'(T)`,
+//! - quoted atoms with escape sequences, and `0'c` character codes,
+//! - double-quoted strings, read as lists of character codes,
+//! - `%` line comments and `/* */` block comments.
 //!
-//! Each non–empty clause is then trimmed and parsed. The parser supports:
-//! - Facts and rules (using ":-" for rules)
-//! - Infix operator "=" (at the top level) by producing a compound term with functor "=" and two arguments.
-//! - Single–quoted strings (parsed as atoms), numbers, variables (starting with uppercase or underscore),
-//!   compound terms, and atoms.
+//! A clause ends with a `.` followed by layout or the end of the input, so a
+//! period inside a quoted atom, a graphic atom such as `=..` or a float is never
+//! mistaken for the end of a clause.
+//!
+//! Clauses of the form `Head :- Body` become rules whose body is the list of goals
+//! of the top-level conjunction; `:- Goal` is a directive and any other clause is
+//! a fact. Queries (`?- Goal.`) are skipped.
+//!
+//! The operator table belongs to the parser state: an `op/3` directive such as
+//! `:- op(700, xfx, ===>).` takes effect immediately, so it changes how the
+//! clauses after it are read.
+//!
+//! Every error carries the `Span` of the offending token (or, for an invalid
+//! clause, of the whole clause), so callers can point at it in the source.
 
+use std::fmt;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use crate::diagnostic::Span;
 use crate::prolog::ast::{Clause, Term};
+use crate::prolog::lexer::{tokenize, Token, TokenKind};
+use crate::machine::ops::OpTable;
 
 /// Errors that can occur during parsing.
 #[derive(Debug)]
 pub enum ParseError {
     /// An expected token was missing or the syntax was incorrect.
-    UnexpectedToken(String),
+    UnexpectedToken(String, Span),
     /// The input was empty.
-    IncompleteInput,
+    IncompleteInput(Span),
 }
 
-/// Splits a string on commas that are not inside parentheses or quotes.
-/// This ensures that commas inside compound terms are not treated as separators.
-fn split_goals(s: &str) -> Vec<&str> {
-    let mut goals = Vec::new();
-    let mut start = 0;
-    let mut paren_count = 0;
-    let mut in_quote = false;
-    for (i, c) in s.char_indices() {
-        match c {
-            '\'' => in_quote = !in_quote,
-            '(' if !in_quote => paren_count += 1,
-            ')' if !in_quote => {
-                if paren_count > 0 {
-                    paren_count -= 1;
-                }
-            }
-            ',' if paren_count == 0 && !in_quote => {
-                goals.push(&s[start..i]);
-                start = i + 1;
-            }
-            _ => {}
+impl ParseError {
+    /// Returns the description of the error, without its position.
+    pub fn message(&self) -> String {
+        match self {
+            ParseError::UnexpectedToken(msg, _) => msg.clone(),
+            ParseError::IncompleteInput(_) => "Empty input".to_string(),
         }
     }
-    if start < s.len() {
-        goals.push(&s[start..]);
+
+    /// Returns the location of the error in the source text.
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken(_, span) | ParseError::IncompleteInput(span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "line {}, column {}: {}", span.line, span.column, self.message())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Maximum priority of a term.
+const MAX_PRIORITY: u32 = 1200;
+/// Maximum priority of an argument of a compound term or a list element.
+const ARG_PRIORITY: u32 = 999;
+
+/// Describes a token for error messages.
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Name(name) => format!("atom '{}'", name),
+        TokenKind::Var(name) => format!("variable {}", name),
+        TokenKind::Int(n) => format!("number {}", n),
+        TokenKind::Float(x) => format!("number {}", x),
+        TokenKind::Str(s) => format!("string \"{}\"", s),
+        TokenKind::Punct(c) => format!("'{}'", c),
+        TokenKind::End => "end of clause".to_string(),
+    }
+}
+
+/// Builds a list term from its elements and tail.
+fn make_list(items: Vec<Term>, tail: Term) -> Term {
+    items.into_iter().rev().fold(tail, |tail, item| {
+        Term::Compound(".".to_string(), vec![item, tail])
+    })
+}
+
+/// Converts an integer literal to a term: a `Number` if it fits in an `i32`
+/// and a `BigInt` otherwise.
+fn make_number(n: BigInt) -> Term {
+    match n.to_i32() {
+        Some(n) => Term::Number(n),
+        None => Term::BigInt(n),
     }
-    goals.into_iter().map(|g| g.trim()).filter(|g| !g.is_empty()).collect()
 }
 
-/// Splits a string on the first occurrence of the infix operator `op` that is not inside parentheses or quotes.
-/// Returns Some((left, right)) if found, otherwise None.
-fn split_infix_operator(s: &str, op: char) -> Option<(String, String)> {
-    let mut nest = 0;
-    let mut in_quote = false;
-    for (i, c) in s.char_indices() {
-        match c {
-            '\'' => in_quote = !in_quote,
-            '(' if !in_quote => nest += 1,
-            ')' if !in_quote => {
-                if nest > 0 {
-                    nest -= 1;
+/// Reads terms from a token stream.
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    ops: OpTable,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, ops: OpTable) -> Result<Self, ParseError> {
+        Ok(Parser { src: input, tokens: tokenize(input)?, pos: 0, ops })
+    }
+
+    /// Returns the span of the source text between two byte offsets.
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.src, start, end)
+    }
+
+    /// Reports an error at `token`.
+    fn error_at(&self, token: &Token, msg: String) -> ParseError {
+        ParseError::UnexpectedToken(msg, self.span(token.start, token.end))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_kind(&self) -> Option<&TokenKind> {
+        self.peek().map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| {
+            // Point just past the last token, where the period is missing.
+            let end = self.tokens.last().map_or(self.src.len(), |token| token.end);
+            ParseError::UnexpectedToken("Clause must end with a period".to_string(), self.span(end, end))
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    /// Consumes the punctuation character `c` or fails.
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        let token = self.next()?;
+        if token.kind == TokenKind::Punct(c) {
+            Ok(())
+        } else {
+            Err(self.error_at(&token, format!("Expected '{}' but found {}", c, describe(&token.kind))))
+        }
+    }
+
+    /// Reads the next clause term, including its terminating period, together with
+    /// the span of the clause. Returns `None` when the input is exhausted.
+    fn read_clause(&mut self) -> Result<Option<(Term, Span)>, ParseError> {
+        let start = match self.peek() {
+            Some(token) => token.start,
+            None => return Ok(None),
+        };
+        let (term, _) = self.parse(MAX_PRIORITY)?;
+        let token = self.next()?;
+        if token.kind != TokenKind::End {
+            return Err(self.error_at(&token, format!("Unexpected {} after a complete term", describe(&token.kind))));
+        }
+        Ok(Some((term, self.span(start, token.end))))
+    }
+
+    /// Parses a term whose priority is at most `max`. Returns the term and its priority.
+    fn parse(&mut self, max: u32) -> Result<(Term, u32), ParseError> {
+        let (mut left, mut left_priority) = self.parse_primary(max)?;
+        loop {
+            let name = match self.peek_kind() {
+                Some(TokenKind::Name(name)) => name.clone(),
+                Some(TokenKind::Punct(',')) => ",".to_string(),
+                Some(TokenKind::Punct('|')) => "|".to_string(),
+                _ => break,
+            };
+            if let Some((priority, op_type)) = self.ops.infix(&name) {
+                let (left_max, right_max) = op_type.arg_priorities(priority);
+                if priority <= max && left_priority <= left_max {
+                    self.pos += 1;
+                    let (right, _) = self.parse(right_max)?;
+                    // An infix bar is read as a disjunction.
+                    let functor = if name == "|" { ";".to_string() } else { name };
+                    left = Term::Compound(functor, vec![left, right]);
+                    left_priority = priority;
+                    continue;
                 }
             }
-            c if c == op && nest == 0 && !in_quote => {
-                let left = s[..i].trim().to_string();
-                let right = s[i + 1..].trim().to_string();
-                return Some((left, right));
+            if let Some((priority, op_type)) = self.ops.postfix(&name) {
+                let (left_max, _) = op_type.arg_priorities(priority);
+                if priority <= max && left_priority <= left_max {
+                    self.pos += 1;
+                    left = Term::Compound(name, vec![left]);
+                    left_priority = priority;
+                    continue;
+                }
             }
-            _ => {}
+            break;
         }
+        Ok((left, left_priority))
     }
-    None
-}
 
-/// Splits a string on the period ('.') character that is not part of a decimal number,
-/// and that occurs at the outermost level (i.e. not inside quotes or parentheses).
-///
-/// A period is considered part of a decimal if it is preceded and followed by a digit.
-fn split_clauses(s: &str) -> Vec<String> {
-    let mut clauses = Vec::new();
-    let mut start = 0;
-    let mut in_quote = false;
-    let mut paren_count = 0;
-    let chars: Vec<char> = s.chars().collect();
-    let len = chars.len();
-
-    for i in 0..len {
-        let c = chars[i];
-        match c {
-            '\'' => in_quote = !in_quote,
-            '(' if !in_quote => paren_count += 1,
-            ')' if !in_quote => {
-                if paren_count > 0 {
-                    paren_count -= 1;
+    /// Parses a primary term: a literal, a variable, a parenthesized term, a list,
+    /// a curly term, a compound term in functional notation or a prefix operator
+    /// application.
+    fn parse_primary(&mut self, max: u32) -> Result<(Term, u32), ParseError> {
+        let token = self.next()?;
+        match token.kind.clone() {
+            TokenKind::Int(n) => Ok((make_number(n), 0)),
+            TokenKind::Float(x) => Ok((Term::Float(x), 0)),
+            TokenKind::Str(s) => {
+                let codes = s.chars().map(|c| Term::Number(c as i32)).collect();
+                Ok((make_list(codes, Term::Atom("[]".to_string())), 0))
+            },
+            TokenKind::Var(name) => Ok((Term::Var(name), 0)),
+            TokenKind::Name(name) => self.parse_name(name, max),
+            TokenKind::Punct('(') => {
+                let (term, _) = self.parse(MAX_PRIORITY)?;
+                self.expect(')')?;
+                Ok((term, 0))
+            },
+            TokenKind::Punct('[') => {
+                if self.peek_kind() == Some(&TokenKind::Punct(']')) {
+                    self.pos += 1;
+                    return self.parse_name("[]".to_string(), max);
                 }
-            }
-            '.' if !in_quote && paren_count == 0 => {
-                // Check if this period is part of a decimal.
-                let is_decimal = if i > 0 && i + 1 < len {
-                    chars[i - 1].is_digit(10) && chars[i + 1].is_digit(10)
+                let mut items = vec![self.parse(ARG_PRIORITY)?.0];
+                while self.peek_kind() == Some(&TokenKind::Punct(',')) {
+                    self.pos += 1;
+                    items.push(self.parse(ARG_PRIORITY)?.0);
+                }
+                let tail = if self.peek_kind() == Some(&TokenKind::Punct('|')) {
+                    self.pos += 1;
+                    self.parse(ARG_PRIORITY)?.0
                 } else {
-                    false
+                    Term::Atom("[]".to_string())
                 };
-                if !is_decimal {
-                    let clause = s[start..i].trim();
-                    if !clause.is_empty() {
-                        clauses.push(clause.to_string());
-                    }
-                    start = i + 1;
+                self.expect(']')?;
+                Ok((make_list(items, tail), 0))
+            },
+            TokenKind::Punct('{') => {
+                if self.peek_kind() == Some(&TokenKind::Punct('}')) {
+                    self.pos += 1;
+                    return self.parse_name("{}".to_string(), max);
                 }
+                let (term, _) = self.parse(MAX_PRIORITY)?;
+                self.expect('}')?;
+                Ok((Term::Compound("{}".to_string(), vec![term]), 0))
+            },
+            kind => Err(self.error_at(&token, format!("Unexpected {}", describe(&kind)))),
+        }
+    }
+
+    /// Parses what follows an atom name: functional notation, a negative number,
+    /// a prefix operator application, or nothing (a plain atom).
+    fn parse_name(&mut self, name: String, max: u32) -> Result<(Term, u32), ParseError> {
+        let next = self.peek().cloned();
+        if let Some(Token { kind: TokenKind::Punct('('), layout_before: false, .. }) = next {
+            self.pos += 1;
+            let mut args = vec![self.parse(ARG_PRIORITY)?.0];
+            while self.peek_kind() == Some(&TokenKind::Punct(',')) {
+                self.pos += 1;
+                args.push(self.parse(ARG_PRIORITY)?.0);
+            }
+            self.expect(')')?;
+            return Ok((Term::Compound(name, args), 0));
+        }
+        if name == "-" {
+            if let Some(Token { kind: TokenKind::Int(n), layout_before: false, .. }) = next {
+                self.pos += 1;
+                return Ok((make_number(-n), 0));
+            }
+            if let Some(Token { kind: TokenKind::Float(x), layout_before: false, .. }) = next {
+                self.pos += 1;
+                return Ok((Term::Float(-x), 0));
+            }
+        }
+        if let Some((priority, op_type)) = self.ops.prefix(&name) {
+            if self.next_starts_term() {
+                // Be lenient with prefix operators whose priority exceeds the context,
+                // as in `X = \+ a`: the application is treated as if it were bracketed.
+                let (_, arg_max) = op_type.arg_priorities(priority);
+                let priority = priority.min(max);
+                let (arg, _) = self.parse(arg_max.min(max))?;
+                return Ok((Term::Compound(name, vec![arg]), priority));
             }
-            _ => {}
         }
+        Ok((Term::Atom(name), 0))
     }
-    if start < len {
-        let clause = s[start..].trim();
-        if !clause.is_empty() {
-            clauses.push(clause.to_string());
+
+    /// Returns true if the next token can start the operand of a prefix operator.
+    fn next_starts_term(&self) -> bool {
+        match self.peek_kind() {
+            None | Some(TokenKind::End) => false,
+            Some(TokenKind::Punct(c)) => matches!(c, '(' | '[' | '{'),
+            Some(TokenKind::Name(name)) => {
+                let is_infix = self.ops.infix(name).is_some() || self.ops.postfix(name).is_some();
+                !is_infix || self.ops.prefix(name).is_some()
+            },
+            Some(_) => true,
         }
     }
-    clauses
 }
 
-/// Splits the input string into clauses. This function first removes comment and query lines,
-/// joins the remaining lines into one string, and then splits that string into clauses using `split_clauses`.
-/// It then enforces that the program text must end with a period.
-pub fn split_program_into_clauses(input: &str) -> Result<Vec<String>, ParseError> {
-    let mut program = String::new();
-    for line in input.lines() {
-        let trimmed = line.trim();
-        // Skip lines that are comments or queries.
-        if trimmed.starts_with('%') || trimmed.starts_with("//") || trimmed.starts_with("?-") {
-            continue;
+/// Splits a conjunction `(A, B, ...)` into its goals.
+fn conjunction_goals(term: Term, goals: &mut Vec<Term>) {
+    match term {
+        Term::Compound(functor, args) if functor == "," && args.len() == 2 => {
+            let mut args = args.into_iter();
+            conjunction_goals(args.next().unwrap(), goals);
+            conjunction_goals(args.next().unwrap(), goals);
+        },
+        goal => goals.push(goal),
+    }
+}
+
+/// Checks that a clause head is an atom or a compound term.
+fn check_head(head: &Term) -> Result<(), String> {
+    match head {
+        Term::Atom(_) | Term::Compound(_, _) => Ok(()),
+        other => Err(format!("Invalid clause head: {:?}", other)),
+    }
+}
+
+/// Returns the elements of a proper list term, or `None` if the term is not a list.
+fn list_items(term: &Term) -> Option<Vec<&Term>> {
+    let mut items = Vec::new();
+    let mut current = term;
+    loop {
+        match current {
+            Term::Atom(name) if name == "[]" => return Some(items),
+            Term::Compound(functor, args) if functor == "." && args.len() == 2 => {
+                items.push(&args[0]);
+                current = &args[1];
+            },
+            _ => return None,
         }
-        program.push_str(trimmed);
-        program.push(' ');
     }
-    let program = program.trim().to_string();
-    if program.is_empty() {
-        return Err(ParseError::IncompleteInput);
+}
+
+/// Applies the operator definitions of an `op(Priority, Type, Names)` directive
+/// to the operator table. `Names` is an atom or a list of atoms.
+fn apply_op_directive(ops: &mut OpTable, args: &[Term]) -> Result<(), String> {
+    let invalid = |msg: String| format!("Invalid op/3 directive: {}", msg);
+    let priority = match &args[0] {
+        Term::Number(n) => *n as i64,
+        other => return Err(invalid(format!("priority must be an integer, got {:?}", other))),
+    };
+    let op_type = match &args[1] {
+        Term::Atom(name) => name.as_str(),
+        other => return Err(invalid(format!("type must be an atom, got {:?}", other))),
+    };
+    let names = match &args[2] {
+        Term::Atom(name) if name != "[]" => vec![&args[2]],
+        other => list_items(other).ok_or_else(|| invalid(format!("expected an atom or a list of atoms, got {:?}", other)))?,
+    };
+    for name in names {
+        match name {
+            Term::Atom(name) => ops.define(priority, op_type, name).map_err(invalid)?,
+            other => return Err(invalid(format!("operator name must be an atom, got {:?}", other))),
+        }
     }
-    // Enforce that the program ends with a period.
-    if !program.ends_with('.') {
-        return Err(ParseError::UnexpectedToken("Clause must end with a period".to_string()));
+    Ok(())
+}
+
+/// Converts a clause term read from the source into a clause.
+/// Returns `None` for queries, which are not part of the program.
+/// `op/3` directives are applied to `ops` before being returned. Errors are
+/// reported as messages; the caller locates them at the clause.
+fn term_to_clause(term: Term, ops: &mut OpTable) -> Result<Option<Clause>, String> {
+    match term {
+        Term::Compound(functor, args) if functor == ":-" && args.len() == 2 => {
+            let mut args = args.into_iter();
+            let head = args.next().unwrap();
+            check_head(&head)?;
+            let mut body = Vec::new();
+            conjunction_goals(args.next().unwrap(), &mut body);
+            Ok(Some(Clause::Rule { head, body }))
+        },
+        Term::Compound(functor, args) if functor == "?-" && args.len() == 1 => Ok(None),
+        Term::Compound(functor, args) if functor == ":-" && args.len() == 1 => {
+            let goal = args.into_iter().next().unwrap();
+            if let Term::Compound(name, op_args) = &goal {
+                if name == "op" && op_args.len() == 3 {
+                    apply_op_directive(ops, op_args)?;
+                }
+            }
+            Ok(Some(Clause::Directive { goal }))
+        },
+        head => {
+            check_head(&head)?;
+            Ok(Some(Clause::Fact { head }))
+        },
     }
-    Ok(split_clauses(&program))
 }
 
-/// Parses a single term from the given input string.
+/// Parses a single term from the given input string, using the standard operator table.
+/// A terminating period is optional.
 ///
-/// Supported term types:
-/// - **Single-quoted strings:** e.g. `'Hello world'` is parsed as `Atom("Hello world")`.
-/// - **Variables:** A token that starts with an uppercase letter or underscore.
-/// - **Numbers:** Parsed as integers. (Decimal numbers containing a period will later be supported.)
-/// - **Compound terms:** A functor followed by a parenthesized, comma-separated list of arguments.
-/// - **Infix operator "=":** If the input contains a top–level "=" not inside quotes or parentheses,
-///   it is parsed as a compound term with functor "=" and two arguments.
-/// - **Atoms:** All other tokens.
+/// Examples of accepted input: `42`, `X`, `john`, `father(john, X)`, `X = [1, 2 | T]`.
 pub fn parse_term(input: &str) -> Result<Term, ParseError> {
-    let s = input.trim();
-    if s.is_empty() {
-        return Err(ParseError::IncompleteInput);
-    }
-    // If not a quoted string, check for a top–level infix "=".
-    if !s.starts_with('\'') {
-        if let Some((left, right)) = split_infix_operator(s, '=') {
-            let left_term = parse_term(&left)?;
-            let right_term = parse_term(&right)?;
-            return Ok(Term::Compound("=".to_string(), vec![left_term, right_term]));
-        }
+    let mut parser = Parser::new(input, OpTable::default())?;
+    if parser.at_end() {
+        return Err(ParseError::IncompleteInput(parser.span(input.len(), input.len())));
     }
-    // Handle single-quoted strings.
-    if s.starts_with('\'') && s.ends_with('\'') && s.len() >= 2 {
-        return Ok(Term::Atom(s[1..s.len()-1].to_string()));
-    }
-    // Variables: tokens starting with uppercase or underscore.
-    let first = s.chars().next().unwrap();
-    if first.is_uppercase() || first == '_' {
-        return Ok(Term::Var(s.to_string()));
-    }
-    // Numbers: tokens starting with a digit.
-    if first.is_digit(10) {
-        return s.parse::<i32>()
-            .map(Term::Number)
-            .map_err(|_| ParseError::UnexpectedToken(format!("Invalid number: {}", s)));
-    }
-    // Compound term: check for '('.
-    if let Some(pos) = s.find('(') {
-        let functor = s[..pos].trim().to_string();
-        // Assume the closing parenthesis is the last character.
-        let args_str = s[pos+1..].trim_end_matches(')').trim();
-        let args: Result<Vec<_>, _> = if args_str.is_empty() {
-            Ok(vec![])
-        } else {
-            let parts = split_goals(args_str);
-            parts.into_iter().map(|arg| parse_term(arg)).collect()
-        };
-        return Ok(Term::Compound(functor, args?));
+    let (term, _) = parser.parse(MAX_PRIORITY)?;
+    if parser.peek_kind() == Some(&TokenKind::End) {
+        parser.pos += 1;
+    }
+    match parser.peek() {
+        None => Ok(term),
+        Some(token) => Err(parser.error_at(token, format!("Unexpected {} after a complete term", describe(&token.kind)))),
     }
-    // Otherwise, treat the token as an atom.
-    Ok(Term::Atom(s.to_string()))
 }
 
 /// Parses an entire Prolog program from the given input string.
 ///
-/// This parser supports multi–line clauses by first removing comment and query lines,
-/// then joining the remaining lines into a single string, splitting that string into clauses
-/// using the period ('.') as a delimiter (ignoring periods in decimals), and then parsing each clause.
-/// 
-/// Facts are clauses that do not contain ":-"; rules are clauses that contain ":-".
+/// Each clause must end with a period. Facts are clauses without `:-`; rules are
+/// clauses of the form `Head :- Body`; directives have the form `:- Goal`.
+/// Queries (`?- Goal.`) are skipped.
 pub fn parse_program(input: &str) -> Result<Vec<Clause>, ParseError> {
-    let clauses_str = split_program_into_clauses(input)?;
-
+    let mut parser = Parser::new(input, OpTable::default())?;
     let mut clauses = Vec::new();
-    for clause in clauses_str {
-        // Check if the clause contains ":-" to decide if it is a rule.
-        if clause.contains(":-") {
-            let parts: Vec<&str> = clause.split(":-").collect();
-            if parts.len() != 2 {
-                return Err(ParseError::UnexpectedToken(format!("Invalid clause syntax: {}", clause)));
-            }
-            let head_str = parts[0].trim();
-            let body_str = parts[1].trim();
-            let head = parse_term(head_str)?;
-            let body_parts = split_goals(body_str);
-            let mut body = Vec::new();
-            for part in body_parts {
-                let term = parse_term(part)?;
-                body.push(term);
-            }
-            clauses.push(Clause::Rule { head, body });
-        } else {
-            let head = parse_term(&clause)?;
-            clauses.push(Clause::Fact { head });
+    while let Some((term, span)) = parser.read_clause()? {
+        let clause = term_to_clause(term, &mut parser.ops)
+            .map_err(|msg| ParseError::UnexpectedToken(msg, span))?;
+        if let Some(clause) = clause {
+            clauses.push(clause);
         }
     }
     Ok(clauses)
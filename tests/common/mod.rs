@@ -0,0 +1,39 @@
+// tests/common/mod.rs
+//! Helpers shared by the integration tests. Each test file uses only some of
+//! them.
+#![allow(dead_code)]
+
+use lam::machine::core::Machine;
+use lam::machine::program::Program;
+use lam::machine::term::Term;
+use lam::prolog::compiler::compile_prolog;
+
+/// Compiles a Prolog program into a machine with the registers it uses, ready
+/// to run from its `main` entry point.
+pub fn compiled_machine(source: &str) -> Machine {
+    let (code, predicate_table, num_registers) = compile_prolog(source).expect("Program should compile");
+    Program { instructions: code, predicate_table, ..Program::default() }.into_machine(num_registers)
+}
+
+/// Creates a machine whose fresh variables are numbered from 100, above the
+/// variable ids and lambda parameters written in the tests.
+pub fn new_machine() -> Machine {
+    let mut machine = Machine::new(1, vec![]);
+    machine.next_var_id = 100;
+    machine
+}
+
+/// The variable with the given id.
+pub fn var(id: usize) -> Term {
+    Term::Var(id)
+}
+
+/// The abstraction `\param. body`.
+pub fn lambda(param: usize, body: Term) -> Term {
+    Term::Lambda(param, Box::new(body))
+}
+
+/// The application of `fun` to `arg`.
+pub fn app(fun: Term, arg: Term) -> Term {
+    Term::App(Box::new(fun), Box::new(arg))
+}
@@ -1,7 +1,20 @@
 #[cfg(test)]
 mod tests {
-    use lam::machine::arithmetic::{Expression, evaluate, parse_expression};
-    use lam::machine::error_handling::MachineError;
+    use lam::machine::arithmetic::{Expression, evaluate, evaluate_term, parse_expression};
+    use lam::machine::error_handling::{IsoError, MachineError};
+    use lam::machine::number::{Float, Number};
+    use lam::machine::term::Term;
+
+    fn eval(input: &str) -> Result<Number, MachineError> {
+        evaluate(&parse_expression(input).unwrap(), &[])
+    }
+
+    fn evaluation_error(result: Result<Number, MachineError>) -> String {
+        match result {
+            Err(MachineError::Iso(IsoError::Evaluation(error))) => error,
+            other => panic!("Expected an evaluation error, got {:?}", other),
+        }
+    }
 
     // In these tests, our arithmetic expressions only use constants
     // so we can pass an empty vector (or a vector of Nones) as registers.
@@ -13,7 +26,7 @@ mod tests {
     #[test]
     fn test_evaluate_constant() {
         let expr = Expression::Const(42);
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 42);
     }
 
@@ -23,7 +36,7 @@ mod tests {
             Box::new(Expression::Const(3)),
             Box::new(Expression::Const(4)),
         );
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 7);
     }
 
@@ -33,7 +46,7 @@ mod tests {
             Box::new(Expression::Const(10)),
             Box::new(Expression::Const(3)),
         );
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 7);
     }
 
@@ -43,7 +56,7 @@ mod tests {
             Box::new(Expression::Const(6)),
             Box::new(Expression::Const(7)),
         );
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 42);
     }
 
@@ -53,7 +66,7 @@ mod tests {
             Box::new(Expression::Const(20)),
             Box::new(Expression::Const(5)),
         );
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 4);
     }
 
@@ -63,11 +76,9 @@ mod tests {
             Box::new(Expression::Const(10)),
             Box::new(Expression::Const(0)),
         );
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         match result {
-            Err(MachineError::UnificationFailed(msg)) => {
-                assert_eq!(msg, "Division by zero".to_string())
-            },
+            Err(MachineError::Iso(IsoError::Evaluation(error))) => assert_eq!(error, "zero_divisor"),
             _ => panic!("Expected division by zero error"),
         }
     }
@@ -82,7 +93,7 @@ mod tests {
         let expr = parse_expression(input).unwrap();
         // Expect an AST representing a constant 42.
         assert_eq!(expr, Expression::Const(42));
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 42);
     }
 
@@ -95,7 +106,7 @@ mod tests {
             Box::new(Expression::Const(4)),
         );
         assert_eq!(expr, expected);
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 7);
     }
 
@@ -105,7 +116,7 @@ mod tests {
         // 3+4*2 should be parsed as 3+(4*2)=11.
         let input = "3+4*2";
         let expr = parse_expression(input).unwrap();
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 11);
     }
 
@@ -114,7 +125,7 @@ mod tests {
         // Parentheses override default precedence: (3+4)*2 = 14.
         let input = "(3+4)*2";
         let expr = parse_expression(input).unwrap();
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 14);
     }
 
@@ -124,7 +135,7 @@ mod tests {
         // For example, "-3+5" is parsed as (0-3)+5 which equals 2.
         let input = "-3+5";
         let expr = parse_expression(input).unwrap();
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 2);
     }
 
@@ -133,7 +144,7 @@ mod tests {
         // A more complex expression: (10-2)*(1+3) = 32.
         let input = "(10-2)*(1+3)";
         let expr = parse_expression(input).unwrap();
-        let result = evaluate(&expr, &vec![]);
+        let result = evaluate(&expr, &[]);
         assert_eq!(result.unwrap(), 32);
     }
 
@@ -167,4 +178,216 @@ mod tests {
         // The error should indicate an unexpected token.
         assert!(err.contains("Unexpected token") || err.contains("extra"), "Error was: {}", err);
     }
+
+    #[test]
+    fn test_parse_expression_registers_and_negative_constants() {
+        let expr = parse_expression("R1 * -2 + r0").unwrap();
+        assert_eq!(expr, Expression::Add(
+            Box::new(Expression::Mul(Box::new(Expression::Var(1)), Box::new(Expression::Const(-2)))),
+            Box::new(Expression::Var(0)),
+        ));
+        assert_eq!(parse_expression("-2147483648").unwrap(), Expression::Const(i32::MIN));
+        assert!(parse_expression("2147483648").is_err());
+    }
+
+    #[test]
+    fn test_integer_division_and_remainders() {
+        assert_eq!(eval("-7 // 2").unwrap(), -3);
+        assert_eq!(eval("-7 div 2").unwrap(), -4);
+        assert_eq!(eval("-7 rem 2").unwrap(), -1);
+        assert_eq!(eval("-7 mod 2").unwrap(), 1);
+        assert_eq!(eval("7 mod -2").unwrap(), -1);
+        assert_eq!(eval("-2147483648 mod -1").unwrap(), 0);
+        assert_eq!(evaluation_error(eval("1 mod 0")), "zero_divisor");
+        assert_eq!(eval("-2147483648 // -1").unwrap().to_string(), "2147483648");
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(eval("min(3, -4) + max(3, -4)").unwrap(), -1);
+        assert_eq!(eval("abs(-5) * sign(-5)").unwrap(), -5);
+        assert_eq!(eval("gcd(12, -18)").unwrap(), 6);
+        assert_eq!(eval("msb(1000)").unwrap(), 9);
+        assert!(matches!(eval("msb(0)"), Err(MachineError::Iso(IsoError::Type(_, _)))));
+        assert!(parse_expression("foo(1)").is_err());
+    }
+
+    #[test]
+    fn test_powers() {
+        assert_eq!(eval("2 ^ 3 ^ 2").unwrap(), 512);
+        assert_eq!(eval("2 ** 10").unwrap(), 1024);
+        assert_eq!(eval("-1 ^ -3").unwrap(), -1);
+        assert_eq!(evaluation_error(eval("0 ^ -1")), "zero_divisor");
+        assert_eq!(eval("2 ^ 31").unwrap().to_string(), "2147483648");
+        assert!(matches!(eval("2 ^ -1"), Err(MachineError::Iso(IsoError::Type(kind, _))) if kind == "float"));
+        // ** is not associative.
+        assert!(parse_expression("2 ** 3 ** 2").is_err());
+    }
+
+    #[test]
+    fn test_bit_operations() {
+        assert_eq!(eval("12 /\\ 10").unwrap(), 8);
+        assert_eq!(eval("12 \\/ 10").unwrap(), 14);
+        assert_eq!(eval("12 xor 10").unwrap(), 6);
+        assert_eq!(eval("\\ 5").unwrap(), -6);
+        assert_eq!(eval("1 << 4").unwrap(), 16);
+        assert_eq!(eval("-16 >> 2").unwrap(), -4);
+        // << binds tighter than +.
+        assert_eq!(eval("1 + 2 << 1").unwrap(), 5);
+        assert_eq!(eval("1 << 31").unwrap().to_string(), "2147483648");
+    }
+
+    #[test]
+    fn test_overflow_falls_back_to_big_integers() {
+        assert_eq!(eval("2147483647 + 1").unwrap().to_string(), "2147483648");
+        assert_eq!(eval("abs(-2147483648)").unwrap().to_string(), "2147483648");
+        // 20! does not fit in 64 bits.
+        let factorial = (1..=20).map(|n| n.to_string()).collect::<Vec<_>>().join(" * ");
+        assert_eq!(eval(&factorial).unwrap().to_string(), "2432902008176640000");
+        let big = eval("2 ^ 100").unwrap();
+        assert_eq!(big.to_string(), "1267650600228229401496703205376");
+        // Results that fit again are small.
+        assert_eq!(evaluate_term(&Term::Compound("-".to_string(), vec![big.clone().into_term(), big.into_term()])).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_big_integer_operations() {
+        let big = |text: &str| Term::BigInt(text.parse().unwrap());
+        let op = |name: &str, a: Term, b: Term| evaluate_term(&Term::Compound(name.to_string(), vec![a, b])).unwrap().to_string();
+        let n = big("-100000000000000000000");
+        assert_eq!(op("//", n.clone(), Term::Const(7)), "-14285714285714285714");
+        assert_eq!(op("div", n.clone(), Term::Const(7)), "-14285714285714285715");
+        assert_eq!(op("rem", n.clone(), Term::Const(7)), "-2");
+        assert_eq!(op("mod", n.clone(), Term::Const(7)), "5");
+        assert_eq!(op(">>", n.clone(), Term::Const(100)), "-1");
+        assert_eq!(op("gcd", n.clone(), big("30000000000")), "10000000000");
+        assert_eq!(op("min", n.clone(), Term::Const(0)), n.to_string());
+        assert_eq!(eval("msb(1 << 40)").unwrap(), 40);
+        assert!(matches!(
+            evaluate_term(&Term::Compound("//".to_string(), vec![n, Term::Const(0)])),
+            Err(MachineError::Iso(IsoError::Evaluation(error))) if error == "zero_divisor"
+        ));
+    }
+
+    #[test]
+    fn test_results_beyond_the_size_limit_overflow() {
+        assert_eq!(evaluation_error(eval("2 ^ 100000000")), "int_overflow");
+        assert_eq!(evaluation_error(eval("1 << 2147483647")), "int_overflow");
+        // Powers of 0, 1 and -1 stay small whatever the exponent.
+        assert_eq!(eval("-1 ^ 2147483647").unwrap(), -1);
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        assert_eq!(eval("2.5 * 2").unwrap(), 5.0);
+        assert_eq!(eval("1 + 0.5").unwrap(), 1.5);
+        assert_eq!(eval("2.0e3 - 1").unwrap(), 1999.0);
+        // The quotient of two integers is an integer only if the division is exact.
+        assert_eq!(eval("7 / 2").unwrap(), 3.5);
+        assert_eq!(eval("6 / 2").unwrap(), 3);
+        assert_eq!(eval("2 ** -1").unwrap(), 0.5);
+        assert_eq!(eval("2.0 ^ 3").unwrap(), 8.0);
+        assert_eq!(eval("max(1, 1.5)").unwrap(), 1.5);
+        assert_eq!(eval("min(2, 2.5)").unwrap(), 2);
+        assert_eq!(eval("-(2.5)").unwrap(), -2.5);
+        assert_eq!(eval("sign(-0.0)").unwrap().to_string(), "-0.0");
+        assert_eq!(evaluation_error(eval("1.0 / 0")), "zero_divisor");
+        assert_eq!(evaluation_error(eval("1.0e308 * 10")), "float_overflow");
+        assert_eq!(evaluation_error(eval("float(2 ^ 2000)")), "float_overflow");
+    }
+
+    #[test]
+    fn test_float_functions() {
+        assert_eq!(eval("float(3)").unwrap(), 3.0);
+        assert_eq!(eval("integer(2.5)").unwrap(), 3);
+        assert_eq!(eval("round(-2.5)").unwrap(), -3);
+        assert_eq!(eval("truncate(-2.7)").unwrap(), -2);
+        assert_eq!(eval("ceiling(2.1)").unwrap(), 3);
+        assert_eq!(eval("floor(-2.1)").unwrap(), -3);
+        assert_eq!(eval("floor(7)").unwrap(), 7);
+        assert_eq!(eval("truncate(1.0e20)").unwrap().to_string(), "100000000000000000000");
+        assert_eq!(eval("sqrt(16)").unwrap(), 4.0);
+        assert_eq!(eval("exp(0)").unwrap(), 1.0);
+        assert_eq!(eval("log(e)").unwrap(), 1.0);
+        assert_eq!(eval("sin(0)").unwrap(), 0.0);
+        assert_eq!(eval("cos(pi)").unwrap(), -1.0);
+        assert_eq!(eval("atan2(1, 1) * 4").unwrap(), std::f64::consts::PI);
+        assert_eq!(evaluation_error(eval("sqrt(-1)")), "undefined");
+        assert_eq!(evaluation_error(eval("log(0)")), "undefined");
+        assert_eq!(evaluation_error(eval("asin(2)")), "undefined");
+    }
+
+    #[test]
+    fn test_integer_functors_reject_floats() {
+        for input in ["7.0 // 2", "7 mod 2.0", "1.0 << 2", "\\ 1.0", "msb(4.0)", "gcd(4, 2.0)"] {
+            match eval(input) {
+                Err(MachineError::Iso(error)) => assert!(error.to_string().starts_with("type_error(integer, "), "{}: {}", input, error),
+                other => panic!("Expected a type error for {}, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate_float_terms() {
+        let times = Term::Compound("*".to_string(), vec![Term::Float(Float(1.5)), Term::Const(2)]);
+        assert_eq!(evaluate_term(&times).unwrap(), 3.0);
+        assert_eq!(evaluate_term(&Term::Str("pi".to_string())).unwrap(), std::f64::consts::PI);
+        assert_eq!(Number::Float(3.0).into_term(), Term::Float(Float(3.0)));
+    }
+
+    #[test]
+    fn test_compare_with_non_finite_floats() {
+        use std::cmp::Ordering;
+        let one = Number::from_term(&Term::Const(1)).unwrap();
+        let nan = Number::Float(f64::NAN);
+        assert_eq!(one.compare(&Number::Float(f64::INFINITY)), Ordering::Less);
+        assert_eq!(one.compare(&Number::Float(f64::NEG_INFINITY)), Ordering::Greater);
+        // NaN orders after every other number, so the order stays total.
+        assert_eq!(one.compare(&nan), Ordering::Less);
+        assert_eq!(nan.compare(&one), Ordering::Greater);
+        assert_eq!(Number::Float(f64::INFINITY).compare(&nan), Ordering::Less);
+        assert_eq!(nan.compare(&Number::Float(f64::NAN)), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_parse_float_literals() {
+        assert_eq!(parse_expression("1.5").unwrap(), Expression::Float(1.5));
+        assert_eq!(parse_expression("-2.5e-3").unwrap(), Expression::Float(-0.0025));
+        assert!(parse_expression("1.0e400").is_err());
+        let expr = parse_expression("sqrt(R0) + atan2(1.0e20, -0.5) * truncate(2.0)").unwrap();
+        assert_eq!(expr.to_string(), "(sqrt(R0)) + ((atan2(1.0e20, -0.5)) * (truncate(2.0)))");
+        assert_eq!(parse_expression(&expr.to_string()).unwrap(), expr);
+    }
+
+    #[test]
+    fn test_evaluate_term_errors() {
+        let plus = |a: Term, b: Term| Term::Compound("+".to_string(), vec![a, b]);
+        assert_eq!(evaluate_term(&plus(Term::Const(1), Term::Const(2))).unwrap(), 3);
+        assert!(matches!(
+            evaluate_term(&plus(Term::Var(0), Term::Const(2))),
+            Err(MachineError::Iso(IsoError::Instantiation))
+        ));
+        let foo = Term::Compound("foo".to_string(), vec![Term::Var(0), Term::Const(1)]);
+        match evaluate_term(&foo) {
+            Err(MachineError::Iso(error)) => assert_eq!(error.to_string(), "type_error(evaluable, foo/2)"),
+            other => panic!("Expected a type error, got {:?}", other),
+        }
+        match evaluate_term(&Term::Str("a".to_string())) {
+            Err(MachineError::Iso(error)) => assert_eq!(error.to_string(), "type_error(evaluable, a/0)"),
+            other => panic!("Expected a type error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_extended_expression_display_round_trip() {
+        let expr = parse_expression("max(R0, 2) mod (\\R1) ^ abs(-3) - 7 // 2 xor 1 << 2").unwrap();
+        assert_eq!(parse_expression(&expr.to_string()).unwrap(), expr);
+    }
+
+    #[test]
+    fn test_expression_display() {
+        let expr = parse_expression("(R1 + 2) * -(3 - R0) / 4").unwrap();
+        assert_eq!(expr.to_string(), "((R1 + 2) * (0 - (3 - R0))) / 4");
+        assert_eq!(parse_expression(&expr.to_string()).unwrap(), expr);
+    }
 }
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use lam::machine::atom::AtomTable;
+    use lam::machine::core::Machine;
+    use lam::machine::heap::Cell;
+    use lam::machine::instruction::Instruction;
+    use lam::machine::term::Term;
+
+    #[test]
+    fn test_intern_returns_the_same_atom_for_a_name() {
+        let mut atoms = AtomTable::new();
+        let foo = atoms.intern("foo");
+        let bar = atoms.intern("bar");
+        assert_ne!(foo, bar);
+        assert_eq!(atoms.intern("foo"), foo);
+        assert_eq!(atoms.len(), 2);
+        assert_eq!(atoms.name(foo), "foo");
+        assert_eq!(atoms.name(bar), "bar");
+    }
+
+    #[test]
+    fn test_lookup_does_not_intern() {
+        let mut atoms = AtomTable::new();
+        assert_eq!(atoms.lookup("foo"), None);
+        assert!(atoms.is_empty());
+        let foo = atoms.intern("foo");
+        assert_eq!(atoms.lookup("foo"), Some(foo));
+    }
+
+    #[test]
+    fn test_machine_intern_and_atom_name() {
+        let mut machine = Machine::new(1, vec![]);
+        let atom = machine.intern("hello");
+        assert_eq!(machine.atom_name(atom), "hello");
+        assert_eq!(machine.intern("hello"), atom);
+    }
+
+    #[test]
+    fn test_atoms_functors_and_predicates_share_the_table() {
+        let mut machine = Machine::new(2, vec![
+            Instruction::PutStr { register: 0, value: "f".to_string() },
+            Instruction::Halt,
+        ]);
+        machine.register_predicate("f".to_string(), 0);
+        machine.run().unwrap();
+        let f = machine.intern("f");
+        assert_eq!(machine.registers[0], Some(Cell::Atom(f)));
+        assert_eq!(machine.predicate_table.get(&f), Some(&vec![0]));
+
+        machine.set_register(1, Term::Compound("f".to_string(), vec![Term::Const(1)])).unwrap();
+        let Some(Cell::Str(address)) = machine.registers[1] else { panic!("Expected a structure cell") };
+        let (functor, _) = machine.heap.arguments(address);
+        assert_eq!(machine.heap.functor_atom(functor), f);
+    }
+
+    #[test]
+    fn test_atoms_survive_backtracking() {
+        let mut machine = Machine::new(1, vec![]);
+        let top = machine.heap.len();
+        let atom = machine.intern("kept");
+        machine.heap.truncate(top);
+        assert_eq!(machine.atom_name(atom), "kept");
+    }
+}
@@ -23,8 +23,8 @@ mod tests {
         machine.run().expect("Machine run should succeed");
         
         // Verify that reg0 remains 10 and reg1 becomes 30.
-        assert_eq!(machine.registers[0], Some(Term::Const(10)));
-        assert_eq!(machine.registers[1], Some(Term::Const(30)));
+        assert_eq!(machine.register(0), Some(Term::Const(10)));
+        assert_eq!(machine.register(1), Some(Term::Const(30)));
         // The choice stack should be empty.
         assert_eq!(machine.choice_stack.len(), 0);
     }
@@ -1,6 +1,10 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
+    use super::common::compiled_machine;
     use lam::machine::core::Machine;
+    use lam::machine::heap::Cell;
     use lam::machine::instruction::Instruction;
     use lam::machine::term::Term;
 
@@ -23,8 +27,46 @@ mod tests {
         machine.run().expect("Machine run should succeed");
 
         // Verify that the union-find binding for variable 0 now yields Const(300).
-        assert_eq!(machine.uf.resolve(&Term::Var(0)), Term::Const(300));
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(300));
         // The choice stack should be empty.
         assert_eq!(machine.choice_stack.len(), 0);
     }
+
+    #[test]
+    fn test_backtracking_restores_environment_and_fresh_variables() {
+        let code = vec![
+          // Step 0: Allocate an environment and store a constant in its slot.
+          Instruction::Allocate { n: 1 },
+          Instruction::SetLocal { index: 0, value: Term::Const(1) },
+          // Step 2: Create a choice point with alternative branch at address 7.
+          Instruction::Choice { alternative: 7 },
+          // Step 3: First alternative: overwrite the slot, bind a fresh variable,
+          // drop the environment and fail.
+          Instruction::SetLocal { index: 0, value: Term::Const(2) },
+          Instruction::NewVar { register: 0, name: "Y".to_string() },
+          Instruction::Deallocate,
+          Instruction::Fail,
+          // Step 7: Second alternative: the environment is back with its old slot.
+          Instruction::GetLocal { index: 0, register: 1 },
+          Instruction::NewVar { register: 0, name: "Z".to_string() },
+        ];
+
+        let mut machine = Machine::new(2, code);
+        let first_var = machine.next_var_id;
+        machine.run().expect("Machine run should succeed");
+
+        assert_eq!(machine.environment_stack.len(), 1);
+        assert_eq!(machine.environment_stack.last(), Some(&vec![Some(Cell::Int(1))]));
+        assert_eq!(machine.register(1), Some(Term::Const(1)));
+        // The variable created by the failed branch was discarded and its id reused.
+        assert_eq!(machine.register(0), Some(Term::Var(first_var)));
+        assert_eq!(machine.uf.trail.len(), 0);
+    }
+
+    #[test]
+    fn test_deterministic_calls_leave_no_choice_points() {
+        let mut machine = compiled_machine("a :- b.\nb :- c.\nc :- d.\nd.\nmain :- a.\n");
+        machine.run().expect("Machine run should succeed");
+        assert!(machine.choice_stack.is_empty());
+    }
 }
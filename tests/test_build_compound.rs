@@ -20,6 +20,6 @@ mod tests {
         machine.run().expect("Machine run should succeed");
         
         let expected = Term::Compound("f".to_string(), vec![Term::Const(42), Term::Const(99)]);
-        assert_eq!(machine.registers[2], Some(expected));
+        assert_eq!(machine.register(2), Some(expected));
     }
 }
@@ -3,16 +3,21 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
+    use lam::machine::heap::Cell;
+    use lam::machine::error_handling::{IsoError, MachineError};
+    use lam::machine::instruction::Instruction;
+    use lam::machine::number::Float;
     use lam::machine::term::Term;
+    use lam::machine::ops::OpType;
 
     #[test]
     fn test_builtin_print() {
         // Create a machine with two registers.
         let mut machine = Machine::new(2, vec![]);
         // Set register 0 to a variable (with name "X") and register 1 to a constant.
-        machine.registers[0] = Some(Term::Var(0));
+        machine.set_register(0, Term::Var(0)).unwrap();
         machine.variable_names.insert(0, "X".to_string());
-        machine.registers[1] = Some(Term::Const(42));
+        machine.set_register(1, Term::Const(42)).unwrap();
 
         // Enable verbose mode so that builtin_print actually logs output.
         machine.verbose = true;
@@ -25,7 +30,7 @@ mod tests {
         // Test builtin_write when register 0 holds a compound with functor "-" and exactly two arguments.
         let mut machine = Machine::new(1, vec![]);
         let compound = Term::Compound("-".to_string(), vec![Term::Const(3), Term::Const(4)]);
-        machine.registers[0] = Some(compound);
+        machine.set_register(0, compound).unwrap();
         // Expect builtin_write to succeed.
         assert!(machine.builtin_write().is_ok());
     }
@@ -34,7 +39,7 @@ mod tests {
     fn test_builtin_write_non_dash() {
         // Test builtin_write when register 0 holds a non-special term.
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Const(99));
+        machine.set_register(0, Term::Const(99)).unwrap();
         // Expect builtin_write to succeed (printing the term normally).
         assert!(machine.builtin_write().is_ok());
     }
@@ -45,4 +50,195 @@ mod tests {
         let mut machine = Machine::new(1, vec![]);
         assert!(machine.builtin_nl().is_ok());
     }
+
+    #[test]
+    fn test_builtin_is_evaluates_runtime_terms() {
+        // is(X, +(Y, *(Z, 2))) with Y bound to 5 and Z to 1.5.
+        let mut machine = Machine::new(2, vec![]);
+        machine.set_register(0, Term::Var(1)).unwrap();
+        machine.set_register(1, Term::Const(5)).unwrap();
+        machine.builtin_eq().unwrap();
+        machine.set_register(0, Term::Var(2)).unwrap();
+        machine.set_register(1, Term::Float(Float(1.5))).unwrap();
+        machine.builtin_eq().unwrap();
+        let times = Term::Compound("*".to_string(), vec![Term::Var(2), Term::Const(2)]);
+        machine.set_register(0, Term::Var(0)).unwrap();
+        machine.set_register(1, Term::Compound("+".to_string(), vec![Term::Var(1), times])).unwrap();
+        machine.builtin_is().expect("is/2 should succeed");
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Float(Float(8.0)));
+        // The value is unified with the first argument.
+        machine.set_register(0, Term::Const(9)).unwrap();
+        assert!(matches!(machine.builtin_is(), Err(MachineError::UnificationFailed(_))));
+    }
+
+    #[test]
+    fn test_builtin_is_errors() {
+        let mut machine = Machine::new(2, vec![]);
+        machine.set_register(0, Term::Var(0)).unwrap();
+        machine.set_register(1, Term::Compound("+".to_string(), vec![Term::Var(1), Term::Const(1)])).unwrap();
+        assert!(matches!(machine.builtin_is(), Err(MachineError::Iso(IsoError::Instantiation))));
+        machine.set_register(1, Term::Compound("foo".to_string(), vec![Term::Const(1)])).unwrap();
+        match machine.builtin_is() {
+            Err(MachineError::Iso(error)) => assert_eq!(error.to_string(), "type_error(evaluable, foo/1)"),
+            other => panic!("Expected a type error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_comparison_builtins() {
+        let plus = |a: Term, b: Term| Term::Compound("+".to_string(), vec![a, b]);
+        let mut machine = Machine::new(2, vec![]);
+        machine.set_register(0, Term::Const(1)).unwrap();
+        machine.set_register(1, Term::Float(Float(1.0))).unwrap();
+        assert!(machine.builtin_arithmetic_equal().is_ok());
+        assert!(machine.builtin_less_or_equal().is_ok());
+        assert!(machine.builtin_greater_or_equal().is_ok());
+        assert!(matches!(machine.builtin_arithmetic_not_equal(), Err(MachineError::UnificationFailed(_))));
+        assert!(matches!(machine.builtin_less_than(), Err(MachineError::UnificationFailed(_))));
+        // Both sides are evaluated.
+        machine.set_register(0, plus(Term::Const(2), Term::Const(1))).unwrap();
+        machine.set_register(1, Term::Float(Float(2.5))).unwrap();
+        assert!(machine.builtin_greater_than().is_ok());
+        assert!(machine.builtin_arithmetic_not_equal().is_ok());
+        machine.set_register(1, plus(Term::Var(0), Term::Const(1))).unwrap();
+        assert!(matches!(machine.builtin_less_than(), Err(MachineError::Iso(IsoError::Instantiation))));
+    }
+
+    #[test]
+    fn test_builtin_compare() {
+        let mut machine = Machine::new(3, vec![]);
+        machine.set_register(0, Term::Var(0)).unwrap();
+        machine.set_register(1, Term::Const(1)).unwrap();
+        machine.set_register(2, Term::Float(Float(1.0))).unwrap();
+        machine.builtin_compare().expect("compare/3 should succeed");
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Str(">".to_string()));
+    }
+
+    #[test]
+    fn test_standard_order_builtins() {
+        let mut machine = Machine::new(2, vec![]);
+        machine.set_register(0, Term::Str("a".to_string())).unwrap();
+        machine.set_register(1, Term::Compound("f".to_string(), vec![Term::Const(1)])).unwrap();
+        assert!(machine.builtin_term_less().is_ok());
+        assert!(machine.builtin_term_less_or_equal().is_ok());
+        assert!(machine.builtin_not_identical().is_ok());
+        assert!(matches!(machine.builtin_term_greater(), Err(MachineError::UnificationFailed(_))));
+        assert!(matches!(machine.builtin_term_greater_or_equal(), Err(MachineError::UnificationFailed(_))));
+        assert!(matches!(machine.builtin_identical(), Err(MachineError::UnificationFailed(_))));
+        // 1 and 1.0 are equal numbers but not identical terms.
+        machine.set_register(0, Term::Const(1)).unwrap();
+        machine.set_register(1, Term::Float(Float(1.0))).unwrap();
+        assert!(machine.builtin_not_identical().is_ok());
+    }
+
+    /// Builds the list `[1, 1, ..., last]` of the given length on the heap,
+    /// without a `Term` whose recursive drop would exhaust the stack.
+    fn long_list(machine: &mut Machine, length: usize, last: i32) -> Cell {
+        let cons = machine.heap.functor(".", 2);
+        let mut list = machine.heap.atom("[]");
+        for i in 0..length {
+            let head = Cell::Int(if i == 0 { last } else { 1 });
+            list = machine.heap.structure(cons, [head, list]);
+        }
+        list
+    }
+
+    #[test]
+    fn test_standard_order_of_long_lists() {
+        let mut machine = Machine::new(3, vec![]);
+        let a = long_list(&mut machine, 300_000, 1);
+        let b = long_list(&mut machine, 300_000, 1);
+        let c = long_list(&mut machine, 300_000, 2);
+        machine.registers[0] = Some(a.clone());
+        machine.registers[1] = Some(b);
+        assert!(machine.builtin_identical().is_ok());
+        machine.registers[1] = Some(c.clone());
+        assert!(machine.builtin_term_less().is_ok());
+        assert!(matches!(machine.builtin_identical(), Err(MachineError::UnificationFailed(_))));
+        machine.set_register(0, Term::Var(0)).unwrap();
+        machine.registers[1] = Some(c);
+        machine.registers[2] = Some(a);
+        machine.builtin_compare().expect("compare/3 should succeed");
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Str(">".to_string()));
+    }
+
+    #[test]
+    fn test_builtin_op_defines_operators() {
+        let mut machine = Machine::new(3, vec![]);
+        machine.set_register(0, Term::Const(700)).unwrap();
+        machine.set_register(1, Term::Str("xfx".to_string())).unwrap();
+        machine.set_register(2, Term::Compound(".".to_string(), vec![
+            Term::Str("===>".to_string()),
+            Term::Compound(".".to_string(), vec![Term::Str("<===".to_string()), Term::Str("[]".to_string())]),
+        ])).unwrap();
+        machine.builtin_op().expect("op/3 should succeed");
+        assert_eq!(machine.op_table.infix("===>"), Some((700, OpType::Xfx)));
+        assert_eq!(machine.op_table.infix("<==="), Some((700, OpType::Xfx)));
+    }
+
+    #[test]
+    fn test_builtin_op_rejects_invalid_definitions() {
+        let mut machine = Machine::new(3, vec![]);
+        machine.set_register(0, Term::Const(700)).unwrap();
+        machine.set_register(1, Term::Str("xfz".to_string())).unwrap();
+        machine.set_register(2, Term::Str("foo".to_string())).unwrap();
+        assert!(matches!(machine.builtin_op(), Err(MachineError::InvalidOperator(_))));
+    }
+
+    #[test]
+    fn test_builtin_current_op_enumerates_on_backtracking() {
+        // current_op(P, T, -) has two solutions: 200 fy and 500 yfx.
+        let mut machine = Machine::new(3, vec![]);
+        machine.set_register(0, Term::Var(0)).unwrap();
+        machine.set_register(1, Term::Var(1)).unwrap();
+        machine.set_register(2, Term::Str("-".to_string())).unwrap();
+        machine.builtin_current_op().expect("current_op/3 should succeed");
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(200));
+        assert_eq!(machine.resolve(&Term::Var(1)), Term::Str("fy".to_string()));
+        machine.execute_fail().expect("A second solution should remain");
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(500));
+        assert_eq!(machine.resolve(&Term::Var(1)), Term::Str("yfx".to_string()));
+        assert!(machine.execute_fail().is_err(), "No third solution");
+    }
+
+    #[test]
+    fn test_builtin_current_op_fails_for_non_operator() {
+        let mut machine = Machine::new(3, vec![]);
+        machine.set_register(0, Term::Var(0)).unwrap();
+        machine.set_register(1, Term::Var(1)).unwrap();
+        machine.set_register(2, Term::Str("foo".to_string())).unwrap();
+        assert!(matches!(machine.builtin_current_op(), Err(MachineError::UnificationFailed(_))));
+    }
+
+    /// Returns the goal `(A = true, A), (fail ; B = value)`.
+    fn construct_goal(a: usize, b: usize, value: i32) -> Term {
+        let eq = |left: Term, right: Term| Term::Compound("=".to_string(), vec![left, right]);
+        let conjunction = |left: Term, right: Term| Term::Compound(",".to_string(), vec![left, right]);
+        conjunction(
+            conjunction(eq(Term::Var(a), Term::Str("true".to_string())), Term::Var(a)),
+            Term::Compound(";".to_string(), vec![Term::Str("fail".to_string()), eq(Term::Var(b), Term::Const(value))]),
+        )
+    }
+
+    #[test]
+    fn test_builtin_call_lowers_control_constructs_once_per_shape() {
+        // The machine runs without the Prolog front end: the construct is
+        // lowered by the machine itself.
+        let mut machine = Machine::new(6, vec![Instruction::Halt]);
+        machine.set_register(0, construct_goal(0, 1, 1)).unwrap();
+        machine.set_register(1, Term::Str("[]".to_string())).unwrap();
+        machine.builtin_call().expect("call/1 should succeed");
+        machine.run().expect("The goal should succeed");
+        assert_eq!(machine.resolve(&Term::Var(1)), Term::Const(1));
+        let code_len = machine.code.len();
+
+        // A goal of the same shape reuses the lowered clause.
+        machine.pc = 0;
+        machine.set_register(0, construct_goal(2, 3, 2)).unwrap();
+        machine.set_register(1, Term::Str("[]".to_string())).unwrap();
+        machine.builtin_call().expect("call/1 should succeed");
+        machine.run().expect("The goal should succeed");
+        assert_eq!(machine.resolve(&Term::Var(3)), Term::Const(2));
+        assert_eq!(machine.code.len(), code_len);
+    }
 }
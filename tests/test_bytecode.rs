@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use lam::machine::arithmetic::Expression;
+    use lam::machine::bytecode::{BytecodeError, MAGIC, VERSION};
+    use lam::machine::instruction::{CallTarget, Instruction};
+    use lam::machine::program::Program;
+    use lam::machine::term::Term;
+    use lam::prolog::compiler::compile_prolog;
+
+    fn encode(program: &Program) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        program.write_bytecode(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn sample_program() -> Program {
+        let mut program = Program::new(vec![
+            Instruction::PutStr { register: 0, value: "hello".to_string() },
+            Instruction::ArithmeticIs {
+                target: 1,
+                expression: Expression::Sub(Box::new(Expression::Var(0)), Box::new(Expression::Const(-7))),
+            },
+            Instruction::SetLocal { index: 0, value: Term::Compound("f".to_string(), vec![Term::Var(3), Term::Str("hello".to_string())]) },
+            Instruction::Call { predicate: "hello".to_string(), target: CallTarget::Unresolved },
+            Instruction::Halt,
+        ]);
+        program.predicate_table.insert("hello".to_string(), vec![3]);
+        program.index_table.entry("hello".to_string()).or_default().insert(vec![Term::Const(1)], vec![3]);
+        program
+    }
+
+    #[test]
+    fn test_bytecode_round_trips() {
+        let program = sample_program();
+        let bytes = encode(&program);
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), VERSION);
+        assert_eq!(Program::read_bytecode(&mut bytes.as_slice()).unwrap(), program);
+    }
+
+    #[test]
+    fn test_bytecode_stores_strings_once() {
+        let bytes = encode(&sample_program());
+        let occurrences = bytes.windows(5).filter(|w| *w == b"hello").count();
+        assert_eq!(occurrences, 1);
+    }
+
+    #[test]
+    fn test_bytecode_is_deterministic() {
+        let source = "p(a). p(b). q(X) :- p(X). main :- q(Y), write(Y), nl, halt.";
+        let (instructions, predicate_table, _) = compile_prolog(source).unwrap();
+        let program = Program { instructions, predicate_table, ..Program::default() };
+        assert_eq!(encode(&program), encode(&program.clone()));
+        let bytes = encode(&program);
+        assert_eq!(Program::read_bytecode(&mut bytes.as_slice()).unwrap(), program);
+    }
+
+    #[test]
+    fn test_bad_magic_is_rejected() {
+        let mut bytes = encode(&sample_program());
+        bytes[0] = b'X';
+        assert!(matches!(Program::read_bytecode(&mut bytes.as_slice()), Err(BytecodeError::BadMagic)));
+        assert!(matches!(Program::read_bytecode(&mut &b"LA"[..]), Err(BytecodeError::BadMagic)));
+    }
+
+    #[test]
+    fn test_unsupported_version_is_rejected() {
+        let mut bytes = encode(&sample_program());
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Program::read_bytecode(&mut bytes.as_slice()),
+            Err(BytecodeError::UnsupportedVersion(v)) if v == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_truncated_bytecode_is_rejected() {
+        let bytes = encode(&sample_program());
+        for len in 6..bytes.len() {
+            assert!(
+                matches!(Program::read_bytecode(&mut &bytes[..len]), Err(BytecodeError::Malformed { .. })),
+                "prefix of length {} was accepted", len
+            );
+        }
+    }
+
+    #[test]
+    fn test_trailing_bytes_are_rejected() {
+        let mut bytes = encode(&sample_program());
+        bytes.push(0);
+        let err = Program::read_bytecode(&mut bytes.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("trailing bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_opcode_is_rejected() {
+        let program = Program::new(vec![Instruction::Halt]);
+        let mut bytes = encode(&program);
+        // The empty program has no strings: magic, version, 0 strings, 1 instruction.
+        assert_eq!(bytes[8], 28);
+        bytes[8] = 200;
+        let err = Program::read_bytecode(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "Malformed bytecode at byte 8: unknown opcode 200");
+    }
+}
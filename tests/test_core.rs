@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
     use lam::machine::error_handling::MachineError;
 
@@ -13,7 +13,6 @@ mod tests {
         assert_eq!(machine.registers.len(), 5);
         assert_eq!(machine.code, code);
         assert_eq!(machine.pc, 0);
-        assert!(machine.substitution.is_empty());
         assert!(machine.control_stack.is_empty());
         assert!(machine.choice_stack.is_empty());
         assert!(machine.environment_stack.is_empty());
@@ -22,11 +21,11 @@ mod tests {
         assert!(machine.uf.bindings.is_empty());
         assert!(!machine.verbose);
         // Verify builtins exist
-        assert!(machine.builtins.contains_key("halt"));
-        assert!(machine.builtins.contains_key("print"));
-        assert!(machine.builtins.contains_key("print_subst"));
-        assert!(machine.builtins.contains_key("write"));
-        assert!(machine.builtins.contains_key("nl"));
+        assert!(machine.builtins.contains_key("halt/0"));
+        assert!(machine.builtins.contains_key("print/0"));
+        assert!(machine.builtins.contains_key("print_subst/0"));
+        assert!(machine.builtins.contains_key("write/1"));
+        assert!(machine.builtins.contains_key("nl/0"));
     }
 
     #[test]
@@ -34,8 +33,9 @@ mod tests {
         let mut machine = Machine::new(1, vec![]);
         machine.register_predicate("p".to_string(), 10);
         machine.register_predicate("p".to_string(), 20);
-        assert!(machine.predicate_table.contains_key("p"));
-        let clauses = machine.predicate_table.get("p").unwrap();
+        let p = machine.intern("p");
+        assert!(machine.predicate_table.contains_key(&p));
+        let clauses = machine.predicate_table.get(&p).unwrap();
         assert_eq!(clauses.len(), 2);
         assert!(clauses.contains(&10));
         assert!(clauses.contains(&20));
@@ -46,8 +46,9 @@ mod tests {
         let mut machine = Machine::new(1, vec![]);
         let key = vec![Term::Const(5)];
         machine.register_indexed_clause("p".to_string(), key.clone(), 42);
-        assert!(machine.index_table.contains_key("p"));
-        let index_map = machine.index_table.get("p").unwrap();
+        let p = machine.intern("p");
+        assert!(machine.index_table.contains_key(&p));
+        let index_map = machine.index_table.get(&p).unwrap();
         assert!(index_map.contains_key(&key));
         let clauses = index_map.get(&key).unwrap();
         assert_eq!(clauses.len(), 1);
@@ -57,9 +58,9 @@ mod tests {
     #[test]
     fn test_execute_move() {
         let mut machine = Machine::new(3, vec![]);
-        machine.registers[0] = Some(Term::Const(7));
+        machine.set_register(0, Term::Const(7)).unwrap();
         machine.execute_move(0, 1).expect("execute_move should succeed");
-        assert_eq!(machine.registers[1], Some(Term::Const(7)));
+        assert_eq!(machine.register(1), Some(Term::Const(7)));
     }
 
     #[test]
@@ -103,9 +104,9 @@ mod tests {
     #[test]
     fn test_builtin_print_subst() {
         let mut machine = Machine::new(1, vec![]);
-        // Set a substitution and variable name.
-        machine.substitution.insert(0, Term::Const(42));
+        // Bind a named variable.
         machine.variable_names.insert(0, "X".to_string());
+        machine.unify(&Term::Var(0), &Term::Const(42)).expect("Unification should succeed");
         // Calling builtin_print_subst should succeed (output not captured).
         machine.builtin_print_subst().expect("builtin_print_subst should succeed");
     }
@@ -115,7 +116,7 @@ mod tests {
         let mut machine = Machine::new(1, vec![]);
         // Set register 0 to a compound with functor "-" and two arguments.
         let compound = Term::Compound("-".to_string(), vec![Term::Const(3), Term::Const(4)]);
-        machine.registers[0] = Some(compound);
+        machine.set_register(0, compound).unwrap();
         machine.builtin_write().expect("builtin_write should succeed");
     }
 
@@ -143,7 +144,7 @@ mod tests {
         let mut machine = Machine::new(1, code.clone());
         // Step once.
         machine.step().expect("step should succeed");
-        assert_eq!(machine.registers[0], Some(Term::Const(77)));
+        assert_eq!(machine.register(0), Some(Term::Const(77)));
         // Now run() will see the Halt instruction and break out.
         machine.run().expect("run should succeed");
         // Depending on your implementation of run(), it might leave pc at the Halt instruction index.
@@ -151,4 +152,25 @@ mod tests {
         // Here, we assert that pc is 1.
         assert_eq!(machine.pc, 1);
     }
+
+    #[test]
+    fn test_calls_are_resolved_when_loaded() {
+        let code = vec![
+            Instruction::Call { predicate: "p/0".to_string(), target: CallTarget::Unresolved },
+            Instruction::Call { predicate: "nl/0".to_string(), target: CallTarget::Unresolved },
+            Instruction::Halt,
+            Instruction::PutConst { register: 0, value: 1 },
+            Instruction::Proceed,
+        ];
+        let mut machine = Machine::new(1, code);
+        let p = machine.intern("p/0");
+        assert_eq!(machine.code[0], Instruction::Call { predicate: "p/0".to_string(), target: CallTarget::Predicate(p) });
+        let nl = machine.builtins["nl/0"];
+        assert_eq!(machine.code[1], Instruction::Call { predicate: "nl/0".to_string(), target: CallTarget::Builtin(nl) });
+
+        // A predicate resolved before its clauses are registered still finds them.
+        machine.register_predicate("p/0".to_string(), 3);
+        machine.run().expect("run should succeed");
+        assert_eq!(machine.register(0), Some(Term::Const(1)));
+    }
 }
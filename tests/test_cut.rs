@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::core::Machine;
     use lam::machine::term::Term;
     use lam::machine::error_handling::MachineError;
@@ -31,6 +31,79 @@ mod tests {
             other => panic!("Expected NoChoicePoint error, got {:?}", other),
         }
         // Verify that register 0 holds the value from the first alternative.
-        assert_eq!(machine.registers[0], Some(Term::Const(1)));
+        assert_eq!(machine.register(0), Some(Term::Const(1)));
+    }
+
+    #[test]
+    fn test_cut_to_removes_choice_points_above_level() {
+        let code = vec![
+            Instruction::Choice { alternative: 6 },
+            // The level is taken after the first choice point.
+            Instruction::GetLevel { register: 1 },
+            Instruction::Choice { alternative: 7 },
+            Instruction::Choice { alternative: 7 },
+            Instruction::CutTo { register: 1 },
+            Instruction::Fail,
+            // Reached from the first choice point, which the cut keeps.
+            Instruction::PutConst { register: 0, value: 1 },
+            Instruction::Halt,
+        ];
+        let mut machine = Machine::new(2, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(0), Some(Term::Const(1)));
+        assert!(machine.choice_stack.is_empty());
+    }
+
+    #[test]
+    fn test_soft_cut_disables_one_choice_point() {
+        let code = vec![
+            Instruction::GetLevel { register: 1 },
+            Instruction::Choice { alternative: 6 },
+            Instruction::Choice { alternative: 7 },
+            Instruction::SoftCut { register: 1 },
+            Instruction::Fail,
+            Instruction::Halt,
+            // The disabled choice point is never resumed.
+            Instruction::PutConst { register: 0, value: 1 },
+            // The choice point above it still is.
+            Instruction::PutConst { register: 0, value: 2 },
+            Instruction::Jump { address: 5 },
+        ];
+        let mut machine = Machine::new(2, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(0), Some(Term::Const(2)));
+        assert!(matches!(machine.execute_fail(), Err(MachineError::NoChoicePoint)));
+    }
+
+    #[test]
+    fn test_cut_after_tail_call_keeps_caller_alternatives() {
+        let code = vec![
+            Instruction::Call { predicate: "p".to_string(), target: CallTarget::Unresolved },
+            // Rejects the first solution of p.
+            Instruction::GetConst { register: 0, value: 2 },
+            Instruction::Halt,
+            // p :- q.
+            Instruction::Allocate { n: 0 },
+            Instruction::TailCall { predicate: "q".to_string(), target: CallTarget::Unresolved },
+            // p :- R0 = 2.
+            Instruction::PutConst { register: 0, value: 2 },
+            Instruction::Proceed,
+            // q :- !, R0 = 1.
+            Instruction::Cut,
+            Instruction::PutConst { register: 0, value: 1 },
+            Instruction::Proceed,
+            // q :- R0 = 3.
+            Instruction::PutConst { register: 0, value: 3 },
+            Instruction::Proceed,
+        ];
+        let mut machine = Machine::new(1, code);
+        machine.register_predicate("p".to_string(), 3);
+        machine.register_predicate("p".to_string(), 5);
+        machine.register_predicate("q".to_string(), 7);
+        machine.register_predicate("q".to_string(), 10);
+        // The cut in q removes the alternative of q but not the one of p.
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(0), Some(Term::Const(2)));
+        assert_eq!(machine.cut_barrier, 0);
     }
 }
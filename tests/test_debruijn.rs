@@ -0,0 +1,94 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::common::{app, lambda, var};
+    use lam::machine::core::Machine;
+    use lam::machine::debruijn::{alpha_equivalent, eta_reduce, instantiate, normalize, DbTerm, Name};
+    use lam::machine::lambda::Strategy;
+    use lam::machine::term::Term;
+
+    fn db_lambda(name: usize, body: DbTerm) -> DbTerm {
+        DbTerm::Lambda(Name(name), Box::new(body))
+    }
+    fn db_app(fun: DbTerm, arg: DbTerm) -> DbTerm {
+        DbTerm::App(Box::new(fun), Box::new(arg))
+    }
+
+    #[test]
+    fn test_from_term_uses_indices_for_bound_variables() {
+        // \x. \y. x y z
+        let term = lambda(0, lambda(1, app(app(var(0), var(1)), var(2))));
+        let expected = db_lambda(0, db_lambda(1, db_app(db_app(DbTerm::Bound(1), DbTerm::Bound(0)), DbTerm::Free(2))));
+        assert_eq!(DbTerm::from_term(&term), expected);
+    }
+
+    #[test]
+    fn test_inner_parameter_shadows_outer() {
+        // \x. \x. x refers to the inner parameter.
+        let term = lambda(0, lambda(0, var(0)));
+        assert_eq!(DbTerm::from_term(&term), db_lambda(0, db_lambda(0, DbTerm::Bound(0))));
+    }
+
+    #[test]
+    fn test_alpha_equivalence() {
+        assert!(alpha_equivalent(&lambda(0, app(var(0), var(5))), &lambda(7, app(var(7), var(5)))));
+        assert!(!alpha_equivalent(&lambda(0, var(0)), &lambda(1, var(0))));
+        assert!(!alpha_equivalent(&lambda(0, lambda(1, var(0))), &lambda(0, lambda(1, var(1)))));
+    }
+
+    #[test]
+    fn test_round_trip_keeps_parameter_names() {
+        let term = lambda(3, lambda(4, app(var(3), Term::Compound("f".to_string(), vec![var(4), var(9)]))));
+        assert_eq!(DbTerm::from_term(&term).to_term(), term);
+    }
+
+    #[test]
+    fn test_to_term_renames_parameters_that_would_capture() {
+        // The abstraction named 0 whose body mentions the free variable 0.
+        let db = db_lambda(0, db_app(DbTerm::Bound(0), DbTerm::Free(0)));
+        let Term::Lambda(param, body) = db.to_term() else { panic!("Expected a lambda") };
+        assert_ne!(param, 0);
+        assert_eq!(*body, app(var(param), var(0)));
+    }
+
+    #[test]
+    fn test_instantiate_shifts_the_argument() {
+        // In the body \. 1, index 1 is the parameter being instantiated. An
+        // argument that refers to an outer binder is shifted past the inner one.
+        let body = db_lambda(1, DbTerm::Bound(1));
+        assert_eq!(instantiate(&body, &DbTerm::Free(7)), db_lambda(1, DbTerm::Free(7)));
+        assert_eq!(instantiate(&body, &DbTerm::Bound(0)), db_lambda(1, DbTerm::Bound(1)));
+    }
+
+    #[test]
+    fn test_reduction_avoids_capture() {
+        // (\x. \y. x) y reduces to \y'. y, not \y. y.
+        let term = app(lambda(0, lambda(1, var(0))), var(1));
+        let normal = normalize(&DbTerm::from_term(&term), Strategy::NormalOrder, 10, false).unwrap();
+        assert_eq!(normal, db_lambda(1, DbTerm::Free(1)));
+        let Term::Lambda(param, body) = normal.to_term() else { panic!("Expected a lambda") };
+        assert_ne!(param, 1);
+        assert_eq!(*body, var(1));
+    }
+
+    #[test]
+    fn test_eta_reduce() {
+        // \. f 0 reduces to f; \. 0 0 does not.
+        let f = DbTerm::Free(4);
+        assert_eq!(eta_reduce(&db_lambda(0, db_app(f.clone(), DbTerm::Bound(0)))), Some(f));
+        assert_eq!(eta_reduce(&db_lambda(0, db_app(DbTerm::Bound(0), DbTerm::Bound(0)))), None);
+        // Under an outer binder, \. 1 0 reduces to 0.
+        assert_eq!(eta_reduce(&db_lambda(0, db_app(DbTerm::Bound(1), DbTerm::Bound(0)))), Some(DbTerm::Bound(0)));
+    }
+
+    #[test]
+    fn test_machine_names_parameters_with_fresh_variables() {
+        let mut machine = Machine::new(1, vec![]);
+        machine.next_var_id = 100;
+        let term = app(lambda(0, lambda(1, var(0))), var(1));
+        let normal = machine.normalize_term(&term, Strategy::NormalOrder, false).unwrap();
+        assert_eq!(normal, lambda(100, var(1)));
+        assert_eq!(machine.next_var_id, 101);
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use lam::diagnostic::{Diagnostic, Span};
+
+    #[test]
+    fn test_span_line_and_column() {
+        let source = "first line\nsecond line\n";
+        let span = Span::new(source, 18, 22);
+        assert_eq!(span.line, 2);
+        assert_eq!(span.column, 8);
+        assert_eq!(&source[span.start..span.end], "line");
+    }
+
+    #[test]
+    fn test_span_columns_count_characters() {
+        let source = "p('héllo', x).";
+        let start = source.find('x').unwrap();
+        let span = Span::new(source, start, start + 1);
+        assert_eq!(span.column, 12);
+    }
+
+    #[test]
+    fn test_span_of_slice() {
+        let source = "MOVE R0, X1\n";
+        let span = Span::of_slice(source, &source[9..11]);
+        assert_eq!((span.start, span.end, span.line, span.column), (9, 11, 1, 10));
+    }
+
+    #[test]
+    fn test_render_underlines_span() {
+        let source = "parent(john, mary).\nparent(mary, ann.\n";
+        let diagnostic = Diagnostic::new("family.pl", "Expected ')' but found end of clause", Span::new(source, 36, 37));
+        assert_eq!(
+            diagnostic.render(source),
+            "error: Expected ')' but found end of clause\n  --> family.pl:2:17\n  |\n2 | parent(mary, ann.\n  |                 ^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_empty_span_at_end_of_line() {
+        let source = "foo(a)";
+        let diagnostic = Diagnostic::new("x.pl", "Clause must end with a period", Span::new(source, 6, 6));
+        assert!(diagnostic.render(source).ends_with("1 | foo(a)\n  |       ^\n"));
+    }
+
+    #[test]
+    fn test_render_multiline_span_stops_at_end_of_line() {
+        let source = "foo('abc).\nbar.\n";
+        let diagnostic = Diagnostic::new("x.pl", "Unterminated quoted atom", Span::new(source, 4, source.len()));
+        assert!(diagnostic.render(source).ends_with("1 | foo('abc).\n  |     ^^^^^^\n"));
+    }
+
+    #[test]
+    fn test_display() {
+        let source = "a\nbb\n";
+        let diagnostic = Diagnostic::new("x.lam", "bad", Span::new(source, 3, 4));
+        assert_eq!(diagnostic.to_string(), "x.lam:2:2: bad");
+    }
+}
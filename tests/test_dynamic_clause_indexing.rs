@@ -22,12 +22,13 @@ mod tests {
         // Pre-populate the index table for predicate "p" with a dummy key.
         let mut dummy_index: HashMap<Vec<Term>, Vec<usize>> = HashMap::new();
         dummy_index.insert(vec![Term::Const(1)], Vec::new());
-        machine.index_table.insert("p".to_string(), dummy_index);
+        let p = machine.intern("p");
+        machine.index_table.insert(p, dummy_index);
         
         machine.run().expect("Machine run should succeed");
         
         // Check that for key [Const(1)] the clause addresses (42 or 43) are present.
-        if let Some(index_map) = machine.index_table.get("p") {
+        if let Some(index_map) = machine.index_table.get(&p) {
             if let Some(clause_list) = index_map.get(&vec![Term::Const(1)]) {
                 assert!(clause_list.contains(&42) || clause_list.contains(&43),
                         "Clause addresses 42 or 43 should be indexed");
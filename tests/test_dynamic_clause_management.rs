@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
     use lam::machine::error_handling::MachineError;
 
@@ -19,12 +19,12 @@ mod tests {
         // 8: Halt.
         let code = vec![
             Instruction::AssertClause { predicate: "p".to_string(), address: 3 },
-            Instruction::Call { predicate: "p".to_string() },
+            Instruction::Call { predicate: "p".to_string(), target: CallTarget::Unresolved },
             Instruction::Proceed,
             Instruction::PutConst { register: 0, value: 1 },
             Instruction::Proceed,
             Instruction::RetractClause { predicate: "p".to_string(), address: 3 },
-            Instruction::Call { predicate: "p".to_string() },
+            Instruction::Call { predicate: "p".to_string(), target: CallTarget::Unresolved },
             Instruction::Proceed,
             Instruction::Halt,
         ];
@@ -39,6 +39,6 @@ mod tests {
             other => panic!("Expected predicate not found error, got {:?}", other),
         }
         // Verify that register 0 remains as set by the first call.
-        assert_eq!(machine.registers[0], Some(Term::Const(1)));
+        assert_eq!(machine.register(0), Some(Term::Const(1)));
     }
 }
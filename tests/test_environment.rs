@@ -22,8 +22,8 @@ mod tests {
         let mut machine = Machine::new(2, code);
         machine.run().expect("Machine run should succeed");
         
-        assert_eq!(machine.registers[0], Some(Term::Const(42)));
-        assert_eq!(machine.registers[1], Some(Term::Const(99)));
+        assert_eq!(machine.register(0), Some(Term::Const(42)));
+        assert_eq!(machine.register(1), Some(Term::Const(99)));
         assert_eq!(machine.environment_stack.len(), 0);
     }
 }
@@ -3,7 +3,7 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
     use lam::machine::error_handling::MachineError;
 
@@ -14,28 +14,28 @@ mod tests {
         // We assume that the equality built–in expects its left argument in register 0
         // and its right argument in register 1.
         let code = vec![
-            Instruction::Call { predicate: "=".to_string() },
+            Instruction::Call { predicate: "=/2".to_string(), target: CallTarget::Unresolved },
         ];
         let mut machine = Machine::new(2, code);
         // Place two identical constants in registers 0 and 1.
-        machine.registers[0] = Some(Term::Const(42));
-        machine.registers[1] = Some(Term::Const(42));
+        machine.set_register(0, Term::Const(42)).unwrap();
+        machine.set_register(1, Term::Const(42)).unwrap();
         // Execute the equality built–in.
         machine.step().expect("Unification should succeed");
         // Verify that both registers remain unchanged.
-        assert_eq!(machine.registers[0], Some(Term::Const(42)));
-        assert_eq!(machine.registers[1], Some(Term::Const(42)));
+        assert_eq!(machine.register(0), Some(Term::Const(42)));
+        assert_eq!(machine.register(1), Some(Term::Const(42)));
     }
 
     /// Test that the equality built–in fails when unifying two different constants.
     #[test]
     fn test_builtin_eq_failure() {
         let code = vec![
-            Instruction::Call { predicate: "=".to_string() },
+            Instruction::Call { predicate: "=/2".to_string(), target: CallTarget::Unresolved },
         ];
         let mut machine = Machine::new(2, code);
-        machine.registers[0] = Some(Term::Const(42));
-        machine.registers[1] = Some(Term::Const(43));
+        machine.set_register(0, Term::Const(42)).unwrap();
+        machine.set_register(1, Term::Const(43)).unwrap();
         let result = machine.step();
         match result {
             Err(MachineError::UnificationFailed(_)) => { /* Expected failure */ },
@@ -68,9 +68,9 @@ mod tests {
             // Put the string 'Hello world' into register 1.
             Instruction::PutStr { register: 1, value: "Hello world".to_string() },
             // Call the equality built–in to unify register 0 and register 1.
-            Instruction::Call { predicate: "=".to_string() },
+            Instruction::Call { predicate: "=/2".to_string(), target: CallTarget::Unresolved },
             // Call the built–in "write" to print the value of X (now bound).
-            Instruction::Call { predicate: "write".to_string() },
+            Instruction::Call { predicate: "write/1".to_string(), target: CallTarget::Unresolved },
             // Halt the machine.
             Instruction::Halt,
         ];
@@ -78,7 +78,7 @@ mod tests {
         machine.run().expect("Machine run should succeed");
         // Check that unification was successful:
         // The union–find structure should now resolve Var(0) to the string 'Hello world'.
-        let resolved = machine.uf.resolve(&Term::Var(0));
+        let resolved = machine.resolve(&Term::Var(0));
         assert_eq!(resolved, Term::Str("Hello world".to_string()));
     }
 }
@@ -54,17 +54,14 @@ mod tests {
     }
 
     #[test]
-    fn test_get_structure_on_constant_error() {
+    fn test_get_structure_on_constant_fails() {
         let code = vec![
             Instruction::PutConst { register: 0, value: 42 },
             Instruction::GetStructure { register: 0, functor: "f".to_string(), arity: 2 },
         ];
         let mut machine = Machine::new(1, code);
         let result = machine.run();
-        match result {
-            Err(MachineError::NotACompoundTerm(reg)) => assert_eq!(reg, 0),
-            _ => panic!("Expected NotACompoundTerm error on register 0"),
-        }
+        assert!(matches!(result, Err(MachineError::UnificationFailed(_))), "Expected a unification failure, got {:?}", result);
     }
 
     #[test]
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use lam::machine::instruction_parser::parse_program;
+    use lam::machine::program::Program;
+    use lam::prolog::compiler::compile_prolog;
+
+    /// The files in examples/ with the given extension, sorted by name.
+    fn examples(extension: &str) -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir).expect("examples/ should be readable")
+            .map(|entry| entry.expect("examples/ should be readable").path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty(), "examples/ should hold .{} files", extension);
+        paths
+    }
+
+    #[test]
+    fn test_lam_examples_run() {
+        for path in examples("lam") {
+            let source = fs::read_to_string(&path).unwrap();
+            let program = parse_program(&source).unwrap_or_else(|e| panic!("{:?} should parse: {:?}", path, e));
+            let num_registers = program.num_registers();
+            program.into_machine(num_registers).run()
+                .unwrap_or_else(|e| panic!("{:?} should run: {:?}", path, e));
+        }
+    }
+
+    #[test]
+    fn test_prolog_examples_run_and_match_their_lam_files() {
+        for path in examples("pl") {
+            let source = fs::read_to_string(&path).unwrap();
+            let (instructions, predicate_table, num_registers) = compile_prolog(&source)
+                .unwrap_or_else(|e| panic!("{:?} should compile: {}", path, e));
+            let program = Program { instructions, predicate_table, ..Program::default() };
+            // The .lam file is what `lamc` writes for the .pl file.
+            let lam = fs::read_to_string(path.with_extension("lam")).unwrap();
+            assert_eq!(program.to_string(), lam, "{:?} should be regenerated with `lamc {:?}`", path.with_extension("lam"), path);
+            program.into_machine(num_registers).run()
+                .unwrap_or_else(|e| panic!("{:?} should run: {:?}", path, e));
+        }
+    }
+}
@@ -3,11 +3,13 @@ mod tests {
     use std::collections::HashMap;
     use lam::machine::arithmetic::Expression;
     use lam::machine::core::Machine;
+    use lam::machine::heap::Cell;
     use lam::machine::error_handling::MachineError;
     use lam::machine::frame::Frame;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
     use lam::machine::choice_point::ChoicePoint;
+    use lam::machine::stack::Stack;
 
     // ---------------------------
     // execute_put_const tests
@@ -16,7 +18,7 @@ mod tests {
     fn test_execute_put_const_success() {
         let mut machine = Machine::new(2, vec![]);
         machine.execute_put_const(0, 42).unwrap();
-        assert_eq!(machine.registers[0], Some(Term::Const(42)));
+        assert_eq!(machine.register(0), Some(Term::Const(42)));
     }
 
     #[test]
@@ -36,7 +38,7 @@ mod tests {
     fn test_execute_put_var_success() {
         let mut machine = Machine::new(2, vec![]);
         machine.execute_put_var(1, 7, "X".to_string()).unwrap();
-        assert_eq!(machine.registers[1], Some(Term::Var(7)));
+        assert_eq!(machine.register(1), Some(Term::Var(7)));
         assert_eq!(machine.variable_names.get(&7), Some(&"X".to_string()));
     }
 
@@ -56,16 +58,16 @@ mod tests {
     #[test]
     fn test_execute_get_const_success() {
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Const(100));
+        machine.set_register(0, Term::Const(100)).unwrap();
         machine.execute_get_const(0, 100).unwrap();
         // Unification succeeds and register remains unchanged.
-        assert_eq!(machine.registers[0], Some(Term::Const(100)));
+        assert_eq!(machine.register(0), Some(Term::Const(100)));
     }
 
     #[test]
     fn test_execute_get_const_failure_unification() {
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Const(50));
+        machine.set_register(0, Term::Const(50)).unwrap();
         let err = machine.execute_get_const(0, 100).unwrap_err();
         match err {
             MachineError::UnificationFailed(msg) => assert!(msg.contains("Cannot unify")),
@@ -91,7 +93,7 @@ mod tests {
         let mut machine = Machine::new(1, vec![]);
         // When register is uninitialized, GetVar should set it.
         machine.execute_get_var(0, 3, "A".to_string()).unwrap();
-        assert_eq!(machine.registers[0], Some(Term::Var(3)));
+        assert_eq!(machine.register(0), Some(Term::Var(3)));
         assert_eq!(machine.variable_names.get(&3), Some(&"A".to_string()));
     }
 
@@ -99,10 +101,10 @@ mod tests {
     fn test_execute_get_var_with_unification() {
         let mut machine = Machine::new(1, vec![]);
         // Set register 0 to Const(77) so GetVar will unify.
-        machine.registers[0] = Some(Term::Const(77));
+        machine.set_register(0, Term::Const(77)).unwrap();
         machine.execute_get_var(0, 5, "B".to_string()).unwrap();
         // The union–find should bind Var(5) to 77 and update register 0.
-        assert_eq!(machine.registers[0], Some(Term::Const(77)));
+        assert_eq!(machine.register(0), Some(Term::Const(77)));
     }
 
     #[test]
@@ -115,15 +117,53 @@ mod tests {
         }
     }
 
+    // ---------------------------
+    // execute_get_value tests
+    // ---------------------------
+    #[test]
+    fn test_execute_get_value_binds_variable() {
+        let mut machine = Machine::new(2, vec![]);
+        machine.set_register(0, Term::Var(1)).unwrap();
+        machine.set_register(1, Term::Compound("f".to_string(), vec![Term::Const(1)])).unwrap();
+        machine.execute_get_value(0, 1).unwrap();
+        assert_eq!(
+            machine.resolve(&Term::Var(1)),
+            Term::Compound("f".to_string(), vec![Term::Const(1)])
+        );
+    }
+
+    #[test]
+    fn test_execute_get_value_failure_unification() {
+        let mut machine = Machine::new(2, vec![]);
+        machine.set_register(0, Term::Const(1)).unwrap();
+        machine.set_register(1, Term::Const(2)).unwrap();
+        let err = machine.execute_get_value(0, 1).unwrap_err();
+        match err {
+            MachineError::UnificationFailed(msg) => assert!(msg.contains("Cannot unify")),
+            _ => panic!("Expected UnificationFailed error"),
+        }
+    }
+
+    #[test]
+    fn test_execute_get_value_error_uninitialized() {
+        let mut machine = Machine::new(2, vec![]);
+        machine.set_register(0, Term::Const(1)).unwrap();
+        let err = machine.execute_get_value(0, 1).unwrap_err();
+        match err {
+            MachineError::UninitializedRegister(reg) => assert_eq!(reg, 1),
+            _ => panic!("Expected UninitializedRegister error"),
+        }
+    }
+
     // ---------------------------
     // execute_call tests
     // ---------------------------
     #[test]
     fn test_execute_call_builtin() {
         // Built-in "halt" should set pc = code.len()
-        let code = vec![Instruction::Call { predicate: "halt".to_string() }];
+        let code = vec![Instruction::Call { predicate: "halt/0".to_string(), target: CallTarget::Unresolved }];
         let mut machine = Machine::new(1, code);
-        machine.execute_call("halt".to_string()).unwrap();
+        machine.execute_call("halt/0".to_string()).unwrap();
         assert_eq!(machine.pc, machine.code.len());
     }
 
@@ -133,9 +173,10 @@ mod tests {
         let mut machine = Machine::new(1, vec![Instruction::Proceed]);
         machine.register_predicate("dummy".to_string(), 0);
         machine.execute_call("dummy".to_string()).unwrap();
-        // A control frame and a choice point should have been pushed.
+        // A control frame should have been pushed, but no choice point since
+        // the predicate has a single clause.
         assert_eq!(machine.control_stack.len(), 1);
-        assert_eq!(machine.choice_stack.len(), 1);
+        assert!(machine.choice_stack.is_empty());
         // PC should be set to clause address (0).
         assert_eq!(machine.pc, 0);
     }
@@ -156,7 +197,7 @@ mod tests {
     #[test]
     fn test_execute_proceed() {
         let mut machine = Machine::new(1, vec![]);
-        machine.control_stack.push(Frame { return_pc: 42 });
+        machine.control_stack.push(Frame { return_pc: 42, cut_barrier: 0 });
         machine.execute_proceed().unwrap();
         assert_eq!(machine.pc, 42);
         assert!(machine.control_stack.is_empty());
@@ -220,7 +261,7 @@ mod tests {
         );
         let mut machine = Machine::new(1, vec![]);
         machine.execute_arithmetic_is(0, expr).unwrap();
-        assert_eq!(machine.registers[0], Some(Term::Const(14)));
+        assert_eq!(machine.register(0), Some(Term::Const(14)));
     }
 
     #[test]
@@ -243,7 +284,7 @@ mod tests {
         machine.execute_allocate(2).unwrap();
         machine.execute_set_local(1, Term::Const(88)).unwrap();
         let env = machine.environment_stack.last().unwrap();
-        assert_eq!(env[1], Some(Term::Const(88)));
+        assert_eq!(env[1], Some(Cell::Int(88)));
     }
 
     #[test]
@@ -267,6 +308,76 @@ mod tests {
         }
     }
 
+    // ---------------------------
+    // execute_new_var tests
+    // ---------------------------
+    #[test]
+    fn test_execute_new_var_creates_fresh_variables() {
+        let mut machine = Machine::new(2, vec![]);
+        machine.execute_new_var(0, "X".to_string()).unwrap();
+        machine.execute_new_var(1, "X".to_string()).unwrap();
+        let (first, second) = match (&machine.register(0), &machine.register(1)) {
+            (Some(Term::Var(a)), Some(Term::Var(b))) => (*a, *b),
+            other => panic!("Expected two variables, got {:?}", other),
+        };
+        assert_ne!(first, second);
+        assert_eq!(machine.variable_names.get(&second), Some(&"X".to_string()));
+    }
+
+    #[test]
+    fn test_execute_new_var_avoids_ids_in_code() {
+        let code = vec![Instruction::PutVar { register: 0, var_id: 4, name: "Y".to_string() }];
+        let mut machine = Machine::new(1, code);
+        machine.execute_new_var(0, "X".to_string()).unwrap();
+        assert_eq!(machine.register(0), Some(Term::Var(5)));
+    }
+
+    #[test]
+    fn test_execute_new_var_error_out_of_bounds() {
+        let mut machine = Machine::new(1, vec![]);
+        let err = machine.execute_new_var(1, "X".to_string()).unwrap_err();
+        match err {
+            MachineError::RegisterOutOfBounds(reg) => assert_eq!(reg, 1),
+            _ => panic!("Expected RegisterOutOfBounds error"),
+        }
+    }
+
+    // ---------------------------
+    // execute_store_local / execute_put_local tests
+    // ---------------------------
+    #[test]
+    fn test_execute_store_local_and_put_local() {
+        let mut machine = Machine::new(2, vec![]);
+        machine.execute_allocate(1).unwrap();
+        machine.set_register(0, Term::Const(7)).unwrap();
+        machine.execute_store_local(0, 0).unwrap();
+        // PUT_LOCAL overwrites whatever the register held.
+        machine.set_register(1, Term::Const(1)).unwrap();
+        machine.execute_put_local(0, 1).unwrap();
+        assert_eq!(machine.register(1), Some(Term::Const(7)));
+    }
+
+    #[test]
+    fn test_execute_store_local_error_uninitialized_register() {
+        let mut machine = Machine::new(1, vec![]);
+        machine.execute_allocate(1).unwrap();
+        let err = machine.execute_store_local(0, 0).unwrap_err();
+        match err {
+            MachineError::UninitializedRegister(reg) => assert_eq!(reg, 0),
+            _ => panic!("Expected UninitializedRegister error"),
+        }
+    }
+
+    #[test]
+    fn test_execute_put_local_error_no_env() {
+        let mut machine = Machine::new(1, vec![]);
+        let err = machine.execute_put_local(0, 0).unwrap_err();
+        match err {
+            MachineError::EnvironmentMissing => {},
+            _ => panic!("Expected EnvironmentMissing error"),
+        }
+    }
+
     // ---------------------------
     // execute_get_local tests
     // ---------------------------
@@ -277,10 +388,10 @@ mod tests {
         // Set local[0] to Const(77)
         {
             let env = machine.environment_stack.last_mut().unwrap();
-            env[0] = Some(Term::Const(77));
+            env[0] = Some(Cell::Int(77));
         }
         machine.execute_get_local(0, 1).unwrap();
-        assert_eq!(machine.registers[1], Some(Term::Const(77)));
+        assert_eq!(machine.register(1), Some(Term::Const(77)));
     }
 
     #[test]
@@ -313,19 +424,23 @@ mod tests {
         // Prepare a choice point.
         let cp = ChoicePoint {
             saved_pc: 10,
-            saved_registers: vec![Some(Term::Const(5))],
-            saved_substitution: HashMap::new(),
-            saved_control_stack: vec![Frame { return_pc: 20 }],
+            saved_registers: vec![Some(Cell::Int(5))],
+            saved_control_stack: Stack::from(vec![Frame { return_pc: 20, cut_barrier: 0 }]),
+            saved_environment_stack: Stack::new(),
             alternative_clauses: Some(vec![30]),
+            alternative_bindings: None,
             uf_trail_len: machine.uf.trail.len(),
-            call_level: 0,
+            heap_top: 0,
+            var_top: 0,
+            saved_delayed: Vec::new(),
+            cut_barrier: 0,
         };
         machine.choice_stack.push(Box::new(cp));
         // Change the machine state.
-        machine.registers[0] = Some(Term::Const(100));
+        machine.set_register(0, Term::Const(100)).unwrap();
         machine.execute_fail().unwrap();
         // State should be restored from the choice point.
-        assert_eq!(machine.registers[0], Some(Term::Const(5)));
+        assert_eq!(machine.register(0), Some(Term::Const(5)));
         assert_eq!(machine.control_stack.len(), 1);
         assert_eq!(machine.pc, 30);
     }
@@ -347,19 +462,16 @@ mod tests {
     fn test_execute_get_structure_success() {
         let compound = Term::Compound("foo".to_string(), vec![Term::Const(1), Term::Const(2)]);
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(compound);
+        machine.set_register(0, compound).unwrap();
         machine.execute_get_structure(0, "foo".to_string(), 2).unwrap();
     }
 
     #[test]
-    fn test_execute_get_structure_error_not_compound() {
+    fn test_execute_get_structure_fails_on_constant() {
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Const(42));
+        machine.set_register(0, Term::Const(42)).unwrap();
         let err = machine.execute_get_structure(0, "foo".to_string(), 2).unwrap_err();
-        match err {
-            MachineError::NotACompoundTerm(reg) => assert_eq!(reg, 0),
-            _ => panic!("Expected NotACompoundTerm error"),
-        }
+        assert!(matches!(err, MachineError::UnificationFailed(_)), "Expected a unification failure, got {:?}", err);
     }
 
     #[test]
@@ -379,11 +491,12 @@ mod tests {
     fn test_execute_indexed_call_success() {
         let mut machine = Machine::new(1, vec![]);
         // Set register 0 to Const(7)
-        machine.registers[0] = Some(Term::Const(7));
+        machine.set_register(0, Term::Const(7)).unwrap();
         // Prepare an index table for predicate "p" with key [Const(7)] → [100, 200]
         let mut index_map = HashMap::new();
         index_map.insert(vec![Term::Const(7)], vec![100, 200]);
-        machine.index_table.insert("p".to_string(), index_map);
+        let p = machine.intern("p");
+        machine.index_table.insert(p, index_map);
         machine.execute_indexed_call("p".to_string(), 0).unwrap();
         // PC should be set to the first clause (100) and a choice point pushed with alternative [200]
         assert_eq!(machine.pc, 100);
@@ -394,7 +507,7 @@ mod tests {
     #[test]
     fn test_execute_indexed_call_error_no_index_map() {
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Const(5));
+        machine.set_register(0, Term::Const(5)).unwrap();
         let err = machine.execute_indexed_call("nonexistent".to_string(), 0).unwrap_err();
         match err {
             MachineError::PredicateNotInIndex(pred) => assert_eq!(pred, "nonexistent".to_string()),
@@ -405,10 +518,11 @@ mod tests {
     #[test]
     fn test_execute_indexed_call_error_no_index_entry() {
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Const(5));
+        machine.set_register(0, Term::Const(5)).unwrap();
         let mut index_map = HashMap::new();
         index_map.insert(vec![Term::Const(1)], vec![10]);
-        machine.index_table.insert("p".to_string(), index_map);
+        let p = machine.intern("p");
+        machine.index_table.insert(p, index_map);
         let err = machine.execute_indexed_call("p".to_string(), 0).unwrap_err();
         match err {
             MachineError::NoIndexEntry(pred, key) => {
@@ -426,7 +540,7 @@ mod tests {
     fn test_execute_put_str_success() {
         let mut machine = Machine::new(1, vec![]);
         machine.execute_put_str(0, "hello".to_string()).unwrap();
-        assert_eq!(machine.registers[0], Some(Term::Str("hello".to_string())));
+        assert_eq!(machine.register(0), Some(Term::Str("hello".to_string())));
     }
 
     #[test]
@@ -445,14 +559,14 @@ mod tests {
     #[test]
     fn test_execute_get_str_success() {
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Str("world".to_string()));
+        machine.set_register(0, Term::Str("world".to_string())).unwrap();
         machine.execute_get_str(0, "world".to_string()).unwrap();
     }
 
     #[test]
     fn test_execute_get_str_unification_failure() {
         let mut machine = Machine::new(1, vec![]);
-        machine.registers[0] = Some(Term::Str("foo".to_string()));
+        machine.set_register(0, Term::Str("foo".to_string())).unwrap();
         let err = machine.execute_get_str(0, "bar".to_string()).unwrap_err();
         match err {
             MachineError::UnificationFailed(msg) => assert!(msg.contains("Cannot unify")),
@@ -476,11 +590,12 @@ mod tests {
     #[test]
     fn test_execute_multi_indexed_call_success() {
         let mut machine = Machine::new(2, vec![]);
-        machine.registers[0] = Some(Term::Const(3));
-        machine.registers[1] = Some(Term::Const(4));
+        machine.set_register(0, Term::Const(3)).unwrap();
+        machine.set_register(1, Term::Const(4)).unwrap();
         let mut index_map = HashMap::new();
         index_map.insert(vec![Term::Const(3), Term::Const(4)], vec![300]);
-        machine.index_table.insert("p".to_string(), index_map);
+        let p = machine.intern("p");
+        machine.index_table.insert(p, index_map);
         machine.execute_multi_indexed_call("p".to_string(), vec![0, 1]).unwrap();
         assert_eq!(machine.pc, 300);
     }
@@ -502,10 +617,10 @@ mod tests {
     fn test_execute_tail_call_builtin() {
         // Tail call using built-in "halt" should deallocate environment and set pc accordingly.
         let mut machine = Machine::new(1, vec![
-            Instruction::TailCall { predicate: "halt".to_string() },
+            Instruction::TailCall { predicate: "halt/0".to_string(), target: CallTarget::Unresolved },
         ]);
         machine.execute_allocate(1).unwrap();
-        machine.execute_tail_call("halt".to_string()).unwrap();
+        machine.execute_tail_call("halt/0".to_string()).unwrap();
         assert_eq!(machine.pc, machine.code.len());
         assert!(machine.environment_stack.is_empty());
     }
@@ -514,7 +629,7 @@ mod tests {
     fn test_execute_tail_call_user_defined() {
         // Tail call for a user-defined predicate "dummy"
         let mut machine = Machine::new(1, vec![
-            Instruction::TailCall { predicate: "dummy".to_string() },
+            Instruction::TailCall { predicate: "dummy".to_string(), target: CallTarget::Unresolved },
         ]);
         machine.execute_allocate(1).unwrap();
         machine.register_predicate("dummy".to_string(), 50);
@@ -528,13 +643,13 @@ mod tests {
         machine.step().unwrap();
         // The environment should have been deallocated and clause executed.
         assert!(machine.environment_stack.is_empty());
-        assert_eq!(machine.registers[0], Some(Term::Const(555)));
+        assert_eq!(machine.register(0), Some(Term::Const(555)));
     }
 
     #[test]
     fn test_execute_tail_call_error_no_env() {
         let mut machine = Machine::new(1, vec![
-            Instruction::TailCall { predicate: "dummy".to_string() },
+            Instruction::TailCall { predicate: "dummy".to_string(), target: CallTarget::Unresolved },
         ]);
         let err = machine.execute_tail_call("dummy".to_string()).unwrap_err();
         match err {
@@ -550,13 +665,14 @@ mod tests {
     fn test_execute_assert_clause() {
         let mut machine = Machine::new(1, vec![]);
         machine.execute_assert_clause("p".to_string(), 123).unwrap();
-        assert!(machine.predicate_table.get("p").unwrap().contains(&123));
+        let p = machine.intern("p");
+        assert!(machine.predicate_table.get(&p).unwrap().contains(&123));
         // If an index table exists for "p", it should be updated.
         let mut index_map = HashMap::new();
         index_map.insert(vec![Term::Const(10)], vec![10]);
-        machine.index_table.insert("p".to_string(), index_map);
+        machine.index_table.insert(p, index_map);
         machine.execute_assert_clause("p".to_string(), 456).unwrap();
-        let clauses = machine.index_table.get("p").unwrap().get(&vec![Term::Const(10)]).unwrap();
+        let clauses = machine.index_table.get(&p).unwrap().get(&vec![Term::Const(10)]).unwrap();
         assert!(clauses.contains(&456));
     }
 
@@ -568,7 +684,8 @@ mod tests {
         let mut machine = Machine::new(1, vec![]);
         machine.register_predicate("p".to_string(), 777);
         machine.execute_retract_clause("p".to_string(), 777).unwrap();
-        assert!(machine.predicate_table.get("p").unwrap().is_empty());
+        let p = machine.intern("p");
+        assert!(machine.predicate_table.get(&p).unwrap().is_empty());
     }
 
     #[test]
@@ -598,35 +715,42 @@ mod tests {
     #[test]
     fn test_execute_cut() {
         let mut machine = Machine::new(1, vec![]);
-        // Simulate a control stack of length 2.
-        machine.control_stack.push(Frame { return_pc: 10 });
-        machine.control_stack.push(Frame { return_pc: 20 });
-        // Add two choice points with different call levels.
+        // Simulate a predicate entered with one choice point below it.
+        machine.cut_barrier = 1;
+        // Add a choice point below the cut barrier and one above it.
         let cp1 = ChoicePoint {
             saved_pc: 5,
             saved_registers: vec![],
-            saved_substitution: HashMap::new(),
-            saved_control_stack: vec![],
+            saved_control_stack: Stack::from(vec![]),
+            saved_environment_stack: Stack::new(),
             alternative_clauses: Some(vec![5]),
+            alternative_bindings: None,
             uf_trail_len: 0,
-            call_level: 1,
+            heap_top: 0,
+            var_top: 0,
+            saved_delayed: Vec::new(),
+            cut_barrier: 0,
         };
         let cp2 = ChoicePoint {
             saved_pc: 6,
             saved_registers: vec![],
-            saved_substitution: HashMap::new(),
-            saved_control_stack: vec![],
+            saved_control_stack: Stack::from(vec![]),
+            saved_environment_stack: Stack::new(),
             alternative_clauses: Some(vec![6]),
+            alternative_bindings: None,
             uf_trail_len: 0,
-            call_level: 2,
+            heap_top: 0,
+            var_top: 0,
+            saved_delayed: Vec::new(),
+            cut_barrier: 1,
         };
         machine.choice_stack.push(Box::new(cp1));
         machine.choice_stack.push(Box::new(cp2));
         machine.execute_cut().unwrap();
-        // Only choice points with call_level < current (2) should remain.
+        // Only the choice point below the cut barrier should remain.
         assert_eq!(machine.choice_stack.len(), 1);
         let remaining_cp = &machine.choice_stack[0];
-        assert_eq!(remaining_cp.call_level, 1);
+        assert_eq!(remaining_cp.saved_pc, 5);
     }
 
     // ---------------------------
@@ -635,18 +759,18 @@ mod tests {
     #[test]
     fn test_execute_build_compound_success() {
         let mut machine = Machine::new(3, vec![]);
-        machine.registers[0] = Some(Term::Const(10));
-        machine.registers[1] = Some(Term::Const(20));
+        machine.set_register(0, Term::Const(10)).unwrap();
+        machine.set_register(1, Term::Const(20)).unwrap();
         machine.execute_build_compound(2, "f".to_string(), vec![0, 1]).unwrap();
         let expected = Term::Compound("f".to_string(), vec![Term::Const(10), Term::Const(20)]);
-        assert_eq!(machine.registers[2], Some(expected));
+        assert_eq!(machine.register(2), Some(expected));
     }
 
     #[test]
     fn test_execute_build_compound_error_arg_register_uninitialized() {
         let mut machine = Machine::new(3, vec![]);
         // Register 0 is uninitialized.
-        machine.registers[1] = Some(Term::Const(20));
+        machine.set_register(1, Term::Const(20)).unwrap();
         let err = machine.execute_build_compound(2, "f".to_string(), vec![0, 1]).unwrap_err();
         match err {
             MachineError::UninitializedRegister(reg) => assert_eq!(reg, 0),
@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod tests {
-    use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::core::{Machine, UnifyMode};
+    use lam::machine::error_handling::MachineError;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
 
     #[test]
@@ -17,6 +18,110 @@ mod tests {
         machine.run().expect("Machine run should succeed");
         
         let expected = Term::Compound("f".to_string(), vec![Term::Const(1), Term::Const(2)]);
-        assert_eq!(machine.registers[2], Some(expected));
+        assert_eq!(machine.register(2), Some(expected));
+    }
+
+    #[test]
+    fn test_get_structure_read_mode_unifies_arguments() {
+        let code = vec![
+            Instruction::PutConst { register: 0, value: 1 },
+            Instruction::PutVar { register: 1, var_id: 0, name: "Y".to_string() },
+            Instruction::BuildCompound { target: 2, functor: "f".to_string(), arg_registers: vec![0, 1] },
+            Instruction::PutConst { register: 3, value: 2 },
+            Instruction::GetStructure { register: 2, functor: "f".to_string(), arity: 2 },
+            Instruction::UnifyVar { register: 4 },
+            Instruction::UnifyValue { register: 3 },
+        ];
+        let mut machine = Machine::new(5, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.mode, UnifyMode::Read);
+        assert_eq!(machine.register(4), Some(Term::Const(1)));
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(2));
+    }
+
+    #[test]
+    fn test_get_structure_write_mode_builds_and_binds() {
+        let code = vec![
+            Instruction::PutVar { register: 0, var_id: 0, name: "X".to_string() },
+            Instruction::PutConst { register: 1, value: 7 },
+            Instruction::GetStructure { register: 0, functor: "f".to_string(), arity: 3 },
+            Instruction::UnifyConst { value: 1 },
+            Instruction::UnifyVar { register: 2 },
+            Instruction::UnifyValue { register: 1 },
+        ];
+        let mut machine = Machine::new(3, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.mode, UnifyMode::Write);
+        let Some(Term::Var(fresh)) = machine.register(2) else { panic!("Expected a fresh variable") };
+        let expected = Term::Compound("f".to_string(), vec![Term::Const(1), Term::Var(fresh), Term::Const(7)]);
+        assert_eq!(machine.resolve_deep(&Term::Var(0)), expected);
+    }
+
+    #[test]
+    fn test_get_structure_read_mode_mismatch_backtracks() {
+        let code = vec![
+            Instruction::Choice { alternative: 6 },
+            Instruction::PutConst { register: 0, value: 1 },
+            Instruction::BuildCompound { target: 1, functor: "f".to_string(), arg_registers: vec![0] },
+            Instruction::GetStructure { register: 1, functor: "f".to_string(), arity: 1 },
+            Instruction::UnifyConst { value: 2 },
+            Instruction::Halt,
+            Instruction::PutConst { register: 0, value: 99 },
+        ];
+        let mut machine = Machine::new(2, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(0), Some(Term::Const(99)));
+    }
+
+    #[test]
+    fn test_write_mode_binding_is_undone_on_backtracking() {
+        let code = vec![
+            Instruction::PutVar { register: 0, var_id: 0, name: "X".to_string() },
+            Instruction::Choice { alternative: 5 },
+            Instruction::GetStructure { register: 0, functor: "f".to_string(), arity: 1 },
+            Instruction::UnifyConst { value: 1 },
+            Instruction::Fail,
+            Instruction::Halt,
+        ];
+        let mut machine = Machine::new(1, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Var(0));
+    }
+
+    #[test]
+    fn test_unify_without_structure_argument_errors() {
+        let code = vec![
+            Instruction::PutVar { register: 0, var_id: 0, name: "X".to_string() },
+            Instruction::GetStructure { register: 0, functor: "f".to_string(), arity: 1 },
+            Instruction::UnifyConst { value: 1 },
+            Instruction::UnifyConst { value: 2 },
+        ];
+        let mut machine = Machine::new(1, code);
+        match machine.run() {
+            Err(MachineError::NoStructureArgument) => {},
+            other => panic!("Expected NoStructureArgument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_get_structure_mismatch_tries_next_clause() {
+        // p(g(X)) is tried first and does not match f(a); p(f(X)) does.
+        let code = vec![
+            Instruction::Call { predicate: "p".to_string(), target: CallTarget::Unresolved },
+            Instruction::Halt,
+            Instruction::GetStructure { register: 0, functor: "g".to_string(), arity: 1 },
+            Instruction::UnifyVar { register: 1 },
+            Instruction::Proceed,
+            Instruction::GetStructure { register: 0, functor: "f".to_string(), arity: 1 },
+            Instruction::UnifyVar { register: 1 },
+            Instruction::Proceed,
+        ];
+        let mut machine = Machine::new(2, code);
+        machine.register_predicate("p".to_string(), 2);
+        machine.register_predicate("p".to_string(), 5);
+        let term = Term::Compound("f".to_string(), vec![Term::Str("a".to_string())]);
+        machine.set_register(0, term).unwrap();
+        machine.run().expect("The second clause should match");
+        assert_eq!(machine.register(1), Some(Term::Str("a".to_string())));
     }
 }
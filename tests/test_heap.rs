@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use lam::machine::heap::{Cell, Heap};
+    use lam::machine::term::Term;
+
+    #[test]
+    fn test_put_term_round_trip() {
+        let mut heap = Heap::new();
+        let term = Term::Compound("f".to_string(), vec![
+            Term::Const(1),
+            Term::Var(0),
+            Term::Compound("g".to_string(), vec![Term::Str("a".to_string())]),
+        ]);
+        let cell = heap.put_term(&term);
+        assert_eq!(heap.term(&cell), term);
+    }
+
+    #[test]
+    fn test_simple_terms_need_no_heap_cells() {
+        let mut heap = Heap::new();
+        assert_eq!(heap.put_term(&Term::Const(7)), Cell::Int(7));
+        assert_eq!(heap.put_term(&Term::Var(3)), Cell::Var(3));
+        let atom = heap.put_term(&Term::Str("a".to_string()));
+        assert!(matches!(atom, Cell::Atom(_)));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_structure_arguments_are_contiguous() {
+        let mut heap = Heap::new();
+        let term = Term::Compound("f".to_string(), vec![
+            Term::Compound("g".to_string(), vec![Term::Const(1)]),
+            Term::Const(2),
+        ]);
+        let Cell::Str(address) = heap.put_term(&term) else { panic!("Expected a structure cell") };
+        let (functor, args) = heap.arguments(address);
+        assert_eq!(heap.functor_name(functor), "f");
+        assert_eq!(heap.functor_arity(functor), 2);
+        assert!(matches!(args[0], Cell::Str(_)));
+        assert_eq!(args[1], Cell::Int(2));
+    }
+
+    #[test]
+    fn test_functors_are_interned_by_name_and_arity() {
+        let mut heap = Heap::new();
+        let f1 = heap.functor("f", 1);
+        let f2 = heap.functor("f", 2);
+        assert_ne!(f1, f2);
+        assert_eq!(heap.functor("f", 1), f1);
+        assert_eq!(heap.atom("f"), heap.atom("f"));
+    }
+
+    #[test]
+    fn test_copying_a_cell_shares_the_structure() {
+        let mut heap = Heap::new();
+        let term = Term::Compound("f".to_string(), vec![Term::Const(1), Term::Const(2)]);
+        let cell = heap.put_term(&term);
+        let len = heap.len();
+        let copy = cell.clone();
+        assert_eq!(heap.len(), len);
+        assert_eq!(heap.term(&copy), term);
+    }
+
+    #[test]
+    fn test_truncate_discards_newer_cells() {
+        let mut heap = Heap::new();
+        let first = heap.put_term(&Term::Compound("f".to_string(), vec![Term::Const(1)]));
+        let top = heap.len();
+        heap.put_term(&Term::Compound("g".to_string(), vec![Term::Const(2)]));
+        heap.truncate(top);
+        assert_eq!(heap.len(), top);
+        assert_eq!(heap.term(&first), Term::Compound("f".to_string(), vec![Term::Const(1)]));
+    }
+
+    #[test]
+    fn test_extended_terms_are_shared_whole() {
+        let mut heap = Heap::new();
+        let term = Term::Lambda(0, Box::new(Term::Var(0)));
+        let cell = heap.put_term(&term);
+        assert!(matches!(cell, Cell::Term(_)));
+        assert_eq!(heap.term(&cell), term);
+    }
+}
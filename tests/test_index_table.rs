@@ -17,13 +17,14 @@ mod tests {
         let mut index_map: HashMap<Vec<Term>, Vec<usize>> = HashMap::new();
         index_map.insert(key1.clone(), vec![42, 43]);
         index_map.insert(key2.clone(), vec![42]);
-        machine.index_table.insert("p".to_string(), index_map);
+        let p = machine.intern("p");
+        machine.index_table.insert(p, index_map);
 
         // Call update_index_table_on_retract to remove clause address 42
         machine.update_index_table_on_retract("p", 42);
 
         // Retrieve and check the updated index table.
-        let updated_index = machine.index_table.get("p").unwrap();
+        let updated_index = machine.index_table.get(&p).unwrap();
         // For key1, only 43 should remain.
         assert_eq!(updated_index.get(&key1).unwrap(), &vec![43]);
         // For key2, the list should now be empty.
@@ -24,11 +24,11 @@ mod tests {
         machine.register_indexed_clause("p".to_string(), vec![Term::Const(2)], 3);
         
         // Set register 0 to key [Const(2)].
-        machine.registers[0] = Some(Term::Const(2));
+        machine.set_register(0, Term::Const(2)).unwrap();
         
         machine.run().expect("Machine run should succeed");
         
         // Expect that Clause for key [Const(2)] was chosen, so reg0 becomes 20.
-        assert_eq!(machine.registers[0], Some(Term::Const(20)));
+        assert_eq!(machine.register(0), Some(Term::Const(20)));
     }
 }
@@ -3,10 +3,12 @@ mod tests {
     use std::collections::HashMap;
     use lam::machine::arithmetic::Expression;
     use lam::machine::choice_point::ChoicePoint;
+    use lam::machine::stack::Stack;
     use lam::machine::core::Machine;
+    use lam::machine::heap::Cell;
     use lam::machine::error_handling::MachineError;
     use lam::machine::frame::Frame;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
 
     // === Basic Instructions ===
@@ -18,7 +20,7 @@ mod tests {
         ];
         let mut machine = Machine::new(2, code);
         machine.step().unwrap();
-        assert_eq!(machine.registers[0], Some(Term::Const(42)));
+        assert_eq!(machine.register(0), Some(Term::Const(42)));
     }
 
     #[test]
@@ -28,7 +30,7 @@ mod tests {
         ];
         let mut machine = Machine::new(3, code);
         machine.step().unwrap();
-        assert_eq!(machine.registers[1], Some(Term::Var(5)));
+        assert_eq!(machine.register(1), Some(Term::Var(5)));
         assert_eq!(machine.variable_names.get(&5), Some(&"X".to_string()));
     }
 
@@ -42,7 +44,7 @@ mod tests {
         machine.step().unwrap(); // PutConst
         machine.step().unwrap(); // GetConst
         // The term unifies so register remains unchanged.
-        assert_eq!(machine.registers[0], Some(Term::Const(100)));
+        assert_eq!(machine.register(0), Some(Term::Const(100)));
     }
 
     #[test]
@@ -68,7 +70,7 @@ mod tests {
         let mut machine = Machine::new(1, code);
         machine.step().unwrap();
         // Since the register was uninitialized, it should now be set to Var(10)
-        assert_eq!(machine.registers[0], Some(Term::Var(10)));
+        assert_eq!(machine.register(0), Some(Term::Var(10)));
         assert_eq!(machine.variable_names.get(&10), Some(&"Y".to_string()));
     }
 
@@ -82,7 +84,7 @@ mod tests {
         machine.step().unwrap(); // PutConst
         machine.step().unwrap(); // GetVar (should unify Var(20) with Const(77))
         // The register remains Const(77) and union-find binds Var(20) accordingly.
-        assert_eq!(machine.registers[0], Some(Term::Const(77)));
+        assert_eq!(machine.register(0), Some(Term::Const(77)));
     }
 
     // === Arithmetic Instruction ===
@@ -98,7 +100,39 @@ mod tests {
         let mut machine = Machine::new(1, vec![instr.clone()]);
         // Execute the instruction via the execute() method.
         instr.execute(&mut machine).unwrap();
-        assert_eq!(machine.registers[0], Some(Term::Const(7)));
+        assert_eq!(machine.register(0), Some(Term::Const(7)));
+    }
+
+    #[test]
+    fn test_arithmetic_is_reads_bound_variables() {
+        // R0 holds a variable that GET_CONST binds to 5.
+        let code = vec![
+            Instruction::PutVar { register: 0, var_id: 0, name: "X".to_string() },
+            Instruction::GetConst { register: 0, value: 5 },
+            Instruction::ArithmeticIs { target: 1, expression: Expression::Add(Box::new(Expression::Var(0)), Box::new(Expression::Const(1))) },
+        ];
+        let mut machine = Machine::new(2, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(1), Some(Term::Const(6)));
+    }
+
+    #[test]
+    fn test_arithmetic_is_promotes_to_big_integers() {
+        // R0 * R0 overflows an i32, so the result is stored as a BigInt; a
+        // result that fits again is stored as Const.
+        let square = Expression::Mul(Box::new(Expression::Var(0)), Box::new(Expression::Var(0)));
+        let code = vec![
+            Instruction::PutConst { register: 0, value: 100_000 },
+            Instruction::ArithmeticIs { target: 1, expression: square },
+            Instruction::ArithmeticIs { target: 2, expression: Expression::Sub(Box::new(Expression::Var(1)), Box::new(Expression::Var(1))) },
+            Instruction::PutBigInt { register: 3, value: 10_000_000_000i64.into() },
+            Instruction::GetBigInt { register: 1, value: 10_000_000_000i64.into() },
+        ];
+        let mut machine = Machine::new(4, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(1), Some(Term::BigInt(10_000_000_000i64.into())));
+        assert_eq!(machine.register(2), Some(Term::Const(0)));
+        assert_eq!(machine.register(3), machine.register(1));
     }
 
     // === Control Flow Instructions ===
@@ -107,7 +141,7 @@ mod tests {
     fn test_call_instruction_builtin() {
         // Calling built-in "halt" should stop execution.
         let code = vec![
-            Instruction::Call { predicate: "halt".to_string() },
+            Instruction::Call { predicate: "halt/0".to_string(), target: CallTarget::Unresolved },
         ];
         let mut machine = Machine::new(1, code);
         machine.step().unwrap();
@@ -119,7 +153,7 @@ mod tests {
     fn test_call_instruction_user_defined() {
         // Test a user-defined call: register a predicate "dummy" with two clause addresses.
         let code = vec![
-            Instruction::Call { predicate: "dummy".to_string() },
+            Instruction::Call { predicate: "dummy".to_string(), target: CallTarget::Unresolved },
         ];
         let mut machine = Machine::new(1, code);
         machine.register_predicate("dummy".to_string(), 5);
@@ -139,7 +173,7 @@ mod tests {
         ];
         let mut machine = Machine::new(1, code);
         // Simulate a call by pushing a frame.
-        machine.control_stack.push(Frame { return_pc: 42 });
+        machine.control_stack.push(Frame { return_pc: 42, cut_barrier: 0 });
         machine.step().unwrap();
         assert_eq!(machine.pc, 42);
         assert!(machine.control_stack.is_empty());
@@ -181,7 +215,7 @@ mod tests {
             Instruction::Deallocate,
         ];
         let mut machine = Machine::new(1, code);
-        machine.environment_stack.push(vec![Some(Term::Const(1))]);
+        machine.environment_stack.push(vec![Some(Cell::Int(1))]);
         machine.step().unwrap();
         assert!(machine.environment_stack.is_empty());
     }
@@ -209,7 +243,7 @@ mod tests {
         machine.step().unwrap(); // Allocate
         machine.step().unwrap(); // SetLocal
         let env = machine.environment_stack.last().unwrap();
-        assert_eq!(env[1], Some(Term::Const(99)));
+        assert_eq!(env[1], Some(Cell::Int(99)));
     }
 
     #[test]
@@ -223,7 +257,7 @@ mod tests {
         machine.step().unwrap(); // Allocate
         machine.step().unwrap(); // SetLocal
         machine.step().unwrap(); // GetLocal
-        assert_eq!(machine.registers[0], Some(Term::Const(77)));
+        assert_eq!(machine.register(0), Some(Term::Const(77)));
     }
 
     // === Failure and Backtracking Instructions ===
@@ -237,19 +271,23 @@ mod tests {
         let mut machine = Machine::new(1, code);
         let cp = ChoicePoint {
             saved_pc: 10,
-            saved_registers: vec![Some(Term::Const(5))],
-            saved_substitution: HashMap::new(),
-            saved_control_stack: vec![Frame { return_pc: 20 }],
+            saved_registers: vec![Some(Cell::Int(5))],
+            saved_control_stack: Stack::from(vec![Frame { return_pc: 20, cut_barrier: 0 }]),
+            saved_environment_stack: Stack::new(),
             alternative_clauses: Some(vec![30]),
+            alternative_bindings: None,
             uf_trail_len: machine.uf.trail.len(),
-            call_level: 0,
+            heap_top: 0,
+            var_top: 0,
+            saved_delayed: Vec::new(),
+            cut_barrier: 0,
         };
         machine.choice_stack.push(Box::new(cp));
         // Modify register state.
-        machine.registers[0] = Some(Term::Const(100));
+        machine.set_register(0, Term::Const(100)).unwrap();
         machine.step().unwrap();
         // After failure, state should be restored.
-        assert_eq!(machine.registers[0], Some(Term::Const(5)));
+        assert_eq!(machine.register(0), Some(Term::Const(5)));
         assert_eq!(machine.control_stack.len(), 1);
         assert_eq!(machine.pc, 30);
     }
@@ -269,7 +307,7 @@ mod tests {
         machine.step().unwrap(); // PutConst reg1
         machine.step().unwrap(); // BuildCompound into reg2
         machine.step().unwrap(); // GetStructure
-        assert_eq!(machine.registers[2], Some(compound));
+        assert_eq!(machine.register(2), Some(compound));
     }
 
     // === Clause Indexing Instructions ===
@@ -283,7 +321,8 @@ mod tests {
         let mut machine = Machine::new(1, code);
         let mut index_map = HashMap::new();
         index_map.insert(vec![Term::Const(7)], vec![100]);
-        machine.index_table.insert("p".to_string(), index_map);
+        let p = machine.intern("p");
+        machine.index_table.insert(p, index_map);
         machine.step().unwrap(); // PutConst sets register 0
         machine.step().unwrap(); // IndexedCall should update PC
         assert_eq!(machine.pc, 100);
@@ -301,7 +340,8 @@ mod tests {
         machine.step().unwrap(); // PutConst reg1
         let mut index_map = HashMap::new();
         index_map.insert(vec![Term::Const(3), Term::Const(4)], vec![300]);
-        machine.index_table.insert("p".to_string(), index_map);
+        let p = machine.intern("p");
+        machine.index_table.insert(p, index_map);
         machine.step().unwrap(); // MultiIndexedCall
         assert_eq!(machine.pc, 300);
     }
@@ -313,7 +353,7 @@ mod tests {
         // TailCall with built-in "halt" deallocates environment and halts.
         let code = vec![
             Instruction::Allocate { n: 1 },
-            Instruction::TailCall { predicate: "halt".to_string() },
+            Instruction::TailCall { predicate: "halt/0".to_string(), target: CallTarget::Unresolved },
         ];
         let mut machine = Machine::new(1, code);
         machine.step().unwrap(); // Allocate
@@ -327,7 +367,7 @@ mod tests {
     fn test_tail_call_instruction_user_defined() {
         let code = vec![
             Instruction::Allocate { n: 1 },
-            Instruction::TailCall { predicate: "dummy".to_string() },
+            Instruction::TailCall { predicate: "dummy".to_string(), target: CallTarget::Unresolved },
         ];
         let mut machine = Machine::new(1, code);
         machine.step().unwrap(); // Allocate
@@ -338,7 +378,7 @@ mod tests {
         machine.code.push(Instruction::Proceed);
         machine.step().unwrap(); // TailCall for dummy
         machine.step().unwrap(); // Execute instruction at address 50
-        assert_eq!(machine.registers[0], Some(Term::Const(555)));
+        assert_eq!(machine.register(0), Some(Term::Const(555)));
         assert!(machine.environment_stack.is_empty());
     }
 
@@ -352,16 +392,17 @@ mod tests {
         let mut machine = Machine::new(1, code);
         machine.step().unwrap();
         // Check that the predicate table has the new clause.
-        assert!(machine.predicate_table.get("p").unwrap().contains(&123));
+        let p = machine.intern("p");
+        assert!(machine.predicate_table.get(&p).unwrap().contains(&123));
         // If an index table for "p" exists, the clause should be appended.
-        machine.index_table.insert("p".to_string(), {
+        machine.index_table.insert(p, {
             let mut map = HashMap::new();
             map.insert(vec![Term::Const(10)], vec![10]);
             map
         });
         let instr = Instruction::AssertClause { predicate: "p".to_string(), address: 456 };
         instr.execute(&mut machine).unwrap();
-        let index_map = machine.index_table.get("p").unwrap();
+        let index_map = machine.index_table.get(&p).unwrap();
         let clauses = index_map.get(&vec![Term::Const(10)]).unwrap();
         assert!(clauses.contains(&456));
     }
@@ -375,7 +416,8 @@ mod tests {
         let mut machine = Machine::new(1, code);
         machine.step().unwrap(); // AssertClause
         machine.step().unwrap(); // RetractClause
-        assert!(machine.predicate_table.get("p").unwrap().is_empty());
+        let p = machine.intern("p");
+        assert!(machine.predicate_table.get(&p).unwrap().is_empty());
     }
 
     #[test]
@@ -397,34 +439,41 @@ mod tests {
             Instruction::Cut,
         ];
         let mut machine = Machine::new(1, code);
-        // Simulate two choice points with different call levels.
-        machine.control_stack.push(Frame { return_pc: 10 });
-        machine.control_stack.push(Frame { return_pc: 20 });
+        // Simulate a predicate entered with one choice point below it.
+        machine.cut_barrier = 1;
         let cp1 = ChoicePoint {
             saved_pc: 5,
             saved_registers: vec![],
-            saved_substitution: HashMap::new(),
-            saved_control_stack: vec![],
+            saved_control_stack: Stack::from(vec![]),
+            saved_environment_stack: Stack::new(),
             alternative_clauses: Some(vec![5]),
+            alternative_bindings: None,
             uf_trail_len: 0,
-            call_level: 1,
+            heap_top: 0,
+            var_top: 0,
+            saved_delayed: Vec::new(),
+            cut_barrier: 0,
         };
         let cp2 = ChoicePoint {
             saved_pc: 6,
             saved_registers: vec![],
-            saved_substitution: HashMap::new(),
-            saved_control_stack: vec![],
+            saved_control_stack: Stack::from(vec![]),
+            saved_environment_stack: Stack::new(),
             alternative_clauses: Some(vec![6]),
+            alternative_bindings: None,
             uf_trail_len: 0,
-            call_level: 2,
+            heap_top: 0,
+            var_top: 0,
+            saved_delayed: Vec::new(),
+            cut_barrier: 1,
         };
         machine.choice_stack.push(Box::new(cp1));
         machine.choice_stack.push(Box::new(cp2));
         machine.step().unwrap(); // Execute Cut
-        // Only choice points with call_level less than the current control stack length (2) remain.
+        // Only the choice point below the cut barrier remains.
         assert_eq!(machine.choice_stack.len(), 1);
         let remaining_cp = machine.choice_stack[0].clone();
-        assert_eq!(remaining_cp.call_level, 1);
+        assert_eq!(remaining_cp.saved_pc, 5);
     }
 
     #[test]
@@ -439,7 +488,7 @@ mod tests {
         machine.step().unwrap(); // PutConst reg1
         machine.step().unwrap(); // BuildCompound
         let expected = Term::Compound("f".to_string(), vec![Term::Const(10), Term::Const(20)]);
-        assert_eq!(machine.registers[2], Some(expected));
+        assert_eq!(machine.register(2), Some(expected));
     }
 
     // === String Handling and Move ===
@@ -451,7 +500,7 @@ mod tests {
         ];
         let mut machine = Machine::new(1, code);
         machine.step().unwrap();
-        assert_eq!(machine.registers[0], Some(Term::Str("hello".to_string())));
+        assert_eq!(machine.register(0), Some(Term::Str("hello".to_string())));
     }
 
     #[test]
@@ -463,7 +512,7 @@ mod tests {
         let mut machine = Machine::new(1, code);
         machine.step().unwrap(); // PutStr
         machine.step().unwrap(); // GetStr
-        assert_eq!(machine.registers[0], Some(Term::Str("world".to_string())));
+        assert_eq!(machine.register(0), Some(Term::Str("world".to_string())));
     }
 
     #[test]
@@ -475,7 +524,7 @@ mod tests {
         let mut machine = Machine::new(3, code);
         machine.step().unwrap(); // PutConst
         machine.step().unwrap(); // Move
-        assert_eq!(machine.registers[1], Some(Term::Const(77)));
+        assert_eq!(machine.register(1), Some(Term::Const(77)));
     }
 
     #[test]
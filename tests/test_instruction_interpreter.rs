@@ -1,6 +1,7 @@
 use lam::machine::instruction_parser::parse_instructions;
 use lam::machine::instruction::Instruction;
 use lam::machine::term::Term;
+use lam::machine::number::Float;
 use lam::machine::arithmetic::Expression;
 
 #[test]
@@ -67,7 +68,7 @@ fn test_call() {
     let instructions = parse_instructions(input).expect("Failed to parse CALL");
     assert_eq!(instructions.len(), 1);
     match &instructions[0] {
-        Instruction::Call { predicate } => {
+        Instruction::Call { predicate, .. } => {
             assert_eq!(predicate, "halt");
         }
         _ => panic!("Expected Call instruction"),
@@ -131,6 +132,32 @@ fn test_arithmetic_is() {
     }
 }
 
+#[test]
+fn test_arithmetic_is_with_function_call() {
+    // The comma inside the call does not separate parameters.
+    let instructions = parse_instructions("ARITHMETIC_IS R0, max(R1, 2) + 1").expect("Failed to parse ARITHMETIC_IS");
+    assert_eq!(instructions, vec![Instruction::ArithmeticIs {
+        target: 0,
+        expression: Expression::Add(
+            Box::new(Expression::Max(Box::new(Expression::Var(1)), Box::new(Expression::Const(2)))),
+            Box::new(Expression::Const(1)),
+        ),
+    }]);
+}
+
+#[test]
+fn test_float_literals() {
+    let instructions = parse_instructions("PUT_FLOAT R0, 2.5\nGET_FLOAT R1, -1.0e-3\nSET_LOCAL 0, 0.5")
+        .expect("Failed to parse float literals");
+    assert_eq!(instructions, vec![
+        Instruction::PutFloat { register: 0, value: 2.5 },
+        Instruction::GetFloat { register: 1, value: -0.001 },
+        Instruction::SetLocal { index: 0, value: Term::Float(Float(0.5)) },
+    ]);
+    assert_eq!(instructions[1].to_string(), "GET_FLOAT R1, -0.001");
+    assert!(parse_instructions("PUT_FLOAT R0, inf").is_err());
+}
+
 #[test]
 fn test_set_local() {
     let input = "SET_LOCAL 0, 42";
@@ -219,7 +246,7 @@ fn test_tail_call() {
     let instructions = parse_instructions(input).expect("Failed to parse TAIL_CALL");
     assert_eq!(instructions.len(), 1);
     match &instructions[0] {
-        Instruction::TailCall { predicate } => {
+        Instruction::TailCall { predicate, .. } => {
             assert_eq!(predicate, "dummy");
         }
         _ => panic!("Expected TailCall instruction"),
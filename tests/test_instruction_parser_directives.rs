@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction_parser::{parse_instructions, parse_program, InstructionParseError};
+    use lam::machine::term::Term;
+
+    #[test]
+    fn test_labels_resolve_to_addresses() {
+        let input = "\
+            CHOICE retry
+            HALT
+            ; a label may stand on its own line or precede an instruction
+            retry:
+            second: PUT_CONST R0, 1
+            ASSERT_CLAUSE p, second
+            RETRACT_CLAUSE \"p\", retry
+            CHOICE 0
+        ";
+        let instructions = parse_instructions(input).expect("Labels should be resolved");
+        assert_eq!(instructions, vec![
+            Instruction::Choice { alternative: 2 },
+            Instruction::Halt,
+            Instruction::PutConst { register: 0, value: 1 },
+            Instruction::AssertClause { predicate: "p".to_string(), address: 2 },
+            Instruction::RetractClause { predicate: "p".to_string(), address: 2 },
+            Instruction::Choice { alternative: 0 },
+        ]);
+    }
+
+    #[test]
+    fn test_colon_inside_parameters_is_not_a_label() {
+        let instructions = parse_instructions("PUT_STR R0, \"a:b\"").unwrap();
+        assert_eq!(instructions, vec![Instruction::PutStr { register: 0, value: "a:b".to_string() }]);
+    }
+
+    #[test]
+    fn test_pred_directive_fills_predicate_table() {
+        let input = "\
+            CALL greet/1
+            HALT
+            .pred greet/1
+            GET_STR R0, \"nobody\"
+            PROCEED
+            .pred greet/1
+            PROCEED
+            .pred \"main\"/0
+            PROCEED
+        ";
+        let program = parse_program(input).expect("Directives should be accepted");
+        assert_eq!(program.instructions.len(), 6);
+        assert_eq!(program.predicate_table.get("greet/1"), Some(&vec![2, 4]));
+        assert_eq!(program.predicate_table.get("main/0"), Some(&vec![5]));
+
+        // The arity may be left out, leaving the name alone as the key.
+        let program = parse_program(".pred greet\nPROCEED").unwrap();
+        assert_eq!(program.predicate_table.get("greet"), Some(&vec![0]));
+    }
+
+    #[test]
+    fn test_index_directive_fills_index_table() {
+        let input = "\
+            .index color/1 red
+            PROCEED
+            .index pair/2 1, \"two words\"
+            .pred pair/2
+            PROCEED
+        ";
+        let program = parse_program(input).unwrap();
+        let mut color = HashMap::new();
+        color.insert(vec![Term::Str("red".to_string())], vec![0]);
+        assert_eq!(program.index_table.get("color/1"), Some(&color));
+        let mut pair = HashMap::new();
+        pair.insert(vec![Term::Const(1), Term::Str("two words".to_string())], vec![1]);
+        assert_eq!(program.index_table.get("pair/2"), Some(&pair));
+        assert_eq!(program.predicate_table.get("pair/2"), Some(&vec![1]));
+    }
+
+    #[test]
+    fn test_program_runs_without_compiler() {
+        let input = "\
+            PUT_CONST R0, 2
+            INDEXED_CALL p/1, R0
+            HALT
+            .index p/1 1
+            .pred p/1
+            PUT_CONST R1, 10
+            PROCEED
+            .index p/1 2
+            .pred p/1
+            PUT_CONST R1, 20
+            PROCEED
+        ";
+        let mut machine = parse_program(input).unwrap().into_machine(2);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(1), Some(Term::Const(20)));
+        let p = machine.intern("p/1");
+        assert_eq!(machine.predicate_table.get(&p), Some(&vec![3, 5]));
+    }
+
+    #[test]
+    fn test_label_errors() {
+        let err = parse_instructions("CHOICE nowhere").unwrap_err();
+        assert!(matches!(&err, InstructionParseError::UnknownLabel { label, .. } if label == "nowhere"));
+        assert_eq!(err.column(), 8);
+
+        let err = parse_instructions("a: HALT\na: HALT").unwrap_err();
+        assert!(matches!(&err, InstructionParseError::DuplicateLabel { label, .. } if label == "a"));
+        assert_eq!(err.line(), 2);
+
+        let err = parse_instructions("CHOICE 1x").unwrap_err();
+        assert!(matches!(err, InstructionParseError::BadLiteral { expected: "alternative", .. }));
+    }
+
+    #[test]
+    fn test_directive_errors() {
+        let cases = [
+            (".proc p/1\nHALT", "unknown directive '.proc'"),
+            (".pred p/x\nHALT", "expected a predicate name or name/arity, got 'p/x'"),
+            (".pred p/1 extra\nHALT", "unexpected text after p/1"),
+            (".index p/1\nHALT", "expected index keys after p/1"),
+            (".index p/1 f(x)\nHALT", "invalid index key 'f(x)'"),
+            ("HALT\n.pred p/0", "a directive must be followed by an instruction"),
+        ];
+        for (input, reason) in cases {
+            match parse_program(input) {
+                Err(InstructionParseError::BadDirective { reason: found, .. }) => assert_eq!(found, reason, "input: {}", input),
+                other => panic!("Expected a directive error for {:?}, got {:?}", input, other),
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use lam::machine::instruction_parser::parse_instructions;
+use lam::machine::instruction_parser::{parse_instructions, InstructionParseError};
 use lam::machine::instruction::Instruction;
 
 #[test]
@@ -29,7 +29,7 @@ fn test_invalid_mnemonic() {
     assert!(result.is_err(), "An unknown mnemonic should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.to_lowercase().contains("unknown"),
+        matches!(&err, InstructionParseError::UnknownMnemonic { mnemonic, .. } if mnemonic == "FOO"),
         "Error message should mention the unknown mnemonic, got: {}",
         err
     );
@@ -42,7 +42,7 @@ fn test_missing_parameter_put_const() {
     assert!(result.is_err(), "Missing parameter should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("expects 2 parameters"),
+        matches!(&err, InstructionParseError::WrongArity { expected: 2, found: 1, at_least: false, .. }),
         "Expected error about missing parameters, got: {}",
         err
     );
@@ -55,7 +55,7 @@ fn test_extra_parameter_proceed() {
     assert!(result.is_err(), "Extra parameter for PROCEED should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("expects no parameters"),
+        matches!(&err, InstructionParseError::WrongArity { expected: 0, found: 1, .. }),
         "Error message should mention no parameters allowed, got: {}",
         err
     );
@@ -68,7 +68,7 @@ fn test_invalid_register_token() {
     assert!(result.is_err(), "Invalid register token should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("must start with 'R' or 'r'"),
+        matches!(&err, InstructionParseError::BadRegister { token, .. } if token == "X0"),
         "Error message should mention invalid register token, got: {}",
         err
     );
@@ -81,7 +81,7 @@ fn test_invalid_integer_in_put_const() {
     assert!(result.is_err(), "Non-numeric integer parameter should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("failed to parse integer"),
+        matches!(&err, InstructionParseError::BadLiteral { expected: "integer", token, .. } if token == "abc"),
         "Error message should mention failed parsing of integer, got: {}",
         err
     );
@@ -94,7 +94,7 @@ fn test_invalid_arithmetic_expression() {
     assert!(result.is_err(), "Malformed arithmetic expression should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("failed to parse expression"),
+        matches!(&err, InstructionParseError::BadExpression { mnemonic, .. } if mnemonic == "ARITHMETIC_IS"),
         "Error message should mention failure to parse arithmetic expression, got: {}",
         err
     );
@@ -107,7 +107,7 @@ fn test_set_local_invalid_index() {
     assert!(result.is_err(), "Non-numeric index in SET_LOCAL should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("failed to parse index"),
+        matches!(&err, InstructionParseError::BadLiteral { expected: "index", .. }),
         "Error message should mention failed parsing of index, got: {}",
         err
     );
@@ -120,7 +120,7 @@ fn test_get_local_invalid_register() {
     assert!(result.is_err(), "Invalid register token in GET_LOCAL should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("must start with 'R' or 'r'"),
+        matches!(&err, InstructionParseError::BadRegister { token, .. } if token == "X"),
         "Error message should mention invalid register token, got: {}",
         err
     );
@@ -133,7 +133,7 @@ fn test_move_invalid_register() {
     assert!(result.is_err(), "Invalid register token in MOVE should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("must start with 'R' or 'r'"),
+        matches!(&err, InstructionParseError::BadRegister { token, .. } if token == "A2"),
         "Error message should mention invalid register token, got: {}",
         err
     );
@@ -146,7 +146,7 @@ fn test_trailing_comma() {
     assert!(result.is_err(), "Trailing comma should cause a parameter count error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("Empty token detected"),
+        matches!(&err, InstructionParseError::EmptyParameter { .. }),
         "Error message should mention empty token, got: {}",
         err
     );
@@ -190,8 +190,53 @@ fn test_multi_indexed_call_invalid_register() {
     assert!(result.is_err(), "Invalid register token in MULTI_INDEXED_CALL should yield an error");
     let err = result.unwrap_err();
     assert!(
-        err.contains("must start with 'R' or 'r'"),
+        matches!(&err, InstructionParseError::BadRegister { token, .. } if token == "BAD"),
         "Error message should mention invalid register token, got: {}",
         err
     );
 }
+
+#[test]
+fn test_error_locates_bad_parameter() {
+    let input = "PUT_CONST R0, 1\n  MOVE R0, X1\n";
+    let err = parse_instructions(input).unwrap_err();
+    assert_eq!((err.line(), err.column()), (2, 12));
+    assert_eq!(&input[err.span().start..err.span().end], "X1");
+    assert_eq!(
+        err.to_string(),
+        "line 2, column 12: Register token 'X1' must start with 'R' or 'r' followed by a register number"
+    );
+}
+
+#[test]
+fn test_error_locates_unknown_mnemonic_and_wrong_arity() {
+    let input = "HALT\nBRANCH R1\n";
+    let err = parse_instructions(input).unwrap_err();
+    assert_eq!(&input[err.span().start..err.span().end], "BRANCH");
+
+    let input = "PROCEED\nMOVE R1\n";
+    let err = parse_instructions(input).unwrap_err();
+    assert_eq!(&input[err.span().start..err.span().end], "MOVE R1");
+    assert_eq!(err.to_string(), "line 2, column 1: MOVE expects 2 parameters, got 1");
+}
+
+#[test]
+fn test_trailing_comma_points_at_empty_parameter() {
+    let input = "PUT_CONST R0, 42,";
+    let err = parse_instructions(input).unwrap_err();
+    assert_eq!(err.column(), 18);
+}
+
+#[test]
+fn test_error_messages() {
+    let cases = [
+        ("FOO", "unknown instruction mnemonic 'FOO'"),
+        ("MULTI_INDEXED_CALL p", "MULTI_INDEXED_CALL expects at least 2 parameters, got 1"),
+        ("CALL", "CALL expects 1 parameter, got 0"),
+        ("ALLOCATE x", "failed to parse integer in ALLOCATE: 'x'"),
+        ("PUT_STR R0, hello world", "failed to parse quoted string in PUT_STR: 'hello world'"),
+    ];
+    for (input, message) in cases {
+        assert_eq!(parse_instructions(input).unwrap_err().message(), message);
+    }
+}
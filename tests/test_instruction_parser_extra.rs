@@ -1,13 +1,14 @@
 // tests/test_instruction_parser_extra.rs
 
 use lam::machine::instruction_parser::parse_instructions;
+use lam::machine::instruction::Instruction;
 
 #[test]
 fn test_put_const_extra_parameters() {
     let input = "PUT_CONST R0, 42, 100";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("PUT_CONST expects 2 parameters"),
+        err.message().contains("PUT_CONST expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -18,7 +19,7 @@ fn test_put_const_missing_parameters() {
     let input = "PUT_CONST R0";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("PUT_CONST expects 2 parameters"),
+        err.message().contains("PUT_CONST expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -29,7 +30,7 @@ fn test_put_var_missing_parameters() {
     let input = "PUT_VAR R1, 0";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("PUT_VAR expects 3 parameters"),
+        err.message().contains("PUT_VAR expects 3 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -40,7 +41,7 @@ fn test_put_var_non_numeric_var_id() {
     let input = "PUT_VAR R1, not_a_number, \"X\"";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("failed to parse variable id"),
+        err.message().contains("failed to parse variable id"),
         "Unexpected error: {}",
         err
     );
@@ -51,7 +52,7 @@ fn test_get_var_non_numeric_var_id() {
     let input = "GET_VAR R0, not_a_number, \"X\"";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("failed to parse variable id"),
+        err.message().contains("failed to parse variable id"),
         "Unexpected error: {}",
         err
     );
@@ -62,7 +63,7 @@ fn test_call_extra_parameters() {
     let input = "CALL \"halt\", extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("CALL expects 1 parameter"),
+        err.message().contains("CALL expects 1 parameter"),
         "Unexpected error: {}",
         err
     );
@@ -73,7 +74,7 @@ fn test_choice_missing_parameters() {
     let input = "CHOICE";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("CHOICE expects 1 parameter"),
+        err.message().contains("CHOICE expects 1 parameter"),
         "Unexpected error: {}",
         err
     );
@@ -84,7 +85,7 @@ fn test_choice_extra_parameters() {
     let input = "CHOICE 100, 200";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("CHOICE expects 1 parameter"),
+        err.message().contains("CHOICE expects 1 parameter"),
         "Unexpected error: {}",
         err
     );
@@ -95,7 +96,7 @@ fn test_allocate_non_numeric() {
     let input = "ALLOCATE abc";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("failed to parse integer in ALLOCATE"),
+        err.message().contains("failed to parse integer in ALLOCATE"),
         "Unexpected error: {}",
         err
     );
@@ -106,7 +107,7 @@ fn test_arithmetic_is_extra_parameters() {
     let input = "ARITHMETIC_IS R0, 3+4, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("ARITHMETIC_IS expects 2 parameters"),
+        err.message().contains("ARITHMETIC_IS expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -117,7 +118,7 @@ fn test_set_local_extra_parameters() {
     let input = "SET_LOCAL 0, 42, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("SET_LOCAL expects 2 parameters"),
+        err.message().contains("SET_LOCAL expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -128,7 +129,7 @@ fn test_get_local_extra_parameters() {
     let input = "GET_LOCAL 0, R1, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("GET_LOCAL expects 2 parameters"),
+        err.message().contains("GET_LOCAL expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -139,7 +140,7 @@ fn test_fail_extra_parameters() {
     let input = "FAIL extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("FAIL expects no parameters"),
+        err.message().contains("FAIL expects no parameters"),
         "Unexpected error: {}",
         err
     );
@@ -150,7 +151,7 @@ fn test_get_structure_extra_parameters() {
     let input = "GET_STRUCTURE R0, \"f\", 2, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("GET_STRUCTURE expects 3 parameters"),
+        err.message().contains("GET_STRUCTURE expects 3 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -161,7 +162,7 @@ fn test_indexed_call_extra_parameters() {
     let input = "INDEXED_CALL \"p\", R0, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("INDEXED_CALL expects 2 parameters"),
+        err.message().contains("INDEXED_CALL expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -172,7 +173,7 @@ fn test_multi_indexed_call_insufficient_parameters() {
     let input = "MULTI_INDEXED_CALL \"p\"";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("MULTI_INDEXED_CALL expects at least 2 parameters"),
+        err.message().contains("MULTI_INDEXED_CALL expects at least 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -183,7 +184,7 @@ fn test_tail_call_extra_parameters() {
     let input = "TAIL_CALL \"dummy\", extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("TAIL_CALL expects 1 parameter"),
+        err.message().contains("TAIL_CALL expects 1 parameter"),
         "Unexpected error: {}",
         err
     );
@@ -194,7 +195,7 @@ fn test_assert_clause_extra_parameters() {
     let input = "ASSERT_CLAUSE \"p\", 123, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("ASSERT_CLAUSE expects 2 parameters"),
+        err.message().contains("ASSERT_CLAUSE expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -205,7 +206,7 @@ fn test_retract_clause_extra_parameters() {
     let input = "RETRACT_CLAUSE \"p\", 456, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("RETRACT_CLAUSE expects 2 parameters"),
+        err.message().contains("RETRACT_CLAUSE expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -216,7 +217,7 @@ fn test_build_compound_insufficient_parameters() {
     let input = "BUILD_COMPOUND R2, \"f\"";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("BUILD_COMPOUND expects at least 3 parameters"),
+        err.message().contains("BUILD_COMPOUND expects at least 3 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -227,7 +228,7 @@ fn test_put_str_extra_parameters() {
     let input = "PUT_STR R0, \"hello\", extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("PUT_STR expects 2 parameters"),
+        err.message().contains("PUT_STR expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -238,7 +239,7 @@ fn test_get_str_extra_parameters() {
     let input = "GET_STR R0, \"world\", extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("GET_STR expects 2 parameters"),
+        err.message().contains("GET_STR expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -249,7 +250,7 @@ fn test_move_extra_parameters() {
     let input = "MOVE R1, R2, extra";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("MOVE expects 2 parameters"),
+        err.message().contains("MOVE expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
@@ -261,7 +262,7 @@ fn test_get_str_unquoted_string_with_space() {
     let input = "GET_STR R0, hello world";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("Expected a quoted string if spaces are present"),
+        err.message().contains("failed to parse quoted string"),
         "Unexpected error: {}",
         err
     );
@@ -272,7 +273,7 @@ fn test_set_local_unquoted_string_with_space() {
     let input = "SET_LOCAL 0, hello world";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("Expected a quoted string if spaces are present"),
+        err.message().contains("failed to parse quoted string"),
         "Unexpected error: {}",
         err
     );
@@ -283,7 +284,7 @@ fn test_get_local_invalid_register_format() {
     let input = "GET_LOCAL 0, X1";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("must start with 'R' or 'r'"),
+        err.message().contains("must start with 'R' or 'r'"),
         "Unexpected error: {}",
         err
     );
@@ -295,7 +296,47 @@ fn test_parse_register_too_short() {
     let input = "PUT_CONST R, 42";
     let err = parse_instructions(input).unwrap_err();
     assert!(
-        err.contains("Register token 'R' must start with 'R' or 'r'"),
+        err.message().contains("Register token 'R' must start with 'R' or 'r'"),
+        "Unexpected error: {}",
+        err
+    );
+}
+
+#[test]
+fn test_get_value_parses_two_registers() {
+    let input = "GET_VALUE R0, R3";
+    let instructions = parse_instructions(input).expect("GET_VALUE should parse");
+    assert_eq!(instructions, vec![Instruction::GetValue { register: 0, src: 3 }]);
+}
+
+#[test]
+fn test_get_value_missing_parameter() {
+    let input = "GET_VALUE R0";
+    let err = parse_instructions(input).unwrap_err();
+    assert!(
+        err.message().contains("GET_VALUE expects 2 parameters"),
+        "Unexpected error: {}",
+        err
+    );
+}
+
+#[test]
+fn test_environment_instructions_parse() {
+    let input = "NEW_VAR R2, \"X\"\nSTORE_LOCAL 0, R2\nPUT_LOCAL 0, R1";
+    let instructions = parse_instructions(input).expect("Environment instructions should parse");
+    assert_eq!(instructions, vec![
+        Instruction::NewVar { register: 2, name: "X".to_string() },
+        Instruction::StoreLocal { index: 0, register: 2 },
+        Instruction::PutLocal { index: 0, register: 1 },
+    ]);
+}
+
+#[test]
+fn test_store_local_missing_parameter() {
+    let input = "STORE_LOCAL 0";
+    let err = parse_instructions(input).unwrap_err();
+    assert!(
+        err.message().contains("STORE_LOCAL expects 2 parameters"),
         "Unexpected error: {}",
         err
     );
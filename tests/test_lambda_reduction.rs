@@ -0,0 +1,149 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::common::{app, lambda, var};
+    use lam::machine::core::Machine;
+    use lam::machine::error_handling::MachineError;
+    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction_parser::parse_instructions;
+    use lam::machine::lambda::{eta_reduce, normalize, Strategy};
+    use lam::machine::term::Term;
+
+    // (\x. x x) (\x. x x), which reduces to itself forever.
+    fn omega() -> Term {
+        let w = lambda(0, app(var(0), var(0)));
+        app(w.clone(), w)
+    }
+
+    // (\x. \y. y) omega: the argument is discarded, but only if it is not
+    // reduced first.
+    fn discards_omega() -> Term {
+        app(lambda(1, lambda(2, var(2))), omega())
+    }
+
+    #[test]
+    fn test_normal_order_reduces_under_lambdas() {
+        // \z. (\x. x) z  ->  \z. z
+        let term = lambda(3, app(lambda(0, var(0)), var(3)));
+        assert_eq!(normalize(&term, Strategy::NormalOrder, 100, false).unwrap(), lambda(3, var(3)));
+    }
+
+    #[test]
+    fn test_normal_order_finds_normal_form_applicative_order_misses() {
+        assert_eq!(normalize(&discards_omega(), Strategy::NormalOrder, 100, false).unwrap(), lambda(2, var(2)));
+        assert!(matches!(
+            normalize(&discards_omega(), Strategy::ApplicativeOrder, 100, false),
+            Err(MachineError::ReductionLimit(100))
+        ));
+    }
+
+    #[test]
+    fn test_applicative_order_reduces_arguments_first() {
+        // (\x. c) ((\y. y) d): both strategies reach c.
+        let term = app(
+            lambda(0, Term::Const(1)),
+            app(lambda(1, var(1)), Term::Const(2)),
+        );
+        assert_eq!(normalize(&term, Strategy::ApplicativeOrder, 100, false).unwrap(), Term::Const(1));
+    }
+
+    #[test]
+    fn test_head_normal_form_leaves_arguments() {
+        // \z. z ((\x. x) z) is already in head normal form.
+        let term = lambda(3, app(var(3), app(lambda(0, var(0)), var(3))));
+        assert_eq!(normalize(&term, Strategy::HeadNormalForm, 100, false).unwrap(), term);
+        assert_eq!(normalize(&term, Strategy::NormalOrder, 100, false).unwrap(), lambda(3, app(var(3), var(3))));
+    }
+
+    #[test]
+    fn test_step_limit_stops_omega() {
+        for strategy in [Strategy::NormalOrder, Strategy::ApplicativeOrder, Strategy::HeadNormalForm] {
+            assert!(matches!(normalize(&omega(), strategy, 50, false), Err(MachineError::ReductionLimit(50))));
+        }
+    }
+
+    #[test]
+    fn test_eta_reduction() {
+        // \x. f x  ->  f, but \x. x x is left alone.
+        let f = Term::Str("f".to_string());
+        assert_eq!(eta_reduce(&lambda(0, app(f.clone(), var(0)))), Some(f.clone()));
+        assert_eq!(eta_reduce(&lambda(0, app(var(0), var(0)))), None);
+
+        let term = lambda(1, lambda(0, app(f.clone(), var(0))));
+        assert_eq!(normalize(&term, Strategy::NormalOrder, 100, false).unwrap(), term);
+        assert_eq!(normalize(&term, Strategy::NormalOrder, 100, true).unwrap(), lambda(1, f));
+    }
+
+    #[test]
+    fn test_strategy_names_round_trip() {
+        for strategy in [Strategy::NormalOrder, Strategy::ApplicativeOrder, Strategy::HeadNormalForm] {
+            assert_eq!(strategy.name().parse::<Strategy>(), Ok(strategy));
+        }
+        assert!("lazy".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn test_lambda_instructions() {
+        let code = vec![
+            Instruction::PutVar { register: 0, var_id: 10, name: "X".to_string() },
+            Instruction::BuildLambda { target: 1, param: 0, body: 0 },
+            Instruction::PutConst { register: 2, value: 7 },
+            Instruction::Apply { target: 3, function: 1, argument: 2 },
+            Instruction::Normalize { register: 3, strategy: Strategy::NormalOrder },
+        ];
+        let mut machine = Machine::new(4, code);
+        machine.run().expect("Machine run should succeed");
+        assert_eq!(machine.register(1), Some(lambda(10, var(10))));
+        assert_eq!(machine.register(3), Some(Term::Const(7)));
+    }
+
+    #[test]
+    fn test_build_lambda_requires_a_variable() {
+        let code = vec![
+            Instruction::PutConst { register: 0, value: 1 },
+            Instruction::BuildLambda { target: 1, param: 0, body: 0 },
+        ];
+        let mut machine = Machine::new(2, code);
+        assert!(matches!(machine.run(), Err(MachineError::NotAVariable(0))));
+    }
+
+    #[test]
+    fn test_normalize_instruction_respects_reduction_limit() {
+        let mut machine = Machine::new(1, vec![Instruction::Normalize { register: 0, strategy: Strategy::NormalOrder }]);
+        machine.set_register(0, omega()).unwrap();
+        machine.reduction_limit = 20;
+        assert!(matches!(machine.run(), Err(MachineError::ReductionLimit(20))));
+    }
+
+    #[test]
+    fn test_parse_lambda_instructions() {
+        let code = parse_instructions("BUILD_LAMBDA R1, R0, R0\nAPPLY R3, R1, R2\nNORMALIZE R3, head").unwrap();
+        assert_eq!(code, vec![
+            Instruction::BuildLambda { target: 1, param: 0, body: 0 },
+            Instruction::Apply { target: 3, function: 1, argument: 2 },
+            Instruction::Normalize { register: 3, strategy: Strategy::HeadNormalForm },
+        ]);
+        assert!(parse_instructions("NORMALIZE R0, lazy").is_err());
+    }
+
+    #[test]
+    fn test_normalize_builtins() {
+        let mut machine = Machine::new(3, vec![]);
+        machine.next_var_id = 100;
+        machine.set_register(0, app(lambda(0, var(0)), Term::Const(5))).unwrap();
+        machine.set_register(1, var(1)).unwrap();
+        machine.builtin_normalize().unwrap();
+        assert_eq!(machine.resolve_deep(&var(1)), Term::Const(5));
+
+        let mut machine = Machine::new(3, vec![]);
+        machine.set_register(0, Term::Str("applicative".to_string())).unwrap();
+        machine.set_register(1, discards_omega()).unwrap();
+        machine.set_register(2, var(3)).unwrap();
+        machine.reduction_limit = 30;
+        assert!(matches!(machine.builtin_normalize_with(), Err(MachineError::ReductionLimit(30))));
+
+        machine.set_register(0, Term::Str("lazy".to_string())).unwrap();
+        assert!(matches!(machine.builtin_normalize_with(), Err(MachineError::InvalidStrategy(_))));
+    }
+}
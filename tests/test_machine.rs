@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
 
     #[test]
@@ -10,12 +10,12 @@ mod tests {
         let mut machine = Machine::new(2, code);
 
         // Initially, registers should be None.
-        assert_eq!(machine.registers[0], None);
-        assert_eq!(machine.registers[1], None);
+        assert_eq!(machine.register(0), None);
+        assert_eq!(machine.register(1), None);
 
         machine.step().expect("Step should succeed");
-        assert_eq!(machine.registers[0], Some(Term::Const(42)));
-        assert_eq!(machine.registers[1], None);
+        assert_eq!(machine.register(0), Some(Term::Const(42)));
+        assert_eq!(machine.register(1), None);
 
         // No more instructions; step should return an error.
         assert!(machine.step().is_err());
@@ -26,7 +26,7 @@ mod tests {
         let code = vec![Instruction::PutVar { register: 1, var_id: 0, name: "X".to_string() }];
         let mut machine = Machine::new(2, code);
         machine.run().expect("Machine run should succeed");
-        assert_eq!(machine.registers[1], Some(Term::Var(0)));
+        assert_eq!(machine.register(1), Some(Term::Var(0)));
     }
 
     #[test]
@@ -37,7 +37,7 @@ mod tests {
         ];
         let mut machine = Machine::new(1, code);
         machine.run().expect("Machine run should succeed");
-        assert_eq!(machine.registers[0], Some(Term::Const(42)));
+        assert_eq!(machine.register(0), Some(Term::Const(42)));
     }
 
     #[test]
@@ -60,15 +60,15 @@ mod tests {
         machine.run().expect("Machine run should succeed");
 
         // After unification, register 0 should remain Var(0) and Var(1) should resolve to Var(0).
-        assert_eq!(machine.registers[0], Some(Term::Var(0)));
-        assert_eq!(machine.uf.resolve(&Term::Var(1)), Term::Var(0));
+        assert_eq!(machine.register(0), Some(Term::Var(0)));
+        assert_eq!(machine.resolve(&Term::Var(1)), Term::Var(0));
     }
 
     #[test]
     fn test_call_and_proceed() {
         let code = vec![
             Instruction::PutConst { register: 0, value: 10 },
-            Instruction::Call { predicate: "dummy".to_string() },
+            Instruction::Call { predicate: "dummy".to_string(), target: CallTarget::Unresolved },
             Instruction::PutConst { register: 1, value: 20 },
             Instruction::Proceed,
             Instruction::PutConst { register: 2, value: 30 },
@@ -78,9 +78,9 @@ mod tests {
         machine.register_predicate("dummy".to_string(), 2);
         machine.run().expect("Machine run should succeed");
         
-        assert_eq!(machine.registers[0], Some(Term::Const(10)));
-        assert_eq!(machine.registers[1], Some(Term::Const(20)));
-        assert_eq!(machine.registers[2], Some(Term::Const(30)));
+        assert_eq!(machine.register(0), Some(Term::Const(10)));
+        assert_eq!(machine.register(1), Some(Term::Const(20)));
+        assert_eq!(machine.register(2), Some(Term::Const(30)));
         assert_eq!(machine.control_stack.len(), 0);
     }
 }
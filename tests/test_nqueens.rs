@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
     use lam::machine::error_handling::MachineError;
     use std::time::Instant;
@@ -76,7 +76,7 @@ mod tests {
             },
             // --- Print and Halt ---
             // Call the built-in "print_solution" predicate.
-            Instruction::Call { predicate: "print".to_string() },
+            Instruction::Call { predicate: "print/0".to_string(), target: CallTarget::Unresolved },
             // Halt execution.
             Instruction::Halt,
         ];
@@ -107,7 +107,7 @@ mod tests {
         ]);
 
         // Check that register5 contains the expected solution.
-        assert_eq!(machine.registers[5], Some(expected));
+        assert_eq!(machine.register(5), Some(expected));
 
         Ok(())
     }
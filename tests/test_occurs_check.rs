@@ -0,0 +1,110 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::common::compiled_machine;
+    use lam::machine::core::{Machine, OccursCheck};
+    use lam::machine::error_handling::MachineError;
+    use lam::machine::instruction::Instruction;
+    use lam::machine::term::Term;
+    use lam::machine::unification::UnionFind;
+
+    fn f(arg: Term) -> Term {
+        Term::Compound("f".to_string(), vec![arg])
+    }
+
+    #[test]
+    fn test_occurs_check_off_binds_cyclic_term() {
+        let mut machine = Machine::new(1, vec![]);
+        assert_eq!(machine.occurs_check, OccursCheck::Off);
+        machine.unify(&Term::Var(0), &f(Term::Var(0))).unwrap();
+        // Reading the cyclic term stops where it refers back to itself.
+        assert_eq!(machine.resolve_deep(&Term::Var(0)), f(Term::Var(0)));
+    }
+
+    #[test]
+    fn test_occurs_check_on_fails() {
+        let mut machine = Machine::new(1, vec![]);
+        machine.occurs_check = OccursCheck::On;
+        let err = machine.unify(&Term::Var(0), &f(Term::Var(0))).unwrap_err();
+        assert!(matches!(err, MachineError::UnificationFailed(_)));
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Var(0));
+    }
+
+    #[test]
+    fn test_occurs_check_follows_bindings() {
+        let mut machine = Machine::new(1, vec![]);
+        machine.occurs_check = OccursCheck::On;
+        machine.unify(&Term::Var(1), &f(Term::Var(0))).unwrap();
+        assert!(machine.unify(&Term::Var(0), &f(Term::Var(1))).is_err());
+        assert!(machine.unify(&Term::Var(0), &f(Term::Var(2))).is_ok());
+    }
+
+    #[test]
+    fn test_occurs_check_error_mode() {
+        let mut machine = Machine::new(1, vec![]);
+        machine.occurs_check = OccursCheck::Error;
+        match machine.unify(&f(Term::Var(0)), &Term::Var(0)) {
+            Err(MachineError::OccursCheck(0, term)) => assert_eq!(term, f(Term::Var(0))),
+            other => panic!("Expected an occurs check error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unifying_cyclic_terms_terminates() {
+        let mut machine = Machine::new(1, vec![]);
+        machine.unify(&Term::Var(0), &f(Term::Var(0))).unwrap();
+        machine.unify(&Term::Var(1), &f(Term::Var(1))).unwrap();
+        machine.unify(&Term::Var(0), &Term::Var(1)).unwrap();
+        machine.unify(&Term::Var(2), &f(f(Term::Var(2)))).unwrap();
+        machine.unify(&Term::Var(0), &Term::Var(2)).unwrap();
+        let g = Term::Compound("g".to_string(), vec![Term::Var(3)]);
+        machine.unify(&Term::Var(3), &g).unwrap();
+        assert!(machine.unify(&Term::Var(0), &Term::Var(3)).is_err());
+    }
+
+    #[test]
+    fn test_comparing_cyclic_terms_terminates() {
+        let mut machine = compiled_machine("main :- X = f(X), Y = f(Y), X == Y.");
+        machine.run().expect("X = f(X) and Y = f(Y) are identical");
+
+        let mut machine = compiled_machine("main :- X = f(X), Y = f(f(Y)), compare(O, X, Y), O = (=).");
+        machine.run().expect("X = f(X) and Y = f(f(Y)) are identical");
+
+        let mut machine = compiled_machine("main :- X = f(X), Y = f(a), X @> Y, X \\== Y.");
+        machine.run().expect("X = f(X) follows f(a)");
+    }
+
+    #[test]
+    fn test_unify_with_occurs_check_builtin() {
+        let mut machine = compiled_machine("main :- unify_with_occurs_check(X, f(X)).");
+        assert!(machine.run().is_err(), "X cannot be unified with f(X)");
+
+        let mut machine = compiled_machine("main :- unify_with_occurs_check(X, f(Y)), Y = 1.");
+        machine.run().expect("Machine run should succeed");
+    }
+
+    #[test]
+    fn test_write_mode_occurs_check() {
+        let code = vec![
+            Instruction::PutVar { register: 0, var_id: 0, name: "X".to_string() },
+            Instruction::GetStructure { register: 0, functor: "f".to_string(), arity: 1 },
+            Instruction::UnifyValue { register: 0 },
+        ];
+        let mut machine = Machine::new(1, code.clone());
+        machine.occurs_check = OccursCheck::On;
+        assert!(matches!(machine.run(), Err(MachineError::UnificationFailed(_))));
+
+        let mut machine = Machine::new(1, code);
+        machine.run().expect("Machine run should succeed");
+        let Term::Compound(_, args) = machine.resolve_deep(&Term::Var(0)) else { panic!("Expected a compound term") };
+        assert!(matches!(args[0], Term::Compound(_, _)));
+    }
+
+    #[test]
+    fn test_union_find_resolve_deep_on_cyclic_binding() {
+        let mut uf = UnionFind::new();
+        uf.bind(0, &f(Term::Var(0))).unwrap();
+        assert_eq!(uf.resolve_deep(&Term::Var(0)), f(Term::Var(0)));
+    }
+}
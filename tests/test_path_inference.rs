@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
 
     #[test]
     fn benchmark_path_inference() {
@@ -10,7 +10,7 @@ mod tests {
             // Main Query: path(1,3)
             Instruction::PutConst { register: 0, value: 1 },   // X = 1
             Instruction::PutConst { register: 1, value: 3 },   // Y = 3
-            Instruction::Call { predicate: "path".to_string() },
+            Instruction::Call { predicate: "path".to_string(), target: CallTarget::Unresolved },
             Instruction::Proceed,
             // Facts for edge/2:
             // edge(1,2)
@@ -26,12 +26,12 @@ mod tests {
             Instruction::PutConst { register: 1, value: 3 },
             Instruction::Proceed,
             // Clause for path/2: path(X,Y) :- edge(X,Y).
-            Instruction::Call { predicate: "edge".to_string() },
+            Instruction::Call { predicate: "edge".to_string(), target: CallTarget::Unresolved },
             Instruction::Proceed,
             // Clause for path/2: path(X,Y) :- edge(X,Z), path(Z,Y).
             Instruction::PutVar { register: 2, var_id: 0, name: "Z".to_string() },
-            Instruction::Call { predicate: "edge".to_string() },
-            Instruction::Call { predicate: "path".to_string() },
+            Instruction::Call { predicate: "edge".to_string(), target: CallTarget::Unresolved },
+            Instruction::Call { predicate: "path".to_string(), target: CallTarget::Unresolved },
             Instruction::Proceed,
         ];
 
@@ -48,11 +48,11 @@ mod tests {
         machine.run().expect("Machine run should succeed");
         let duration = start.elapsed();
         
-        println!("Path Inference Benchmark: Solution: X = {:?}, Y = {:?}", machine.registers[0], machine.registers[1]);
+        println!("Path Inference Benchmark: Solution: X = {:?}, Y = {:?}", machine.register(0), machine.register(1));
         println!("Path Inference Benchmark: Execution time: {:?}", duration);
         
         // Assert that a solution was found.
-        assert!(machine.registers[0].is_some());
-        assert!(machine.registers[1].is_some());
+        assert!(machine.register(0).is_some());
+        assert!(machine.register(1).is_some());
     }
 }
@@ -0,0 +1,140 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::common::{app, lambda, new_machine, var};
+    use lam::machine::core::{Machine, Unifier};
+    use lam::machine::instruction::Instruction;
+    use lam::machine::lambda::{normalize, Strategy};
+    use lam::machine::term::Term;
+
+    fn g(args: Vec<Term>) -> Term {
+        Term::Compound("g".to_string(), args)
+    }
+    fn a() -> Term {
+        Term::Str("a".to_string())
+    }
+    fn b() -> Term {
+        Term::Str("b".to_string())
+    }
+
+    // Logic variables are numbered from 0, lambda parameters from 50 and fresh
+    // variables from 100.
+    fn pattern_machine() -> Machine {
+        let mut machine = new_machine();
+        machine.unifier = Unifier::Pattern;
+        machine
+    }
+
+    // Applies the value of a variable to arguments and normalizes the result.
+    fn call(machine: &mut Machine, var: usize, args: Vec<Term>) -> Term {
+        let function = machine.resolve_deep(&Term::Var(var));
+        let term = args.into_iter().fold(function, app);
+        normalize(&term, Strategy::NormalOrder, 100, true).unwrap()
+    }
+
+    #[test]
+    fn test_first_order_unifier_is_the_default() {
+        let mut machine = Machine::new(1, vec![]);
+        assert_eq!(machine.unifier, Unifier::FirstOrder);
+        assert!(machine.unify(&lambda(50, app(var(0), var(50))), &lambda(51, g(vec![var(51)]))).is_err());
+    }
+
+    #[test]
+    fn test_flex_rigid_abstracts_over_the_arguments() {
+        // \x. F x = \y. g(y, a)  gives  F = \x. g(x, a).
+        let mut machine = pattern_machine();
+        machine.unify(&lambda(50, app(var(0), var(50))), &lambda(51, g(vec![var(51), a()]))).unwrap();
+        assert_eq!(call(&mut machine, 0, vec![b()]), g(vec![b(), a()]));
+        assert!(machine.delayed.is_empty());
+    }
+
+    #[test]
+    fn test_eta_expansion() {
+        // \x. F x = f  gives  F = f.
+        let mut machine = pattern_machine();
+        let function = Term::Str("f".to_string());
+        machine.unify(&lambda(50, app(var(0), var(50))), &function).unwrap();
+        assert_eq!(call(&mut machine, 0, vec![]), function);
+    }
+
+    #[test]
+    fn test_flex_flex_same_head() {
+        // \x. \y. F x y = \x. \y. F y x  gives  F = \x. \y. H.
+        let mut machine = pattern_machine();
+        let left = lambda(50, lambda(51, app(app(var(0), var(50)), var(51))));
+        let right = lambda(50, lambda(51, app(app(var(0), var(51)), var(50))));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(call(&mut machine, 0, vec![a(), b()]), call(&mut machine, 0, vec![b(), a()]));
+    }
+
+    #[test]
+    fn test_flex_flex_different_heads() {
+        // \x. \y. F x y = \x. \y. G y  gives  F = \x. \y. H y  and  G = \y. H y.
+        let mut machine = pattern_machine();
+        let left = lambda(50, lambda(51, app(app(var(0), var(50)), var(51))));
+        let right = lambda(50, lambda(51, app(var(1), var(51))));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(call(&mut machine, 0, vec![a(), b()]), call(&mut machine, 1, vec![b()]));
+        assert_eq!(call(&mut machine, 0, vec![a(), b()]), call(&mut machine, 0, vec![b(), b()]));
+    }
+
+    #[test]
+    fn test_bound_variable_cannot_escape() {
+        // \x. F = \x. x has no solution.
+        let mut machine = pattern_machine();
+        assert!(machine.unify(&lambda(50, var(0)), &lambda(51, var(51))).is_err());
+    }
+
+    #[test]
+    fn test_occurs_check() {
+        // \x. F x = \x. g(F x) has no solution.
+        let mut machine = pattern_machine();
+        let left = lambda(50, app(var(0), var(50)));
+        let right = lambda(51, g(vec![app(var(0), var(51))]));
+        assert!(machine.unify(&left, &right).is_err());
+    }
+
+    #[test]
+    fn test_pruning() {
+        // \x. \y. F x = \x. \y. g(G x y)  prunes y from G.
+        let mut machine = pattern_machine();
+        let left = lambda(50, lambda(51, app(var(0), var(50))));
+        let right = lambda(50, lambda(51, g(vec![app(app(var(1), var(50)), var(51))])));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(call(&mut machine, 1, vec![a(), a()]), call(&mut machine, 1, vec![a(), b()]));
+        let inner = call(&mut machine, 1, vec![a(), b()]);
+        assert_eq!(call(&mut machine, 0, vec![a()]), g(vec![inner]));
+    }
+
+    #[test]
+    fn test_non_pattern_problems_are_delayed_and_woken() {
+        // F a = a is not a pattern; it is delayed until F is known.
+        let mut machine = pattern_machine();
+        machine.unify(&app(var(0), a()), &a()).unwrap();
+        assert_eq!(machine.delayed.len(), 1);
+        machine.unify(&var(0), &lambda(50, var(50))).unwrap();
+        assert!(machine.delayed.is_empty());
+
+        let mut machine = pattern_machine();
+        machine.unify(&app(var(0), a()), &b()).unwrap();
+        assert!(machine.unify(&var(0), &lambda(50, var(50))).is_err());
+    }
+
+    #[test]
+    fn test_backtracking_restores_delayed_problems() {
+        let code = vec![
+            Instruction::Choice { alternative: 3 },
+            Instruction::Fail,
+            Instruction::Halt,
+            Instruction::Halt,
+        ];
+        let mut machine = Machine::new(1, code);
+        machine.unifier = Unifier::Pattern;
+        machine.step().unwrap();
+        machine.unify(&app(var(0), a()), &a()).unwrap();
+        assert_eq!(machine.delayed.len(), 1);
+        machine.step().unwrap();
+        assert!(machine.delayed.is_empty());
+    }
+}
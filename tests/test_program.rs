@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use lam::machine::arithmetic::Expression;
+    use lam::machine::instruction::{CallTarget, Instruction};
+    use lam::machine::instruction_parser::{parse_instructions, parse_program};
+    use lam::machine::program::Program;
+    use lam::machine::term::Term;
+    use lam::prolog::compiler::compile_prolog;
+
+    #[test]
+    fn test_instruction_display_is_parseable() {
+        let instructions = vec![
+            Instruction::SetLocal { index: 1, value: Term::Str("a, b".to_string()) },
+            Instruction::GetLocal { index: 0, register: 2 },
+            Instruction::GetStructure { register: 0, functor: "f".to_string(), arity: 2 },
+            Instruction::Move { src: 1, dst: 3 },
+            Instruction::BuildCompound { target: 2, functor: ".".to_string(), arg_registers: vec![0, 1] },
+            Instruction::MultiIndexedCall { predicate: "p".to_string(), index_registers: vec![0, 1] },
+            Instruction::PutStr { register: 0, value: "say \"hi\"\n".to_string() },
+        ];
+        let text: Vec<String> = instructions.iter().map(|instr| instr.to_string()).collect();
+        assert_eq!(text, vec![
+            "SET_LOCAL 1, \"a, b\"",
+            "GET_LOCAL 0, R2",
+            "GET_STRUCTURE R0, \"f\", 2",
+            "MOVE      R1, R3",
+            "BUILD_COMPOUND R2, \".\", R0, R1",
+            "MULTI_INDEXED_CALL \"p\", R0, R1",
+            "PUT_STR   R0, \"say \\\"hi\\\"\\n\"",
+        ]);
+        assert_eq!(parse_instructions(&text.join("\n")).unwrap(), instructions);
+    }
+
+    #[test]
+    fn test_program_display_writes_directives() {
+        let mut program = Program::new(vec![Instruction::Proceed, Instruction::Proceed, Instruction::Halt]);
+        program.predicate_table.insert("p".to_string(), vec![0, 1]);
+        program.predicate_table.insert("not plain".to_string(), vec![1]);
+        program.index_table.entry("p".to_string()).or_default().insert(vec![Term::Const(1), Term::Str("x".to_string())], vec![1]);
+        assert_eq!(
+            program.to_string(),
+            ".pred p\nPROCEED\n.pred \"not plain\"\n.pred p\n.index p 1, \"x\"\nPROCEED\nHALT\n"
+        );
+        assert_eq!(parse_program(&program.to_string()), Ok(program));
+    }
+
+    #[test]
+    fn test_compiled_program_round_trips() {
+        let source = "parent(john, 'mary ann').\n\
+                      grandparent(X, Z) :- parent(X, Y), parent(Y, Z).\n\
+                      main :- grandparent(john, W), write(W), nl, halt.";
+        let (instructions, predicate_table, _) = compile_prolog(source).unwrap();
+        let program = Program { instructions, predicate_table, ..Program::default() };
+        assert_eq!(parse_program(&program.to_string()), Ok(program));
+    }
+
+    #[test]
+    fn test_num_registers_covers_every_register_used() {
+        let program = Program::new(vec![
+            Instruction::PutConst { register: 1, value: 7 },
+            Instruction::ArithmeticIs { target: 0, expression: Expression::Add(Box::new(Expression::Var(5)), Box::new(Expression::Const(1))) },
+            Instruction::Call { predicate: "p".to_string(), target: CallTarget::Unresolved },
+        ]);
+        assert_eq!(program.num_registers(), 6);
+        assert_eq!(Program::new(vec![Instruction::Halt]).num_registers(), 0);
+
+        let source = "main :- X = f(g(1, 2), h(3)), write(X).";
+        let (instructions, predicate_table, num_registers) = compile_prolog(source).unwrap();
+        let program = Program { instructions, predicate_table, ..Program::default() };
+        assert_eq!(program.num_registers(), num_registers);
+    }
+}
@@ -0,0 +1,535 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::common::compiled_machine;
+    use lam::machine::arithmetic::Expression;
+    use lam::machine::core::Machine;
+    use lam::machine::error_handling::MachineError;
+    use lam::machine::instruction::{CallTarget, Instruction};
+    use lam::machine::number::Float;
+    use lam::machine::term::Term;
+    use lam::prolog::compiler::compile_prolog;
+
+    /// Compiles a program and runs it from its `main` entry point.
+    fn run_program(program: &str) -> Machine {
+        let mut machine = compiled_machine(program);
+        machine.run().expect("Machine run should succeed");
+        machine
+    }
+
+    /// Compiles a program and returns the result of running it.
+    fn run_result(program: &str) -> Result<(), MachineError> {
+        compiled_machine(program).run()
+    }
+
+    /// Returns the value bound to the variable with the given source name.
+    fn binding_of(machine: &mut Machine, name: &str) -> Term {
+        let var_id = machine.variable_names.iter()
+            .find(|(_, var_name)| var_name.as_str() == name)
+            .map(|(id, _)| *id)
+            .unwrap_or_else(|| panic!("Variable {} not found", name));
+        machine.resolve_deep(&Term::Var(var_id))
+    }
+
+    #[test]
+    fn test_fact_head_compiles_to_get_instructions() {
+        let (code, predicate_table, _) = compile_prolog("parent(john, X).").expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::GetStr { register: 0, value: "john".to_string() },
+            Instruction::Proceed,
+        ]);
+        assert_eq!(predicate_table.get("parent/2"), Some(&vec![0]));
+    }
+
+    #[test]
+    fn test_rule_allocates_permanent_variables() {
+        let (code, _, _) = compile_prolog("grandparent(X, Z) :- parent(X, Y), parent(Y, Z).")
+            .expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::Allocate { n: 2 },
+            Instruction::Move { src: 0, dst: 2 },
+            Instruction::StoreLocal { index: 0, register: 1 },
+            Instruction::Move { src: 2, dst: 0 },
+            Instruction::NewVar { register: 1, name: "Y".to_string() },
+            Instruction::StoreLocal { index: 1, register: 1 },
+            Instruction::Call { predicate: "parent/2".to_string(), target: CallTarget::Unresolved },
+            Instruction::PutLocal { index: 1, register: 0 },
+            Instruction::PutLocal { index: 0, register: 1 },
+            Instruction::Call { predicate: "parent/2".to_string(), target: CallTarget::Unresolved },
+            Instruction::Deallocate,
+            Instruction::Proceed,
+        ]);
+    }
+
+    #[test]
+    fn test_main_entry_point() {
+        let (code, predicate_table, _) = compile_prolog("main :- nl.").expect("Program should compile");
+        assert_eq!(code[0], Instruction::Call { predicate: "main/0".to_string(), target: CallTarget::Unresolved });
+        assert_eq!(code[1], Instruction::Halt);
+        assert_eq!(predicate_table.get("main/0"), Some(&vec![2]));
+    }
+
+    #[test]
+    fn test_predicates_are_distinguished_by_arity() {
+        let program = "p(one).
+                       p(two, three).
+                       main :- p(A), p(B, C).";
+        let (_, predicate_table, _) = compile_prolog(program).expect("Program should compile");
+        assert_eq!(predicate_table.get("p/1"), Some(&vec![2]));
+        assert_eq!(predicate_table.get("p/2"), Some(&vec![4]));
+        assert_eq!(predicate_table.get("p"), None);
+
+        let mut machine = run_program(program);
+        assert_eq!(binding_of(&mut machine, "A"), Term::Str("one".to_string()));
+        assert_eq!(binding_of(&mut machine, "B"), Term::Str("two".to_string()));
+        assert_eq!(binding_of(&mut machine, "C"), Term::Str("three".to_string()));
+
+        match run_result("p(one). main :- p(a, b, c).") {
+            Err(MachineError::PredicateNotFound(predicate)) => assert_eq!(predicate, "p/3"),
+            other => panic!("Expected p/3 to be missing, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_head_filters_clauses() {
+        let mut machine = run_program(
+            "parent(john, mary).
+             parent(bob, alice).
+             main :- parent(bob, X).",
+        );
+        assert_eq!(binding_of(&mut machine, "X"), Term::Str("alice".to_string()));
+    }
+
+    #[test]
+    fn test_head_numbers_and_repeated_variables() {
+        let mut machine = run_program(
+            "same(X, X).
+             age(ann, 30).
+             age(tom, 42).
+             main :- age(Who, 42), same(Who, Y).",
+        );
+        assert_eq!(binding_of(&mut machine, "Who"), Term::Str("tom".to_string()));
+        assert_eq!(binding_of(&mut machine, "Y"), Term::Str("tom".to_string()));
+    }
+
+    #[test]
+    fn test_big_integer_literals() {
+        let big: num_bigint::BigInt = "2432902008176640000".parse().unwrap();
+        let (code, _, _) = compile_prolog("factorial(20, 2432902008176640000).").expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::GetConst { register: 0, value: 20 },
+            Instruction::GetBigInt { register: 1, value: big.clone() },
+            Instruction::Proceed,
+        ]);
+
+        let mut machine = run_program(
+            "factorial(20, 2432902008176640000).
+             factorial(21, 51090942171709440000).
+             main :- factorial(N, 51090942171709440000), factorial(20, F).",
+        );
+        assert_eq!(binding_of(&mut machine, "N"), Term::Const(21));
+        assert_eq!(binding_of(&mut machine, "F"), Term::BigInt(big));
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let (code, _, _) = compile_prolog("half(1, 0.5).").expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::GetConst { register: 0, value: 1 },
+            Instruction::GetFloat { register: 1, value: 0.5 },
+            Instruction::Proceed,
+        ]);
+
+        let mut machine = run_program(
+            "half(1, 0.5).
+             half(3, 1.5).
+             main :- half(N, 1.5), half(1, H).",
+        );
+        assert_eq!(binding_of(&mut machine, "N"), Term::Const(3));
+        assert_eq!(binding_of(&mut machine, "H"), Term::Float(Float(0.5)));
+    }
+
+    #[test]
+    fn test_is_compiles_to_arithmetic_is() {
+        let (code, _, _) = compile_prolog("inc(X, Y) :- Y is X + 1.").expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::Move { src: 0, dst: 2 },
+            Instruction::Move { src: 1, dst: 3 },
+            Instruction::ArithmeticIs {
+                target: 4,
+                expression: Expression::Add(Box::new(Expression::Var(2)), Box::new(Expression::Const(1))),
+            },
+            Instruction::GetValue { register: 4, src: 3 },
+            Instruction::Proceed,
+        ]);
+    }
+
+    #[test]
+    fn test_comparison_compiles_to_evaluated_call() {
+        let (code, _, _) = compile_prolog("big(X) :- X > 10.").expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::Move { src: 0, dst: 2 },
+            Instruction::ArithmeticIs { target: 0, expression: Expression::Var(2) },
+            Instruction::ArithmeticIs { target: 1, expression: Expression::Const(10) },
+            Instruction::Call { predicate: ">/2".to_string(), target: CallTarget::Unresolved },
+            Instruction::Proceed,
+        ]);
+    }
+
+    #[test]
+    fn test_arithmetic_in_rules() {
+        let mut machine = run_program(
+            "age(ann, 12).
+             age(bob, 30).
+             adult(X) :- age(X, A), A >= 18.
+             length_of([], 0).
+             length_of([_|T], N) :- length_of(T, M), N is M + 1.
+             main :- adult(P), length_of([a, b, c], L), L =:= 3.0, Half is L / 2.",
+        );
+        assert_eq!(binding_of(&mut machine, "P"), Term::Str("bob".to_string()));
+        assert_eq!(binding_of(&mut machine, "L"), Term::Const(3));
+        assert_eq!(binding_of(&mut machine, "Half"), Term::Float(Float(1.5)));
+    }
+
+    #[test]
+    fn test_non_arithmetic_terms_are_evaluated_at_run_time() {
+        // foo is not evaluable, so the goal is left to is/2, which reports the ISO error.
+        let program = "main :- X is foo + 1.";
+        let (code, _, _) = compile_prolog(program).expect("Program should compile");
+        assert!(code.contains(&Instruction::Call { predicate: "is/2".to_string(), target: CallTarget::Unresolved }));
+        match run_result(program) {
+            Err(MachineError::Iso(error)) => assert_eq!(error.to_string(), "type_error(evaluable, foo/0)"),
+            other => panic!("Expected a type error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_evaluates_values_from_unification() {
+        let mut machine = run_program(
+            "price(apple, 3).
+             main :- price(apple, P), N = 4, Total is P * N + 0.5.",
+        );
+        assert_eq!(binding_of(&mut machine, "Total"), Term::Float(Float(12.5)));
+    }
+
+    #[test]
+    fn test_backtracking_through_rule_body() {
+        let mut machine = run_program(
+            "parent(john, mary).
+             parent(bob, alice).
+             parent(alice, carol).
+             grandparent(X, Z) :- parent(X, Y), parent(Y, Z).
+             main :- grandparent(bob, G).",
+        );
+        assert_eq!(binding_of(&mut machine, "G"), Term::Str("carol".to_string()));
+    }
+
+    #[test]
+    fn test_compound_head_arguments() {
+        let mut machine = run_program(
+            "point(p(1, 2)).
+             first(p(A, _), A).
+             main :- point(P), first(P, F).",
+        );
+        assert_eq!(
+            binding_of(&mut machine, "P"),
+            Term::Compound("p".to_string(), vec![Term::Const(1), Term::Const(2)])
+        );
+        assert_eq!(binding_of(&mut machine, "F"), Term::Const(1));
+    }
+
+    #[test]
+    fn test_compound_head_compiles_to_get_structure() {
+        let (code, _, _) = compile_prolog("second(c(_, c(X, nil)), X).").expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::GetStructure { register: 0, functor: "c".to_string(), arity: 2 },
+            Instruction::UnifyVar { register: 4 },
+            Instruction::UnifyVar { register: 3 },
+            Instruction::GetStructure { register: 3, functor: "c".to_string(), arity: 2 },
+            Instruction::UnifyVar { register: 2 },
+            Instruction::UnifyVar { register: 3 },
+            Instruction::GetStr { register: 3, value: "nil".to_string() },
+            Instruction::GetValue { register: 1, src: 2 },
+            Instruction::Proceed,
+        ]);
+    }
+
+    #[test]
+    fn test_long_lists_reuse_registers() {
+        let items: Vec<String> = (1..=25).map(|n| n.to_string()).collect();
+        let program = format!(
+            "numbers([{}]).
+             main :- numbers(Xs), Xs = [{}].",
+            items.join(", "),
+            items.join(", "),
+        );
+        let (_, _, num_registers) = compile_prolog(&program).expect("Program should compile");
+        assert_eq!(num_registers, 4);
+        let mut machine = run_program(&program);
+        let list = (1..=25).rev().fold(Term::Str("[]".to_string()), |tail, n| {
+            Term::Compound(".".to_string(), vec![Term::Const(n), tail])
+        });
+        assert_eq!(binding_of(&mut machine, "Xs"), list);
+    }
+
+    #[test]
+    fn test_head_mismatch_fails_without_alternatives() {
+        let result = run_result(
+            "parent(john, mary).
+             main :- parent(bob, X).",
+        );
+        assert!(result.is_err(), "No clause matches parent(bob, X)");
+    }
+
+    #[test]
+    fn test_same_clause_called_twice() {
+        let mut machine = run_program(
+            "pair(A, B, p(A, B)).
+             main :- pair(one, two, P), pair(x, y, Q).",
+        );
+        assert_eq!(
+            binding_of(&mut machine, "P"),
+            Term::Compound("p".to_string(), vec![Term::Str("one".to_string()), Term::Str("two".to_string())])
+        );
+        assert_eq!(
+            binding_of(&mut machine, "Q"),
+            Term::Compound("p".to_string(), vec![Term::Str("x".to_string()), Term::Str("y".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_recursive_append() {
+        let mut machine = run_program(
+            "app(nil, L, L).
+             app(c(H, T), L, c(H, R)) :- app(T, L, R).
+             main :- app(c(1, c(2, nil)), c(3, nil), Xs).",
+        );
+        let list = |items: &[i32]| items.iter().rev().fold(Term::Str("nil".to_string()), |tail, n| {
+            Term::Compound("c".to_string(), vec![Term::Const(*n), tail])
+        });
+        assert_eq!(binding_of(&mut machine, "Xs"), list(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_recursive_ancestor_backtracks() {
+        let mut machine = run_program(
+            "parent(tom, bob).
+             parent(bob, ann).
+             parent(ann, joe).
+             ancestor(X, Y) :- parent(X, Y).
+             ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y).
+             main :- ancestor(tom, joe), ancestor(bob, D), D = joe.",
+        );
+        assert_eq!(binding_of(&mut machine, "D"), Term::Str("joe".to_string()));
+    }
+
+    #[test]
+    fn test_directives_run_before_main() {
+        let mut machine = run_program(
+            ":- op(700, xfx, ===>).
+             rule(a ===> b).
+             main :- rule(X ===> Y), current_op(P, T, ===>).",
+        );
+        assert_eq!(binding_of(&mut machine, "X"), Term::Str("a".to_string()));
+        assert_eq!(binding_of(&mut machine, "P"), Term::Const(700));
+        assert_eq!(binding_of(&mut machine, "T"), Term::Str("xfx".to_string()));
+    }
+
+    #[test]
+    fn test_directive_entry_point_without_main() {
+        let (code, _, _) = compile_prolog(":- op(200, xfy, and).").expect("Program should compile");
+        assert_eq!(code.last(), Some(&Instruction::Halt));
+        assert!(code.contains(&Instruction::Call { predicate: "op/3".to_string(), target: CallTarget::Unresolved }));
+    }
+    #[test]
+    fn test_if_then_else_compiles_to_choice_and_cut_to() {
+        let (code, _, _) = compile_prolog("max(X, Y, Z) :- ( X >= Y -> Z = X ; Z = Y ).")
+            .expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::Allocate { n: 4 },
+            Instruction::StoreLocal { index: 0, register: 0 },
+            Instruction::StoreLocal { index: 1, register: 1 },
+            Instruction::StoreLocal { index: 2, register: 2 },
+            Instruction::GetLevel { register: 3 },
+            Instruction::StoreLocal { index: 3, register: 3 },
+            Instruction::Choice { alternative: 18 },
+            Instruction::PutLocal { index: 0, register: 3 },
+            Instruction::ArithmeticIs { target: 0, expression: Expression::Var(3) },
+            Instruction::PutLocal { index: 1, register: 3 },
+            Instruction::ArithmeticIs { target: 1, expression: Expression::Var(3) },
+            Instruction::Call { predicate: ">=/2".to_string(), target: CallTarget::Unresolved },
+            Instruction::PutLocal { index: 3, register: 3 },
+            Instruction::CutTo { register: 3 },
+            Instruction::PutLocal { index: 2, register: 0 },
+            Instruction::PutLocal { index: 0, register: 1 },
+            Instruction::Call { predicate: "=/2".to_string(), target: CallTarget::Unresolved },
+            Instruction::Jump { address: 21 },
+            Instruction::PutLocal { index: 2, register: 0 },
+            Instruction::PutLocal { index: 1, register: 1 },
+            Instruction::Call { predicate: "=/2".to_string(), target: CallTarget::Unresolved },
+            Instruction::Deallocate,
+            Instruction::Proceed,
+        ]);
+    }
+
+    #[test]
+    fn test_disjunction_backtracks_into_second_branch() {
+        let mut machine = run_program(
+            "color(C) :- ( C = red ; C = green ; C = blue ).
+             main :- color(X), X = green, ( Y = a ; Y = b ), Y == b.",
+        );
+        assert_eq!(binding_of(&mut machine, "X"), Term::Str("green".to_string()));
+        assert_eq!(binding_of(&mut machine, "Y"), Term::Str("b".to_string()));
+    }
+
+    #[test]
+    fn test_if_then_else() {
+        let mut machine = run_program(
+            "max(X, Y, Z) :- ( X >= Y -> Z = X ; Z = Y ).
+             sign(N, S) :- ( N < 0 -> S = negative ; N =:= 0 -> S = zero ; S = positive ).
+             main :- max(3, 7, A), max(9, 2, B), sign(-4, S1), sign(0, S2), sign(5, S3).",
+        );
+        assert_eq!(binding_of(&mut machine, "A"), Term::Const(7));
+        assert_eq!(binding_of(&mut machine, "B"), Term::Const(9));
+        assert_eq!(binding_of(&mut machine, "S1"), Term::Str("negative".to_string()));
+        assert_eq!(binding_of(&mut machine, "S2"), Term::Str("zero".to_string()));
+        assert_eq!(binding_of(&mut machine, "S3"), Term::Str("positive".to_string()));
+    }
+
+    #[test]
+    fn test_if_then_else_commits_to_first_solution_of_condition() {
+        let program = "p(1). p(2).
+                       main :- ( p(X) -> Y = X ; Y = 0 ), Y = 2.";
+        assert!(run_result(program).is_err(), "The condition must not be retried");
+
+        let mut machine = run_program(
+            "p(1). p(2).
+             main :- ( p(X) -> true ), ( q -> Z = yes ; Z = no ).
+             q :- fail.",
+        );
+        assert_eq!(binding_of(&mut machine, "X"), Term::Const(1));
+        assert_eq!(binding_of(&mut machine, "Z"), Term::Str("no".to_string()));
+    }
+
+    #[test]
+    fn test_soft_cut_keeps_solutions_of_condition() {
+        let mut machine = run_program(
+            "p(1). p(2). p(3).
+             main :- ( p(X) *-> Y = X ; Y = 0 ), Y = 2, ( fail *-> Z = 1 ; Z = 0 ).",
+        );
+        assert_eq!(binding_of(&mut machine, "Y"), Term::Const(2));
+        assert_eq!(binding_of(&mut machine, "Z"), Term::Const(0));
+    }
+
+    #[test]
+    fn test_negation_as_failure() {
+        let mut machine = run_program(
+            "p(1).
+             main :- \\+ p(2), ( \\+ p(1) -> R = yes ; R = no ), \\+ \\+ X = 1.",
+        );
+        assert_eq!(binding_of(&mut machine, "R"), Term::Str("no".to_string()));
+        assert!(matches!(binding_of(&mut machine, "X"), Term::Var(_)), "Negation leaves no bindings");
+    }
+
+    #[test]
+    fn test_call_with_extra_arguments() {
+        let mut machine = run_program(
+            "add(X, Y, Z) :- Z is X + Y.
+             twice(P, X, Y) :- call(P, X, Z), call(P, Z, Y).
+             main :- twice(add(10), 1, A), call(add, 2, 3, B), P = add(1, 1), call(P, C).",
+        );
+        assert_eq!(binding_of(&mut machine, "A"), Term::Const(21));
+        assert_eq!(binding_of(&mut machine, "B"), Term::Const(5));
+        assert_eq!(binding_of(&mut machine, "C"), Term::Const(2));
+    }
+
+    #[test]
+    fn test_call_of_control_constructs() {
+        let mut machine = run_program(
+            "p(1). p(2). p(3).
+             main :- G = (p(X), X > 1), call(G), H = (Y = a ; Y = b), call(H), Y == b,
+                     call((p(Z) -> true ; Z = 0)), call(\\+ p(4)).",
+        );
+        assert_eq!(binding_of(&mut machine, "X"), Term::Const(2));
+        assert_eq!(binding_of(&mut machine, "Y"), Term::Str("b".to_string()));
+        assert_eq!(binding_of(&mut machine, "Z"), Term::Const(1));
+    }
+
+    #[test]
+    fn test_call_of_construct_with_more_arguments_than_registers() {
+        // The lowered clause takes all ten arguments of the conjunction, more
+        // registers than the compiled code uses.
+        let mut machine = run_program(
+            "a(1, 2, 3, 4, 5).
+             b(6, 7, 8, 9, 10).
+             main :- G = (a(A, _, _, _, _), b(_, _, _, _, B)), call(G).",
+        );
+        assert_eq!(binding_of(&mut machine, "A"), Term::Const(1));
+        assert_eq!(binding_of(&mut machine, "B"), Term::Const(10));
+    }
+
+    #[test]
+    fn test_call_errors() {
+        for program in ["main :- call(G).", "main :- G = 1, call(G).", "main :- G = (true, 1), call(G)."] {
+            assert!(matches!(run_result(program), Err(MachineError::Iso(_))), "{} should raise an error", program);
+        }
+    }
+
+    #[test]
+    fn test_cut_compiles_to_cut() {
+        let (code, _, _) = compile_prolog("p(X) :- q(X), !, r.").expect("Program should compile");
+        assert_eq!(code, vec![
+            Instruction::Move { src: 0, dst: 1 },
+            Instruction::Move { src: 1, dst: 0 },
+            Instruction::Call { predicate: "q/1".to_string(), target: CallTarget::Unresolved },
+            Instruction::Cut,
+            Instruction::Call { predicate: "r/0".to_string(), target: CallTarget::Unresolved },
+            Instruction::Proceed,
+        ]);
+    }
+
+    #[test]
+    fn test_cut_commits_to_clause() {
+        let program = "max(X, Y, X) :- X >= Y, !.
+                       max(_, Y, Y).";
+        let mut machine = run_program(&format!("{} main :- max(5, 3, A), max(2, 7, B).", program));
+        assert_eq!(binding_of(&mut machine, "A"), Term::Const(5));
+        assert_eq!(binding_of(&mut machine, "B"), Term::Const(7));
+        assert!(run_result(&format!("{} main :- max(5, 3, C), C = 3.", program)).is_err());
+    }
+
+    #[test]
+    fn test_cut_after_calls_keeps_caller_choice_points() {
+        let program = "m(a). m(b). m(c).
+                       second(X) :- m(X), X \\== a, !.";
+        let mut machine = run_program(&format!("{} main :- m(Y), second(X), Y == c.", program));
+        assert_eq!(binding_of(&mut machine, "X"), Term::Str("b".to_string()));
+        assert_eq!(binding_of(&mut machine, "Y"), Term::Str("c".to_string()));
+        assert!(run_result(&format!("{} main :- second(X), X = c.", program)).is_err());
+    }
+
+    #[test]
+    fn test_cut_in_branch_cuts_clause() {
+        let mut machine = run_program(
+            "c(X, R) :- ( X > 0 -> !, R = positive ; true ), fail.
+             c(_, other).
+             main :- c(0, R), \\+ c(1, _).",
+        );
+        assert_eq!(binding_of(&mut machine, "R"), Term::Str("other".to_string()));
+    }
+
+    #[test]
+    fn test_cut_is_local_to_conditions_negation_and_call() {
+        let mut machine = run_program(
+            "m(a). m(b). m(c).
+             main :- ( m(X), !, X == b -> R = yes ; R = no ),
+                     \\+ (!, fail),
+                     m(Y), call((!, true)), G = (!, m(Z)), call(G), Y == c, Z == b.",
+        );
+        assert_eq!(binding_of(&mut machine, "R"), Term::Str("no".to_string()));
+        assert_eq!(binding_of(&mut machine, "Y"), Term::Str("c".to_string()));
+        assert_eq!(binding_of(&mut machine, "Z"), Term::Str("b".to_string()));
+        assert!(run_result("m(a). m(b). main :- m(X), !, X == b.").is_err());
+    }
+}
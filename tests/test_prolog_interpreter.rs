@@ -79,3 +79,149 @@ main :-
        .assert()
        .stdout(predicate::str::contains("mary").and(predicate::str::contains("\n")));
 }
+
+#[test]
+fn test_main_selects_clause_by_head() {
+    // Only the second parent/2 fact matches the call, so its child is written.
+    let mut file = Builder::new()
+        .suffix(".pl")
+        .tempfile()
+        .expect("Could not create temp file");
+    writeln!(
+        file,
+        "parent(john, mary).
+parent(bob, alice).
+
+main :-
+  parent(bob, X),
+  write(X),
+  nl,
+  halt."
+    )
+    .expect("Could not write to temp file");
+
+    let path = file.path();
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(path).arg("--execute")
+       .assert()
+       .stdout(predicate::str::contains("alice").and(predicate::str::contains("mary").not()));
+}
+
+#[test]
+fn test_user_defined_operators_are_written_infix() {
+    let mut file = Builder::new()
+        .suffix(".pl")
+        .tempfile()
+        .expect("Could not create temp file");
+    writeln!(
+        file,
+        ":- op(700, xfx, ===>).
+
+rule(a ===> b).
+
+main :-
+  rule(R),
+  write(R),
+  nl,
+  halt."
+    )
+    .expect("Could not write to temp file");
+
+    let path = file.path();
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(path).arg("--execute")
+       .assert()
+       .stdout(predicate::str::contains("a===>b"));
+}
+
+#[test]
+fn test_syntax_error_is_reported_with_source_line() {
+    let mut file = Builder::new()
+        .suffix(".pl")
+        .tempfile()
+        .expect("Could not create temp file");
+    writeln!(
+        file,
+        "parent(john, mary).
+parent(mary, ann."
+    )
+    .expect("Could not write to temp file");
+
+    let path = file.path();
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(path).arg("--execute")
+       .assert()
+       .failure()
+       .stderr(predicate::str::contains("error: Expected ')' but found end of clause")
+           .and(predicate::str::contains(":2:17"))
+           .and(predicate::str::contains("2 | parent(mary, ann.\n  |                 ^\n")));
+}
+
+#[test]
+fn test_lam_syntax_error_is_reported_with_source_line() {
+    let mut file = Builder::new()
+        .suffix(".lam")
+        .tempfile()
+        .expect("Could not create temp file");
+    writeln!(file, "PUT_CONST R0, 1\nMOVE R0, X1").expect("Could not write to temp file");
+
+    let path = file.path();
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(path)
+       .assert()
+       .failure()
+       .stderr(predicate::str::contains(":2:10").and(predicate::str::contains("  |          ^^\n")));
+}
+
+#[test]
+fn test_bytecode_is_written_and_executed() {
+    let dir = tempfile::tempdir().expect("Could not create temp dir");
+    let source = dir.path().join("hello.pl");
+    std::fs::write(&source, "main :- write('Hello bytecode'), nl, halt.").expect("Could not write to temp file");
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(&source).arg("--bytecode").assert().success();
+
+    let bytecode = dir.path().join("hello.lamb");
+    assert!(bytecode.exists());
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(&bytecode)
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("Hello bytecode"));
+}
+
+#[test]
+fn test_lam_file_is_assembled_to_bytecode() {
+    let dir = tempfile::tempdir().expect("Could not create temp dir");
+    let source = dir.path().join("hello.lam");
+    std::fs::write(&source, "PUT_STR R0, \"assembled\"\nCALL \"write/1\"\nCALL \"nl/0\"\nHALT\n").expect("Could not write to temp file");
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(&source).arg("-b").assert().success();
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(dir.path().join("hello.lamb"))
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("assembled"));
+}
+
+#[test]
+fn test_bad_bytecode_file_is_reported() {
+    let mut file = Builder::new()
+        .suffix(".lamb")
+        .tempfile()
+        .expect("Could not create temp file");
+    file.write_all(b"not bytecode").expect("Could not write to temp file");
+
+    let mut cmd = Command::cargo_bin("lamc").expect("Could not find lamc binary");
+    cmd.arg(file.path())
+       .assert()
+       .failure()
+       .stderr(predicate::str::contains("bad magic header"));
+}
@@ -57,7 +57,7 @@ mod tests {
         let input = "parent(john, mary)";
         let err = parse_program(input);
         assert!(err.is_err());
-        if let Err(ParseError::UnexpectedToken(msg)) = err {
+        if let Err(ParseError::UnexpectedToken(msg, _)) = err {
             assert!(msg.contains("Clause must end with a period"));
         } else {
             panic!("Expected an UnexpectedToken error");
@@ -70,6 +70,28 @@ mod tests {
         assert_eq!(term, Term::Number(42));
     }
 
+    #[test]
+    fn test_parse_integers_of_any_size() {
+        let big = |text: &str| Term::BigInt(text.parse().unwrap());
+        assert_eq!(parse_term("2147483647").unwrap(), Term::Number(i32::MAX));
+        assert_eq!(parse_term("-2147483648").unwrap(), Term::Number(i32::MIN));
+        assert_eq!(parse_term("2147483648").unwrap(), big("2147483648"));
+        assert_eq!(parse_term("-123456789012345678901234567890").unwrap(), big("-123456789012345678901234567890"));
+        assert_eq!(parse_term("0xffffffffffffffffff").unwrap(), big("4722366482869645213695"));
+    }
+
+    #[test]
+    fn test_parse_floats() {
+        assert_eq!(parse_term("2.5").unwrap(), Term::Float(2.5));
+        assert_eq!(parse_term("-0.5").unwrap(), Term::Float(-0.5));
+        assert_eq!(parse_term("1.0e10").unwrap(), Term::Float(1.0e10));
+        assert_eq!(parse_term("X is 2.5 * 2").unwrap(), Term::Compound("is".to_string(), vec![
+            Term::Var("X".to_string()),
+            Term::Compound("*".to_string(), vec![Term::Float(2.5), Term::Number(2)]),
+        ]));
+        assert!(parse_term("1.0e999").is_err());
+    }
+
     #[test]
     fn test_parse_variable() {
         let term = parse_term("X").expect("Should parse a variable");
@@ -94,4 +116,199 @@ mod tests {
             panic!("Expected a compound term");
         }
     }
+
+    fn atom(name: &str) -> Term {
+        Term::Atom(name.into())
+    }
+
+    fn var(name: &str) -> Term {
+        Term::Var(name.into())
+    }
+
+    fn op(functor: &str, args: Vec<Term>) -> Term {
+        Term::Compound(functor.into(), args)
+    }
+
+    fn list(items: Vec<Term>, tail: Term) -> Term {
+        items.into_iter().rev().fold(tail, |tail, item| op(".", vec![item, tail]))
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let term = parse_term("X is 1 + 2 * 3 - 4").expect("Should parse arithmetic");
+        assert_eq!(term, op("is", vec![
+            var("X"),
+            op("-", vec![
+                op("+", vec![Term::Number(1), op("*", vec![Term::Number(2), Term::Number(3)])]),
+                Term::Number(4),
+            ]),
+        ]));
+    }
+
+    #[test]
+    fn test_right_associative_operators() {
+        let term = parse_term("a :- b, c ; d -> e").expect("Should parse control operators");
+        assert_eq!(term, op(":-", vec![
+            atom("a"),
+            op(";", vec![
+                op(",", vec![atom("b"), atom("c")]),
+                op("->", vec![atom("d"), atom("e")]),
+            ]),
+        ]));
+    }
+
+    #[test]
+    fn test_prefix_operators_and_negative_numbers() {
+        assert_eq!(parse_term("\\+ X = Y").unwrap(), op("\\+", vec![op("=", vec![var("X"), var("Y")])]));
+        assert_eq!(parse_term("-1").unwrap(), Term::Number(-1));
+        assert_eq!(parse_term("- 1").unwrap(), op("-", vec![Term::Number(1)]));
+        assert_eq!(parse_term("a - 1").unwrap(), op("-", vec![atom("a"), Term::Number(1)]));
+        assert_eq!(parse_term("-(-(a))").unwrap(), op("-", vec![op("-", vec![atom("a")])]));
+        assert_eq!(parse_term("f(-, +)").unwrap(), op("f", vec![atom("-"), atom("+")]));
+    }
+
+    #[test]
+    fn test_non_associative_operator_clash() {
+        assert!(parse_term("a = b = c").is_err());
+        assert_eq!(parse_term("(a = b) = c").unwrap(), op("=", vec![op("=", vec![atom("a"), atom("b")]), atom("c")]));
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(parse_term("[]").unwrap(), atom("[]"));
+        assert_eq!(parse_term("[1, 2]").unwrap(), list(vec![Term::Number(1), Term::Number(2)], atom("[]")));
+        assert_eq!(parse_term("[H|T]").unwrap(), list(vec![var("H")], var("T")));
+        assert_eq!(
+            parse_term("[a, (b, c) | []]").unwrap(),
+            list(vec![atom("a"), op(",", vec![atom("b"), atom("c")])], atom("[]"))
+        );
+    }
+
+    #[test]
+    fn test_curly_terms() {
+        assert_eq!(parse_term("{}").unwrap(), atom("{}"));
+        assert_eq!(parse_term("{a, b}").unwrap(), op("{}", vec![op(",", vec![atom("a"), atom("b")])]));
+    }
+
+    #[test]
+    fn test_quoted_atoms_and_escapes() {
+        assert_eq!(parse_term("'hello world'").unwrap(), atom("hello world"));
+        assert_eq!(parse_term("'it''s'").unwrap(), atom("it's"));
+        assert_eq!(parse_term(r"'a\nb\\c\x41\'").unwrap(), atom("a\nb\\cA"));
+        assert_eq!(parse_term("'end.'").unwrap(), atom("end."));
+        assert_eq!(parse_term("'Hello'(x)").unwrap(), op("Hello", vec![atom("x")]));
+    }
+
+    #[test]
+    fn test_strings_and_character_codes() {
+        assert_eq!(parse_term("\"ab\"").unwrap(), list(vec![Term::Number(97), Term::Number(98)], atom("[]")));
+        assert_eq!(parse_term("0'a").unwrap(), Term::Number(97));
+        assert_eq!(parse_term("0' ").unwrap(), Term::Number(32));
+        assert_eq!(parse_term(r"0'\n").unwrap(), Term::Number(10));
+        assert_eq!(parse_term("0'''").unwrap(), Term::Number(39));
+        assert_eq!(parse_term("0xff").unwrap(), Term::Number(255));
+    }
+
+    #[test]
+    fn test_comments_and_clause_ends() {
+        let input = "/* a block\n comment. */ p('a.b'). % trailing. comment\n\
+                     q(X) :- X =.. [f, 1]. /* last */";
+        let clauses = parse_program(input).expect("Should parse program with comments");
+        assert_eq!(clauses, vec![
+            Clause::Fact { head: op("p", vec![atom("a.b")]) },
+            Clause::Rule {
+                head: op("q", vec![var("X")]),
+                body: vec![op("=..", vec![var("X"), list(vec![atom("f"), Term::Number(1)], atom("[]"))])],
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_rule_body_conjunction_is_flattened() {
+        let clauses = parse_program("p :- a, (b ; c), \\+ d.").expect("Should parse rule");
+        assert_eq!(clauses, vec![Clause::Rule {
+            head: atom("p"),
+            body: vec![atom("a"), op(";", vec![atom("b"), atom("c")]), op("\\+", vec![atom("d")])],
+        }]);
+    }
+
+    #[test]
+    fn test_queries_are_skipped() {
+        let clauses = parse_program("p(1).\n?- p(X).\n").expect("Should parse program with a query");
+        assert_eq!(clauses, vec![Clause::Fact { head: op("p", vec![Term::Number(1)]) }]);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(parse_program("p(a.").is_err(), "Unclosed parenthesis");
+        assert!(parse_program("p('a).").is_err(), "Unterminated quoted atom");
+        assert!(parse_program("p(a). /* open").is_err(), "Unterminated block comment");
+        assert!(parse_program("X.").is_err(), "Variable clause head");
+        assert!(parse_term("").is_err());
+    }
+
+    #[test]
+    fn test_op_directive_changes_parsing() {
+        let input = ":- op(700, xfx, ===>).\n\
+                     :- op(200, xfy, [and, or]).\n\
+                     rule(a and b ===> c).";
+        let clauses = parse_program(input).expect("Should parse user-defined operators");
+        assert_eq!(clauses.len(), 3);
+        assert!(matches!(&clauses[0], Clause::Directive { goal: Term::Compound(name, _) } if name == "op"));
+        assert_eq!(clauses[2], Clause::Fact {
+            head: op("rule", vec![op("===>", vec![op("and", vec![atom("a"), atom("b")]), atom("c")])]),
+        });
+    }
+
+    #[test]
+    fn test_op_directive_only_affects_later_clauses() {
+        assert!(parse_program("p(a ===> b).\n:- op(700, xfx, ===>).").is_err());
+        // A priority of 0 removes the operator again.
+        assert!(parse_program(":- op(700, xfx, ===>).\n:- op(0, xfx, ===>).\np(a ===> b).").is_err());
+    }
+
+    #[test]
+    fn test_invalid_op_directives() {
+        assert!(parse_program(":- op(1201, xfx, foo).").is_err(), "Priority out of range");
+        assert!(parse_program(":- op(700, abc, foo).").is_err(), "Invalid operator type");
+        assert!(parse_program(":- op(700, xfx, ',').").is_err(), "Comma cannot be redefined");
+        assert!(parse_program(":- op(700, xfx, [foo, 1]).").is_err(), "Operator names must be atoms");
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = parse_program("parent(john, mary).\nparent(mary, ann.\n").unwrap_err();
+        let span = err.span();
+        assert_eq!((span.line, span.column), (2, 17));
+        assert_eq!(err.message(), "Expected ')' but found end of clause");
+        assert_eq!(err.to_string(), "line 2, column 17: Expected ')' but found end of clause");
+
+        let input = "foo(1) bar.";
+        let span = parse_program(input).unwrap_err().span();
+        assert_eq!(&input[span.start..span.end], "bar");
+    }
+
+    #[test]
+    fn test_missing_period_points_after_last_token() {
+        let span = parse_program("foo(a)  ").unwrap_err().span();
+        assert_eq!((span.line, span.column), (1, 7));
+    }
+
+    #[test]
+    fn test_lexer_error_positions() {
+        let input = "ok.\n  x = 'abc.\n";
+        let span = parse_program(input).unwrap_err().span();
+        assert_eq!((span.line, span.column), (2, 7));
+        let span = parse_term("f(a) /* open").unwrap_err().span();
+        assert_eq!(span.column, 6);
+    }
+
+    #[test]
+    fn test_invalid_clause_error_covers_clause() {
+        let input = "a.\n:- op(1201, xfx, foo).";
+        let err = parse_program(input).unwrap_err();
+        let span = err.span();
+        assert_eq!(&input[span.start..span.end], ":- op(1201, xfx, foo).");
+        assert!(err.message().contains("priority"));
+    }
 }
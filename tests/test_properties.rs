@@ -1,8 +1,156 @@
 #[cfg(test)]
 mod tests {
-    use quickcheck::quickcheck;
+    use num_bigint::BigInt;
+    use quickcheck::{quickcheck, Arbitrary, Gen};
+    use lam::machine::arithmetic::Expression;
+    use lam::machine::instruction::{CallTarget, Instruction};
+    use lam::machine::instruction_parser::parse_program;
+    use lam::machine::integer::Integer;
+    use lam::machine::lambda::Strategy;
+    use lam::machine::number::Float;
+    use lam::machine::program::Program;
     use lam::machine::term::Term;
     use lam::machine::unification::UnionFind;
+
+    /// Generates an arithmetic expression of at most the given depth.
+    fn arbitrary_expression(g: &mut Gen, depth: usize) -> Expression {
+        const BINARY: [&str; 19] = [
+            "+", "-", "*", "/", "//", "div", "mod", "rem", "min", "max",
+            "**", "^", "/\\", "\\/", "xor", "<<", ">>", "gcd", "atan2",
+        ];
+        const UNARY: [&str; 19] = [
+            "abs", "sign", "\\", "msb", "float", "integer", "truncate", "round", "ceiling",
+            "floor", "sqrt", "exp", "log", "sin", "cos", "tan", "asin", "acos", "atan",
+        ];
+        let choice = if depth == 0 { u8::arbitrary(g) % 3 } else { u8::arbitrary(g) % 5 };
+        let operand = |g: &mut Gen| arbitrary_expression(g, depth - 1);
+        match choice {
+            0 => Expression::Const(i32::arbitrary(g)),
+            1 => Expression::Var(usize::arbitrary(g)),
+            2 => Expression::Float(arbitrary_float(g)),
+            3 => Expression::from_unary(g.choose(&UNARY).unwrap(), operand(g)).unwrap(),
+            _ => Expression::from_binary(g.choose(&BINARY).unwrap(), operand(g), operand(g)).unwrap(),
+        }
+    }
+
+    /// Generates an integer of up to a few hundred bits.
+    fn arbitrary_big_int(g: &mut Gen) -> BigInt {
+        BigInt::from_signed_bytes_le(&Vec::<u8>::arbitrary(g))
+    }
+
+    /// Generates a finite float.
+    fn arbitrary_float(g: &mut Gen) -> f64 {
+        let x = f64::arbitrary(g);
+        if x.is_finite() { x } else { 0.0 }
+    }
+
+    /// Generates a term that has a `.lam` literal syntax.
+    fn arbitrary_literal(g: &mut Gen) -> Term {
+        match u8::arbitrary(g) % 5 {
+            0 => Term::Const(i32::arbitrary(g)),
+            1 => Term::Var(usize::arbitrary(g)),
+            2 => Integer::from(arbitrary_big_int(g)).into_term(),
+            3 => Term::Float(Float(arbitrary_float(g))),
+            _ => Term::Str(String::arbitrary(g)),
+        }
+    }
+
+    /// Generates a non-empty list of registers.
+    fn arbitrary_registers(g: &mut Gen) -> Vec<usize> {
+        let mut registers = Vec::<usize>::arbitrary(g);
+        registers.push(usize::arbitrary(g));
+        registers
+    }
+
+    fn arbitrary_instruction(g: &mut Gen) -> Instruction {
+        let r = usize::arbitrary;
+        let s = String::arbitrary;
+        match u8::arbitrary(g) % 43 {
+            0 => Instruction::PutConst { register: r(g), value: i32::arbitrary(g) },
+            1 => Instruction::PutVar { register: r(g), var_id: r(g), name: s(g) },
+            2 => Instruction::GetConst { register: r(g), value: i32::arbitrary(g) },
+            3 => Instruction::GetVar { register: r(g), var_id: r(g), name: s(g) },
+            4 => Instruction::Call { predicate: s(g), target: CallTarget::Unresolved },
+            5 => Instruction::Proceed,
+            6 => Instruction::Choice { alternative: r(g) },
+            7 => Instruction::Allocate { n: r(g) },
+            8 => Instruction::Deallocate,
+            9 => Instruction::ArithmeticIs { target: r(g), expression: arbitrary_expression(g, 3) },
+            10 => Instruction::SetLocal { index: r(g), value: arbitrary_literal(g) },
+            11 => Instruction::GetLocal { index: r(g), register: r(g) },
+            12 => Instruction::Fail,
+            13 => Instruction::GetStructure { register: r(g), functor: s(g), arity: r(g) },
+            14 => Instruction::IndexedCall { predicate: s(g), index_register: r(g) },
+            15 => Instruction::MultiIndexedCall { predicate: s(g), index_registers: arbitrary_registers(g) },
+            16 => Instruction::TailCall { predicate: s(g), target: CallTarget::Unresolved },
+            17 => Instruction::AssertClause { predicate: s(g), address: r(g) },
+            18 => Instruction::RetractClause { predicate: s(g), address: r(g) },
+            19 => Instruction::Cut,
+            20 => Instruction::BuildCompound { target: r(g), functor: s(g), arg_registers: arbitrary_registers(g) },
+            21 => Instruction::PutStr { register: r(g), value: s(g) },
+            22 => Instruction::GetStr { register: r(g), value: s(g) },
+            23 => Instruction::Move { src: r(g), dst: r(g) },
+            24 => Instruction::GetValue { register: r(g), src: r(g) },
+            25 => Instruction::NewVar { register: r(g), name: s(g) },
+            26 => Instruction::StoreLocal { index: r(g), register: r(g) },
+            27 => Instruction::PutLocal { index: r(g), register: r(g) },
+            28 => Instruction::UnifyVar { register: r(g) },
+            29 => Instruction::UnifyValue { register: r(g) },
+            30 => Instruction::UnifyConst { value: i32::arbitrary(g) },
+            31 => Instruction::BuildLambda { target: r(g), param: r(g), body: r(g) },
+            32 => Instruction::Apply { target: r(g), function: r(g), argument: r(g) },
+            33 => Instruction::Normalize { register: r(g), strategy: *g.choose(&[Strategy::NormalOrder, Strategy::ApplicativeOrder, Strategy::HeadNormalForm]).unwrap() },
+            34 => Instruction::PutBigInt { register: r(g), value: arbitrary_big_int(g) },
+            35 => Instruction::GetBigInt { register: r(g), value: arbitrary_big_int(g) },
+            36 => Instruction::PutFloat { register: r(g), value: arbitrary_float(g) },
+            37 => Instruction::GetFloat { register: r(g), value: arbitrary_float(g) },
+            38 => Instruction::GetLevel { register: r(g) },
+            39 => Instruction::CutTo { register: r(g) },
+            40 => Instruction::SoftCut { register: r(g) },
+            41 => Instruction::Jump { address: r(g) },
+            _ => Instruction::Halt,
+        }
+    }
+
+    /// A program whose tables only refer to addresses inside it, in increasing order.
+    #[derive(Debug, Clone)]
+    struct ArbitraryProgram(Program);
+
+    impl Arbitrary for ArbitraryProgram {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let len = usize::arbitrary(g) % 20;
+            let mut program = Program::new((0..len).map(|_| arbitrary_instruction(g)).collect());
+            for address in 0..len {
+                if bool::arbitrary(g) {
+                    let name = String::arbitrary(g);
+                    program.predicate_table.entry(name).or_default().push(address);
+                }
+                if u8::arbitrary(g) % 4 == 0 {
+                    let name = String::arbitrary(g);
+                    let key = (0..1 + usize::arbitrary(g) % 3)
+                        .map(|_| if bool::arbitrary(g) { Term::Const(i32::arbitrary(g)) } else { Term::Str(String::arbitrary(g)) })
+                        .collect();
+                    program.index_table.entry(name).or_default().entry(key).or_default().push(address);
+                }
+            }
+            ArbitraryProgram(program)
+        }
+    }
+
+    quickcheck! {
+        fn prop_program_display_round_trips(program: ArbitraryProgram) -> bool {
+            let text = program.0.to_string();
+            parse_program(&text) == Ok(program.0)
+        }
+    }
+
+    quickcheck! {
+        fn prop_program_bytecode_round_trips(program: ArbitraryProgram) -> bool {
+            let mut bytes = Vec::new();
+            program.0.write_bytecode(&mut bytes).unwrap();
+            Program::read_bytecode(&mut bytes.as_slice()).ok() == Some(program.0)
+        }
+    }
     
     quickcheck! {
         fn prop_undo_binding(var_id: usize, value: i32) -> bool {
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use lam::machine::stack::Stack;
+
+    #[test]
+    fn test_push_pop_and_last() {
+        let mut stack = Stack::new();
+        assert!(stack.is_empty());
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.last(), Some(&2));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_clones_are_independent() {
+        let mut stack = Stack::from(vec![1, 2, 3]);
+        let saved = stack.clone();
+        stack.pop();
+        stack.pop();
+        stack.push(20);
+        assert_eq!(stack, Stack::from(vec![1, 20]));
+        assert_eq!(saved, Stack::from(vec![1, 2, 3]));
+        assert_eq!(saved.iter().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_last_mut_copies_a_shared_frame() {
+        let mut stack = Stack::from(vec![vec![0; 3]]);
+        let saved = stack.clone();
+        stack.last_mut().unwrap()[1] = 7;
+        stack.last_mut().unwrap()[2] = 8;
+        assert_eq!(stack.last(), Some(&vec![0, 7, 8]));
+        assert_eq!(saved.last(), Some(&vec![0, 0, 0]));
+    }
+
+    #[test]
+    fn test_deep_stack_drops_without_overflow() {
+        let mut stack = Stack::new();
+        for i in 0..1_000_000 {
+            stack.push(i);
+        }
+        let saved = stack.clone();
+        drop(stack);
+        assert_eq!(saved.len(), 1_000_000);
+    }
+
+    #[test]
+    fn test_debug_lists_frames_bottom_first() {
+        assert_eq!(format!("{:?}", Stack::from(vec![1, 2])), "[1, 2]");
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use lam::machine::core::Machine;
-    use lam::machine::instruction::Instruction;
+    use lam::machine::instruction::{CallTarget, Instruction};
     use lam::machine::term::Term;
 
     #[test]
@@ -13,7 +13,7 @@ mod tests {
             // Set local variable 0 to 100.
             Instruction::SetLocal { index: 0, value: Term::Const(100) },
             // Tail call to predicate "p" (this should deallocate the current environment).
-            Instruction::TailCall { predicate: "p".to_string() },
+            Instruction::TailCall { predicate: "p".to_string(), target: CallTarget::Unresolved },
             // Dummy instruction (should not execute if tail call works).
             Instruction::PutConst { register: 0, value: 999 },
             // Predicate "p" code (starting at index 4).
@@ -28,7 +28,7 @@ mod tests {
         machine.run().expect("Machine run should succeed");
         
         // Expect that reg0 was set to 200 and the environment stack is empty.
-        assert_eq!(machine.registers[0], Some(Term::Const(200)));
+        assert_eq!(machine.register(0), Some(Term::Const(200)));
         assert_eq!(machine.environment_stack.len(), 0);
     }
 }
@@ -1,5 +1,7 @@
 #[cfg(test)]
 mod tests {
+    use std::cmp::Ordering;
+    use lam::machine::number::Float;
     use lam::machine::term::Term;
 
     #[test]
@@ -75,4 +77,32 @@ mod tests {
         let cloned = original.clone();
         assert_eq!(original, cloned);
     }
+
+    #[test]
+    fn test_standard_order() {
+        let f = |name: &str, args: Vec<Term>| Term::Compound(name.to_string(), args);
+        // Each term precedes the next one in the standard order.
+        let ordered = vec![
+            Term::Var(3),
+            Term::Var(7),
+            Term::BigInt("-100000000000000000000".parse().unwrap()),
+            Term::Float(Float(-1.5)),
+            Term::Float(Float(1.0)),
+            Term::Const(1),
+            Term::Float(Float(1.5)),
+            Term::Const(2),
+            Term::Str("a".to_string()),
+            Term::Str("b".to_string()),
+            f("z", vec![Term::Const(1)]),
+            f("a", vec![Term::Const(1), Term::Const(2)]),
+            f("a", vec![Term::Const(1), Term::Str("x".to_string())]),
+            f("b", vec![Term::Var(0), Term::Var(0)]),
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(a.standard_order(b), i.cmp(&j), "{} compared with {}", a, b);
+            }
+        }
+        assert_eq!(Term::Float(Float(-0.0)).standard_order(&Term::Float(Float(0.0))), Ordering::Less);
+    }
 }
@@ -60,4 +60,59 @@ mod tests {
             output
         );
     }
+
+    fn atom(name: &str) -> Term {
+        Term::Str(name.to_string())
+    }
+
+    fn op(functor: &str, args: Vec<Term>) -> Term {
+        Term::Compound(functor.to_string(), args)
+    }
+
+    #[test]
+    fn test_display_infix_operators() {
+        let term = op("=", vec![Term::Var(1), op("+", vec![Term::Const(1), op("*", vec![Term::Const(2), Term::Const(3)])])]);
+        assert_eq!(term.to_string(), "Var(1)=1+2*3");
+        assert_eq!(op("is", vec![atom("x"), Term::Const(1)]).to_string(), "x is 1");
+        assert_eq!(op(",", vec![atom("a"), atom("b")]).to_string(), "a, b");
+    }
+
+    #[test]
+    fn test_display_parenthesizes_by_priority() {
+        let sum = op("+", vec![Term::Const(1), Term::Const(2)]);
+        assert_eq!(op("*", vec![sum.clone(), Term::Const(3)]).to_string(), "(1+2)*3");
+        // `-` is left-associative: only a right operand of the same priority needs parentheses.
+        let left = op("-", vec![op("-", vec![Term::Const(1), Term::Const(2)]), Term::Const(3)]);
+        let right = op("-", vec![Term::Const(1), op("-", vec![Term::Const(2), Term::Const(3)])]);
+        assert_eq!(left.to_string(), "1-2-3");
+        assert_eq!(right.to_string(), "1-(2-3)");
+        // Arguments of a compound term bind tighter than the comma.
+        assert_eq!(op("f", vec![op(",", vec![atom("a"), atom("b")])]).to_string(), "f((a, b))");
+    }
+
+    #[test]
+    fn test_display_prefix_operators() {
+        assert_eq!(op("-", vec![atom("a")]).to_string(), "-a");
+        assert_eq!(op("-", vec![Term::Const(1)]).to_string(), "- 1");
+        assert_eq!(op("\\+", vec![op(",", vec![atom("a"), atom("b")])]).to_string(), "\\+ (a, b)");
+        assert_eq!(op("-", vec![Term::Const(1), Term::Const(-1)]).to_string(), "1- -1");
+    }
+
+    #[test]
+    fn test_display_lists_and_curly_terms() {
+        let list = op(".", vec![Term::Const(1), op(".", vec![Term::Const(2), atom("[]")])]);
+        assert_eq!(list.to_string(), "[1, 2]");
+        assert_eq!(op(".", vec![Term::Const(1), Term::Var(3)]).to_string(), "[1|Var(3)]");
+        assert_eq!(op("{}", vec![atom("a")]).to_string(), "{a}");
+    }
+
+    #[test]
+    fn test_display_with_custom_operator_table() {
+        use lam::machine::ops::{OpTable, OpType};
+        let term = op("===>", vec![atom("a"), atom("b")]);
+        assert_eq!(term.to_string(), "===>(a, b)");
+        let mut ops = OpTable::default();
+        ops.add(700, OpType::Xfx, "===>");
+        assert_eq!(term.display_with(&ops).to_string(), "a===>b");
+    }
 }
@@ -1,5 +1,8 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
+    use super::common::{app, lambda, new_machine};
     use lam::machine::unification::{UnionFind};
     use lam::machine::term::Term;
 
@@ -74,5 +77,116 @@ mod tests {
         let binding = uf.bindings.get(&1).expect("Var(1) should have a binding");
         assert_eq!(binding, &Term::Const(7));
     }
-}
 
+    fn atom(name: &str) -> Term {
+        Term::Str(name.to_string())
+    }
+
+    #[test]
+    fn test_unify_lambda_alpha_equivalent() {
+        let mut machine = new_machine();
+        // \x. f(x) and \y. f(y) are the same function.
+        let f = |v| Term::Compound("f".to_string(), vec![Term::Var(v)]);
+        assert!(machine.unify(&lambda(10, f(10)), &lambda(11, f(11))).is_ok());
+        // \x. \y. x and \x. \y. y are not.
+        let first = lambda(10, lambda(11, Term::Var(10)));
+        let second = lambda(10, lambda(11, Term::Var(11)));
+        assert!(machine.unify(&first, &second).is_err());
+    }
+
+    #[test]
+    fn test_unify_lambda_binds_free_variables() {
+        let mut machine = new_machine();
+        let left = lambda(10, app(Term::Var(10), Term::Var(0)));
+        let right = lambda(11, app(Term::Var(11), Term::Const(3)));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(3));
+    }
+
+    #[test]
+    fn test_unify_lambda_parameter_cannot_escape() {
+        let mut machine = new_machine();
+        // \x. x and \y. Y would need Y to be the parameter itself.
+        assert!(machine.unify(&lambda(10, Term::Var(10)), &lambda(11, Term::Var(0))).is_err());
+        let mut machine = new_machine();
+        let wrapped = lambda(10, Term::Compound("f".to_string(), vec![Term::Var(10)]));
+        assert!(machine.unify(&wrapped, &lambda(11, Term::Var(0))).is_err());
+    }
+
+    #[test]
+    fn test_unify_app() {
+        let mut machine = new_machine();
+        machine.unify(&app(atom("f"), Term::Var(0)), &app(Term::Var(1), Term::Const(1))).unwrap();
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(1));
+        assert_eq!(machine.resolve(&Term::Var(1)), atom("f"));
+        assert!(machine.unify(&app(atom("f"), atom("a")), &app(atom("g"), atom("a"))).is_err());
+    }
+
+    #[test]
+    fn test_unify_prob() {
+        let mut machine = new_machine();
+        let left = Term::Prob(Box::new(Term::Var(0)));
+        let right = Term::Prob(Box::new(Term::Const(5)));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(machine.resolve_deep(&left), right);
+        assert!(machine.unify(&right, &Term::Prob(Box::new(Term::Const(6)))).is_err());
+    }
+
+    #[test]
+    fn test_unify_constraint() {
+        let mut machine = new_machine();
+        let left = Term::Constraint("lt".to_string(), vec![Term::Var(0), Term::Const(2)]);
+        let right = Term::Constraint("lt".to_string(), vec![Term::Const(1), Term::Var(1)]);
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(1));
+        assert_eq!(machine.resolve(&Term::Var(1)), Term::Const(2));
+        let other_name = Term::Constraint("gt".to_string(), vec![Term::Const(1), Term::Const(2)]);
+        assert!(machine.unify(&left, &other_name).is_err());
+        let other_arity = Term::Constraint("lt".to_string(), vec![Term::Const(1)]);
+        assert!(machine.unify(&left, &other_arity).is_err());
+    }
+
+    #[test]
+    fn test_unify_modal() {
+        let mut machine = new_machine();
+        let left = Term::Modal("box".to_string(), Box::new(Term::Var(0)));
+        let right = Term::Modal("box".to_string(), Box::new(atom("p")));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(machine.resolve(&Term::Var(0)), atom("p"));
+        let diamond = Term::Modal("diamond".to_string(), Box::new(atom("p")));
+        assert!(machine.unify(&right, &diamond).is_err());
+    }
+
+    #[test]
+    fn test_unify_temporal() {
+        let mut machine = new_machine();
+        let left = Term::Temporal("always".to_string(), Box::new(Term::Var(0)));
+        let right = Term::Temporal("always".to_string(), Box::new(Term::Const(1)));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(1));
+        let eventually = Term::Temporal("eventually".to_string(), Box::new(Term::Const(1)));
+        assert!(machine.unify(&right, &eventually).is_err());
+        // A temporal term does not unify with a modal term of the same operator.
+        let modal = Term::Modal("always".to_string(), Box::new(Term::Const(1)));
+        assert!(machine.unify(&right, &modal).is_err());
+    }
+
+    #[test]
+    fn test_unify_higher_order() {
+        let mut machine = new_machine();
+        let left = Term::HigherOrder(Box::new(Term::Compound("p".to_string(), vec![Term::Var(0)])));
+        let right = Term::HigherOrder(Box::new(Term::Compound("p".to_string(), vec![Term::Const(4)])));
+        machine.unify(&left, &right).unwrap();
+        assert_eq!(machine.resolve(&Term::Var(0)), Term::Const(4));
+        assert!(machine.unify(&right, &Term::Prob(Box::new(Term::Const(4)))).is_err());
+    }
+
+    #[test]
+    fn test_bind_variable_to_extended_term() {
+        let mut machine = new_machine();
+        let term = lambda(10, Term::Var(10));
+        machine.unify(&Term::Var(0), &term).unwrap();
+        assert_eq!(machine.resolve_deep(&Term::Var(0)), term);
+        assert!(machine.unify(&Term::Var(0), &lambda(11, Term::Var(11))).is_ok());
+    }
+}
@@ -48,4 +48,18 @@ mod tests {
       // Resolution should yield the same unbound variable.
       assert_eq!(uf.resolve(&Term::Var(1)), Term::Var(1));
   }
-}
\ No newline at end of file
+
+  /// Test that deep resolution replaces bound variables nested inside compound terms.
+  #[test]
+  fn test_resolve_deep() {
+      let mut uf = UnionFind::new();
+      uf.bind(1, &Term::Const(7)).expect("Binding should succeed");
+      uf.bind(2, &Term::Compound("g".to_string(), vec![Term::Var(1)])).expect("Binding should succeed");
+      let term = Term::Compound("f".to_string(), vec![Term::Var(2), Term::Var(3)]);
+      let expected = Term::Compound("f".to_string(), vec![
+          Term::Compound("g".to_string(), vec![Term::Const(7)]),
+          Term::Var(3),
+      ]);
+      assert_eq!(uf.resolve_deep(&term), expected);
+  }
+}